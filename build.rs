@@ -0,0 +1,51 @@
+//! Capture build info into compile-time ENV, which can be read by `option_env!()`.
+//! Values given by runtime ENV of the build process (e.g. `lambda.Dockerfile`) take precedence.
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const REVISION_ENV: &str = "RELATION_SERVER_REVISION";
+const BUILT_AT_ENV: &str = "RELATION_SERVER_BUILT_AT";
+
+fn main() {
+    println!("cargo:rerun-if-env-changed={}", REVISION_ENV);
+    println!("cargo:rerun-if-env-changed={}", BUILT_AT_ENV);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let revision = given_env(REVISION_ENV).or_else(git_revision);
+    if let Some(revision) = revision {
+        println!("cargo:rustc-env={}={}", REVISION_ENV, revision);
+    }
+
+    let built_at = given_env(BUILT_AT_ENV).unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+            .to_string()
+    });
+    println!("cargo:rustc-env={}={}", BUILT_AT_ENV, built_at);
+}
+
+fn given_env(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+fn git_revision() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let revision = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if revision.is_empty() {
+        None
+    } else {
+        Some(revision)
+    }
+}
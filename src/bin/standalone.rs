@@ -1,21 +1,30 @@
 use async_graphql::{
     http::{playground_source, GraphQLPlaygroundConfig},
-    EmptyMutation, EmptySubscription, Schema,
+    BatchRequest, EmptySubscription, Schema,
 };
 use async_graphql_warp::{GraphQLBadRequest, GraphQLResponse};
 use dataloader::non_cached::Loader;
 use http::StatusCode;
 use relation_server::{
-    config::{self, C},
-    controller::graphql::Query,
+    config::C,
+    controller::export::identity_export,
+    controller::graphql::{execute_batch, parse_get_request, parse_graphql_body, Mutation, Query},
+    controller::rate_limit::{ClientKey, RateLimiter},
     error::Result,
     graph::arangopool::new_connection_pool,
+    graph::compaction::spawn_edge_compactor,
+    graph::consistency::spawn_display_name_fixer,
+    graph::prune::spawn_unreachable_pruner,
+    graph::staleness::{latest_staleness, render_metrics, spawn_staleness_sampler},
     graph::vertex::contract::ContractLoadFn,
     graph::vertex::FromToLoadFn,
     graph::vertex::IdentityLoadFn,
+    graph::vertex::PlatformIdentityLoadFn,
+    graph::ConnectionPool,
+    upstream,
 };
 // use aragog::{AuthMode, DatabaseConnection, OperationOptions};
-use std::{convert::Infallible, net::SocketAddr};
+use std::convert::Infallible;
 use tracing::{info, warn};
 use tracing_subscriber::filter::{EnvFilter, LevelFilter};
 use warp::{http::Response as HttpResponse, Filter, Rejection};
@@ -34,6 +43,9 @@ async fn main() -> Result<()> {
     tracing::subscriber::set_global_default(log_subscriber)
         .expect("Setting default subscriber failed");
 
+    // Fail fast on a bad `web.listen`, before connecting to anything.
+    let address = C.web.bind_address()?;
+
     let middleware_cors = warp::cors()
         .allow_any_origin() // : maybe more strict CORS in production?
         .allow_methods(vec!["GET", "POST"])
@@ -49,8 +61,21 @@ async fn main() -> Result<()> {
         .build()
         .await?;
 
+    upstream::spawn_fetching_watchdog();
+    upstream::refetch::spawn_refetch_workers();
+    upstream::liveness::spawn_liveness_monitor();
+
     // Runtime::Tokio1
     let pool = new_connection_pool().await?;
+    spawn_staleness_sampler(pool.to_owned());
+    // Display names are still checked for `displayNameReport`, but only fixed if writable.
+    spawn_display_name_fixer(pool.to_owned(), !C.web.read_only);
+    // Background jobs writing into DB. Those are left to the writable deployment.
+    if !C.web.read_only {
+        spawn_edge_compactor(pool.to_owned());
+        spawn_unreachable_pruner(pool.to_owned());
+    }
+    let export_pool = pool.to_owned();
     let contract_loader_fn = ContractLoadFn {
         pool: pool.to_owned(),
     };
@@ -60,6 +85,9 @@ async fn main() -> Result<()> {
     let from_to_loader_fn = FromToLoadFn {
         pool: pool.to_owned(),
     };
+    let platform_identity_loader_fn = PlatformIdentityLoadFn {
+        pool: pool.to_owned(),
+    };
     // HOLD ON: Specify the batch size number
     let contract_loader = Loader::new(contract_loader_fn)
         .with_max_batch_size(100)
@@ -70,32 +98,158 @@ async fn main() -> Result<()> {
     let from_to_loader = Loader::new(from_to_loader_fn)
         .with_max_batch_size(100)
         .with_yield_count(10);
+    let platform_identity_loader = Loader::new(platform_identity_loader_fn)
+        .with_max_batch_size(100)
+        .with_yield_count(10);
 
-    let schema = Schema::build(Query::default(), EmptyMutation, EmptySubscription)
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
         .data(pool)
         .data(contract_loader)
         .data(identity_loader)
         .data(from_to_loader)
+        .data(platform_identity_loader)
+        .data(RateLimiter::from_config())
         .finish();
 
-    let graphql_post = async_graphql_warp::graphql(schema)
+    let client_key = warp::header::optional::<String>("x-api-key")
+        .and(warp::header::optional::<String>("x-forwarded-for"))
+        .and(warp::addr::remote())
+        .map(ClientKey::from_request);
+    let with_schema = {
+        let schema = schema.clone();
+        warp::any().map(move || schema.clone())
+    };
+
+    let graphql_post = async_graphql_warp::graphql_batch(schema)
+        .and(client_key.clone())
         .and_then(
             |(schema, request): (
-                Schema<Query, EmptyMutation, EmptySubscription>,
-                async_graphql::Request,
-            )| async move {
-                Ok::<_, Infallible>(GraphQLResponse::from(schema.execute(request).await))
+                Schema<Query, Mutation, EmptySubscription>,
+                async_graphql::BatchRequest,
+            ),
+             client: ClientKey| async move {
+                execute_batch(&schema, request.data(client), C.web.max_batch_size)
+                    .await
+                    .map(GraphQLResponse::from)
+                    .map_err(warp::reject::custom)
             },
         )
-        .with(middleware_cors);
+        .with(middleware_cors.clone());
 
-    let playground = warp::path::end().and(warp::get()).map(|| {
-        HttpResponse::builder()
-            .header("content-type", "text/html")
-            .body(playground_source(GraphQLPlaygroundConfig::new("/")))
-    });
+    let query_string = warp::query::raw().or(warp::any().map(String::new)).unify();
 
-    let routes = playground
+    // `GET /?query=...`. Cacheable. Playground is served instead if no `query` is given.
+    let graphql_get = warp::path::end()
+        .and(warp::get())
+        .and(query_string.clone())
+        .and_then(|query_string: String| async move {
+            if is_get_query(&query_string) {
+                Ok(query_string)
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .and(client_key.clone())
+        .and(with_schema.clone())
+        .and_then(
+            |query_string: String,
+             client: ClientKey,
+             schema: Schema<Query, Mutation, EmptySubscription>| async move {
+                let request = parse_get_request(&query_string).map_err(warp::reject::custom)?;
+                execute_batch(
+                    &schema,
+                    BatchRequest::Single(request.data(client)),
+                    C.web.max_batch_size,
+                )
+                .await
+                .map(GraphQLResponse::from)
+                .map_err(warp::reject::custom)
+            },
+        )
+        .with(middleware_cors.clone());
+
+    // `POST` with `Content-Type: application/graphql`.
+    let graphql_raw = warp::path::end()
+        .and(warp::post())
+        .and(warp::header::<String>("content-type"))
+        .and_then(|content_type: String| async move {
+            match content_type.split(';').next().map(str::trim) {
+                Some("application/graphql") => Ok(()),
+                _ => Err(warp::reject::not_found()),
+            }
+        })
+        .untuple_one()
+        .and(query_string.clone())
+        .and(warp::body::bytes())
+        .and(client_key.clone())
+        .and(with_schema)
+        .and_then(
+            |query_string: String,
+             body: warp::hyper::body::Bytes,
+             client: ClientKey,
+             schema: Schema<Query, Mutation, EmptySubscription>| async move {
+                let request =
+                    parse_graphql_body(&body, &query_string).map_err(warp::reject::custom)?;
+                execute_batch(
+                    &schema,
+                    BatchRequest::Single(request.data(client)),
+                    C.web.max_batch_size,
+                )
+                .await
+                .map(GraphQLResponse::from)
+                .map_err(warp::reject::custom)
+            },
+        )
+        .with(middleware_cors.clone());
+
+    let playground = warp::path::end()
+        .and(warp::get())
+        .and(query_string)
+        .and_then(|query_string: String| async move {
+            if is_get_query(&query_string) {
+                Err(warp::reject::not_found())
+            } else {
+                Ok(())
+            }
+        })
+        .untuple_one()
+        .map(|| {
+            HttpResponse::builder()
+                .header("content-type", "text/html")
+                .body(playground_source(GraphQLPlaygroundConfig::new("/")))
+        });
+
+    let metrics = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(|| {
+            HttpResponse::builder()
+                .header("content-type", "text/plain; version=0.0.4")
+                .body(render_metrics(&latest_staleness()))
+        });
+
+    // `GET /identity/{platform}/{identity}`. See `controller::export`.
+    let export = warp::path!("identity" / String / String)
+        .and(warp::get())
+        .and(warp::any().map(move || export_pool.clone()))
+        .and(client_key.clone())
+        .and_then(
+            |platform: String,
+             identity: String,
+             pool: ConnectionPool,
+             client: ClientKey| async move {
+                identity_export(&pool, &platform, &identity, &client)
+                    .await
+                    .map_err(warp::reject::custom)
+            },
+        )
+        .with(middleware_cors.clone());
+
+    let routes = graphql_get
+        .or(playground)
+        .or(metrics)
+        .or(export)
+        .or(graphql_raw)
         .or(graphql_post)
         .recover(|err: Rejection| async move {
             if let Some(GraphQLBadRequest(err)) = err.find() {
@@ -119,7 +273,6 @@ async fn main() -> Result<()> {
             ))
         });
 
-    let address = SocketAddr::new(config::C.web.listen.parse().unwrap(), config::C.web.port);
     info!("Playground: http://{}", address);
 
     warp::serve(routes).run(address).await;
@@ -127,3 +280,8 @@ async fn main() -> Result<()> {
     println!("Shutting down...");
     Ok(())
 }
+
+/// If a GraphQL query is given in `query_string` (i.e. GraphQL over GET).
+fn is_get_query(query_string: &str) -> bool {
+    url::form_urlencoded::parse(query_string.as_bytes()).any(|(key, _)| key == "query")
+}
@@ -0,0 +1,63 @@
+//! Cache shared by every instance of RelationService, so that a horizontally-scaled deployment
+//! doesn't compute (or crawl) the same thing once per instance. See `C.cache`.
+
+mod memory;
+#[cfg(feature = "redis_cache")]
+mod redis;
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    config::{CacheBackend, C},
+    error::Error,
+};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+#[cfg(feature = "redis_cache")]
+pub use self::redis::RedisCache;
+pub use memory::MemoryCache;
+
+lazy_static! {
+    /// Backend selected by `C.cache`. See `cache`.
+    static ref CACHE: OnceCell<Box<dyn Cache>> = OnceCell::new();
+}
+
+/// A key-value store whose values expire.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    /// Value of `key`. `None` if absent or expired.
+    async fn get(&self, key: &str) -> Result<Option<String>, Error>;
+
+    /// Set `key` to `value`, which expires in `ttl`.
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), Error>;
+
+    /// Same as `set`, but only if `key` is absent (or expired), all at once.
+    /// Returns whether it's set, i.e. no one else holds `key` now.
+    async fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error>;
+
+    /// Remove `key`, if any.
+    async fn invalidate(&self, key: &str) -> Result<(), Error>;
+}
+
+/// The cache backend configured in `C.cache`, connected at the first use.
+pub async fn cache() -> Result<&'static dyn Cache, Error> {
+    let cache = CACHE
+        .get_or_try_init(|| async {
+            let backend: Box<dyn Cache> = match C.cache.backend {
+                CacheBackend::Memory => Box::new(MemoryCache::new()),
+                #[cfg(feature = "redis_cache")]
+                CacheBackend::Redis => Box::new(RedisCache::connect(&C.cache.redis_url).await?),
+                #[cfg(not(feature = "redis_cache"))]
+                CacheBackend::Redis => {
+                    return Err(Error::CacheError(
+                        "redis backend requires building with `redis_cache` feature".into(),
+                    ))
+                }
+            };
+            Ok::<_, Error>(backend)
+        })
+        .await?;
+    Ok(cache.as_ref())
+}
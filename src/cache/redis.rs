@@ -0,0 +1,84 @@
+use crate::{cache::Cache, error::Error};
+use async_trait::async_trait;
+use redis::{aio::ConnectionLike, aio::ConnectionManager, Cmd};
+use std::time::Duration;
+
+/// Every key is stored under this prefix, so the Redis can be shared with other services.
+const KEY_PREFIX: &str = "relation_server:";
+
+/// Kept in Redis, shared by every instance connected to it.
+pub struct RedisCache<C = ConnectionManager> {
+    conn: C,
+}
+
+impl RedisCache {
+    /// `url`: e.g. `redis://127.0.0.1:6379/0`. Reconnects by itself once connected.
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let client = redis::Client::open(url).map_err(cache_error)?;
+        let conn = ConnectionManager::new(client).await.map_err(cache_error)?;
+        Ok(Self { conn })
+    }
+}
+
+impl<C> RedisCache<C> {
+    pub fn with_connection(conn: C) -> Self {
+        Self { conn }
+    }
+}
+
+fn cache_error(err: redis::RedisError) -> Error {
+    Error::CacheError(err.to_string())
+}
+
+fn prefixed(key: &str) -> String {
+    format!("{}{}", KEY_PREFIX, key)
+}
+
+/// Redis takes no TTL less than a second.
+fn ttl_seconds(ttl: Duration) -> u64 {
+    ttl.as_secs().max(1)
+}
+
+impl<C: ConnectionLike + Clone + Send + Sync> RedisCache<C> {
+    async fn query<T: redis::FromRedisValue>(&self, cmd: &Cmd) -> Result<T, Error> {
+        let mut conn = self.conn.clone();
+        cmd.query_async(&mut conn).await.map_err(cache_error)
+    }
+}
+
+#[async_trait]
+impl<C: ConnectionLike + Clone + Send + Sync> Cache for RedisCache<C> {
+    async fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        self.query(redis::cmd("GET").arg(prefixed(key))).await
+    }
+
+    async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), Error> {
+        self.query(
+            redis::cmd("SET")
+                .arg(prefixed(key))
+                .arg(value)
+                .arg("EX")
+                .arg(ttl_seconds(ttl)),
+        )
+        .await
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str, ttl: Duration) -> Result<bool, Error> {
+        // `OK` if set, nil otherwise.
+        let set: Option<String> = self
+            .query(
+                redis::cmd("SET")
+                    .arg(prefixed(key))
+                    .arg(value)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_seconds(ttl)),
+            )
+            .await?;
+        Ok(set.is_some())
+    }
+
+    async fn invalidate(&self, key: &str) -> Result<(), Error> {
+        self.query(redis::cmd("DEL").arg(prefixed(key))).await
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    cache::{Cache, MemoryCache},
+    error::Error,
+};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_memory_cache() -> Result<(), Error> {
+    let cache = MemoryCache::new();
+    let ttl = Duration::from_secs(60);
+    assert_eq!(cache.get("key").await?, None);
+
+    cache.set("key", "value", ttl).await?;
+    assert_eq!(cache.get("key").await?, Some("value".into()));
+    cache.set("key", "another", ttl).await?;
+    assert_eq!(cache.get("key").await?, Some("another".into()));
+
+    // Only the first one claims it.
+    assert!(cache.set_if_absent("claim", "first", ttl).await?);
+    assert!(!cache.set_if_absent("claim", "second", ttl).await?);
+    assert_eq!(cache.get("claim").await?, Some("first".into()));
+    cache.invalidate("claim").await?;
+    assert_eq!(cache.get("claim").await?, None);
+    assert!(cache.set_if_absent("claim", "second", ttl).await?);
+
+    // Expired ones are absent.
+    cache
+        .set("short", "value", Duration::from_millis(50))
+        .await?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(cache.get("short").await?, None);
+    assert!(
+        cache
+            .set_if_absent("short", "again", Duration::from_millis(50))
+            .await?
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "redis_cache")]
+#[tokio::test]
+async fn test_redis_cache() -> Result<(), Error> {
+    use crate::cache::RedisCache;
+    use redis::cmd;
+    use redis_test::{MockCmd, MockRedisConnection};
+
+    let conn = MockRedisConnection::new(vec![
+        MockCmd::new(
+            cmd("SET")
+                .arg("relation_server:key")
+                .arg("value")
+                .arg("EX")
+                .arg(60),
+            Ok("OK"),
+        ),
+        MockCmd::new(cmd("GET").arg("relation_server:key"), Ok("value")),
+        MockCmd::new(
+            cmd("SET")
+                .arg("relation_server:claim")
+                .arg("first")
+                .arg("NX")
+                .arg("EX")
+                .arg(60),
+            Ok("OK"),
+        ),
+        MockCmd::new(
+            cmd("SET")
+                .arg("relation_server:claim")
+                .arg("second")
+                .arg("NX")
+                .arg("EX")
+                .arg(60),
+            Ok(redis::Value::Nil),
+        ),
+        MockCmd::new(cmd("DEL").arg("relation_server:claim"), Ok(1i64)),
+        MockCmd::new(
+            cmd("GET").arg("relation_server:claim"),
+            Ok(redis::Value::Nil),
+        ),
+    ]);
+    let cache = RedisCache::with_connection(conn);
+    let ttl = Duration::from_secs(60);
+
+    cache.set("key", "value", ttl).await?;
+    assert_eq!(cache.get("key").await?, Some("value".into()));
+    assert!(cache.set_if_absent("claim", "first", ttl).await?);
+    assert!(!cache.set_if_absent("claim", "second", ttl).await?);
+    cache.invalidate("claim").await?;
+    assert_eq!(cache.get("claim").await?, None);
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Restricting sensitive fields (e.g. `avatarUrl`) to authenticated clients,
+//! while the graph itself stays public.
+
+use async_graphql::{Context, Guard};
+
+use crate::{config::C, controller::rate_limit::ClientKey, error::Error};
+
+/// Put it in GraphQL request data to override authentication by API key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Authenticated(pub bool);
+
+/// Put it in GraphQL schema data to override `C.web.auth.protected_fields`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProtectedFields(pub Vec<String>);
+
+/// Is current client authenticated? By `Authenticated` if given,
+/// or else by its API key being one of `C.web.auth.api_keys`.
+pub fn is_authenticated(ctx: &Context<'_>) -> bool {
+    if let Some(authenticated) = ctx.data_opt::<Authenticated>() {
+        return authenticated.0;
+    }
+    ctx.data_opt::<ClientKey>().map_or(false, has_valid_api_key)
+}
+
+/// Is API key of `client` one of `C.web.auth.api_keys`?
+pub fn has_valid_api_key(client: &ClientKey) -> bool {
+    client
+        .api_key()
+        .map_or(false, |key| C.web.auth.api_keys.iter().any(|k| k == key))
+}
+
+/// Is `field` (`Type.field` in GraphQL schema) one of `C.web.auth.protected_fields`?
+/// Outside GraphQL, the same names protect the same data.
+pub fn is_protected_field(field: &str) -> bool {
+    C.web.auth.protected_fields.iter().any(|f| f == field)
+}
+
+/// Guard of a field which may be protected. See `C.web.auth.protected_fields`.
+pub struct FieldGuard {
+    /// `Type.field` in GraphQL schema.
+    field: &'static str,
+}
+
+impl FieldGuard {
+    pub fn new(field: &'static str) -> Self {
+        Self { field }
+    }
+
+    fn is_protected(&self, ctx: &Context<'_>) -> bool {
+        is_protected_in(ctx, self.field)
+    }
+}
+
+/// Is `field` protected in current schema? By `ProtectedFields` if given, or else by config.
+fn is_protected_in(ctx: &Context<'_>, field: &str) -> bool {
+    match ctx.data_opt::<ProtectedFields>() {
+        Some(fields) => fields.0.iter().any(|f| f == field),
+        None => is_protected_field(field),
+    }
+}
+
+/// May current client see `field`? For the same data served outside of its own GraphQL field,
+/// e.g. in a raw document.
+pub fn can_see(ctx: &Context<'_>, field: &str) -> bool {
+    !is_protected_in(ctx, field) || is_authenticated(ctx)
+}
+
+#[async_trait::async_trait]
+impl Guard for FieldGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if self.is_protected(ctx) && !is_authenticated(ctx) {
+            return Err(Error::Unauthorized(format!("{} needs an API key", self.field)).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! `GET /identity/{platform}/{identity}`: `IdentityDocument` of an identity as JSON,
+//! whatever GraphQL selection sets clients would use. Cacheable.
+
+use crate::{
+    controller::{auth, json_response, rate_limit::ClientKey, Response},
+    error::Error,
+    graph::{export::identity_document, new_db_connection, vertex::Identity, ConnectionPool},
+    upstream::Platform,
+};
+use http::{header::CACHE_CONTROL, HeaderValue, StatusCode};
+use std::str::FromStr;
+
+/// How long clients and proxies may cache a response. Seconds.
+const MAX_AGE: u32 = 300;
+
+pub async fn identity_export(
+    pool: &ConnectionPool,
+    platform: &str,
+    identity: &str,
+    client: &ClientKey,
+) -> Result<Response, Error> {
+    let platform = Platform::from_str(platform)?;
+    let db = new_db_connection().await?;
+    let record = match platform {
+        Platform::Twitter => Identity::find_twitter(&db, identity).await?,
+        _ => Identity::find_by_platform_identity(&db, &platform, identity).await?,
+    }
+    .ok_or_else(|| {
+        Error::General(
+            format!("{} {} not found", platform, identity),
+            StatusCode::NOT_FOUND,
+        )
+    })?;
+
+    let mut document = identity_document(pool, &record).await?;
+    // Same protection as the GraphQL fields.
+    let authenticated = auth::has_valid_api_key(client);
+    let hide_profile_url = !authenticated && auth::is_protected_field("IdentityRecord.profileUrl");
+    let hide_avatar_url = !authenticated && auth::is_protected_field("IdentityRecord.avatarUrl");
+    let identities = std::iter::once(&mut document.identity)
+        .chain(document.neighbors.iter_mut().map(|n| &mut n.identity));
+    for identity in identities {
+        if hide_profile_url {
+            identity.profile_url = None;
+        }
+        if hide_avatar_url {
+            identity.avatar_url = None;
+        }
+    }
+
+    let mut resp = json_response(StatusCode::OK, &document)?;
+    let cache_control = if authenticated {
+        // Not to be shared with others.
+        format!("private, max-age={}", MAX_AGE)
+    } else {
+        format!("public, max-age={}", MAX_AGE)
+    };
+    resp.headers_mut().insert(
+        CACHE_CONTROL,
+        HeaderValue::from_str(&cache_control).expect("valid header value"),
+    );
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{
+        arangopool::new_connection_pool,
+        edge::{Hold, Proof},
+        vertex::{Contract, Vertex},
+        Edge,
+    };
+    use fake::{Fake, Faker};
+    use serde_json::Value;
+
+    /// Keys of a JSON object, sorted.
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[tokio::test]
+    async fn test_identity_export() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        let neighbor = Identity::create_dummy(&db).await?;
+        let proof: Proof = Faker.fake();
+        proof.connect(&db, &wallet, &neighbor).await?;
+        let contract = Contract::create_dummy(&db).await?;
+        let hold: Hold = Faker.fake();
+        hold.connect(&db, &wallet, &*contract).await?;
+
+        let client = ClientKey::from_request(None, None, None);
+        let resp = identity_export(&pool, "ethereum", &wallet.identity, &client).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers()[CACHE_CONTROL]
+            .to_str()
+            .unwrap()
+            .starts_with("public"));
+        let body: Value = serde_json::from_str(resp.body())?;
+
+        assert_eq!(keys(&body), ["holds", "identity", "neighbors", "version"]);
+        assert_eq!(body["version"], 1);
+        let identity_keys = [
+            "added_at",
+            "avatar_url",
+            "created_at",
+            "display_name",
+            "identity",
+            "platform",
+            "profile_url",
+            "updated_at",
+            "uuid",
+        ];
+        assert_eq!(keys(&body["identity"]), identity_keys);
+        assert_eq!(body["identity"]["platform"], "ethereum");
+        assert_eq!(body["identity"]["identity"], wallet.identity.as_str());
+
+        let neighbors = body["neighbors"].as_array().unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(keys(&neighbors[0]), ["identity", "sources"]);
+        assert_eq!(keys(&neighbors[0]["identity"]), identity_keys);
+        assert_eq!(
+            neighbors[0]["identity"]["identity"],
+            neighbor.identity.as_str()
+        );
+
+        let holds = body["holds"].as_array().unwrap();
+        assert_eq!(holds.len(), 1);
+        assert_eq!(
+            keys(&holds[0]),
+            [
+                "contract",
+                "created_at",
+                "id",
+                "source",
+                "transaction",
+                "updated_at"
+            ]
+        );
+        assert_eq!(holds[0]["id"], hold.id.as_str());
+        assert_eq!(
+            keys(&holds[0]["contract"]),
+            ["address", "category", "chain", "symbol"]
+        );
+        assert_eq!(holds[0]["contract"]["address"], contract.address.as_str());
+
+        assert!(matches!(
+            identity_export(&pool, "ethereum", "0xnot-recorded", &client).await,
+            Err(Error::General(_, StatusCode::NOT_FOUND))
+        ));
+        assert!(matches!(
+            identity_export(&pool, "no-such-platform", &wallet.identity, &client).await,
+            Err(Error::EnumParseError(_))
+        ));
+
+        Ok(())
+    }
+}
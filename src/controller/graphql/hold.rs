@@ -1,17 +1,27 @@
 use crate::{
-    controller::graphql::show_pool_status,
+    config::C,
+    controller::{
+        graphql::{report_crawl_cost, show_pool_status},
+        rate_limit::check_crawl,
+    },
     error::{Error, Result},
     graph::{
         edge::{Edge, Hold, HoldRecord},
+        export::DEFAULT_PAGE_SIZE,
         vertex::{
-            contract::{Chain, ContractCategory, ContractLoadFn, ContractRecord},
+            contract::{Chain, Contract, ContractCategory, ContractLoadFn, ContractRecord},
             IdentityLoadFn, IdentityRecord,
         },
         ConnectionPool,
     },
-    upstream::{fetch_all, DataFetcher, DataSource, Target},
+    upstream::{
+        fetch_all, refetch::enqueue_refetch, the_graph::name_of_namehash, DataFetcher, DataSource,
+        Target,
+    },
+    util::{is_namehash, namehash},
 };
 use async_graphql::{Context, Object};
+use deadpool::managed::Object;
 // use dataloader::cached::Loader;
 use dataloader::non_cached::Loader;
 use strum::IntoEnumIterator;
@@ -47,11 +57,34 @@ impl HoldRecord {
         self.id.clone()
     }
 
+    /// Namehash of this name, which is its NFT_ID in ENS contracts. `null` if it's not ENS.
+    async fn namehash(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let loader: &Loader<String, Option<ContractRecord>, ContractLoadFn> =
+            ctx.data().map_err(|err| Error::GraphQLError(err.message))?;
+        match loader.load(self.id.clone()).await {
+            Some(contract) if contract.category == ContractCategory::ENS => {
+                Ok(Some(namehash(&self.id)))
+            }
+            Some(_) => Ok(None),
+            None => Err(Error::GraphQLError("contract no found.".to_string())),
+        }
+    }
+
     /// When the transaction happened. May not be provided by upstream.
     async fn created_at(&self) -> Option<i64> {
         self.created_at.map(|dt| dt.timestamp())
     }
 
+    /// When the registration of this name expires (i.e. ENS). `null` if it never expires or unknown.
+    async fn expires_at(&self) -> Option<i64> {
+        self.expires_at.map(|dt| dt.timestamp())
+    }
+
+    /// Is the registration of this name expired? If so, this ownership is stale.
+    async fn expired(&self) -> bool {
+        self.is_expired()
+    }
+
     /// When this HODL™ relation is fetched by us RelationService.
     async fn updated_at(&self) -> i64 {
         self.updated_at.timestamp()
@@ -143,7 +176,7 @@ impl HoldQuery {
         )]
         category: ContractCategory,
         #[graphql(
-            desc = "ID of this NFT. For ENS, this is the name of the token (abc.eth). For other NFT, this is the NFT_ID in contract."
+            desc = "ID of this NFT. For ENS, this is the name of the token (abc.eth) or its namehash (`0xHEX_STRING`). For other NFT, this is the NFT_ID in contract."
         )]
         id: String,
         #[graphql(
@@ -153,23 +186,94 @@ impl HoldQuery {
     ) -> Result<Option<HoldRecord>> {
         let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
         show_pool_status(pool.status());
+        let chain = chain.canonical();
         let contract_address = address
             .or(category.default_contract_address())
             .ok_or(Error::GraphQLError("Contract address is required.".into()))?;
+        // ENS names are recorded by name. Find out which one a namehash is of.
+        let id = if category == ContractCategory::ENS && is_namehash(&id) {
+            match name_of_namehash(&C.upstream.the_graph.ens, &id).await? {
+                Some(name) => name,
+                None => return Ok(None),
+            }
+        } else {
+            id
+        };
         let target = Target::NFT(chain, category, contract_address.clone(), id.clone());
         match Hold::find_by_id_chain_address_merge(pool, &id, &chain, &contract_address).await? {
             Some(hold) => {
-                if hold.is_outdated() {
-                    // Refetch in the background
-                    tokio::spawn(fetch_all(target));
+                if hold.is_outdated() && check_crawl(ctx).is_ok() {
+                    enqueue_refetch(target);
                 }
                 Ok(Some(hold))
             }
 
             None => {
-                fetch_all(target).await?;
+                check_crawl(ctx)?;
+                let cost = fetch_all(target).await?;
+                report_crawl_cost(ctx, &cost);
                 Hold::find_by_id_chain_address_merge(pool, &id, &chain, &contract_address).await
             }
         }
     }
+
+    /// Identities holding an NFT contract (or a token of it), as recorded by RelationService.
+    /// Nothing is fetched from upstreams.
+    async fn contract_holders(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "On which chain this contract is.")] chain: Chain,
+        #[graphql(desc = "What kind of this contract is.")] category: ContractCategory,
+        #[graphql(
+            desc = "Contract address. Usually `0xHEX_STRING`. For `category: \"ENS\"`, this can be omitted."
+        )]
+        address: Option<String>,
+        #[graphql(desc = "NFT_ID in contract. Holders of any token of it if omitted.")] id: Option<
+            String,
+        >,
+        #[graphql(desc = "How many holders to skip.")] offset: Option<u32>,
+        #[graphql(desc = "Page size. 100 by default, 1000 at most.")] limit: Option<u32>,
+    ) -> Result<Vec<IdentityRecord>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+        let contract_address = address
+            .or(category.default_contract_address())
+            .ok_or(Error::GraphQLError("Contract address is required.".into()))?;
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = Object::take(conn);
+
+        match Contract::find_by_chain_address(&db, &chain.canonical(), &contract_address).await? {
+            Some(contract) if contract.category == category => {
+                contract
+                    .holders(
+                        pool,
+                        id.as_deref(),
+                        offset.unwrap_or(0),
+                        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+                    )
+                    .await
+            }
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Holds created / transferred in a transaction.
+    async fn holds_by_transaction(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Transaction hash. Usually `0xHEX_STRING`.")] transaction: String,
+    ) -> Result<Vec<HoldRecord>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = Object::take(conn);
+
+        Hold::find_by_transaction(&db, &transaction).await
+    }
 }
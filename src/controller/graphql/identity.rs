@@ -1,11 +1,29 @@
-use crate::controller::graphql::show_pool_status;
+use crate::controller::auth::FieldGuard;
+use crate::controller::graphql::{report_crawl_cost, show_pool_status};
+use crate::controller::rate_limit::{check_crawl, is_read_only};
 use crate::controller::vec_string_to_vec_platform;
 use crate::error::{Error, Result};
 use crate::graph::edge::{HoldRecord, ProofRecord};
-use crate::graph::vertex::{Identity, IdentityRecord, IdentityWithSource, Vertex};
-use crate::graph::ConnectionPool;
-use crate::upstream::{fetch_all, DataSource, Platform, Target};
-use async_graphql::{Context, Object};
+use crate::graph::vertex::{
+    self, contract::ContractCategory, neighbor_cursor, CreatedAtRange, DomainGroup, Identity,
+    IdentityRecord, IdentityWithSource, NameSource, NeighborOptions, NeighborPage, NeighborSort,
+    NeighborSortKey, Path, PlatformIdentityLoadFn, SortOrder, Vertex,
+};
+use crate::graph::{
+    export::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+    prune::spawn_mark_requested,
+    score::ActiveScorer,
+    ConnectionPool, ReadConsistency,
+};
+use crate::upstream::{
+    fetch_all, fetch_all_with_sources, is_fetching,
+    refetch::{enqueue_refetch, enqueue_refetch_with_sources},
+    trigger_reverse_lookup, validate_identity, DataSource, Platform, SourceSelection, Target,
+};
+use crate::util::timestamp_to_naive;
+use aragog::DatabaseConnection;
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use dataloader::non_cached::Loader;
 use deadpool::managed::Object;
 use strum::IntoEnumIterator;
 use tracing::info;
@@ -29,6 +47,104 @@ enum DataStatus {
     Fetching,
 }
 
+/// Range of second-based unix timestamp.
+/// Either `from` or `to` can be omitted to make it open-ended.
+#[derive(InputObject, Default, Clone, Copy)]
+struct TimeRange {
+    /// Inclusive.
+    from: Option<i64>,
+    /// Inclusive.
+    to: Option<i64>,
+}
+
+impl From<TimeRange> for CreatedAtRange {
+    fn from(range: TimeRange) -> Self {
+        Self {
+            from: range.from.map(|ts| timestamp_to_naive(ts, 0)),
+            to: range.to.map(|ts| timestamp_to_naive(ts, 0)),
+        }
+    }
+}
+
+/// Record of an identity on one of requested platforms.
+#[derive(SimpleObject)]
+struct PlatformIdentity {
+    platform: Platform,
+    /// `null` if not found on this platform.
+    identity: Option<IdentityRecord>,
+}
+
+/// Max identities in a single `identitiesBatch`.
+const MAX_IDENTITIES_BATCH: usize = 100;
+
+/// An identity to look up in `identitiesBatch`.
+#[derive(InputObject, Clone)]
+struct IdentityInput {
+    platform: Platform,
+    /// Identity on `platform`.
+    identity: String,
+}
+
+/// Upstreams to ask when the query triggers a fetch.
+/// Overrides the default (all upstreams) for this single request.
+#[derive(InputObject, Default, Clone)]
+struct SourceFilter {
+    /// Ask these upstreams only.
+    only: Option<Vec<DataSource>>,
+    /// Never ask these upstreams.
+    exclude: Option<Vec<DataSource>>,
+}
+
+impl From<SourceFilter> for SourceSelection {
+    fn from(filter: SourceFilter) -> Self {
+        Self {
+            only: filter.only,
+            exclude: filter.exclude.unwrap_or_default(),
+        }
+    }
+}
+
+/// Neighbors paginated by `neighborConnection`.
+#[derive(SimpleObject)]
+struct NeighborConnection {
+    edges: Vec<NeighborEdge>,
+    page_info: PageInfo,
+}
+
+#[derive(SimpleObject)]
+struct NeighborEdge {
+    /// Give it as `after` to get those after this one.
+    cursor: String,
+    node: IdentityWithSource,
+}
+
+#[derive(SimpleObject)]
+struct PageInfo {
+    has_next_page: bool,
+    /// Cursor of the last one in `edges`. `null` if `edges` is empty.
+    end_cursor: Option<String>,
+}
+
+impl From<NeighborPage> for NeighborConnection {
+    fn from(page: NeighborPage) -> Self {
+        let edges: Vec<NeighborEdge> = page
+            .neighbors
+            .into_iter()
+            .map(|node| NeighborEdge {
+                cursor: neighbor_cursor(&node),
+                node,
+            })
+            .collect();
+        Self {
+            page_info: PageInfo {
+                has_next_page: page.has_next_page,
+                end_cursor: edges.last().map(|edge| edge.cursor.clone()),
+            },
+            edges,
+        }
+    }
+}
+
 #[Object]
 impl IdentityWithSource {
     async fn sources(&self) -> Vec<DataSource> {
@@ -38,11 +154,30 @@ impl IdentityWithSource {
     async fn identity(&self) -> IdentityRecord {
         self.identity.clone()
     }
+
+    /// At which depth this neighbor is found. `1` means connected directly.
+    async fn depth(&self) -> u16 {
+        self.depth
+    }
+}
+
+#[Object]
+impl Path {
+    /// From the starting identity to the destination, both included.
+    async fn vertices(&self) -> Vec<IdentityRecord> {
+        self.vertices.clone()
+    }
+
+    /// Proofs connecting `vertices`, in the same order.
+    async fn edges(&self) -> Vec<ProofRecord> {
+        self.edges.clone()
+    }
 }
 
 #[Object]
 impl IdentityRecord {
     /// Status for this record in RelationService.
+    /// Computed when resolving, so a crawl running concurrently is reflected.
     async fn status(&self) -> Vec<DataStatus> {
         use DataStatus::*;
         let mut current: Vec<DataStatus> = vec![];
@@ -51,8 +186,9 @@ impl IdentityRecord {
             if self.is_outdated() {
                 current.push(Outdated);
             }
-        } else {
-            current.push(Fetching); // FIXME: Seems like this is never reached.
+        }
+        if is_fetching(&Target::Identity(self.platform, self.identity.clone())) {
+            current.push(Fetching);
         }
         current
     }
@@ -84,12 +220,20 @@ impl IdentityRecord {
         self.display_name.clone()
     }
 
+    /// Where `displayName` comes from, if it's picked among many
+    /// (e.g. ENS reverse record or Lens handle of a wallet).
+    async fn display_name_source(&self) -> Option<NameSource> {
+        self.display_name_source
+    }
+
     /// URL to target identity profile page on `platform` (if any).
+    #[graphql(guard = "FieldGuard::new(\"IdentityRecord.profileUrl\")")]
     async fn profile_url(&self) -> Option<String> {
         self.profile_url.clone()
     }
 
     /// URL to avatar (if any is recorded and given by target platform).
+    #[graphql(guard = "FieldGuard::new(\"IdentityRecord.avatarUrl\")")]
     async fn avatar_url(&self) -> Option<String> {
         self.avatar_url.clone()
     }
@@ -126,28 +270,213 @@ impl IdentityRecord {
         // )]
         // upstream: Option<String>,
         #[graphql(desc = "Depth of traversal. 1 if omitted")] depth: Option<u16>,
+        #[graphql(
+            desc = "Only returns neighbors whose connecting proof is created in this time range."
+        )]
+        created_between: Option<TimeRange>,
+        #[graphql(
+            desc = "Sort neighbors by this key. Ordered by platform and identity if omitted."
+        )]
+        sort_by: Option<NeighborSortKey>,
+        #[graphql(desc = "Order of `sortBy`. `asc` if omitted.")] order: Option<SortOrder>,
+        #[graphql(
+            desc = "Read consistency. `follower` is faster but may be slightly stale. `leader` if omitted."
+        )]
+        consistency: Option<ReadConsistency>,
+        #[graphql(
+            desc = "Also traverse connections from `unknown` upstream (e.g. incomplete imports). `true` if omitted."
+        )]
+        include_unknown_sources: Option<bool>,
+        #[graphql(
+            desc = "Merge neighbors which are the same identity recorded more than once (e.g. a Twitter user by ID and by handle). `false` if omitted."
+        )]
+        collapse_equivalents: Option<bool>,
     ) -> Result<Vec<IdentityWithSource>> {
         let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
         show_pool_status(pool.status());
 
-        self.neighbors(
+        let neighbors = self
+            .neighbors(
+                pool,
+                NeighborOptions {
+                    depth: depth.unwrap_or(1),
+                    created_between: created_between.map(|range| range.into()),
+                    sort: sort_by.map(|by| NeighborSort {
+                        by,
+                        order: order.unwrap_or_default(),
+                    }),
+                    consistency: consistency.unwrap_or_default(),
+                    include_unknown_sources: include_unknown_sources.unwrap_or(true),
+                },
+            )
+            .await?;
+        if collapse_equivalents.unwrap_or(false) {
+            Ok(vertex::collapse_equivalents(self, neighbors))
+        } else {
+            Ok(neighbors)
+        }
+    }
+
+    /// Same as `neighbor`, but paginated. Ordered by how they're stored,
+    /// so pages stay stable while neighbors are updated.
+    async fn neighbor_connection(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Depth of traversal. 1 if omitted")] depth: Option<u16>,
+        #[graphql(
+            desc = "Only returns neighbors whose connecting proof is created in this time range."
+        )]
+        created_between: Option<TimeRange>,
+        #[graphql(
+            desc = "Read consistency. `follower` is faster but may be slightly stale. `leader` if omitted."
+        )]
+        consistency: Option<ReadConsistency>,
+        #[graphql(
+            desc = "Also traverse connections from `unknown` upstream (e.g. incomplete imports). `true` if omitted."
+        )]
+        include_unknown_sources: Option<bool>,
+        #[graphql(desc = "Page size. 100 by default, 1000 at most.")] first: Option<i32>,
+        #[graphql(desc = "`endCursor` of the previous page. From the first one if omitted.")]
+        after: Option<String>,
+    ) -> Result<NeighborConnection> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+        let first = match first {
+            None => DEFAULT_PAGE_SIZE,
+            Some(first) if first > 0 => (first as u32).min(MAX_PAGE_SIZE),
+            Some(first) => {
+                return Err(Error::ParamError(format!(
+                    "first: should be positive, got {}",
+                    first
+                )))
+            }
+        };
+
+        let page = self
+            .neighbor_page(
+                pool,
+                NeighborOptions {
+                    depth: depth.unwrap_or(1),
+                    created_between: created_between.map(|range| range.into()),
+                    sort: None,
+                    consistency: consistency.unwrap_or_default(),
+                    include_unknown_sources: include_unknown_sources.unwrap_or(true),
+                },
+                first as usize,
+                after.as_deref(),
+            )
+            .await?;
+        Ok(page.into())
+    }
+
+    /// Wallets (e.g. Ethereum addresses) controlled by the same person,
+    /// i.e. connected to this identity by proofs. This identity itself is not included.
+    #[graphql(name = "wallets")]
+    async fn wallets_field(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Depth of traversal. 3 if omitted")] depth: Option<u16>,
+    ) -> Result<Vec<IdentityWithSource>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        self.wallets(pool, depth.unwrap_or(3)).await
+    }
+
+    /// The preferred avatar among this identity and those connected to it,
+    /// e.g. the ENS avatar of its wallet over its Twitter one. `null` if none of them has one.
+    /// Protected as `avatarUrl` is.
+    #[graphql(guard = "FieldGuard::new(\"IdentityRecord.avatarUrl\")")]
+    async fn best_avatar(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Depth of traversal. 3 if omitted")] depth: Option<u16>,
+    ) -> Result<Option<String>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        self.best_avatar(pool, depth.unwrap_or(3)).await
+    }
+
+    /// ENS domains owned by or resolving to this identity,
+    /// with their subdomains (e.g. `blog.vitalik.eth` under `vitalik.eth`) grouped under them.
+    #[graphql(name = "ensDomains")]
+    async fn ens_domains_field(&self, ctx: &Context<'_>) -> Result<Vec<DomainGroup>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        self.ens_domains(pool).await
+    }
+
+    /// ENS name resolving to this identity. `null` if none, or it's not an `ethereum` identity.
+    async fn resolved_name(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        self.resolved_name(pool).await
+    }
+
+    /// Trust score of this identity, computed from its neighbors. Higher is more trustworthy.
+    /// See `DefaultScorer` for how it is computed by default.
+    #[graphql(name = "score")]
+    async fn score_field(&self, ctx: &Context<'_>) -> Result<f64> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+        let scorer = ctx.data_opt::<ActiveScorer>().cloned().unwrap_or_default();
+
+        self.score(pool, scorer.0.as_ref()).await
+    }
+
+    /// Is it in a small cluster of brand-new identities proving each other
+    /// (e.g. mutually or in a circle) to look well-connected? Its `score` is lowered if so.
+    async fn suspicious(&self, ctx: &Context<'_>) -> Result<bool> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        self.is_suspicious(pool).await
+    }
+
+    async fn neighbor_with_traversal(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Depth of traversal. 1 if omitted")] depth: Option<u16>,
+        #[graphql(
+            desc = "Also traverse connections from `unknown` upstream (e.g. incomplete imports). `true` if omitted."
+        )]
+        include_unknown_sources: Option<bool>,
+    ) -> Result<Vec<ProofRecord>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+        self.neighbors_with_traversal(
             pool,
             depth.unwrap_or(1),
-            // upstream.map(|u| DataSource::from_str(&u).unwrap_or(DataSource::Unknown))
             None,
+            include_unknown_sources.unwrap_or(true),
         )
         .await
     }
 
-    async fn neighbor_with_traversal(
+    /// How this identity is connected to another one: the shortest path of proofs between them.
+    /// `null` if they're not connected within `depth`, or the other one is not found.
+    async fn path_to(
         &self,
         ctx: &Context<'_>,
-        #[graphql(desc = "Depth of traversal. 1 if omitted")] depth: Option<u16>,
-    ) -> Result<Vec<ProofRecord>> {
+        #[graphql(desc = "Platform of the other identity")] platform: Platform,
+        #[graphql(desc = "The other identity")] identity: String,
+        #[graphql(desc = "Longest path to look for. 3 if omitted")] depth: Option<u16>,
+    ) -> Result<Option<Path>> {
         let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
         show_pool_status(pool.status());
-        self.neighbors_with_traversal(pool, depth.unwrap_or(1), None)
+
+        let conn = pool
+            .get()
             .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = Object::take(conn);
+        match find_identity(&db, platform, &identity).await? {
+            None => Ok(None),
+            Some(to) => self.connection_path(pool, &to, depth.unwrap_or(3)).await,
+        }
     }
 
     /// there's only `platform: lens` identity `ownedBy` is not null
@@ -163,10 +492,26 @@ impl IdentityRecord {
 
     /// NFTs owned by this identity.
     /// For now, there's only `platform: ethereum` identity has NFTs.
-    async fn nft(&self, ctx: &Context<'_>) -> Result<Vec<HoldRecord>> {
+    async fn nft(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            desc = "Filter NFTs by categories. See `availableNftCategoris` for all values supported by RelationService."
+        )]
+        category: Option<Vec<ContractCategory>>,
+        #[graphql(
+            desc = "Only NFTs reported by this upstream. See `availableUpstreams` for all values. All upstreams if omitted."
+        )]
+        source: Option<DataSource>,
+        #[graphql(
+            desc = "Whether names with their registration expired (i.e. ENS) are included. `true` if omitted."
+        )]
+        include_expired: Option<bool>,
+    ) -> Result<Vec<HoldRecord>> {
         let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
         show_pool_status(pool.status());
-        self.nfts(pool).await
+        self.nfts(pool, category, source, include_expired.unwrap_or(true))
+            .await
     }
 }
 
@@ -190,8 +535,14 @@ impl IdentityQuery {
         &self,
         ctx: &Context<'_>,
         #[graphql(desc = "Platform to query")] platform: String,
-        #[graphql(desc = "Identity on target Platform")] identity: String,
+        #[graphql(
+            desc = "Identity on target Platform. For `twitter`, either numeric user ID or handle."
+        )]
+        identity: String,
+        #[graphql(desc = "Upstreams to ask if a fetch is needed. All of them by default.")]
+        sources: Option<SourceFilter>,
     ) -> Result<Option<IdentityRecord>> {
+        validate_identity(&identity)?;
         // let db: &DatabaseConnection = ctx.data().map_err(|err| Error::GraphQLError(err.message))?;
         let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
         show_pool_status(pool.status());
@@ -203,53 +554,193 @@ impl IdentityQuery {
         let db = Object::take(conn);
 
         let platform: Platform = platform.parse()?;
-        let target = Target::Identity(platform, identity.clone());
+        let target = Target::new_identity(platform, identity.clone())?;
+        let sources: SourceSelection = sources.map(Into::into).unwrap_or_default();
         // FIXME: Still kinda dirty. Should be in an background queue/worker-like shape.
-        match Identity::find_by_platform_identity(&db, &platform, &identity).await? {
+        match find_identity(&db, platform, &identity).await? {
             None => {
-                let _ = fetch_all(target).await; // TODO: print error message here (but not break the return value)
-                Ok(Identity::find_by_platform_identity(&db, &platform, &identity).await?)
+                check_crawl(ctx)?;
+                // TODO: print error message here (but not break the return value)
+                if let Ok(cost) = fetch_all_with_sources(target, sources).await {
+                    report_crawl_cost(ctx, &cost);
+                }
+                let fetched = find_identity(&db, platform, &identity).await?;
+                if let Some(fetched) = fetched.as_ref() {
+                    mark_requested(ctx, pool, fetched);
+                }
+                Ok(fetched)
             }
             Some(found) => {
-                if found.is_outdated() {
-                    info!(
-                        "Identity: {}/{} is outdated. Refetching...",
-                        platform, identity
-                    );
-                    tokio::spawn(fetch_all(target)); // Fetch in the background
+                mark_requested(ctx, pool, &found);
+                if found.is_outdated() && check_crawl(ctx).is_ok() {
+                    info!("{} is outdated. Refetching...", target);
+                    enqueue_refetch_with_sources(target, sources);
+                } else if platform == Platform::Ethereum
+                    && found.display_name.as_deref().map_or(true, str::is_empty)
+                    && check_crawl(ctx).is_ok()
+                {
+                    // Names fill in over time, as reverse records may be set after a wallet is found.
+                    trigger_reverse_lookup(&found.identity);
                 }
                 Ok(Some(found))
             }
         }
     }
 
+    /// Query an `identity` on many `platforms`. Ordered as `platforms`; those not found are omitted.
+    /// See `identitiesByPlatform` to tell which platform each of them is on.
     async fn identities(
         &self,
         ctx: &Context<'_>,
         #[graphql(desc = "Platform array to query")] platforms: Vec<String>,
         #[graphql(desc = "Identity on target Platform")] identity: String,
     ) -> Result<Vec<IdentityRecord>> {
-        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::GraphQLError(err.message))?;
+        let platform_list = vec_string_to_vec_platform(platforms)?;
+        find_on_platforms(ctx, &platform_list, &identity).await
+    }
+
+    /// Look up many identities at once, from what's in the database only (nothing is fetched).
+    /// One result for each of `targets`, in the same order. `null` if not found.
+    async fn identities_batch(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Identities to look up. 100 at most.")] targets: Vec<IdentityInput>,
+    ) -> Result<Vec<Option<IdentityRecord>>> {
+        if targets.len() > MAX_IDENTITIES_BATCH {
+            return Err(Error::ParamError(format!(
+                "Too many identities: {} (max {})",
+                targets.len(),
+                MAX_IDENTITIES_BATCH
+            )));
+        }
+        for target in targets.iter() {
+            validate_identity(&target.identity)?;
+        }
+        let loader: &Loader<(Platform, String), Option<IdentityRecord>, PlatformIdentityLoadFn> =
+            ctx.data().map_err(|err| Error::GraphQLError(err.message))?;
+        let keys: Vec<(Platform, String)> = targets
+            .into_iter()
+            .map(|target| (target.platform, target.identity))
+            .collect();
+        let found = loader.load_many(keys.clone()).await;
+        Ok(keys
+            .iter()
+            .map(|key| found.get(key).cloned().flatten())
+            .collect())
+    }
+
+    /// Query an `identity` on many `platforms`. One result for each of `platforms`, in the same order.
+    async fn identities_by_platform(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Platform array to query")] platforms: Vec<Platform>,
+        #[graphql(desc = "Identity on target Platform")] identity: String,
+    ) -> Result<Vec<PlatformIdentity>> {
+        let mut found = find_on_platforms(ctx, &platforms, &identity).await?;
+        Ok(platforms
+            .into_iter()
+            .map(|platform| PlatformIdentity {
+                platform,
+                identity: found
+                    .iter()
+                    .position(|record| record.platform == platform)
+                    .map(|index| found.remove(index)),
+            })
+            .collect())
+    }
+}
+
+#[derive(Default)]
+pub struct IdentityMutation;
+
+#[Object]
+impl IdentityMutation {
+    /// Fetch an identity (and those connected to it) from upstreams right now, however fresh it is,
+    /// then return the updated record. `null` if no upstream knows it.
+    /// If it is being fetched already, that fetch is not waited for.
+    async fn refetch(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            desc = "Platform to query. See `availablePlatforms` for all values supported by RelationService."
+        )]
+        platform: String,
+        #[graphql(desc = "Identity on target Platform")] identity: String,
+    ) -> Result<Option<IdentityRecord>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
         show_pool_status(pool.status());
+        let platform: Platform = platform.parse()?;
+        let target = Target::new_identity(platform, identity.clone())?;
+        check_crawl(ctx)?;
 
-        let platform_list = vec_string_to_vec_platform(platforms)?;
-        let record: Vec<IdentityRecord> =
-            Identity::find_by_platforms_identity(&pool, &platform_list, identity.as_str()).await?;
-        if record.len() == 0 {
-            for platform in &platform_list {
-                let target = Target::Identity(platform.clone(), identity.clone());
-                fetch_all(target).await?;
-            }
-            Identity::find_by_platforms_identity(&pool, &platform_list, identity.as_str()).await
-        } else {
-            record.iter().filter(|r| r.is_outdated()).for_each(|r| {
-                // Refetch in the background
-                tokio::spawn(fetch_all(Target::Identity(
-                    r.platform.clone(),
-                    r.identity.clone(),
-                )));
-            });
-            Ok(record)
+        info!("{} is asked to be refetched.", target);
+        let cost = fetch_all(target).await?;
+        report_crawl_cost(ctx, &cost);
+
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = Object::take(conn);
+        let fetched = find_identity(&db, platform, &identity).await?;
+        if let Some(fetched) = fetched.as_ref() {
+            mark_requested(ctx, pool, fetched);
         }
+        Ok(fetched)
+    }
+}
+
+/// `spawn_mark_requested`, unless read-only: nothing is written into DB then, not even on reads.
+fn mark_requested(ctx: &Context<'_>, pool: &ConnectionPool, record: &IdentityRecord) {
+    if !is_read_only(ctx) {
+        spawn_mark_requested(pool, record);
+    }
+}
+
+/// Same as `Identity::find_by_platform_identity`, but a Twitter identity can be given
+/// as either numeric ID or handle. See `Identity::find_twitter`.
+async fn find_identity(
+    db: &DatabaseConnection,
+    platform: Platform,
+    identity: &str,
+) -> Result<Option<IdentityRecord>> {
+    match platform {
+        Platform::Twitter => Identity::find_twitter(db, identity).await,
+        _ => Identity::find_by_platform_identity(db, &platform, identity).await,
+    }
+}
+
+/// Find `identity` on `platforms`, fetching it if none is found. Ordered as `platforms`.
+async fn find_on_platforms(
+    ctx: &Context<'_>,
+    platforms: &Vec<Platform>,
+    identity: &str,
+) -> Result<Vec<IdentityRecord>> {
+    validate_identity(identity)?;
+    let pool: &ConnectionPool = ctx.data().map_err(|err| Error::GraphQLError(err.message))?;
+    show_pool_status(pool.status());
+
+    let record: Vec<IdentityRecord> =
+        Identity::find_by_platforms_identity(&pool, platforms, identity).await?;
+    if record.len() == 0 {
+        check_crawl(ctx)?;
+        for platform in platforms {
+            let target = Target::new_identity(platform.clone(), identity)?;
+            let cost = fetch_all(target).await?;
+            report_crawl_cost(ctx, &cost);
+        }
+        let fetched = Identity::find_by_platforms_identity(&pool, platforms, identity).await?;
+        fetched.iter().for_each(|r| mark_requested(ctx, pool, r));
+        Ok(fetched)
+    } else {
+        record.iter().for_each(|r| mark_requested(ctx, pool, r));
+        record
+            .iter()
+            .filter(|r| r.is_outdated())
+            .filter(|_| check_crawl(ctx).is_ok())
+            .for_each(|r| {
+                enqueue_refetch(Target::Identity(r.platform.clone(), r.identity.clone()));
+            });
+        Ok(record)
     }
 }
@@ -1,19 +1,228 @@
 mod hold;
 mod identity;
 mod proof;
-use self::{hold::HoldQuery, identity::IdentityQuery, proof::ProofQuery};
-use async_graphql::{MergedObject, Object};
+mod resolve;
+#[cfg(test)]
+mod tests;
+
+use self::{
+    hold::HoldQuery,
+    identity::{IdentityMutation, IdentityQuery},
+    proof::ProofQuery,
+    resolve::ResolveQuery,
+};
+use crate::controller::auth::can_see;
+use crate::error::{Error, Result};
+use crate::graph::{
+    consistency::{latest_display_name_report, DisplayNameMismatch},
+    edge::Proof,
+    export::{
+        edges_added_since, identities_added_since, EdgePage, IdentityPage, DEFAULT_PAGE_SIZE,
+    },
+    staleness::{latest_staleness, Staleness},
+    stats::{cached_stats, Stats},
+    ConnectionPool,
+};
+use crate::upstream::{
+    cost::CrawlCost,
+    liveness::{source_liveness, SourceLiveness},
+};
+use aragog::Record;
+use async_graphql::parser::{
+    parse_query,
+    types::{DocumentOperations, OperationDefinition, OperationType},
+};
+use async_graphql::{
+    BatchRequest, BatchResponse, Context, EmptySubscription, MergedObject, Object, Request,
+    Response, Schema, SimpleObject, Variables,
+};
+use http::StatusCode;
+use std::sync::{Arc, Mutex};
 use tracing::debug;
 
 const API_VERSION: &str = "0.1";
 
 /// Base struct of GraphQL query request.
 #[derive(MergedObject, Default)]
-pub struct Query(GeneralQuery, IdentityQuery, ProofQuery, HoldQuery);
+pub struct Query(
+    GeneralQuery,
+    IdentityQuery,
+    ProofQuery,
+    HoldQuery,
+    ResolveQuery,
+);
+
+/// Base struct of GraphQL mutation request.
+#[derive(MergedObject, Default)]
+pub struct Mutation(IdentityMutation);
 
 #[derive(Default)]
 pub struct GeneralQuery;
 
+/// Which build of RelationService is serving.
+#[derive(SimpleObject)]
+pub struct BuildInfo {
+    /// Crate version.
+    pub version: String,
+    /// Git commit this build is based on.
+    pub revision: String,
+    /// When this build is made. Second-based unix timestamp.
+    pub built_at: String,
+}
+
+impl Default for BuildInfo {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            revision: option_env!("RELATION_SERVER_REVISION")
+                .unwrap_or("UNKNOWN")
+                .to_string(),
+            built_at: option_env!("RELATION_SERVER_BUILT_AT")
+                .unwrap_or("UNKNOWN")
+                .to_string(),
+        }
+    }
+}
+
+/// Execute a single or batched (JSON array of operations) GraphQL request.
+/// All operations in a batch share the same schema data (thus the same DataLoaders).
+/// Batches larger than `max_batch_size` are rejected as a whole.
+pub async fn execute_batch(
+    schema: &Schema<Query, Mutation, EmptySubscription>,
+    request: BatchRequest,
+    max_batch_size: usize,
+) -> Result<BatchResponse> {
+    if let BatchRequest::Batch(requests) = &request {
+        if requests.len() > max_batch_size {
+            return Err(Error::ParamError(format!(
+                "Too many operations in a batch: {} (max {})",
+                requests.len(),
+                max_batch_size
+            )));
+        }
+    }
+    // Each operation has its own cost, reported in its own response.
+    let (request, costs) = match request {
+        BatchRequest::Single(request) => {
+            let costs = CrawlCosts::default();
+            (
+                BatchRequest::Single(request.data(costs.clone())),
+                vec![costs],
+            )
+        }
+        BatchRequest::Batch(requests) => {
+            let costs: Vec<CrawlCosts> = requests.iter().map(|_| CrawlCosts::default()).collect();
+            let requests = requests
+                .into_iter()
+                .zip(costs.iter())
+                .map(|(request, costs)| request.data(costs.clone()))
+                .collect();
+            (BatchRequest::Batch(requests), costs)
+        }
+    };
+    let mut response = schema.execute_batch(request).await;
+    match &mut response {
+        BatchResponse::Single(resp) => attach_crawl_cost(resp, &costs[0])?,
+        BatchResponse::Batch(resps) => {
+            for (resp, costs) in resps.iter_mut().zip(costs.iter()) {
+                attach_crawl_cost(resp, costs)?;
+            }
+        }
+    }
+    Ok(response)
+}
+
+/// Cost of the crawls triggered by a GraphQL operation. See `report_crawl_cost`.
+#[derive(Clone, Default)]
+pub struct CrawlCosts(Arc<Mutex<CrawlCost>>);
+
+/// Add up the cost of a crawl triggered by current operation,
+/// which is returned in `crawlCost` of its response extensions.
+pub fn report_crawl_cost(ctx: &Context<'_>, cost: &CrawlCost) {
+    if let Some(costs) = ctx.data_opt::<CrawlCosts>() {
+        costs.0.lock().unwrap().merge(cost);
+    }
+}
+
+fn attach_crawl_cost(resp: &mut Response, costs: &CrawlCosts) -> Result<()> {
+    let cost = costs.0.lock().unwrap().clone();
+    if cost.is_empty() {
+        return Ok(());
+    }
+    let value = async_graphql::Value::from_json(serde_json::to_value(cost)?)?;
+    resp.extensions.insert("crawlCost".to_string(), value);
+    Ok(())
+}
+
+/// GraphQL over GET: `?query=...&variables=<JSON>&operationName=...`.
+/// GET is cacheable and can be sent cross-site, so mutations are rejected (`405`) as
+/// GraphQL over HTTP requires. They have to be POSTed.
+pub fn parse_get_request(query_string: &str) -> Result<Request> {
+    let request = parse_params(query_string, None)?;
+    if is_mutation(&request) {
+        return Err(Error::General(
+            "Mutations are only accepted over POST".into(),
+            StatusCode::METHOD_NOT_ALLOWED,
+        ));
+    }
+    Ok(request)
+}
+
+/// Would `request` run a mutation? Without an `operationName` to pick one of several
+/// operations, any mutation among them counts. Unparsable queries are left for execution to report.
+fn is_mutation(request: &Request) -> bool {
+    let document = match parse_query(&request.query) {
+        Ok(document) => document,
+        Err(_) => return false,
+    };
+    let is_mutation = |operation: &OperationDefinition| operation.ty == OperationType::Mutation;
+    match (&document.operations, request.operation_name.as_deref()) {
+        (DocumentOperations::Single(operation), _) => is_mutation(&operation.node),
+        (DocumentOperations::Multiple(operations), Some(name)) => operations
+            .iter()
+            .any(|(n, operation)| n.as_str() == name && is_mutation(&operation.node)),
+        (DocumentOperations::Multiple(operations), None) => operations
+            .values()
+            .any(|operation| is_mutation(&operation.node)),
+    }
+}
+
+/// GraphQL over POST with `Content-Type: application/graphql`: the whole body is the query.
+/// `variables` and `operationName` can still be given in query string.
+pub fn parse_graphql_body(body: &[u8], query_string: &str) -> Result<Request> {
+    let query = std::str::from_utf8(body)
+        .map_err(|err| Error::ParamError(format!("Query is not UTF-8: {}", err)))?;
+    parse_params(query_string, Some(query.to_string()))
+}
+
+fn parse_params(query_string: &str, query: Option<String>) -> Result<Request> {
+    let mut query = query;
+    let mut variables: Option<String> = None;
+    let mut operation_name: Option<String> = None;
+    for (key, value) in url::form_urlencoded::parse(query_string.as_bytes()) {
+        match key.as_ref() {
+            "query" if query.is_none() => query = Some(value.into_owned()),
+            "variables" => variables = Some(value.into_owned()),
+            "operationName" => operation_name = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    let query = query
+        .filter(|query| !query.trim().is_empty())
+        .ok_or_else(|| Error::ParamMissing("query".into()))?;
+    let mut request = Request::new(query);
+    if let Some(variables) = variables.filter(|v| !v.is_empty()) {
+        let variables: serde_json::Value = serde_json::from_str(&variables)
+            .map_err(|err| Error::ParamError(format!("Variables is not a JSON: {}", err)))?;
+        request = request.variables(Variables::from_json(variables));
+    }
+    if let Some(operation_name) = operation_name.filter(|name| !name.is_empty()) {
+        request = request.operation_name(operation_name);
+    }
+    Ok(request)
+}
+
 pub fn show_pool_status(status: deadpool::Status) {
     debug!(
         "Connection pool status: max_size={}, size={}, available={}",
@@ -30,4 +239,86 @@ impl GeneralQuery {
     async fn api_version(&self) -> &'static str {
         API_VERSION
     }
+
+    /// Build info of this server.
+    async fn version(&self) -> BuildInfo {
+        BuildInfo::default()
+    }
+
+    /// How many records of each entity type are outdated, as of the latest sample
+    /// (see `metrics.staleness_interval` in config). Useful to tell if refetching falls behind.
+    async fn staleness_report(&self) -> Vec<Staleness> {
+        latest_staleness()
+    }
+
+    /// Totals of the dataset: identities per platform, edges per source and contracts per chain.
+    /// Cached for a while (see `metrics.stats_ttl` in config), so it may lag behind a little.
+    async fn stats(&self, ctx: &Context<'_>) -> Result<Stats> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        cached_stats(pool).await
+    }
+
+    /// Wallets whose `displayName` disagrees with their ENS reverse record, with the expected one.
+    /// As of the latest background check (see `db.display_name_fix_interval` in config), empty if it's disabled.
+    async fn display_name_report(&self) -> Vec<DisplayNameMismatch> {
+        latest_display_name_report()
+    }
+
+    /// Identities added after `since` (second-based unix timestamp), oldest first.
+    /// For incremental syncs: keep giving `nextCursor` as `after` until it's `null`.
+    async fn identities_added_since(
+        &self,
+        ctx: &Context<'_>,
+        since: i64,
+        after: Option<String>,
+        #[graphql(desc = "Page size. 100 by default, 1000 at most.")] limit: Option<u32>,
+    ) -> Result<IdentityPage> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        identities_added_since(
+            pool,
+            since,
+            after.as_deref(),
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await
+    }
+
+    /// Edges (`Proof`, `Hold` and `Resolve`) updated after `since` (second-based unix timestamp), oldest first.
+    /// For incremental syncs: keep giving `nextCursor` as `after` until it's `null`.
+    async fn edges_added_since(
+        &self,
+        ctx: &Context<'_>,
+        since: i64,
+        after: Option<String>,
+        #[graphql(desc = "Page size. 100 by default, 1000 at most.")] limit: Option<u32>,
+    ) -> Result<EdgePage> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        let mut page = edges_added_since(
+            pool,
+            since,
+            after.as_deref(),
+            limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        )
+        .await?;
+        // Same protection as `ProofRecord.recordId`.
+        if !can_see(ctx, "ProofRecord.recordId") {
+            let proofs = format!("{}/", Proof::COLLECTION_NAME);
+            for edge in page.items.iter_mut().filter(|e| e.id.starts_with(&proofs)) {
+                edge.hide_field("record_id");
+            }
+        }
+        Ok(page)
+    }
+
+    /// When each upstream last produced anything, to spot those silently broken.
+    /// See `upstream.liveness` in config.
+    async fn source_liveness(&self) -> Vec<SourceLiveness> {
+        source_liveness()
+    }
 }
@@ -1,7 +1,9 @@
 use async_graphql::{Context, Object};
 use uuid::Uuid;
 
+use crate::controller::auth::FieldGuard;
 use crate::controller::graphql::show_pool_status;
+use crate::controller::rate_limit::check_crawl;
 use crate::error::{Error, Result};
 use crate::graph::edge::proof::ProofRecord;
 use crate::graph::edge::Proof;
@@ -27,10 +29,16 @@ impl ProofRecord {
     }
 
     /// ID of this connection in upstream platform to locate (if any).
+    #[graphql(guard = "FieldGuard::new(\"ProofRecord.recordId\")")]
     async fn record_id(&self) -> Option<String> {
         self.record_id.clone()
     }
 
+    /// URL to the evidence of this connection provided by upstream (if any).
+    async fn proof_url(&self) -> Option<String> {
+        self.proof_url.clone()
+    }
+
     /// When this connection is recorded in upstream platform (if platform gives such data).
     async fn created_at(&self) -> Option<i64> {
         self.created_at.map(|ca| ca.timestamp())
@@ -99,7 +107,8 @@ impl ProofQuery {
     }
 
     /// Prefetch proofs which are prefetchable, e.g. SybilList.
-    async fn prefetch_proof(&self) -> Result<String> {
+    async fn prefetch_proof(&self, ctx: &Context<'_>) -> Result<String> {
+        check_crawl(ctx)?;
         tokio::spawn(async move {
             let _ = crate::upstream::prefetch().await;
         });
@@ -0,0 +1,150 @@
+use crate::{
+    controller::{
+        graphql::{report_crawl_cost, show_pool_status},
+        rate_limit::check_crawl,
+    },
+    error::{Error, Result},
+    graph::{
+        edge::{resolve::DomainNameSystem, Resolve, ResolveWithIdentity},
+        vertex::{
+            contract::{Chain, ContractCategory},
+            IdentityRecord,
+        },
+        ConnectionPool,
+    },
+    upstream::{fetch_all, DataSource, Platform, Target},
+};
+use async_graphql::{Context, InputObject, Object, SimpleObject};
+use futures::future::join_all;
+
+/// Max `(name, system)` pairs in a single `resolveBatch`.
+const MAX_RESOLVE_BATCH: usize = 50;
+
+/// A domain name to resolve.
+#[derive(InputObject, Clone)]
+struct DomainName {
+    /// e.g. `vitalik.eth`
+    name: String,
+    system: DomainNameSystem,
+}
+
+/// Resolve result of a domain name.
+#[derive(SimpleObject)]
+struct ResolveResult {
+    name: String,
+    system: DomainNameSystem,
+    /// Upstream which provides this resolve info. `null` if unresolved.
+    source: Option<DataSource>,
+    /// Identity this name resolves to. `null` if unresolved.
+    resolved: Option<IdentityRecord>,
+}
+
+/// How a name in given system can be fetched from upstreams.
+fn resolve_target(name: &str, system: DomainNameSystem) -> Option<Target> {
+    match system {
+        DomainNameSystem::ENS => Some(Target::NFT(
+            Chain::Ethereum,
+            ContractCategory::ENS,
+            ContractCategory::ENS.default_contract_address().unwrap(),
+            name.to_string(),
+        )),
+        DomainNameSystem::DotBit => Some(Target::Identity(Platform::Dotbit, name.to_string())),
+        DomainNameSystem::SpaceID => Some(Target::Identity(Platform::SpaceId, name.to_string())),
+        DomainNameSystem::Unknown => None,
+    }
+}
+
+#[derive(Default)]
+pub struct ResolveQuery;
+
+#[Object]
+impl ResolveQuery {
+    /// Resolve a domain name into an identity.
+    async fn resolve(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Domain name to resolve. e.g. `vitalik.eth`")] name: String,
+        #[graphql(desc = "Domain name system of this name.")] system: DomainNameSystem,
+    ) -> Result<Option<IdentityRecord>> {
+        let mut results = resolve_names(ctx, vec![DomainName { name, system }]).await?;
+        Ok(results.pop().and_then(|r| r.resolved))
+    }
+
+    /// Resolve many domain names at once.
+    /// Results are in the same order as `names`.
+    async fn resolve_batch(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Domain names to resolve. 50 at most.")] names: Vec<DomainName>,
+    ) -> Result<Vec<ResolveResult>> {
+        resolve_names(ctx, names).await
+    }
+
+    /// Who owns an ENS name, i.e. holds it in the ENS contract.
+    /// It may differ from the identity it resolves to. Only known owners are returned.
+    async fn ens_owner(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "ENS name. e.g. `vitalik.eth`")] name: String,
+    ) -> Result<Option<IdentityRecord>> {
+        let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+        show_pool_status(pool.status());
+
+        Resolve::find_ens_owner(pool, &name).await
+    }
+}
+
+async fn resolve_names(ctx: &Context<'_>, names: Vec<DomainName>) -> Result<Vec<ResolveResult>> {
+    if names.len() > MAX_RESOLVE_BATCH {
+        return Err(Error::ParamError(format!(
+            "Too many names: {} (max {})",
+            names.len(),
+            MAX_RESOLVE_BATCH
+        )));
+    }
+    let pool: &ConnectionPool = ctx.data().map_err(|err| Error::PoolError(err.message))?;
+    show_pool_status(pool.status());
+
+    let pairs: Vec<(String, DomainNameSystem)> = names
+        .iter()
+        .map(|n| Ok((n.system.normalize(&n.name)?, n.system)))
+        .collect::<Result<_, Error>>()?;
+    let mut found = Resolve::find_by_names_systems(pool, &pairs).await?;
+
+    // Fetch all missing names from upstreams concurrently, then look them up again.
+    let missing: Vec<Target> = pairs
+        .iter()
+        .filter(|pair| lookup(&found, pair).is_none())
+        .filter_map(|(name, system)| resolve_target(name, *system))
+        .collect();
+    if !missing.is_empty() {
+        check_crawl(ctx)?;
+        let costs = join_all(missing.into_iter().map(fetch_all)).await;
+        for cost in costs.into_iter().flatten() {
+            report_crawl_cost(ctx, &cost);
+        }
+        found = Resolve::find_by_names_systems(pool, &pairs).await?;
+    }
+
+    Ok(pairs
+        .into_iter()
+        .map(|pair| {
+            let resolved = lookup(&found, &pair);
+            ResolveResult {
+                source: resolved.map(|r| r.resolve.source),
+                resolved: resolved.map(|r| r.identity.clone()),
+                name: pair.0,
+                system: pair.1,
+            }
+        })
+        .collect())
+}
+
+fn lookup<'a>(
+    found: &'a [ResolveWithIdentity],
+    (name, system): &(String, DomainNameSystem),
+) -> Option<&'a ResolveWithIdentity> {
+    found
+        .iter()
+        .find(|r| &r.resolve.name == name && &r.resolve.system == system)
+}
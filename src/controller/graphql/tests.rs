@@ -0,0 +1,802 @@
+use async_graphql::{BatchRequest, EmptySubscription, Schema};
+use serde_json::{json, Value};
+
+use dataloader::non_cached::Loader;
+use fake::{Fake, Faker};
+use uuid::Uuid;
+
+use crate::{
+    controller::{
+        auth::{Authenticated, ProtectedFields},
+        graphql::{execute_batch, parse_get_request, parse_graphql_body, Mutation, Query},
+        rate_limit::ReadOnly,
+    },
+    error::Error,
+    graph::{
+        arangopool::new_connection_pool,
+        edge::{resolve::DomainNameSystem, Hold, Proof, Resolve},
+        new_db_connection,
+        vertex::{contract::ContractCategory, Contract, Identity, PlatformIdentityLoadFn, Vertex},
+        Edge,
+    },
+    upstream::{
+        is_fetching, reverse_lookup_triggered, DataFetcher, DataSource, InFlight, Platform, Target,
+    },
+    util::naive_now,
+};
+
+#[tokio::test]
+async fn test_version() {
+    let schema = Schema::new(Query::default(), Mutation::default(), EmptySubscription);
+    let resp = schema
+        .execute("{ version { version revision builtAt } }")
+        .await;
+    assert!(resp.errors.is_empty());
+
+    let data = resp.data.into_json().unwrap();
+    let version = data["version"]["version"].as_str().unwrap();
+    assert!(!version.is_empty());
+    assert_eq!(version, env!("CARGO_PKG_VERSION"));
+    assert!(!data["version"]["revision"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_batch() -> Result<(), Error> {
+    let schema = Schema::new(Query::default(), Mutation::default(), EmptySubscription);
+    let request: BatchRequest =
+        serde_json::from_str(r#"[{"query": "{ ping }"}, {"query": "{ apiVersion }"}]"#)?;
+
+    let resp = execute_batch(&schema, request, 10).await?;
+    let results = serde_json::to_value(&resp)?;
+    let results = results.as_array().unwrap();
+    assert_eq!(2, results.len());
+    assert_eq!(results[0]["data"]["ping"], "Pong!");
+    assert_eq!(results[1]["data"]["apiVersion"], "0.1");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_too_large() -> Result<(), Error> {
+    let schema = Schema::new(Query::default(), Mutation::default(), EmptySubscription);
+    let request: BatchRequest = serde_json::from_str(
+        r#"[{"query": "{ ping }"}, {"query": "{ ping }"}, {"query": "{ ping }"}]"#,
+    )?;
+
+    let result = execute_batch(&schema, request, 2).await;
+    assert!(matches!(result, Err(Error::ParamError(_))));
+
+    Ok(())
+}
+
+async fn status(schema: &Schema<Query, Mutation, EmptySubscription>, query: &str) -> Value {
+    let resp = schema.execute(query).await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    resp.data.into_json().unwrap()["identity"]["status"].clone()
+}
+
+#[tokio::test]
+async fn test_identity_status() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let identity = Identity::create_dummy(&db).await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let query = format!(
+        r#"{{ identity(platform: "{}", identity: "{}") {{ status }} }}"#,
+        identity.platform, identity.identity
+    );
+    assert_eq!(status(&schema, &query).await, json!(["cached"]));
+    {
+        let _crawling = InFlight::enter(&Target::Identity(
+            identity.platform,
+            identity.identity.clone(),
+        ));
+        assert_eq!(status(&schema, &query).await, json!(["cached", "fetching"]));
+    }
+    assert_eq!(status(&schema, &query).await, json!(["cached"]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_nameless_wallet_triggers_reverse_lookup() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let wallet = |display_name: Option<String>| Identity {
+        platform: Platform::Ethereum,
+        identity: format!("0x{}", Uuid::new_v4().simple()),
+        display_name,
+        ..Faker.fake()
+    };
+    let nameless = wallet(None).create_or_update(&db).await?;
+    let named = wallet(Some("named.eth".into()))
+        .create_or_update(&db)
+        .await?;
+
+    for found in [&nameless, &named] {
+        let resp = schema
+            .execute(format!(
+                r#"{{ identity(platform: "ethereum", identity: "{}") {{ uuid }} }}"#,
+                found.identity
+            ))
+            .await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    }
+    assert!(reverse_lookup_triggered(&nameless.identity));
+    assert!(!reverse_lookup_triggered(&named.identity));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_only_cold_target() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .data(ReadOnly(true))
+        .finish();
+    let cold = format!("read_only_{}", Uuid::new_v4().simple());
+    let target = Target::Identity(Platform::Twitter, cold.clone());
+
+    let resp = schema
+        .execute(format!(
+            r#"{{ identity(platform: "twitter", identity: "{}") {{ uuid }} }}"#,
+            cold
+        ))
+        .await;
+    assert_eq!(1, resp.errors.len());
+    assert!(resp.errors[0].message.contains("Read-only"));
+
+    let resp = schema.execute("{ prefetchProof }").await;
+    assert!(resp.errors[0].message.contains("Read-only"));
+
+    assert!(!is_fetching(&target));
+    assert!(
+        Identity::find_by_platform_identity(&db, &Platform::Twitter, &cold)
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_only_is_not_marked_requested() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let pool = new_connection_pool().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(pool.clone())
+        .data(ReadOnly(true))
+        .finish();
+    let found = Identity::create_dummy(&db).await?;
+
+    let resp = schema
+        .execute(format!(
+            r#"{{ identity(platform: "{}", identity: "{}") {{ uuid }} }}"#,
+            found.platform, found.identity
+        ))
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    // It would have been marked in the background.
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let stored = crate::graph::aql::Aql::new(&[])
+        .clause("RETURN DOCUMENT(@id)")
+        .bind("id", found.id().as_str());
+    let conn = pool.get().await.unwrap();
+    let stored: Vec<Value> = conn.database().aql_query(stored.query()).await?;
+    assert!(stored[0].get("requested_at").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_empty_identity() -> Result<(), Error> {
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+
+    for query in [
+        r#"{ identity(platform: "twitter", identity: "") { uuid } }"#,
+        r#"{ identity(platform: "twitter", identity: "  ") { uuid } }"#,
+        r#"{ identities(platforms: ["twitter", "github"], identity: "") { uuid } }"#,
+    ] {
+        let resp = schema.execute(query).await;
+        assert_eq!(1, resp.errors.len(), "{}", query);
+        assert!(resp.errors[0].message.contains("Param missing"));
+    }
+    assert!(!is_fetching(&Target::Identity(
+        Platform::Twitter,
+        "".into()
+    )));
+    assert!(!is_fetching(&Target::Identity(
+        Platform::Twitter,
+        "  ".into()
+    )));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_identities_batch() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let pool = new_connection_pool().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(pool.clone())
+        .data(Loader::new(PlatformIdentityLoadFn { pool }))
+        .finish();
+    let github = Identity {
+        platform: Platform::Github,
+        identity: format!("batch_{}", Uuid::new_v4().simple()),
+        ..Faker.fake()
+    }
+    .create_or_update(&db)
+    .await?;
+    let twitter = Identity {
+        platform: Platform::Twitter,
+        identity: format!("batch_{}", Uuid::new_v4().simple()),
+        ..Faker.fake()
+    }
+    .create_or_update(&db)
+    .await?;
+    let absent = format!("batch_{}", Uuid::new_v4().simple());
+
+    let resp = schema
+        .execute(format!(
+            r#"{{
+              identitiesBatch(targets: [
+                {{ platform: twitter, identity: "{}" }},
+                {{ platform: nextid, identity: "{}" }},
+                {{ platform: github, identity: "{}" }},
+              ]) {{ platform identity }}
+            }}"#,
+            twitter.identity, absent, github.identity
+        ))
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    assert_eq!(
+        resp.data.into_json().unwrap()["identitiesBatch"],
+        json!([
+            {"platform": "twitter", "identity": twitter.identity},
+            null,
+            {"platform": "github", "identity": github.identity},
+        ])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refetch() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let mut stale = Identity {
+        platform: Platform::Twitter,
+        identity: "yeiwb".into(),
+        ..Faker.fake()
+    }
+    .create_or_update(&db)
+    .await?;
+    stale.updated_at = naive_now() - chrono::Duration::days(2);
+    stale.save(&db).await?;
+    let stale_at = stale.updated_at;
+
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let resp = schema
+        .execute(r#"mutation { refetch(platform: "twitter", identity: "yeiwb") { updatedAt } }"#)
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    let refetched = Identity::find_by_platform_identity(&db, &Platform::Twitter, "yeiwb")
+        .await?
+        .expect("still recorded");
+    assert!(refetched.updated_at > stale_at);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_identities_in_platform_order() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let identity = format!("order_{}", Uuid::new_v4().simple());
+    for platform in [Platform::Github, Platform::Twitter, Platform::Keybase] {
+        Identity {
+            platform,
+            identity: identity.clone(),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+    }
+
+    let resp = schema
+        .execute(format!(
+            r#"{{
+              identities(platforms: ["keybase", "twitter", "github"], identity: "{0}") {{ platform }}
+              identitiesByPlatform(platforms: [twitter, ethereum, keybase, github], identity: "{0}") {{
+                platform
+                identity {{ platform identity }}
+              }}
+            }}"#,
+            identity
+        ))
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    let data = resp.data.into_json().unwrap();
+
+    assert_eq!(
+        data["identities"],
+        json!([{"platform": "keybase"}, {"platform": "twitter"}, {"platform": "github"}])
+    );
+    let by_platform = data["identitiesByPlatform"].as_array().unwrap();
+    assert_eq!(4, by_platform.len());
+    for (result, platform) in by_platform
+        .iter()
+        .zip(["twitter", "ethereum", "keybase", "github"])
+    {
+        assert_eq!(result["platform"], json!(platform));
+        if platform == "ethereum" {
+            assert!(result["identity"].is_null());
+        } else {
+            assert_eq!(result["identity"]["platform"], json!(platform));
+            assert_eq!(result["identity"]["identity"], json!(identity));
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_neighbor_connection() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let hub = Identity::create_dummy(&db).await?;
+    let mut expected = vec![];
+    for _ in 0..3 {
+        let neighbor = Identity::create_dummy(&db).await?;
+        Proof {
+            source: DataSource::Keybase,
+            ..Faker.fake()
+        }
+        .connect(&db, &hub, &neighbor)
+        .await?;
+        expected.push(neighbor.identity.clone());
+    }
+
+    let page = |after: Option<&str>| {
+        format!(
+            r#"{{ identity(platform: "twitter", identity: "{}") {{
+                neighborConnection(first: 2{}) {{
+                  edges {{ cursor node {{ identity {{ identity }} }} }}
+                  pageInfo {{ hasNextPage endCursor }}
+                }}
+            }} }}"#,
+            hub.identity,
+            after.map_or("".into(), |cursor| format!(r#", after: "{}""#, cursor))
+        )
+    };
+    let mut found = vec![];
+    let mut after: Option<String> = None;
+    for has_next_page in [true, false] {
+        let resp = schema.execute(page(after.as_deref())).await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        let connection = &data["identity"]["neighborConnection"];
+        assert_eq!(connection["pageInfo"]["hasNextPage"], json!(has_next_page));
+        let edges = connection["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), if has_next_page { 2 } else { 1 });
+        assert_eq!(
+            connection["pageInfo"]["endCursor"],
+            edges.last().unwrap()["cursor"]
+        );
+        for edge in edges {
+            found.push(
+                edge["node"]["identity"]["identity"]
+                    .as_str()
+                    .unwrap()
+                    .to_string(),
+            );
+        }
+        after = connection["pageInfo"]["endCursor"]
+            .as_str()
+            .map(String::from);
+    }
+    // Every neighbor exactly once.
+    found.sort();
+    expected.sort();
+    assert_eq!(found, expected);
+
+    let resp = schema.execute(page(Some("not a cursor"))).await;
+    assert!(!resp.errors.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_neighbor_collapse_equivalents() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let hub = Identity {
+        platform: Platform::Ethereum,
+        identity: format!("0x{}", Uuid::new_v4().simple()),
+        ..Faker.fake()
+    }
+    .create_or_update(&db)
+    .await?;
+    // The same Twitter user, by numeric ID and by its handle (legacy).
+    let handle = format!("alice{}", Uuid::new_v4().simple());
+    let twitter = |identity: String| Identity {
+        platform: Platform::Twitter,
+        identity,
+        display_name: Some(handle.clone()),
+        ..Faker.fake()
+    };
+    let by_id = twitter((Uuid::new_v4().as_u128() % 10u128.pow(18)).to_string())
+        .create_or_update(&db)
+        .await?;
+    let legacy = twitter(handle.clone()).create_or_update(&db).await?;
+    for (source, neighbor) in [
+        (DataSource::Keybase, &by_id),
+        (DataSource::SybilList, &legacy),
+    ] {
+        Proof {
+            source,
+            ..Faker.fake()
+        }
+        .connect(&db, &hub, neighbor)
+        .await?;
+    }
+
+    let neighbors = |collapse: bool| {
+        let query = format!(
+            r#"{{ identity(platform: "ethereum", identity: "{}") {{
+                neighbor(collapseEquivalents: {}) {{ sources identity {{ identity }} }}
+            }} }}"#,
+            hub.identity, collapse
+        );
+        let schema = &schema;
+        async move {
+            let resp = schema.execute(query).await;
+            assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+            resp.data.into_json().unwrap()["identity"]["neighbor"]
+                .as_array()
+                .unwrap()
+                .clone()
+        }
+    };
+    assert_eq!(neighbors(false).await.len(), 2);
+    let collapsed = neighbors(true).await;
+    assert_eq!(collapsed.len(), 1);
+    assert_eq!(collapsed[0]["identity"]["identity"], json!(by_id.identity));
+    let mut sources: Vec<String> = collapsed[0]["sources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|source| source.as_str().unwrap().to_string())
+        .collect();
+    sources.sort();
+    assert_eq!(sources, vec!["keybase", "sybil"]);
+    // Nothing is merged in the database.
+    assert!(
+        Identity::find_by_platform_identity(&db, &Platform::Twitter, &legacy.identity)
+            .await?
+            .is_some()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resolved_name_and_owner() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let name = format!("resolved{}.eth", Uuid::new_v4().simple());
+    let wallet = |identity: String| Identity {
+        platform: Platform::Ethereum,
+        identity,
+        display_name: Some(name.clone()),
+        ..Faker.fake()
+    };
+    // contract(ENS) --Resolve--> resolved, owner --Hold--> contract(ENS)
+    let resolved = wallet(format!("0x{}", Uuid::new_v4().simple()))
+        .create_or_update(&db)
+        .await?;
+    let owner = wallet(format!("0x{}", Uuid::new_v4().simple()))
+        .create_or_update(&db)
+        .await?;
+    let contract = Contract::create_dummy(&db).await?;
+    Resolve {
+        uuid: Uuid::new_v4(),
+        source: DataSource::TheGraph,
+        system: DomainNameSystem::ENS,
+        name: name.clone(),
+        fetcher: DataFetcher::RelationService,
+        updated_at: naive_now(),
+    }
+    .connect(&db, &*contract, &*resolved)
+    .await?;
+    Hold {
+        id: name.clone(),
+        ..Faker.fake()
+    }
+    .connect(&db, &owner, &contract)
+    .await?;
+
+    let resp = schema
+        .execute(format!(
+            r#"{{
+                resolved: identity(platform: "ethereum", identity: "{}") {{ resolvedName }}
+                owner: identity(platform: "ethereum", identity: "{}") {{ resolvedName }}
+                ensOwner(name: "{}") {{ identity }}
+            }}"#,
+            resolved.identity, owner.identity, name
+        ))
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    let data = resp.data.into_json().unwrap();
+    assert_eq!(data["resolved"]["resolvedName"], json!(name));
+    assert!(data["owner"]["resolvedName"].is_null());
+    assert_eq!(data["ensOwner"]["identity"], json!(owner.identity));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_contract_holders() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let contract = Contract {
+        category: ContractCategory::ERC721,
+        address: format!("0x{}", Uuid::new_v4().simple()),
+        ..Faker.fake()
+    }
+    .create_or_update(&db)
+    .await?;
+    let mut holders = vec![];
+    for id in ["1", "2"] {
+        let holder = Identity::create_dummy(&db).await?;
+        Hold {
+            id: id.into(),
+            ..Faker.fake()
+        }
+        .connect(&db, &holder, &contract)
+        .await?;
+        holders.push(holder);
+    }
+
+    let query = |args: &str| {
+        format!(
+            r#"{{ contractHolders(chain: ethereum, category: ERC721, address: "{}"{}) {{ uuid }} }}"#,
+            contract.address, args
+        )
+    };
+    let holders_of = |resp: async_graphql::Response| -> Vec<String> {
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        data["contractHolders"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|holder| holder["uuid"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let found = holders_of(schema.execute(query("")).await);
+    assert_eq!(found.len(), 2);
+    for holder in holders.iter() {
+        assert!(found.contains(&holder.uuid.unwrap().to_string()));
+    }
+    assert_eq!(
+        holders_of(schema.execute(query(r#", id: "2""#)).await),
+        vec![holders[1].uuid.unwrap().to_string()]
+    );
+    let first = holders_of(schema.execute(query(", limit: 1")).await);
+    let second = holders_of(schema.execute(query(", offset: 1, limit: 1")).await);
+    assert_eq!(first.len(), 1);
+    assert_eq!(second.len(), 1);
+    assert_ne!(first, second);
+    // Of another category: not the one asked.
+    let resp = schema.execute(query("").replace("ERC721", "ERC1155")).await;
+    assert!(holders_of(resp).is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_twitter_identity_by_id_or_handle() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .finish();
+    let id = format!("{}", Faker.fake::<u64>());
+    let handle = format!("handle_{}", Uuid::new_v4().simple());
+    Identity {
+        platform: Platform::Twitter,
+        identity: id.clone(),
+        display_name: Some(handle.clone()),
+        ..Faker.fake()
+    }
+    .create_or_update(&db)
+    .await?;
+
+    for query in [&id, &handle] {
+        let resp = schema
+            .execute(format!(
+                r#"{{ identity(platform: "twitter", identity: "{}") {{ identity displayName }} }}"#,
+                query
+            ))
+            .await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        assert_eq!(
+            resp.data.into_json().unwrap()["identity"],
+            json!({"identity": id, "displayName": handle})
+        );
+    }
+
+    Ok(())
+}
+
+const TYPE_QUERY: &str = "query TypeName($name: String!) { __type(name: $name) { name } }";
+
+#[tokio::test]
+async fn test_get_request() -> Result<(), Error> {
+    let schema = Schema::new(Query::default(), Mutation::default(), EmptySubscription);
+    let query_string: String = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("query", TYPE_QUERY)
+        .append_pair("variables", r#"{"name": "BuildInfo"}"#)
+        .append_pair("operationName", "TypeName")
+        .finish();
+
+    let request = parse_get_request(&query_string)?;
+    let resp = execute_batch(&schema, BatchRequest::Single(request), 10).await?;
+    let result = serde_json::to_value(&resp)?;
+    assert_eq!(result["data"]["__type"]["name"], "BuildInfo");
+
+    assert!(matches!(
+        parse_get_request("variables=%7B%7D"),
+        Err(Error::ParamMissing(_))
+    ));
+    assert!(matches!(
+        parse_get_request("query=%7Bping%7D&variables=not-json"),
+        Err(Error::ParamError(_))
+    ));
+
+    // Mutations are POST only.
+    let mutation = |query: &str, operation_name: Option<&str>| {
+        let mut query_string = url::form_urlencoded::Serializer::new(String::new());
+        query_string.append_pair("query", query);
+        if let Some(name) = operation_name {
+            query_string.append_pair("operationName", name);
+        }
+        parse_get_request(&query_string.finish())
+    };
+    let refetch = r#"mutation { refetch(platform: "twitter", identity: "x") }"#;
+    let rejected = mutation(refetch, None).unwrap_err();
+    assert_eq!(rejected.http_status(), http::StatusCode::METHOD_NOT_ALLOWED);
+    let both =
+        "query Ping { ping } mutation Refetch { refetch(platform: \"twitter\", identity: \"x\") }";
+    assert!(mutation(both, Some("Refetch")).is_err());
+    assert!(mutation(both, None).is_err());
+    assert!(mutation(both, Some("Ping")).is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_application_graphql_body() -> Result<(), Error> {
+    let schema = Schema::new(Query::default(), Mutation::default(), EmptySubscription);
+
+    let request = parse_graphql_body(b"{ ping }", "")?;
+    let resp = execute_batch(&schema, BatchRequest::Single(request), 10).await?;
+    assert_eq!(serde_json::to_value(&resp)?["data"]["ping"], "Pong!");
+
+    // Variables come from query string.
+    let request = parse_graphql_body(
+        TYPE_QUERY.as_bytes(),
+        "variables=%7B%22name%22%3A%22Stats%22%7D",
+    )?;
+    let resp = execute_batch(&schema, BatchRequest::Single(request), 10).await?;
+    assert_eq!(
+        serde_json::to_value(&resp)?["data"]["__type"]["name"],
+        "Stats"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_protected_fields() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let identity = Identity::create_dummy(&db).await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .data(ProtectedFields(vec!["IdentityRecord.avatarUrl".into()]))
+        .finish();
+    let query = format!(
+        r#"{{ identity(platform: "{}", identity: "{}") {{ uuid avatarUrl bestAvatar }} }}"#,
+        identity.platform, identity.identity
+    );
+
+    let resp = schema
+        .execute(async_graphql::Request::new(query.clone()).data(Authenticated(false)))
+        .await;
+    let data = resp.data.into_json().unwrap();
+    assert!(data["identity"]["avatarUrl"].is_null());
+    assert!(data["identity"]["bestAvatar"].is_null());
+    // Others are still public.
+    assert!(!data["identity"]["uuid"].is_null());
+    assert!(resp.errors[0].message.contains("Unauthorized"));
+
+    let resp = schema
+        .execute(async_graphql::Request::new(query).data(Authenticated(true)))
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    assert_eq!(
+        resp.data.into_json().unwrap()["identity"]["avatarUrl"],
+        json!(identity.avatar_url)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_edges_added_since_hides_record_id() -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let schema = Schema::build(Query::default(), Mutation::default(), EmptySubscription)
+        .data(new_connection_pool().await?)
+        .data(ProtectedFields(vec!["ProofRecord.recordId".into()]))
+        .finish();
+    let updated_at = naive_now() + chrono::Duration::days(365 * 2000);
+    let proof = Proof {
+        record_id: Some("secret_record_id".into()),
+        updated_at,
+        ..Faker.fake()
+    }
+    .connect(
+        &db,
+        &Identity::create_dummy(&db).await?,
+        &Identity::create_dummy(&db).await?,
+    )
+    .await?;
+    let query = format!(
+        "{{ edgesAddedSince(since: {}, limit: 1000) {{ items {{ id document }} }} }}",
+        updated_at.timestamp() - 1
+    );
+    let exported = |data: Value| {
+        data["edgesAddedSince"]["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["id"] == json!(proof.id().to_string()))
+            .expect("exported")
+            .clone()
+    };
+
+    let resp = schema
+        .execute(async_graphql::Request::new(query.clone()).data(Authenticated(false)))
+        .await;
+    assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+    let edge = exported(resp.data.into_json().unwrap());
+    assert!(edge["document"].get("record_id").is_none());
+
+    let resp = schema
+        .execute(async_graphql::Request::new(query).data(Authenticated(true)))
+        .await;
+    let edge = exported(resp.data.into_json().unwrap());
+    assert_eq!(edge["document"]["record_id"], "secret_record_id");
+
+    Ok(())
+}
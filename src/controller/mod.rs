@@ -1,5 +1,8 @@
+pub mod auth;
+pub mod export;
 pub mod graphql;
 pub mod healthz;
+pub mod rate_limit;
 
 use crate::upstream::Platform;
 use http::StatusCode;
@@ -0,0 +1,259 @@
+//! Guards of those queries which may trigger a crawl:
+//! read-only mode, and per-client rate limiting.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_graphql::Context;
+
+use crate::{config::C, error::Error};
+
+/// Drop expired windows once this many clients are tracked.
+const SWEEP_THRESHOLD: usize = 10_000;
+
+/// Who sends this request. API key if a known one is given, client IP otherwise.
+/// Should be put in GraphQL request data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientKey(pub String);
+
+impl ClientKey {
+    /// - `api_key`: value of `X-Api-Key` header. Only one of `C.web.auth.api_keys` is used.
+    /// - `forwarded_for`: value of `X-Forwarded-For` header. Only used if the connection comes
+    ///   from one of `C.web.rate_limit.trusted_proxies`.
+    /// - `remote`: peer address of this connection.
+    pub fn from_request(
+        api_key: Option<String>,
+        forwarded_for: Option<String>,
+        remote: Option<SocketAddr>,
+    ) -> Self {
+        Self::from_request_with(
+            api_key,
+            forwarded_for,
+            remote,
+            &C.web.auth.api_keys,
+            &C.web.rate_limit.trusted_proxies,
+        )
+    }
+
+    /// Same as `from_request`, knowing `api_keys` and `trusted_proxies` instead of `C`.
+    /// Clients can put anything in `X-Forwarded-For`, so it's walked from the right
+    /// (the hop our proxy appended), and the first address which isn't a trusted proxy is the client.
+    pub fn from_request_with(
+        api_key: Option<String>,
+        forwarded_for: Option<String>,
+        remote: Option<SocketAddr>,
+        api_keys: &[String],
+        trusted_proxies: &[IpAddr],
+    ) -> Self {
+        if let Some(api_key) = api_key.filter(|k| api_keys.contains(k)) {
+            return Self(format!("key:{}", api_key));
+        }
+        let remote_ip = match remote {
+            Some(addr) => addr.ip(),
+            None => return Self("unknown".into()),
+        };
+        if !trusted_proxies.contains(&remote_ip) {
+            return Self(format!("ip:{}", remote_ip));
+        }
+        let mut client_ip = remote_ip;
+        for hop in forwarded_for.as_deref().unwrap_or_default().rsplit(',') {
+            match hop.trim().parse::<IpAddr>() {
+                Ok(ip) => {
+                    client_ip = ip;
+                    if !trusted_proxies.contains(&ip) {
+                        break;
+                    }
+                }
+                // Garbage a trusted proxy passed on. Nothing left of it can be trusted.
+                Err(_) => break,
+            }
+        }
+        Self(format!("ip:{}", client_ip))
+    }
+
+    /// API key this client gives, if any.
+    pub fn api_key(&self) -> Option<&str> {
+        self.0.strip_prefix("key:")
+    }
+}
+
+/// Put it in GraphQL schema data to override `C.web.read_only`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadOnly(pub bool);
+
+/// Fixed-window rate limiter.
+pub struct RateLimiter {
+    /// `0` means unlimited.
+    max_requests: u32,
+    window: Duration,
+    /// Client => (window started at, requests in this window)
+    hits: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: u32, window: Duration) -> Self {
+        Self {
+            max_requests,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// See `C.web.rate_limit`.
+    pub fn from_config() -> Self {
+        Self::new(
+            C.web.rate_limit.max_requests,
+            Duration::from_secs(C.web.rate_limit.window),
+        )
+    }
+
+    /// Count one request of `client` in. Returns `Err` if it exceeded the limit.
+    pub fn check(&self, client: &ClientKey) -> Result<(), Error> {
+        if self.max_requests == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() > SWEEP_THRESHOLD {
+            let window = self.window;
+            hits.retain(|_, (started_at, _)| now.duration_since(*started_at) < window);
+        }
+
+        let (started_at, count) = hits.entry(client.0.clone()).or_insert((now, 0));
+        if now.duration_since(*started_at) >= self.window {
+            *started_at = now;
+            *count = 0;
+        }
+        if *count >= self.max_requests {
+            return Err(Error::TooManyRequests(format!(
+                "{} requests in {}s at most",
+                self.max_requests,
+                self.window.as_secs()
+            )));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+/// Check if current query may trigger a crawl (`fetch_all`, `prefetch`, etc.).
+/// Fails in read-only mode, or if current client exceeded its rate limit.
+pub fn check_crawl(ctx: &Context<'_>) -> Result<(), Error> {
+    if is_read_only(ctx) {
+        return Err(Error::ReadOnly(
+            "data is not found or outdated in cache, and will not be fetched".into(),
+        ));
+    }
+    check_rate_limit(ctx)
+}
+
+/// Is this server read-only (see `C.web.read_only`, or `ReadOnly` if given), i.e. never writes into DB?
+pub fn is_read_only(ctx: &Context<'_>) -> bool {
+    ctx.data_opt::<ReadOnly>()
+        .map_or(C.web.read_only, |read_only| read_only.0)
+}
+
+/// Check rate limit of current client before triggering a crawl.
+/// Always passes if no `RateLimiter` or `ClientKey` is given in context.
+pub fn check_rate_limit(ctx: &Context<'_>) -> Result<(), Error> {
+    match (ctx.data_opt::<RateLimiter>(), ctx.data_opt::<ClientKey>()) {
+        (Some(limiter), Some(client)) => limiter.check(client),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_over_limit() {
+        let limiter = RateLimiter::new(3, Duration::from_secs(60));
+        let abuser = ClientKey("ip:10.0.0.1".into());
+        let other = ClientKey("ip:10.0.0.2".into());
+
+        for _ in 0..3 {
+            assert!(limiter.check(&abuser).is_ok());
+        }
+        assert!(matches!(
+            limiter.check(&abuser),
+            Err(Error::TooManyRequests(_))
+        ));
+        assert_eq!(
+            limiter.check(&abuser).unwrap_err().http_status(),
+            http::StatusCode::TOO_MANY_REQUESTS
+        );
+        // Other clients are not affected.
+        assert!(limiter.check(&other).is_ok());
+    }
+
+    #[test]
+    fn test_window_reset() {
+        let limiter = RateLimiter::new(1, Duration::from_millis(50));
+        let client = ClientKey("ip:10.0.0.1".into());
+
+        assert!(limiter.check(&client).is_ok());
+        assert!(limiter.check(&client).is_err());
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check(&client).is_ok());
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        let client = ClientKey("ip:10.0.0.1".into());
+        for _ in 0..100 {
+            assert!(limiter.check(&client).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_client_key() {
+        let remote: Option<SocketAddr> = Some("192.168.1.1:4000".parse().unwrap());
+        let api_keys = vec!["abc".to_string()];
+        let proxies: Vec<IpAddr> = vec!["192.168.1.1".parse().unwrap()];
+        let key =
+            |api_key: Option<&str>, forwarded_for: Option<&str>, remote, proxies: &[IpAddr]| {
+                ClientKey::from_request_with(
+                    api_key.map(String::from),
+                    forwarded_for.map(String::from),
+                    remote,
+                    &api_keys,
+                    proxies,
+                )
+            };
+
+        assert_eq!(
+            key(Some("abc"), Some("1.1.1.1"), remote, &proxies),
+            ClientKey("key:abc".into())
+        );
+        // Unknown API keys are not trusted.
+        assert_eq!(
+            key(Some("made-up"), None, remote, &proxies),
+            ClientKey("ip:192.168.1.1".into())
+        );
+        // Rightmost hop which isn't a trusted proxy.
+        assert_eq!(
+            key(None, Some("6.6.6.6, 1.1.1.1"), remote, &proxies),
+            ClientKey("ip:1.1.1.1".into())
+        );
+        assert_eq!(
+            key(None, Some("1.1.1.1, 192.168.1.1"), remote, &proxies),
+            ClientKey("ip:1.1.1.1".into())
+        );
+        // Not from a trusted proxy: `X-Forwarded-For` is ignored.
+        assert_eq!(
+            key(None, Some("1.1.1.1"), remote, &[]),
+            ClientKey("ip:192.168.1.1".into())
+        );
+        assert_eq!(
+            key(None, None, remote, &proxies),
+            ClientKey("ip:192.168.1.1".into())
+        );
+    }
+}
@@ -21,14 +21,15 @@ pub enum Error {
     HttpError(#[from] lambda_http::http::Error),
     #[error("Config error: {0}")]
     ConfigError(#[from] config::ConfigError),
-    #[error("Database error: {0}")]
+    #[error("Signature validation error: {0}")]
     SignatureValidationError(String),
     #[error("Hex parse error: {0}")]
     HttpClientError(#[from] hyper::Error),
     #[error("UUID parse error: {0}")]
     UuidError(#[from] uuid::Error),
+    /// Only the message of `aragog::Error` is kept. See `From<aragog::Error>`.
     #[error("ArangoDB error: {0}")]
-    ArangoDBError(#[from] aragog::Error),
+    ArangoDBError(String),
     #[error("ArangoLiteDB error: {0}")]
     ArangoLiteDBError(#[from] arangors_lite::ClientError),
     #[error("Parse error: {0}")]
@@ -41,6 +42,16 @@ pub enum Error {
     PoolError(String),
     #[error("ArangoConfigError error: {0}")]
     ArangoConfigError(#[from] crate::graph::arangopool::ArangoConfigError),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+    #[error("Read-only mode: {0}")]
+    ReadOnly(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Timeout: {0}")]
+    Timeout(String),
+    #[error("Cache error: {0}")]
+    CacheError(String),
 }
 
 impl Error {
@@ -64,13 +75,40 @@ impl Error {
             Error::ArangoLiteDBError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::PoolError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::ArangoConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::ReadOnly(_) => StatusCode::NOT_FOUND,
+            Error::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Error::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Error::CacheError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
-impl warp::reject::Reject for Error {}
+/// `aragog::Error` may carry a source error which is neither `Send` nor `Sync`
+/// (e.g. that of `UnprocessableEntity`), and `Error` must be both to be returned from
+/// spawned tasks and warp filters. So it's turned into its message here.
+impl From<aragog::Error> for Error {
+    fn from(err: aragog::Error) -> Self {
+        Error::ArangoDBError(err.to_string())
+    }
+}
 
-unsafe impl Sync for Error {}
-unsafe impl Send for Error {}
+impl warp::reject::Reject for Error {}
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_error_is_send_sync() {
+        assert_send_sync::<Error>();
+
+        let err: Error = "not a number".parse::<u16>().unwrap_err().into();
+        let moved = std::thread::spawn(move || err.to_string()).join().unwrap();
+        assert_eq!(moved, "Parse Int error: invalid digit found in string");
+    }
+}
@@ -0,0 +1,146 @@
+//! Construction of AQL queries.
+//! Query text can only be made of `&'static str`s, so dynamic values
+//! (identities, names, collections, ...) always get in as bind parameters,
+//! and are never interpreted as AQL.
+
+use arangors_lite::AqlQuery;
+use serde_json::Value;
+
+/// An AQL query under construction.
+///
+/// ```ignore
+/// let aql = Aql::new(&[("edges", Hold::COLLECTION_NAME)])
+///     .clause("FOR d IN @@edges")
+///     .clause("FILTER d._from == @id")
+///     .clause("RETURN d")
+///     .bind("id", id);
+/// let found: Vec<HoldRecord> = db.aql_query(aql.query()).await?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Aql {
+    text: String,
+    /// `(name, value)`. Collection names are prefixed with `@`.
+    vars: Vec<(String, Value)>,
+}
+
+impl Aql {
+    /// Starts a query. `with`: `(name, collection)` of collections to declare in `WITH`,
+    /// each bound as `@@name`. Empty if no `WITH` is needed.
+    pub fn new(with: &[(&'static str, &str)]) -> Self {
+        let mut aql = Self::default();
+        if !with.is_empty() {
+            let declared: Vec<String> =
+                with.iter().map(|(name, _)| format!("@@{}", name)).collect();
+            aql.text = format!("WITH {}", declared.join(", "));
+        }
+        for (name, collection) in with {
+            aql = aql.bind_collection(name, collection);
+        }
+        aql
+    }
+
+    /// Appends a line to the query.
+    pub fn clause(mut self, clause: &'static str) -> Self {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        self.text.push_str(clause);
+        self
+    }
+
+    /// Same as `clause`, but only if `condition`.
+    pub fn clause_if(self, condition: bool, clause: &'static str) -> Self {
+        if condition {
+            self.clause(clause)
+        } else {
+            self
+        }
+    }
+
+    /// Binds a value, referred as `@name` in clauses. Replaces the one bound before, if any.
+    pub fn bind(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.vars.retain(|(bound, _)| bound != name);
+        self.vars.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Binds a collection (not declared in `WITH`), referred as `@@name` in clauses.
+    pub fn bind_collection(mut self, name: &'static str, collection: &str) -> Self {
+        self.vars
+            .push((format!("@{}", name), Value::String(collection.to_string())));
+        self
+    }
+
+    /// Query text, without any bound value in it.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Body of a request to the cursor API (`POST /_api/cursor`) running this query.
+    pub fn body(&self) -> Value {
+        let vars: serde_json::Map<String, Value> = self.vars.iter().cloned().collect();
+        serde_json::json!({ "query": self.text, "bindVars": vars, "count": false })
+    }
+
+    /// The query to run, with every value bound. Results are read in batches
+    /// and without counting, as the other queries do.
+    pub fn query(&self) -> AqlQuery<'_> {
+        self.vars
+            .iter()
+            .fold(AqlQuery::new(&self.text), |aql, (name, value)| {
+                aql.bind_var(name.as_str(), value.clone())
+            })
+            .batch_size(1)
+            .count(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::Error,
+        graph::{
+            new_db_connection,
+            vertex::{Identity, IdentityRecord},
+        },
+    };
+    use aragog::Record;
+
+    #[test]
+    fn test_text() {
+        let aql = Aql::new(&[("identities", "Identities"), ("proofs", "Proofs")])
+            .clause("FOR d IN @@identities")
+            .clause_if(false, "FILTER d.platform == @platform")
+            .clause("RETURN d");
+        assert_eq!(
+            aql.text(),
+            "WITH @@identities, @@proofs\nFOR d IN @@identities\nRETURN d"
+        );
+        assert_eq!(Aql::new(&[]).clause("RETURN 1").text(), "RETURN 1");
+    }
+
+    #[tokio::test]
+    async fn test_injection_is_bound() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let identity = Identity::create_dummy(&db).await?;
+        let injection = r#"x" || true || "x"#;
+
+        let aql = Aql::new(&[("identities", Identity::COLLECTION_NAME)])
+            .clause("FOR d IN @@identities")
+            .clause("FILTER d.identity == @identity")
+            .clause("RETURN d")
+            .bind("identity", injection);
+        assert!(!aql.text().contains(injection));
+        let found: Vec<IdentityRecord> = db.database().aql_query(aql.query()).await?;
+        // Compared as a plain string, so nothing matches...
+        assert!(found.is_empty());
+
+        // ... while a real value still does.
+        let aql = aql.bind("identity", identity.identity.as_str());
+        let found: Vec<IdentityRecord> = db.database().aql_query(aql.query()).await?;
+        assert!(found.iter().any(|d| d.id() == identity.id()));
+
+        Ok(())
+    }
+}
@@ -157,16 +157,11 @@ pub async fn new_connection_pool() -> Result<ConnectionPool, Error> {
         timeouts: Timeouts::default(),
     };
 
-    let pool = Pool::builder(manager)
+    Pool::builder(manager)
         .config(pool_config)
         // .runtime(runtime)
         .build()
-        .map_err(|err| Error::PoolError(err.to_string()));
-
-    match pool {
-        Ok(p) => Ok(p),
-        Err(err) => todo!(),
-    }
+        .map_err(|err| Error::PoolError(err.to_string()))
 }
 
 impl From<Object<ArangoConnectionManager>> for ArangoConnection {
@@ -0,0 +1,397 @@
+//! Writing many relationships found by a crawl at once.
+//! Instead of a few queries per relationship (see `create_identity_to_identity_record`),
+//! a whole batch is written in a single AQL query, which ArangoDB runs as one transaction.
+
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        aql::Aql,
+        create_identity_to_contract_record, create_identity_to_identity_record,
+        edge::{Hold, Proof},
+        vertex::{Contract, Identity},
+    },
+    upstream::{cost::record_db_writes, Platform},
+    util::naive_now,
+};
+use aragog::{DatabaseConnection, Record};
+use arangors_lite::ClientError;
+use serde_json::{json, to_value, Value};
+use std::collections::HashMap;
+use tracing::warn;
+use uuid::Uuid;
+
+/// ArangoDB error numbers of conflicting writes: write-write conflict and unique constraint violated.
+const CONFLICT_ERRORS: [u16; 2] = [1200, 1210];
+
+/// A relationship to be connected, the same as `create_identity_to_*_record` does.
+#[derive(Clone, Debug)]
+pub enum Relationship {
+    Proof(Identity, Identity, Proof),
+    Hold(Identity, Contract, Hold),
+}
+
+/// Relationships to be connected in one transaction. See `connect_in_bulk`.
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    relationships: Vec<Relationship>,
+}
+
+/// Bind variables of the bulk query. Vertices are deduplicated,
+/// and edges point to them by their index in `identities` / `contracts`.
+#[derive(Default)]
+struct BulkVars {
+    identities: Vec<Value>,
+    contracts: Vec<Value>,
+    proofs: Vec<Value>,
+    holds: Vec<Value>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn proof(mut self, from: Identity, to: Identity, proof: Proof) -> Self {
+        self.relationships
+            .push(Relationship::Proof(from, to, proof));
+        self
+    }
+
+    pub fn hold(mut self, from: Identity, to: Contract, hold: Hold) -> Self {
+        self.relationships.push(Relationship::Hold(from, to, hold));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.relationships.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relationships.is_empty()
+    }
+
+    fn vars(&self) -> Result<BulkVars, Error> {
+        let mut vars = BulkVars::default();
+        let mut identity_index: HashMap<(Platform, String), usize> = HashMap::new();
+        let mut contract_index: HashMap<(String, String), usize> = HashMap::new();
+        let mut identity_at = |vars: &mut BulkVars, identity: &Identity| -> Result<usize, Error> {
+            let key = (
+                identity.platform,
+                identity.platform.fold_identity(&identity.identity),
+            );
+            if let Some(index) = identity_index.get(&key) {
+                return Ok(*index);
+            }
+            // Same as a newly created one in `create_or_update`.
+            let mut to_be_created = identity.clone();
+            to_be_created.identity = key.1.clone();
+            to_be_created.uuid = to_be_created.uuid.or(Some(Uuid::new_v4()));
+            to_be_created.added_at = naive_now();
+            to_be_created.updated_at = naive_now();
+            vars.identities.push(to_value(to_be_created)?);
+            identity_index.insert(key, vars.identities.len() - 1);
+            Ok(vars.identities.len() - 1)
+        };
+
+        for relationship in self.relationships.iter() {
+            match relationship {
+                Relationship::Proof(from, to, proof) => {
+                    let from = identity_at(&mut vars, from)?;
+                    let to = identity_at(&mut vars, to)?;
+                    vars.proofs
+                        .push(json!({ "from": from, "to": to, "edge": proof }));
+                }
+                Relationship::Hold(from, to, hold) => {
+                    let from = identity_at(&mut vars, from)?;
+                    let key = (to.chain.to_string(), to.address.clone());
+                    let to = match contract_index.get(&key) {
+                        Some(index) => *index,
+                        None => {
+                            let mut to_be_created = to.clone();
+                            to_be_created.updated_at = naive_now();
+                            vars.contracts.push(to_value(to_be_created)?);
+                            contract_index.insert(key, vars.contracts.len() - 1);
+                            vars.contracts.len() - 1
+                        }
+                    };
+                    vars.holds
+                        .push(json!({ "from": from, "to": to, "edge": hold }));
+                }
+            }
+        }
+        Ok(vars)
+    }
+}
+
+/// Rank of each source in `C.upstream.display_name_priority`, the smaller the better.
+fn name_ranks() -> Value {
+    let ranks: HashMap<String, usize> = C
+        .upstream
+        .display_name_priority
+        .iter()
+        .enumerate()
+        .map(|(rank, source)| (source.to_string(), rank))
+        .collect();
+    json!(ranks)
+}
+
+/// Upserts every vertex, then links every edge not linked yet.
+/// Existing vertices are updated as `create_or_update` does: display names follow
+/// `C.upstream.display_name_priority`. Profile and avatar URLs missing in the crawl are kept.
+async fn run(db: &DatabaseConnection, vars: BulkVars) -> Result<(), Error> {
+    // Existing edges are left as they are, as `connect` does.
+    // `name_wins` is `display_name_wins`, unlisted sources ranking last.
+    let aql_str = r"
+        LET identities = (
+          FOR v IN @identities
+            LET old = FIRST(
+              FOR i IN @@identities
+                FILTER i.platform == v.platform AND i.identity == v.identity
+                LIMIT 1
+                RETURN i
+            )
+            LET name_wins = v.display_name != null AND (
+              old.display_name_source == null OR old.display_name == null OR old.display_name == ''
+              OR (v.display_name == ''
+                ? v.display_name_source == old.display_name_source
+                : NOT_NULL(@name_ranks[v.display_name_source], LENGTH(@name_ranks))
+                  <= NOT_NULL(@name_ranks[old.display_name_source], LENGTH(@name_ranks)))
+            )
+            UPSERT { platform: v.platform, identity: v.identity }
+            INSERT v
+            UPDATE {
+              uuid: OLD.uuid || v.uuid,
+              display_name: name_wins ? v.display_name : OLD.display_name,
+              display_name_source: name_wins ? v.display_name_source : OLD.display_name_source,
+              profile_url: NOT_NULL(v.profile_url, OLD.profile_url),
+              avatar_url: NOT_NULL(v.avatar_url, OLD.avatar_url),
+              created_at: v.created_at || OLD.created_at,
+              updated_at: v.updated_at
+            }
+            IN @@identities
+            RETURN NEW._id
+        )
+        LET contracts = (
+          FOR c IN @contracts
+            UPSERT { chain: c.chain, address: c.address }
+            INSERT c
+            UPDATE { symbol: c.symbol, updated_at: c.updated_at }
+            IN @@contracts
+            RETURN NEW._id
+        )
+        LET proofs = (
+          FOR e IN @proofs
+            LET edge = MERGE(e.edge, { _from: identities[e.from], _to: identities[e.to] })
+            UPSERT { _from: edge._from, _to: edge._to, source: edge.source, record_id: edge.record_id }
+            INSERT edge
+            UPDATE {}
+            IN @@proofs
+            RETURN 1
+        )
+        LET holds = (
+          FOR e IN @holds
+            LET edge = MERGE(e.edge, { _from: identities[e.from], _to: contracts[e.to] })
+            UPSERT { _from: edge._from, _to: edge._to, id: edge.id }
+            INSERT edge
+            UPDATE {}
+            IN @@holds
+            RETURN 1
+        )
+        RETURN LENGTH(proofs) + LENGTH(holds)";
+    let written =
+        vars.identities.len() + vars.contracts.len() + vars.proofs.len() + vars.holds.len();
+    let aql = Aql::new(&[])
+        .clause(aql_str)
+        .bind_collection("identities", Identity::COLLECTION_NAME)
+        .bind_collection("contracts", Contract::COLLECTION_NAME)
+        .bind_collection("proofs", Proof::COLLECTION_NAME)
+        .bind_collection("holds", Hold::COLLECTION_NAME)
+        .bind("identities", vars.identities)
+        .bind("contracts", vars.contracts)
+        .bind("proofs", vars.proofs)
+        .bind("holds", vars.holds)
+        .bind("name_ranks", name_ranks());
+    let _: Vec<Value> = db.database().aql_query(aql.query()).await?;
+    record_db_writes(written as u64);
+    Ok(())
+}
+
+/// Connect every relationship in `batch` in one transaction: either all of them are written,
+/// or (if it fails) none of them.
+pub async fn connect_in_bulk(db: &DatabaseConnection, batch: &Batch) -> Result<(), Error> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    run(db, batch.vars()?).await
+}
+
+fn is_conflict(err: &Error) -> bool {
+    match err {
+        Error::ArangoLiteDBError(ClientError::Arango(err)) => {
+            CONFLICT_ERRORS.contains(&err.error_num())
+        }
+        _ => false,
+    }
+}
+
+/// Same as `connect_in_bulk`, but if the transaction conflicts with concurrent writes
+/// (e.g. another crawl creating the same identity), connect them one by one instead.
+/// Those failed then are skipped. Returns how many of them are not connected.
+pub async fn connect_in_bulk_or_each(
+    db: &DatabaseConnection,
+    batch: &Batch,
+) -> Result<usize, Error> {
+    match connect_in_bulk(db, batch).await {
+        Ok(()) => return Ok(0),
+        Err(err) if is_conflict(&err) => {
+            warn!(
+                "Bulk | {} relationships conflict ({}), connecting them one by one",
+                batch.len(),
+                err
+            );
+        }
+        Err(err) => return Err(err),
+    }
+
+    let mut failed = 0;
+    for relationship in batch.relationships.iter() {
+        let result = match relationship {
+            Relationship::Proof(from, to, proof) => {
+                create_identity_to_identity_record(db, from, to, proof).await
+            }
+            Relationship::Hold(from, to, hold) => {
+                create_identity_to_contract_record(db, from, to, hold)
+                    .await
+                    .map(|_| ())
+            }
+        };
+        if let Err(err) = result {
+            warn!("Bulk | Failed to connect {:?}: {}", relationship, err);
+            failed += 1;
+        }
+    }
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{
+            new_db_connection,
+            vertex::{NameSource, Vertex},
+        },
+        upstream::DataSource,
+    };
+    use fake::{Fake, Faker};
+
+    #[tokio::test]
+    async fn test_connect_in_bulk() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let hub: Identity = Faker.fake();
+        let mut batch = Batch::new();
+        for i in 0..20 {
+            batch = if i % 2 == 0 {
+                let proof = Proof {
+                    source: DataSource::Keybase,
+                    ..Faker.fake()
+                };
+                batch.proof(hub.clone(), Faker.fake(), proof)
+            } else {
+                batch.hold(hub.clone(), Faker.fake(), Faker.fake())
+            };
+        }
+
+        // One of them fails: nothing is written.
+        let mut vars = batch.vars()?;
+        vars.identities.push(json!({
+            "_key": "not a valid key",
+            "platform": "twitter",
+            "identity": "not a valid key",
+        }));
+        assert!(run(&db, vars).await.is_err());
+        assert!(
+            Identity::find_by_platform_identity(&db, &hub.platform, &hub.identity)
+                .await?
+                .is_none()
+        );
+
+        // All of them are written.
+        connect_in_bulk(&db, &batch).await?;
+        let hub_record = Identity::find_by_platform_identity(&db, &hub.platform, &hub.identity)
+            .await?
+            .expect("hub is created");
+        for relationship in batch.relationships.iter() {
+            match relationship {
+                Relationship::Proof(_, to, proof) => {
+                    let to = Identity::find_by_platform_identity(&db, &to.platform, &to.identity)
+                        .await?
+                        .expect("identity is created");
+                    Proof::find_by_from_to(&db, &hub_record, &to, &proof.source, &proof.record_id)
+                        .await?
+                        .expect("proof is connected");
+                }
+                Relationship::Hold(_, to, hold) => {
+                    let to = Contract::find_by_chain_address(&db, &to.chain, &to.address)
+                        .await?
+                        .expect("contract is created");
+                    Hold::find_by_from_to_id(&db, &hub_record, &to, &hold.id)
+                        .await?
+                        .expect("hold is connected");
+                }
+            }
+        }
+
+        // Connecting them again changes nothing.
+        assert_eq!(connect_in_bulk_or_each(&db, &batch).await?, 0);
+        let neighbors: Vec<Value> = db
+            .database()
+            .aql_query(
+                Aql::new(&[])
+                    .clause("FOR e IN @@proofs FILTER e._from == @id RETURN e")
+                    .bind_collection("proofs", Proof::COLLECTION_NAME)
+                    .bind("id", hub_record.id().as_str())
+                    .query(),
+            )
+            .await?;
+        assert_eq!(neighbors.len(), 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connect_in_bulk_merges_identity() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            display_name: Some("fixture.eth".into()),
+            display_name_source: Some(NameSource::ENS),
+            profile_url: Some("https://example.com/profile".into()),
+            avatar_url: Some("https://example.com/avatar.png".into()),
+            ..Faker.fake()
+        };
+        wallet.create_or_update(&db).await?;
+
+        // Crawled again with a Lens handle, without any profile or avatar.
+        let crawled = Identity {
+            display_name: Some("fixture.lens".into()),
+            display_name_source: Some(NameSource::Lens),
+            profile_url: None,
+            avatar_url: None,
+            ..wallet.clone()
+        };
+        let batch = Batch::new().hold(crawled, Faker.fake(), Faker.fake());
+        connect_in_bulk(&db, &batch).await?;
+
+        let found = Identity::find_by_platform_identity(&db, &wallet.platform, &wallet.identity)
+            .await?
+            .expect("wallet is kept");
+        assert_eq!(found.display_name, wallet.display_name);
+        assert_eq!(found.display_name_source, Some(NameSource::ENS));
+        assert_eq!(found.profile_url, wallet.profile_url);
+        assert_eq!(found.avatar_url, wallet.avatar_url);
+
+        Ok(())
+    }
+}
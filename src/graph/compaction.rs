@@ -0,0 +1,198 @@
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        edge::{Hold, Proof},
+        ConnectionPool,
+    },
+};
+use aragog::Record;
+use arangors_lite::{AqlQuery, Database};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+/// Duplicates of one logical edge.
+#[derive(Deserialize)]
+struct DuplicateGroup {
+    /// `_key` of the edge to keep, i.e. the newest one.
+    keep: String,
+    /// `_key`s of the others.
+    remove: Vec<String>,
+    /// Fields of `keep` filled with what its duplicates know.
+    patch: Value,
+}
+
+/// How duplicates are detected and merged in an edge collection.
+struct EdgeKind {
+    collection: &'static str,
+    /// Besides `_from`, `_to` and `source`, edges with the same value
+    /// of this field are the same logical relationship.
+    key_field: &'static str,
+    /// Optional fields which are filled from duplicates if the kept one lacks them.
+    fill: &'static [&'static str],
+}
+
+fn edge_kinds() -> Vec<EdgeKind> {
+    vec![
+        EdgeKind {
+            collection: Proof::COLLECTION_NAME,
+            key_field: "record_id",
+            fill: &["created_at", "proof_url"],
+        },
+        EdgeKind {
+            collection: Hold::COLLECTION_NAME,
+            key_field: "id",
+            fill: &["created_at", "transaction"],
+        },
+    ]
+}
+
+/// Merge duplicate edges in `kind.collection` into the newest one of them.
+/// Returns how many edges are removed.
+async fn compact_collection(db: &Database, kind: &EdgeKind) -> Result<usize, Error> {
+    // A collection cannot be modified twice in one query, so find them first.
+    let aql_str = r"
+        FOR e IN @@collection_name
+          COLLECT from = e._from, to = e._to, source = e.source, key = e[@key_field] INTO group = e
+          FILTER LENGTH(group) > 1
+          LET sorted = (FOR g IN group SORT g.updated_at DESC RETURN g)
+          LET keep = FIRST(sorted)
+          LET dups = SLICE(sorted, 1)
+          RETURN {
+            keep: keep._key,
+            remove: dups[*]._key,
+            patch: MERGE({}, FOR f IN @fill
+              LET earliest = FIRST(FOR d IN dups FILTER d[f] != null SORT d[f] RETURN d[f])
+              RETURN { [f]: keep[f] != null ? keep[f] : earliest })
+          }";
+    let aql = AqlQuery::new(aql_str)
+        .bind_var("@collection_name", kind.collection)
+        .bind_var("key_field", kind.key_field)
+        .bind_var("fill", json!(kind.fill))
+        .batch_size(1)
+        .count(false);
+    let groups: Vec<DuplicateGroup> = db.aql_query(aql).await?;
+    if groups.is_empty() {
+        return Ok(0);
+    }
+
+    let patches: Vec<Value> = groups
+        .iter()
+        .map(|g| json!({ "key": g.keep, "patch": g.patch }))
+        .collect();
+    let aql = AqlQuery::new("FOR p IN @patches UPDATE p.key WITH p.patch IN @@collection_name")
+        .bind_var("@collection_name", kind.collection)
+        .bind_var("patches", json!(patches));
+    let _: Vec<Value> = db.aql_query(aql).await?;
+
+    let removing: Vec<&String> = groups.iter().flat_map(|g| g.remove.iter()).collect();
+    let aql = AqlQuery::new("FOR key IN @keys REMOVE key IN @@collection_name")
+        .bind_var("@collection_name", kind.collection)
+        .bind_var("keys", json!(removing));
+    let _: Vec<Value> = db.aql_query(aql).await?;
+
+    Ok(removing.len())
+}
+
+/// Merge duplicate `Proof` / `Hold` edges between the same vertices from the same source,
+/// left by versions without conflict handling. The newest one of them is kept.
+/// Returns how many edges are removed.
+pub async fn compact_edges(pool: &ConnectionPool) -> Result<usize, Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let mut removed = 0;
+    for kind in edge_kinds() {
+        let count = compact_collection(db, &kind).await?;
+        if count > 0 {
+            info!(
+                "Compaction | Removed {} duplicates in {}",
+                count, kind.collection
+            );
+        }
+        removed += count;
+    }
+    Ok(removed)
+}
+
+/// Periodically run `compact_edges`. See `C.db.compaction_interval`.
+pub fn spawn_edge_compactor(pool: ConnectionPool) {
+    if C.db.compaction_interval == 0 {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(C.db.compaction_interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = compact_edges(&pool).await {
+                warn!("Compaction | Failed: {}", err);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::vertex::Identity,
+        graph::{arangopool::new_connection_pool, edge::ProofRecord, new_db_connection},
+        util::naive_now,
+    };
+    use aragog::DatabaseRecord;
+    use chrono::Duration;
+    use fake::{Fake, Faker};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_compact_duplicate_proofs() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let from = Identity::create_dummy(&db).await?;
+        let to = Identity::create_dummy(&db).await?;
+        let proof: Proof = Faker.fake();
+
+        // `connect` would find and update the existing one, so seed them directly.
+        let mut seeded: Vec<ProofRecord> = vec![];
+        for hours_ago in [3, 1, 2] {
+            let duplicate = Proof {
+                uuid: Uuid::new_v4(),
+                updated_at: naive_now() - Duration::hours(hours_ago),
+                created_at: if hours_ago == 3 {
+                    proof.created_at
+                } else {
+                    None
+                },
+                ..proof.clone()
+            };
+            let record = DatabaseRecord::link(&from, &to, &*db, duplicate).await?;
+            seeded.push(ProofRecord::from(record));
+        }
+
+        assert!(compact_edges(&pool).await? >= 2);
+
+        let found = Proof::find_by_from_to(&db, &from, &to, &proof.source, &proof.record_id)
+            .await?
+            .expect("one should be kept");
+        assert_eq!(found.key(), seeded[1].key());
+        assert_eq!(found.created_at, proof.created_at);
+        let left: Vec<Value> = db
+            .database()
+            .aql_query(
+                AqlQuery::new(
+                    "FOR e IN Proofs FILTER e._from == @from AND e._to == @to RETURN e._key",
+                )
+                .bind_var("from", from.id().as_str())
+                .bind_var("to", to.id().as_str()),
+            )
+            .await?;
+        assert_eq!(1, left.len());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        edge::Resolve,
+        vertex::{display_name_wins, Identity, IdentityRecord, NameSource},
+        ConnectionPool,
+    },
+    upstream::{ens_reverse::reverse_record_of, Platform},
+};
+use aragog::Record;
+use arangors_lite::{AqlQuery, Database};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+lazy_static! {
+    /// Latest report made by `spawn_display_name_fixer`.
+    static ref LATEST: RwLock<Vec<DisplayNameMismatch>> = RwLock::new(vec![]);
+}
+
+/// A wallet whose `display_name` isn't its current ENS reverse record.
+#[derive(Clone, Deserialize, async_graphql::SimpleObject)]
+pub struct DisplayNameMismatch {
+    pub identity: IdentityRecord,
+    /// Current `display_name`.
+    pub display_name: String,
+    /// Its current ENS reverse record.
+    pub expected: String,
+}
+
+/// Wallets having names resolving to them, none of which is their `display_name`.
+/// Wallets without a `display_name` (e.g. reverse record is cleared) are left alone.
+async fn find_candidates(db: &Database) -> Result<Vec<IdentityRecord>, Error> {
+    let aql_str = r"
+        FOR identity IN @@identities
+          FILTER identity.platform == @platform
+          FILTER identity.display_name != null AND identity.display_name != ''
+          LET names = (FOR r IN @@resolves FILTER r._to == identity._id RETURN r.name)
+          FILTER LENGTH(names) > 0
+          FILTER identity.display_name NOT IN names
+          RETURN identity";
+    let aql = AqlQuery::new(aql_str)
+        .bind_var("@identities", Identity::COLLECTION_NAME)
+        .bind_var("@resolves", Resolve::COLLECTION_NAME)
+        .bind_var("platform", json!(Platform::Ethereum))
+        .batch_size(1)
+        .count(false);
+
+    Ok(db.aql_query(aql).await?)
+}
+
+/// Of the candidates, wallets whose reverse record (looked up at `reverse_url`) isn't their `display_name`.
+/// `Resolve`s only tell which wallets may be stale: anyone can point a name at any wallet,
+/// while only its owner can set the reverse record.
+async fn find_mismatches(
+    db: &Database,
+    reverse_url: &str,
+) -> Result<Vec<DisplayNameMismatch>, Error> {
+    let mut mismatches = vec![];
+    for identity in find_candidates(db).await? {
+        let display_name = identity.display_name.clone().unwrap_or_default();
+        match reverse_record_of(reverse_url, &identity.identity).await {
+            Ok(Some(expected)) if expected != display_name => {
+                mismatches.push(DisplayNameMismatch {
+                    identity,
+                    display_name,
+                    expected,
+                })
+            }
+            Ok(_) => {}
+            Err(err) => warn!(
+                "Consistency | Failed to look up reverse record of {}: {}",
+                identity.identity, err
+            ),
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Report wallets whose `display_name` disagrees with their reverse record (looked up at `reverse_url`).
+/// If `fix`, their `display_name` (and `display_name_source`) is refreshed to the reverse record as well,
+/// following `C.upstream.display_name_priority` like any other update.
+pub async fn check_display_names(
+    pool: &ConnectionPool,
+    reverse_url: &str,
+    fix: bool,
+) -> Result<Vec<DisplayNameMismatch>, Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let mismatches = find_mismatches(db, reverse_url).await?;
+    // Reverse record doesn't always win: a name from a higher-priority source is kept.
+    let fixes: Vec<Value> = mismatches
+        .iter()
+        .filter(|m| {
+            display_name_wins(
+                &C.upstream.display_name_priority,
+                (m.expected.as_str(), Some(NameSource::ENS)),
+                (
+                    m.identity.display_name.as_deref(),
+                    m.identity.display_name_source,
+                ),
+            )
+        })
+        .map(|m| json!({ "key": m.identity.key(), "name": m.expected, "source": NameSource::ENS }))
+        .collect();
+    if fix && !fixes.is_empty() {
+        let aql = AqlQuery::new(
+            "FOR f IN @fixes UPDATE f.key WITH { display_name: f.name, display_name_source: f.source } IN @@collection_name",
+        )
+        .bind_var("@collection_name", Identity::COLLECTION_NAME)
+        .bind_var("fixes", json!(fixes));
+        let _: Vec<Value> = db.aql_query(aql).await?;
+        info!("Consistency | Refreshed {} display names", fixes.len());
+    }
+    Ok(mismatches)
+}
+
+/// Report made by the latest run of `spawn_display_name_fixer`. Empty if it's disabled.
+pub fn latest_display_name_report() -> Vec<DisplayNameMismatch> {
+    LATEST.read().unwrap().clone()
+}
+
+/// Periodically check `display_name`s, and fix them if `fix`. See `C.db.display_name_fix_interval`.
+pub fn spawn_display_name_fixer(pool: ConnectionPool, fix: bool) {
+    if C.db.display_name_fix_interval == 0 {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(C.db.display_name_fix_interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match check_display_names(&pool, &C.upstream.ens_reverse.url, fix).await {
+                Ok(report) => *LATEST.write().unwrap() = report,
+                Err(err) => warn!("Consistency | Failed to check display names: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{
+            arangopool::new_connection_pool,
+            edge::resolve::DomainNameSystem,
+            new_db_connection,
+            vertex::{Contract, Vertex},
+            Edge,
+        },
+        upstream::{
+            mock::{self, Fixture},
+            DataFetcher, DataSource,
+        },
+        util::naive_now,
+    };
+    use fake::{Fake, Faker};
+    use uuid::Uuid;
+
+    async fn point_name_to(
+        db: &Database,
+        name: &str,
+        wallet: &IdentityRecord,
+    ) -> Result<(), Error> {
+        let ens = Contract::create_dummy(db).await?;
+        Resolve {
+            uuid: Uuid::new_v4(),
+            source: DataSource::TheGraph,
+            system: DomainNameSystem::ENS,
+            name: name.to_string(),
+            fetcher: DataFetcher::RelationService,
+            updated_at: naive_now(),
+        }
+        .connect(db, &*ens, wallet)
+        .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_display_name_mismatch() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let name = format!("{}.eth", Uuid::new_v4().simple());
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            identity: format!("0x{}", Uuid::new_v4().simple()),
+            display_name: Some("stale.eth".into()),
+            display_name_source: Some(NameSource::Lens),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        point_name_to(&db, &name, &wallet).await?;
+        // Someone else points their name at it as well.
+        point_name_to(&db, "attacker.eth", &wallet).await?;
+        let reverse_url = format!(
+            "{}/",
+            mock::serve(vec![Fixture::ok(
+                &format!("/{}", wallet.identity),
+                json!({ "reverseRecord": name, "domains": [name] }).to_string(),
+            )])
+        );
+
+        let found = check_display_names(&pool, &reverse_url, false).await?;
+        let mismatch = found
+            .iter()
+            .find(|m| m.identity.id() == wallet.id())
+            .expect("mismatch not detected");
+        assert_eq!(mismatch.display_name, "stale.eth");
+        assert_eq!(mismatch.expected, name);
+
+        check_display_names(&pool, &reverse_url, true).await?;
+        let fixed = Identity::find_by_platform_identity(&db, &Platform::Ethereum, &wallet.identity)
+            .await?
+            .unwrap();
+        assert_eq!(fixed.display_name, Some(name));
+        assert_eq!(fixed.display_name_source, Some(NameSource::ENS));
+        assert!(!check_display_names(&pool, &reverse_url, false)
+            .await?
+            .iter()
+            .any(|m| m.identity.id() == wallet.id()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_alone_is_not_trusted() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            identity: format!("0x{}", Uuid::new_v4().simple()),
+            display_name: Some("owner.eth".into()),
+            display_name_source: Some(NameSource::ENS),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        point_name_to(&db, "attacker.eth", &wallet).await?;
+        // Reverse record is still the current display name.
+        let reverse_url = format!(
+            "{}/",
+            mock::serve(vec![Fixture::ok(
+                &format!("/{}", wallet.identity),
+                json!({ "reverseRecord": "owner.eth", "domains": [] }).to_string(),
+            )])
+        );
+
+        let found = check_display_names(&pool, &reverse_url, true).await?;
+        assert!(!found.iter().any(|m| m.identity.id() == wallet.id()));
+        let kept = Identity::find_by_platform_identity(&db, &Platform::Ethereum, &wallet.identity)
+            .await?
+            .unwrap();
+        assert_eq!(kept.display_name, Some("owner.eth".into()));
+
+        Ok(())
+    }
+}
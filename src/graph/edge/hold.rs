@@ -8,12 +8,13 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
+    config::ConfigFreshness,
     error::Error,
     graph::{
         vertex::{contract::Chain, Contract, Identity},
-        ConnectionPool,
+        ConnectionPool, Freshness,
     },
-    upstream::{DataFetcher, DataSource},
+    upstream::{cost::record_db_writes, DataFetcher, DataSource},
     util::naive_now,
 };
 
@@ -40,6 +41,10 @@ pub struct Hold {
     pub id: String,
     /// When the transaction happened. May not be provided by upstream.
     pub created_at: Option<NaiveDateTime>,
+    /// When the registration of this name expires (i.e. ENS), after which this ownership is stale.
+    /// `None` if it never expires, or not provided by upstream.
+    #[serde(default)]
+    pub expires_at: Option<NaiveDateTime>,
     /// When this HODL™ relation is fetched by us RelationService.
     pub updated_at: NaiveDateTime,
     /// Who collects this data.
@@ -70,6 +75,16 @@ impl From<DatabaseRecord<EdgeRecord<Hold>>> for HoldRecord {
     }
 }
 
+impl Freshness for Hold {
+    fn outdated_in() -> Duration {
+        Duration::hours(ConfigFreshness::current().hold_hours as i64)
+    }
+
+    fn fetched_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
 impl Hold {
     /// Find a hold record by from, to and NFT_ID.
     pub async fn find_by_from_to_id<T: Record + std::marker::Sync>(
@@ -152,12 +167,34 @@ impl Hold {
         }
     }
 
+    /// Find all hold records created / transferred in given transaction.
+    /// `Holds.transaction` is indexed (see migration `add_index_to_hold_transaction`).
+    pub async fn find_by_transaction(
+        db: &DatabaseConnection,
+        transaction: &str,
+    ) -> Result<Vec<HoldRecord>, Error> {
+        let query = EdgeRecord::<Hold>::query().filter(
+            Comparison::field("transaction")
+                .equals_str(transaction)
+                .into(),
+        );
+        let result: QueryResult<EdgeRecord<Self>> = query.call(db).await?;
+        Ok(result.iter().map(|record| record.clone().into()).collect())
+    }
+
+    /// Is the registration of this name expired? See `expires_at`.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.map_or(false, |at| at < naive_now())
+    }
+
+    /// Expired ones are outdated until fetched again after they expired, since the owner
+    /// may be changed since then. After that, `updated_at` rules as usual,
+    /// so one which stays expired isn't refetched on every read.
     pub fn is_outdated(&self) -> bool {
-        let outdated_in = Duration::hours(8);
-        self.updated_at
-            .checked_add_signed(outdated_in)
-            .unwrap()
-            .lt(&naive_now())
+        if self.is_expired() && self.expires_at.map_or(false, |at| self.updated_at < at) {
+            return true;
+        }
+        self.is_stale()
     }
 }
 
@@ -177,10 +214,26 @@ impl<T: Record + std::marker::Sync> Edge<Identity, T, HoldRecord> for Hold {
     ) -> Result<HoldRecord, Error> {
         let found = Self::find_by_from_to_id(db, from, to, &self.id).await?;
         match found {
+            // Renewed (or known to expire for the first time).
+            Some(mut edge) if self.expires_at.is_some() && edge.expires_at != self.expires_at => {
+                edge.expires_at = self.expires_at;
+                edge.save(db).await?;
+                record_db_writes(1);
+                Ok(edge)
+            }
+            // Fetched again after it expired. Recorded so it's not outdated anymore (see `is_outdated`).
+            Some(mut edge) if edge.is_outdated() && edge.is_expired() => {
+                edge.updated_at = self.updated_at;
+                edge.save(db).await?;
+                record_db_writes(1);
+                Ok(edge)
+            }
             Some(edge) => Ok(edge),
-            None => Ok(DatabaseRecord::link(from, to, db, self.clone())
-                .await?
-                .into()),
+            None => {
+                let created = DatabaseRecord::link(from, to, db, self.clone()).await?;
+                record_db_writes(1);
+                Ok(created.into())
+            }
         }
     }
 
@@ -205,6 +258,7 @@ impl<T: Record + std::marker::Sync> Edge<Identity, T, HoldRecord> for Hold {
 #[cfg(test)]
 mod tests {
     use crate::{
+        config::with_freshness,
         graph::{new_db_connection, Proof},
         util::naive_now,
     };
@@ -220,12 +274,39 @@ mod tests {
                 transaction: config.fake(),
                 id: config.fake(),
                 created_at: Some(naive_now()),
+                expires_at: None,
                 updated_at: naive_now(),
                 fetcher: Default::default(),
             }
         }
     }
 
+    #[tokio::test]
+    async fn test_find_by_transaction() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let identity = Identity::create_dummy(&db).await?;
+        let contract = Contract::create_dummy(&db).await?;
+        let transaction = format!("0x{}", Uuid::new_v4().simple());
+        let hold = Hold {
+            transaction: Some(transaction.clone()),
+            ..Faker.fake()
+        };
+        let hold_record = hold.connect(&db, &identity, &contract).await?;
+        let _other = Faker
+            .fake::<Hold>()
+            .connect(&db, &identity, &contract)
+            .await?;
+
+        let found = Hold::find_by_transaction(&db, &transaction).await?;
+        assert_eq!(1, found.len());
+        assert_eq!(found.first().unwrap().key(), hold_record.key());
+        assert!(Hold::find_by_transaction(&db, "0xnonexistent")
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_find_by_id_chain_address() -> Result<(), Error> {
         let db = new_db_connection().await?;
@@ -249,4 +330,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_outdated_in_configured() -> Result<(), Error> {
+        let hold: Hold = Faker.fake();
+        assert!(!hold.is_outdated());
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let outdated = with_freshness(
+            ConfigFreshness {
+                identity_hours: 0,
+                hold_hours: 0,
+                resolve_days: 0,
+            },
+            async { hold.is_outdated() },
+        )
+        .await;
+        assert!(outdated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_outdated_until_refetched() {
+        let expires_at = naive_now() - Duration::days(1);
+        let hold = Hold {
+            expires_at: Some(expires_at),
+            updated_at: expires_at - Duration::days(1),
+            ..Faker.fake()
+        };
+        assert!(hold.is_outdated());
+
+        // Fetched again since then, still expired.
+        let hold = Hold {
+            updated_at: naive_now(),
+            ..hold
+        };
+        assert!(hold.is_expired());
+        assert!(!hold.is_outdated());
+    }
 }
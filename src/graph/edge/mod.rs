@@ -5,7 +5,7 @@ pub mod resolve;
 
 pub use hold::{Hold, HoldRecord};
 pub use proof::{Proof, ProofRecord};
-pub use resolve::{Resolve, ResolveRecord};
+pub use resolve::{Resolve, ResolveRecord, ResolveWithIdentity};
 
 use aragog::{DatabaseConnection, DatabaseRecord, Record};
 use async_trait::async_trait;
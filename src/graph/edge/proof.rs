@@ -8,8 +8,8 @@ use uuid::Uuid;
 
 use crate::{
     error::Error,
-    graph::{vertex::Identity, Edge},
-    upstream::{DataFetcher, DataSource},
+    graph::{vertex::Identity, Edge, Freshness},
+    upstream::{cost::record_db_writes, DataFetcher, DataSource},
     util::naive_now,
 };
 
@@ -27,6 +27,10 @@ pub struct Proof {
     pub source: DataSource,
     /// ID of this connection in upstream platform to locate (if any).
     pub record_id: Option<String>,
+    /// URL to the evidence of this connection provided by upstream (if any).
+    /// e.g. the tweet / gist which contains the signature.
+    #[serde(default)]
+    pub proof_url: Option<String>,
     /// When this connection is recorded in upstream platform (if platform gives such data).
     pub created_at: Option<NaiveDateTime>,
     /// When this connection is fetched by us RelationService.
@@ -42,6 +46,7 @@ impl Default for Proof {
             uuid: Uuid::new_v4(),
             source: DataSource::default(),
             record_id: None,
+            proof_url: None,
             created_at: None,
             updated_at: naive_now(),
             fetcher: Default::default(),
@@ -49,6 +54,16 @@ impl Default for Proof {
     }
 }
 
+impl Freshness for Proof {
+    fn outdated_in() -> Duration {
+        Duration::days(1)
+    }
+
+    fn fetched_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
 impl Proof {
     pub async fn find_by_from_to(
         db: &DatabaseConnection,
@@ -73,11 +88,7 @@ impl Proof {
         }
     }
     pub fn is_outdated(&self) -> bool {
-        let outdated_in = Duration::days(1);
-        self.updated_at
-            .checked_add_signed(outdated_in)
-            .unwrap()
-            .lt(&naive_now())
+        self.is_stale()
     }
 }
 
@@ -112,9 +123,11 @@ impl Edge<Identity, Identity, ProofRecord> for Proof {
         let found = Self::find_by_from_to(db, from, to, &self.source, &self.record_id).await?;
         match found {
             Some(edge) => Ok(edge),
-            None => Ok(DatabaseRecord::link(from, to, db, self.clone())
-                .await?
-                .into()),
+            None => {
+                let created = DatabaseRecord::link(from, to, db, self.clone()).await?;
+                record_db_writes(1);
+                Ok(created.into())
+            }
         }
     }
 }
@@ -130,6 +143,12 @@ impl std::ops::Deref for ProofRecord {
     }
 }
 
+impl std::ops::DerefMut for ProofRecord {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 impl From<DatabaseRecord<EdgeRecord<Proof>>> for ProofRecord {
     fn from(record: DatabaseRecord<EdgeRecord<Proof>>) -> Self {
         ProofRecord(record)
@@ -149,6 +168,7 @@ mod tests {
                 uuid: Uuid::new_v4(),
                 source: DataSource::SybilList,
                 record_id: Some(config.fake()),
+                proof_url: None,
                 created_at: Some(config.fake()),
                 updated_at: naive_now(),
                 fetcher: Default::default(),
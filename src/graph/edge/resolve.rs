@@ -1,16 +1,20 @@
 use crate::{
+    config::ConfigFreshness,
     error::Error,
-    graph::vertex::{Contract, Identity},
-    graph::Edge,
-    upstream::{DataFetcher, DataSource},
+    graph::edge::Hold,
+    graph::vertex::{contract::ContractCategory, Contract, Identity, IdentityRecord},
+    graph::{aql::Aql, ConnectionPool, Edge, Freshness},
+    upstream::{cost::record_db_writes, DataFetcher, DataSource},
     util::naive_now,
 };
 use aragog::{
     query::{Comparison, Filter, QueryResult},
-    DatabaseConnection, DatabaseRecord, EdgeRecord, Record,
+    DatabaseAccess, DatabaseConnection, DatabaseRecord, EdgeRecord, Record,
 };
+use arangors_lite::AqlQuery;
 use chrono::{Duration, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use strum_macros::{Display, EnumIter, EnumString};
 use uuid::Uuid;
 
@@ -41,6 +45,13 @@ pub enum DomainNameSystem {
     #[graphql(name = "dotbit")]
     DotBit,
 
+    /// `.bnb` names of SPACE ID on BNB Smart Chain.
+    /// https://space.id
+    #[strum(serialize = "space_id")]
+    #[serde(rename = "space_id")]
+    #[graphql(name = "space_id")]
+    SpaceID,
+
     #[default]
     #[strum(serialize = "unknown")]
     #[serde(rename = "unknown")]
@@ -48,6 +59,45 @@ pub enum DomainNameSystem {
     Unknown,
 }
 
+impl DomainNameSystem {
+    /// Canonical form of `name` in this system, which is what gets stored and looked up.
+    /// ENS names are normalized by UTS-46 (see ENSIP-15), so `Café.eth` and `cafe\u{301}.eth`
+    /// both become `café.eth`. Names with disallowed codepoints are rejected.
+    /// Others are lowercased only.
+    pub fn normalize(&self, name: &str) -> Result<String, Error> {
+        match self {
+            DomainNameSystem::ENS => normalize_ens(name),
+            DomainNameSystem::DotBit | DomainNameSystem::SpaceID | DomainNameSystem::Unknown => {
+                Ok(name.to_lowercase())
+            }
+        }
+    }
+}
+
+fn normalize_ens(name: &str) -> Result<String, Error> {
+    let invalid =
+        |reason: &str| Error::ParamError(format!("Invalid ENS name {}: {}", name, reason));
+    // Not `use_std3_ascii_rules`: ENS allows `_`, which STD3 doesn't.
+    let (normalized, result) = idna::Config::default()
+        .transitional_processing(false)
+        .check_hyphens(false)
+        .verify_dns_length(false)
+        .to_unicode(name);
+    result.map_err(|err| invalid(&format!("{:?}", err)))?;
+
+    if normalized.split('.').any(|label| label.is_empty()) {
+        return Err(invalid("empty label"));
+    }
+    // Letters are lowercased by now. Other ASCII than these are never valid in ENS.
+    if let Some(c) = normalized
+        .chars()
+        .find(|c| c.is_ascii() && !(c.is_ascii_alphanumeric() || "-_.".contains(*c)))
+    {
+        return Err(invalid(&format!("disallowed character {:?}", c)));
+    }
+    Ok(normalized)
+}
+
 /// Edge to identify which `Identity(Ethereum)` a `Contract` is resolving to.
 /// Basiclly this is served for `ENS` only.
 /// There're 3 kinds of relation between an `Identity(Ethereum)` and `Contract(ENS)` :
@@ -90,7 +140,86 @@ impl Default for Resolve {
     }
 }
 
+/// A `Resolve` edge with the `Identity` it resolves to.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ResolveWithIdentity {
+    pub resolve: ResolveRecord,
+    pub identity: IdentityRecord,
+}
+
+impl Freshness for Resolve {
+    fn outdated_in() -> Duration {
+        Duration::days(ConfigFreshness::current().resolve_days as i64)
+    }
+
+    fn fetched_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
 impl Resolve {
+    /// Find `Resolve` records (with the resolved identity) of many `(name, system)` pairs in one query.
+    /// Unresolved names are absent from the result.
+    pub async fn find_by_names_systems(
+        pool: &ConnectionPool,
+        names: &[(String, DomainNameSystem)],
+    ) -> Result<Vec<ResolveWithIdentity>, Error> {
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        let name_list: Vec<&str> = names.iter().map(|(name, _)| name.as_str()).collect();
+        let aql_str = r"
+        FOR r IN @@collection_name
+          FILTER r.name IN @names
+          RETURN { resolve: r, identity: DOCUMENT(r._to) }";
+        let aql = AqlQuery::new(aql_str)
+            .bind_var("@collection_name", Resolve::COLLECTION_NAME)
+            .bind_var("names", name_list)
+            .batch_size(1)
+            .count(false);
+
+        let result: Vec<ResolveWithIdentity> = db.aql_query(aql).await?;
+        // Same name may exist in different system.
+        Ok(result
+            .into_iter()
+            .filter(|r| names.contains(&(r.resolve.name.clone(), r.resolve.system)))
+            .collect())
+    }
+
+    /// Identity owning ENS `name`, i.e. holding it in the ENS contract
+    /// (`Contract(ENS) <-Hold- Identity`), which may differ from where it resolves to.
+    pub async fn find_ens_owner(
+        pool: &ConnectionPool,
+        name: &str,
+    ) -> Result<Option<IdentityRecord>, Error> {
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        let aql = Aql::new(&[
+            ("holds", Hold::COLLECTION_NAME),
+            ("contracts", Contract::COLLECTION_NAME),
+            ("identities", Identity::COLLECTION_NAME),
+        ])
+        .clause("FOR h IN @@holds")
+        .clause("FILTER h.id == @name AND DOCUMENT(h._to).category == @category")
+        .clause("SORT h.updated_at DESC")
+        .clause("LIMIT 1")
+        .clause("RETURN DOCUMENT(h._from)")
+        .bind("name", DomainNameSystem::ENS.normalize(name)?)
+        .bind("category", json!(ContractCategory::ENS));
+        let owners: Vec<IdentityRecord> = db.aql_query(aql.query()).await?;
+        Ok(owners.into_iter().next())
+    }
+
     pub async fn find_by_name_system(
         db: &DatabaseConnection,
         name: &str,
@@ -109,11 +238,7 @@ impl Resolve {
     }
 
     fn is_outdated(&self) -> bool {
-        let outdated_in = Duration::days(1);
-        self.updated_at
-            .checked_add_signed(outdated_in)
-            .unwrap()
-            .lt(&naive_now())
+        self.is_stale()
     }
 }
 
@@ -159,14 +284,16 @@ impl<T: Record + std::marker::Sync> Edge<T, Identity, ResolveRecord> for Resolve
                 } else {
                     // Destory old edge and create new one.
                     edge.delete(db).await?;
-                    Ok(DatabaseRecord::link(from, to, db, self.clone())
-                        .await?
-                        .into())
+                    let created = DatabaseRecord::link(from, to, db, self.clone()).await?;
+                    record_db_writes(2);
+                    Ok(created.into())
                 }
             }
-            None => Ok(DatabaseRecord::link(from, to, db, self.clone())
-                .await?
-                .into()),
+            None => {
+                let created = DatabaseRecord::link(from, to, db, self.clone()).await?;
+                record_db_writes(1);
+                Ok(created.into())
+            }
         }
     }
 
@@ -186,3 +313,128 @@ impl<T: Record + std::marker::Sync> Edge<T, Identity, ResolveRecord> for Resolve
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::with_freshness,
+        graph::{arangopool::new_connection_pool, new_db_connection},
+    };
+    use fake::{Fake, Faker};
+
+    #[tokio::test]
+    async fn test_find_by_names_systems() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let mut names: Vec<(String, DomainNameSystem)> = vec![];
+        for _ in 0..3 {
+            let name = format!("{}.eth", Faker.fake::<String>());
+            let contract = Contract::create_dummy(&db).await?;
+            let identity = Identity::create_dummy(&db).await?;
+            Resolve {
+                uuid: Uuid::new_v4(),
+                source: DataSource::TheGraph,
+                system: DomainNameSystem::ENS,
+                name: name.clone(),
+                fetcher: DataFetcher::RelationService,
+                updated_at: naive_now(),
+            }
+            .connect(&db, &*contract, &*identity)
+            .await?;
+            names.push((name, DomainNameSystem::ENS));
+        }
+        // Same name in another system should not be found.
+        names.push((names[0].0.clone(), DomainNameSystem::DotBit));
+
+        let found = Resolve::find_by_names_systems(&pool, &names).await?;
+        assert_eq!(3, found.len());
+        for (name, _) in names.iter().take(3) {
+            assert!(found.iter().any(|r| &r.resolve.name == name));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_ens() -> Result<(), Error> {
+        let ens = DomainNameSystem::ENS;
+        assert_eq!(ens.normalize("Vitalik.ETH")?, "vitalik.eth");
+        // Composed, decomposed and uppercase forms are the same name.
+        assert_eq!(ens.normalize("café.eth")?, "café.eth");
+        assert_eq!(ens.normalize("cafe\u{301}.eth")?, "café.eth");
+        assert_eq!(ens.normalize("CAFÉ.eth")?, "café.eth");
+        assert_eq!(ens.normalize("\u{1F4A9}.eth")?, "\u{1F4A9}.eth");
+        assert_eq!(ens.normalize("_under.eth")?, "_under.eth");
+
+        for invalid in ["a b.eth", "a..eth", "", "qu\"ote.eth", "a\u{0}.eth"] {
+            assert!(
+                matches!(ens.normalize(invalid), Err(Error::ParamError(_))),
+                "{:?}",
+                invalid
+            );
+        }
+        // Others are only lowercased.
+        assert_eq!(DomainNameSystem::DotBit.normalize("Café.bit")?, "café.bit");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_unicode_name() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let name = format!("café{}.eth", Uuid::new_v4().simple());
+        let contract = Contract::create_dummy(&db).await?;
+        let identity = Identity::create_dummy(&db).await?;
+        Resolve {
+            uuid: Uuid::new_v4(),
+            source: DataSource::TheGraph,
+            system: DomainNameSystem::ENS,
+            name: DomainNameSystem::ENS.normalize(&name)?,
+            fetcher: DataFetcher::RelationService,
+            updated_at: naive_now(),
+        }
+        .connect(&db, &*contract, &*identity)
+        .await?;
+
+        // Looked up by a variant.
+        let variant = name.replace("café", "CAFE\u{301}");
+        let names = vec![(
+            DomainNameSystem::ENS.normalize(&variant)?,
+            DomainNameSystem::ENS,
+        )];
+        let found = Resolve::find_by_names_systems(&pool, &names).await?;
+        assert_eq!(1, found.len());
+        assert_eq!(found[0].identity.id(), identity.id());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_outdated_in_configured() -> Result<(), Error> {
+        let resolve = Resolve {
+            uuid: Uuid::new_v4(),
+            source: DataSource::TheGraph,
+            system: DomainNameSystem::ENS,
+            name: format!("{}.eth", Faker.fake::<String>()),
+            fetcher: DataFetcher::RelationService,
+            updated_at: naive_now(),
+        };
+        assert!(!resolve.is_outdated());
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let outdated = with_freshness(
+            ConfigFreshness {
+                identity_hours: 0,
+                hold_hours: 0,
+                resolve_days: 0,
+            },
+            async { resolve.is_outdated() },
+        )
+        .await;
+        assert!(outdated);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,459 @@
+//! Incremental export for downstream data pipelines: pages of records added
+//! since a timestamp, with keyset pagination which stays stable while new records come in.
+//! Also a versioned JSON document of one identity, for clients not speaking GraphQL.
+
+use crate::{
+    error::Error,
+    graph::{
+        aql::Aql,
+        edge::{Hold, Proof, Resolve},
+        vertex::{
+            contract::{Chain, ContractCategory},
+            Contract, Identity, IdentityRecord, NeighborOptions,
+        },
+        ConnectionPool,
+    },
+    upstream::{DataSource, Platform},
+    util::timestamp_to_naive,
+};
+use aragog::Record;
+use async_graphql::{Json, SimpleObject};
+use chrono::NaiveDateTime;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{from_value, json, Value};
+
+/// Page size if not given.
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Largest page size allowed.
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// Where the previous page ends: `(timestamp, _id)` of its last record.
+/// Given to clients as an opaque string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Cursor {
+    ts: String,
+    id: String,
+}
+
+impl Cursor {
+    fn parse(cursor: &str) -> Result<Self, Error> {
+        match cursor.split_once('|') {
+            Some((ts, id)) if !ts.is_empty() && !id.is_empty() => Ok(Self {
+                ts: ts.to_string(),
+                id: id.to_string(),
+            }),
+            _ => Err(Error::ParamError(format!("Invalid cursor: {}", cursor))),
+        }
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}|{}", self.ts, self.id)
+    }
+}
+
+/// A record with the keys it is paginated by.
+#[derive(Deserialize)]
+struct Row<T> {
+    doc: T,
+    ts: String,
+    id: String,
+}
+
+/// A page of identities. See `identities_added_since`.
+#[derive(SimpleObject)]
+pub struct IdentityPage {
+    pub items: Vec<IdentityRecord>,
+    /// Give it as `after` to get the next page. `null` if this is the last one.
+    pub next_cursor: Option<String>,
+}
+
+/// An edge of any kind (`Proof`, `Hold` or `Resolve`), as it is in DB.
+#[derive(Clone, Debug, SimpleObject)]
+pub struct ExportedEdge {
+    /// `_id` in DB, e.g. `Proofs/12345`. Collection name tells its kind.
+    pub id: String,
+    /// `_id` of the vertex it is from.
+    pub from: String,
+    /// `_id` of the vertex it is to.
+    pub to: String,
+    pub source: DataSource,
+    /// Second-based unix timestamp.
+    pub updated_at: i64,
+    /// Whole document.
+    pub document: Json<Value>,
+}
+
+impl ExportedEdge {
+    fn from_document(document: Value) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Fields {
+            #[serde(rename = "_id")]
+            id: String,
+            #[serde(rename = "_from")]
+            from: String,
+            #[serde(rename = "_to")]
+            to: String,
+            source: DataSource,
+            updated_at: NaiveDateTime,
+        }
+        let fields: Fields = from_value(document.clone())?;
+        Ok(Self {
+            id: fields.id,
+            from: fields.from,
+            to: fields.to,
+            source: fields.source,
+            updated_at: fields.updated_at.timestamp(),
+            document: Json(document),
+        })
+    }
+
+    /// Remove `field` (as it's named in DB) from `document`.
+    pub fn hide_field(&mut self, field: &str) {
+        if let Some(document) = self.document.0.as_object_mut() {
+            document.remove(field);
+        }
+    }
+}
+
+/// A page of edges. See `edges_added_since`.
+#[derive(SimpleObject)]
+pub struct EdgePage {
+    pub items: Vec<ExportedEdge>,
+    /// Give it as `after` to get the next page. `null` if this is the last one.
+    pub next_cursor: Option<String>,
+}
+
+/// Bind `since`, `after` and `limit` used by every export query.
+fn bind_page(aql: Aql, since: i64, after: Option<&str>, limit: u32) -> Result<Aql, Error> {
+    let after = after.map(Cursor::parse).transpose()?;
+    Ok(aql
+        .bind("since", json!(timestamp_to_naive(since, 0)))
+        .bind("after_ts", json!(after.as_ref().map(|c| &c.ts)))
+        .bind("after_id", json!(after.as_ref().map(|c| &c.id)))
+        .bind("limit", limit.clamp(1, MAX_PAGE_SIZE)))
+}
+
+async fn fetch_page<T: DeserializeOwned>(
+    pool: &ConnectionPool,
+    aql: Aql,
+    limit: u32,
+) -> Result<(Vec<T>, Option<String>), Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let rows: Vec<Row<T>> = db.aql_query(aql.query()).await?;
+    // A full page may not be the last one.
+    let next_cursor = if rows.len() as u32 >= limit.clamp(1, MAX_PAGE_SIZE) {
+        rows.last().map(|row| {
+            Cursor {
+                ts: row.ts.clone(),
+                id: row.id.clone(),
+            }
+            .to_string()
+        })
+    } else {
+        None
+    };
+    Ok((rows.into_iter().map(|row| row.doc).collect(), next_cursor))
+}
+
+/// Identities added (`added_at`) after unix timestamp `since`, oldest first.
+/// `after`: `next_cursor` of the previous page.
+pub async fn identities_added_since(
+    pool: &ConnectionPool,
+    since: i64,
+    after: Option<&str>,
+    limit: u32,
+) -> Result<IdentityPage, Error> {
+    let aql = Aql::new(&[])
+        .bind_collection("identities", Identity::COLLECTION_NAME)
+        .clause("FOR d IN @@identities")
+        .clause("FILTER d.added_at > @since")
+        .clause("FILTER @after_ts == null OR d.added_at > @after_ts OR (d.added_at == @after_ts AND d._id > @after_id)")
+        .clause("SORT d.added_at, d._id")
+        .clause("LIMIT @limit")
+        .clause("RETURN { doc: d, ts: d.added_at, id: d._id }");
+    let aql = bind_page(aql, since, after, limit)?;
+
+    let (items, next_cursor) = fetch_page(pool, aql, limit).await?;
+    Ok(IdentityPage { items, next_cursor })
+}
+
+/// Edges of every kind after unix timestamp `since`, oldest first.
+/// Edges have no `added_at`, so `updated_at` is used: a refetched edge comes again,
+/// which is fine for upserting consumers.
+/// `after`: `next_cursor` of the previous page.
+pub async fn edges_added_since(
+    pool: &ConnectionPool,
+    since: i64,
+    after: Option<&str>,
+    limit: u32,
+) -> Result<EdgePage, Error> {
+    let aql = Aql::new(&[])
+        .bind_collection("proofs", Proof::COLLECTION_NAME)
+        .bind_collection("holds", Hold::COLLECTION_NAME)
+        .bind_collection("resolves", Resolve::COLLECTION_NAME)
+        // Each collection gives at most a page, then they are merged.
+        .clause("LET candidates = UNION(")
+        .clause("  (FOR e IN @@proofs FILTER e.updated_at > @since FILTER @after_ts == null OR e.updated_at > @after_ts OR (e.updated_at == @after_ts AND e._id > @after_id) SORT e.updated_at, e._id LIMIT @limit RETURN e),")
+        .clause("  (FOR e IN @@holds FILTER e.updated_at > @since FILTER @after_ts == null OR e.updated_at > @after_ts OR (e.updated_at == @after_ts AND e._id > @after_id) SORT e.updated_at, e._id LIMIT @limit RETURN e),")
+        .clause("  (FOR e IN @@resolves FILTER e.updated_at > @since FILTER @after_ts == null OR e.updated_at > @after_ts OR (e.updated_at == @after_ts AND e._id > @after_id) SORT e.updated_at, e._id LIMIT @limit RETURN e)")
+        .clause(")")
+        .clause("FOR d IN candidates")
+        .clause("SORT d.updated_at, d._id")
+        .clause("LIMIT @limit")
+        .clause("RETURN { doc: d, ts: d.updated_at, id: d._id }");
+    let aql = bind_page(aql, since, after, limit)?;
+
+    let (documents, next_cursor) = fetch_page::<Value>(pool, aql, limit).await?;
+    let items = documents
+        .into_iter()
+        .map(ExportedEdge::from_document)
+        .collect::<Result<_, _>>()?;
+    Ok(EdgePage { items, next_cursor })
+}
+
+/// Version of `IdentityDocument` schema. Bumped on any incompatible change of it.
+pub const DOCUMENT_VERSION: u32 = 1;
+
+/// An identity and its immediate edges. Its shape only changes along with `DOCUMENT_VERSION`,
+/// whatever happens to the records in DB.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IdentityDocument {
+    pub version: u32,
+    pub identity: DocumentIdentity,
+    /// Connected directly, ordered by `(platform, identity)`.
+    pub neighbors: Vec<DocumentNeighbor>,
+    /// NFTs held, ordered by `(contract.chain, contract.address, id)`.
+    pub holds: Vec<DocumentHold>,
+}
+
+/// Timestamps are second-based unix timestamps.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentIdentity {
+    pub uuid: Option<String>,
+    pub platform: Platform,
+    pub identity: String,
+    pub display_name: Option<String>,
+    pub profile_url: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: Option<i64>,
+    pub added_at: i64,
+    pub updated_at: i64,
+}
+
+impl From<&Identity> for DocumentIdentity {
+    fn from(identity: &Identity) -> Self {
+        Self {
+            uuid: identity.uuid.map(|u| u.to_string()),
+            platform: identity.platform,
+            identity: identity.identity.clone(),
+            display_name: identity.display_name.clone(),
+            profile_url: identity.profile_url.clone(),
+            avatar_url: identity.avatar_url.clone(),
+            created_at: identity.created_at.map(|dt| dt.timestamp()),
+            added_at: identity.added_at.timestamp(),
+            updated_at: identity.updated_at.timestamp(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentNeighbor {
+    pub identity: DocumentIdentity,
+    /// Sources of the edges connecting them.
+    pub sources: Vec<DataSource>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentHold {
+    /// NFT ID in its contract. For ENS, the domain name.
+    pub id: String,
+    pub source: DataSource,
+    pub transaction: Option<String>,
+    pub created_at: Option<i64>,
+    pub updated_at: i64,
+    pub contract: DocumentContract,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DocumentContract {
+    pub category: ContractCategory,
+    pub address: String,
+    pub chain: Chain,
+    pub symbol: Option<String>,
+}
+
+/// `IdentityDocument` of `record`, read from DB as it is (nothing is fetched).
+pub async fn identity_document(
+    pool: &ConnectionPool,
+    record: &IdentityRecord,
+) -> Result<IdentityDocument, Error> {
+    let mut neighbors: Vec<DocumentNeighbor> = record
+        .neighbors(pool, NeighborOptions::default())
+        .await?
+        .iter()
+        .map(|neighbor| DocumentNeighbor {
+            identity: DocumentIdentity::from(&neighbor.identity.record),
+            sources: neighbor.sources.clone(),
+        })
+        .collect();
+    neighbors.sort_by(|a, b| {
+        (a.identity.platform.to_string(), &a.identity.identity)
+            .cmp(&(b.identity.platform.to_string(), &b.identity.identity))
+    });
+
+    #[derive(Deserialize)]
+    struct HoldRow {
+        hold: Hold,
+        contract: Option<Contract>,
+    }
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+    let aql = Aql::new(&[
+        ("holds", Hold::COLLECTION_NAME),
+        ("contracts", Contract::COLLECTION_NAME),
+    ])
+    .clause("FOR d IN @@holds")
+    .clause("FILTER d._from == @id")
+    .clause("RETURN { hold: d, contract: DOCUMENT(d._to) }")
+    .bind("id", record.id().as_str());
+    let rows: Vec<HoldRow> = db.aql_query(aql.query()).await?;
+    let mut holds: Vec<DocumentHold> = rows
+        .into_iter()
+        // Dangling ones (contract removed) have nothing to describe.
+        .filter_map(|row| {
+            let contract = row.contract?;
+            Some(DocumentHold {
+                id: row.hold.id,
+                source: row.hold.source,
+                transaction: row.hold.transaction,
+                created_at: row.hold.created_at.map(|dt| dt.timestamp()),
+                updated_at: row.hold.updated_at.timestamp(),
+                contract: DocumentContract {
+                    category: contract.category,
+                    address: contract.address,
+                    chain: contract.chain,
+                    symbol: contract.symbol,
+                },
+            })
+        })
+        .collect();
+    holds.sort_by(|a, b| {
+        (a.contract.chain.to_string(), &a.contract.address, &a.id).cmp(&(
+            b.contract.chain.to_string(),
+            &b.contract.address,
+            &b.id,
+        ))
+    });
+
+    Ok(IdentityDocument {
+        version: DOCUMENT_VERSION,
+        identity: DocumentIdentity::from(&record.record),
+        neighbors,
+        holds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{arangopool::new_connection_pool, new_db_connection, Edge, Vertex},
+        util::naive_now,
+    };
+    use chrono::Duration;
+    use fake::{Fake, Faker};
+
+    #[tokio::test]
+    async fn test_identities_added_since() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // Far in the future, so records of other tests are all before it.
+        // Whole seconds, as `since` is.
+        let base = timestamp_to_naive((naive_now() + Duration::days(365 * 1000)).timestamp(), 0);
+        let mut seeded = vec![];
+        for seconds in 0..5 {
+            let identity = Identity {
+                added_at: base + Duration::seconds(seconds),
+                ..Faker.fake()
+            }
+            .create_or_update(&db)
+            .await?;
+            seeded.push(identity.id().to_string());
+        }
+
+        let mut exported: Vec<String> = vec![];
+        let mut after: Option<String> = None;
+        loop {
+            let page = identities_added_since(&pool, base.timestamp(), after.as_deref(), 2).await?;
+            assert!(page.items.len() <= 2);
+            exported.extend(page.items.iter().map(|i| i.id().to_string()));
+            match page.next_cursor {
+                Some(cursor) => after = Some(cursor),
+                None => break,
+            }
+        }
+
+        // The one exactly at `since` is not after it.
+        assert!(!exported.contains(&seeded[0]));
+        let ours: Vec<&String> = exported.iter().filter(|id| seeded.contains(id)).collect();
+        assert_eq!(ours, seeded[1..].iter().collect::<Vec<_>>());
+        // Every one is exported once.
+        let mut deduped = exported.clone();
+        deduped.sort();
+        deduped.dedup();
+        assert_eq!(deduped.len(), exported.len());
+
+        assert!(matches!(
+            identities_added_since(&pool, 0, Some("no-separator"), 2).await,
+            Err(Error::ParamError(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_edges_added_since() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let base = timestamp_to_naive((naive_now() + Duration::days(365 * 1000)).timestamp(), 0);
+        let from = Identity::create_dummy(&db).await?;
+        let to = Identity::create_dummy(&db).await?;
+        let mut seeded = vec![];
+        for seconds in 0..4 {
+            let proof = Proof {
+                updated_at: base + Duration::seconds(seconds),
+                ..Faker.fake()
+            }
+            .connect(&db, &from, &to)
+            .await?;
+            seeded.push(proof.id().to_string());
+        }
+
+        let first = edges_added_since(&pool, base.timestamp(), None, 2).await?;
+        let second =
+            edges_added_since(&pool, base.timestamp(), first.next_cursor.as_deref(), 2).await?;
+        let exported: Vec<String> = first
+            .items
+            .iter()
+            .chain(second.items.iter())
+            .map(|e| e.id.clone())
+            .filter(|id| seeded.contains(id))
+            .collect();
+        assert_eq!(exported, seeded[1..].to_vec());
+        let edge = first.items.first().unwrap();
+        assert_eq!(edge.from, from.id().to_string());
+        assert_eq!(edge.to, to.id().to_string());
+
+        Ok(())
+    }
+}
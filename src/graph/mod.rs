@@ -1,26 +1,71 @@
+pub mod aql;
 pub mod arangopool;
+pub mod bulk;
+pub mod compaction;
+pub mod consistency;
 pub mod edge;
+pub mod export;
+pub mod prune;
+pub mod score;
+pub mod staleness;
+pub mod stats;
 mod tests;
 pub mod vertex;
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tracing::debug;
 
-use crate::{config::C, error::Error};
+use crate::{
+    config::C,
+    error::Error,
+    util::{make_client_with_proxies, read_body, HttpClient},
+};
 use aragog::{AuthMode, DatabaseConnection, OperationOptions};
 pub use arangopool::ConnectionPool;
 use arangors_lite::{
     view::ArangoSearchViewLink, view::ArangoSearchViewPropertiesOptions, view::ViewDescription,
-    view::ViewOptions, view::ViewType, Connection, Database,
+    view::ViewOptions, view::ViewType, AqlQuery, Connection, Database,
 };
 pub use edge::Edge;
-use serde::Deserialize;
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    HeaderValue,
+};
+use hyper::Body;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{from_value, Value};
 pub use vertex::Vertex;
 
 use self::{
+    aql::Aql,
     edge::{Hold, HoldRecord, Proof, Resolve},
     vertex::{Contract, ContractRecord, Identity, IdentityRecord},
 };
 
+/// Records (vertices and edges) refetched from upstreams once outdated.
+pub trait Freshness {
+    /// How long a record stays fresh after it is (re-)fetched.
+    fn outdated_in() -> chrono::Duration;
+
+    /// When it is (re-)fetched the last time, i.e. its `updated_at`.
+    fn fetched_at(&self) -> chrono::NaiveDateTime;
+
+    /// Is it fetched longer than `outdated_in` ago?
+    fn is_stale(&self) -> bool {
+        self.fetched_at()
+            .checked_add_signed(Self::outdated_in())
+            .unwrap()
+            .lt(&crate::util::naive_now())
+    }
+}
+
 // TODO: move this under `vertex/`
 #[derive(Deserialize, Debug)]
 pub struct CryptoIdentity {
@@ -38,8 +83,186 @@ pub struct PubKeyDerivation {
     pub method: String,
 }
 
+/// How fresh the results of a read query must be.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum ReadConsistency {
+    /// Always read from shard leaders. Reflects every committed write.
+    #[default]
+    #[graphql(name = "leader")]
+    Leader,
+
+    /// Allow reading from any shard replica (a.k.a. "dirty reads").
+    /// Faster in cluster deployments, but may be slightly stale.
+    /// Fine for analytics, not for verification.
+    #[graphql(name = "follower")]
+    Follower,
+}
+
+impl ReadConsistency {
+    /// Run `aql` on `db` at this consistency level.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        db: &Database,
+        aql: &Aql,
+    ) -> Result<Vec<T>, Error> {
+        match self {
+            Self::Leader => Ok(db.aql_query(aql.query()).await?),
+            Self::Follower => dirty_read(aql).await,
+        }
+    }
+}
+
+lazy_static! {
+    /// Client of `dirty_read`. Straight to DB, never through upstream proxies.
+    static ref DB_CLIENT: HttpClient = make_client_with_proxies(
+        native_tls::TlsConnector::new().expect("Failed to build TLS settings of DB client"),
+        vec![],
+    );
+}
+
+/// Run `aql` letting any shard replica answer. ArangoDB only allows it by the
+/// `x-arango-allow-dirty-read` header (there's no query option for it), which arangors_lite
+/// can't set, so the cursor API is requested directly. Every batch of the cursor carries it.
+async fn dirty_read<T: DeserializeOwned>(aql: &Aql) -> Result<Vec<T>, Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Batch {
+        #[serde(default)]
+        result: Vec<Value>,
+        #[serde(default)]
+        has_more: bool,
+        id: Option<String>,
+        #[serde(default)]
+        error: bool,
+        error_message: Option<String>,
+    }
+
+    let cursor = format!(
+        "{}/_db/{}/_api/cursor",
+        C.db.host.trim_end_matches('/'),
+        C.db.db
+    );
+    let authorization = format!(
+        "Basic {}",
+        base64::encode(format!("{}:{}", C.db.username, C.db.password))
+    );
+    let mut rows: Vec<Value> = vec![];
+    let mut request =
+        http::Request::post(&cursor).body(Body::from(serde_json::to_vec(&aql.body())?))?;
+    loop {
+        let headers = request.headers_mut();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&authorization).map_err(|err| {
+                Error::General(
+                    format!("Invalid DB credentials: {}", err),
+                    http::StatusCode::INTERNAL_SERVER_ERROR,
+                )
+            })?,
+        );
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(
+            "x-arango-allow-dirty-read",
+            HeaderValue::from_static("true"),
+        );
+        let mut resp = DB_CLIENT.request(request).await?;
+        let status = resp.status();
+        let batch: Batch = serde_json::from_slice(&read_body(&mut resp).await?)?;
+        if batch.error || !status.is_success() {
+            return Err(Error::ArangoDBError(
+                batch.error_message.unwrap_or_else(|| status.to_string()),
+            ));
+        }
+        rows.extend(batch.result);
+        match (batch.has_more, batch.id) {
+            (true, Some(id)) => {
+                request = http::Request::put(format!("{}/{}", cursor, id)).body(Body::empty())?;
+            }
+            _ => break,
+        }
+    }
+    Ok(rows.into_iter().map(from_value).collect::<Result<_, _>>()?)
+}
+
+tokio::task_local! {
+    /// Budget of the crawl running in current task. See `with_db_budget`.
+    static DB_BUDGET: Arc<DbBudget>;
+}
+
+/// Limits how many `new_db_connection`s can be alive at the same time.
+struct DbBudget {
+    semaphore: Arc<Semaphore>,
+    in_use: AtomicUsize,
+    /// Most connections ever alive at the same time.
+    peak: AtomicUsize,
+}
+
+impl DbBudget {
+    async fn acquire(self: Arc<Self>) -> BudgetSlot {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("DB budget semaphore is never closed");
+        let in_use = self.in_use.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(in_use, Ordering::SeqCst);
+        BudgetSlot {
+            budget: self,
+            _permit: permit,
+        }
+    }
+}
+
+struct BudgetSlot {
+    budget: Arc<DbBudget>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for BudgetSlot {
+    fn drop(&mut self) {
+        self.budget.in_use.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A connection given by `new_db_connection`.
+/// Gives its slot back to the budget of current crawl (if any) when dropped.
+pub struct DbConnection {
+    connection: DatabaseConnection,
+    _slot: Option<BudgetSlot>,
+}
+
+impl std::ops::Deref for DbConnection {
+    type Target = DatabaseConnection;
+
+    fn deref(&self) -> &Self::Target {
+        &self.connection
+    }
+}
+
+/// Run `fut` allowing at most `budget` `new_db_connection`s in it to be alive at the same time,
+/// so that a single crawl can't monopolize the database. `0` means unlimited.
+/// Returns the most connections it ever used at the same time, besides the output of `fut`.
+pub async fn with_db_budget<F: Future>(budget: usize, fut: F) -> (F::Output, usize) {
+    if budget == 0 {
+        return (fut.await, 0);
+    }
+    let budget = Arc::new(DbBudget {
+        semaphore: Arc::new(Semaphore::new(budget)),
+        in_use: AtomicUsize::new(0),
+        peak: AtomicUsize::new(0),
+    });
+    let output = DB_BUDGET.scope(budget.clone(), fut).await;
+    (output, budget.peak.load(Ordering::SeqCst))
+}
+
 /// Create a database connection instance.
-pub async fn new_db_connection() -> Result<DatabaseConnection, Error> {
+/// Waits for a free slot first if current crawl runs out of its budget. See `with_db_budget`.
+pub async fn new_db_connection() -> Result<DbConnection, Error> {
+    let slot = match DB_BUDGET.try_with(Arc::clone) {
+        Ok(budget) => Some(budget.acquire().await),
+        Err(_) => None,
+    };
     let connection = DatabaseConnection::builder()
         .with_credentials(&C.db.host, &C.db.db, &C.db.username, &C.db.password)
         .with_auth_mode(AuthMode::Basic)
@@ -47,7 +270,10 @@ pub async fn new_db_connection() -> Result<DatabaseConnection, Error> {
         .with_schema_path(&C.db.schema_path)
         .build()
         .await?;
-    Ok(connection)
+    Ok(DbConnection {
+        connection,
+        _slot: slot,
+    })
 }
 
 pub async fn create_identity_to_contract_record(
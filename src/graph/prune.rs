@@ -0,0 +1,183 @@
+//! Removing identities nobody can reach: those with no edge left (e.g. the proof creating
+//! them is removed since) which were only ever found by crawls, never asked for by a client.
+
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        aql::Aql,
+        edge::{Hold, Proof, Resolve},
+        vertex::{Contract, Identity, IdentityRecord},
+        ConnectionPool,
+    },
+    util::naive_now,
+};
+use aragog::Record;
+use chrono::Duration;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+/// Remember `record` as asked for by a client, so it is never pruned.
+/// Set as `requested_at` on its document (once, the earliest is kept).
+/// It is not a field of `Identity`, which is fine as saving one only patches its own fields.
+pub async fn mark_requested(pool: &ConnectionPool, record: &IdentityRecord) -> Result<(), Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let aql = Aql::new(&[])
+        .bind_collection("identities", Identity::COLLECTION_NAME)
+        .clause("FOR d IN @@identities")
+        .clause("FILTER d._id == @id AND d.requested_at == null")
+        .clause("UPDATE d WITH { requested_at: @now } IN @@identities")
+        .bind("id", record.id().as_str())
+        .bind("now", json!(naive_now()));
+    let _: Vec<Value> = db.aql_query(aql.query()).await?;
+    Ok(())
+}
+
+/// Same as `mark_requested`, in the background. Failures are only logged.
+pub fn spawn_mark_requested(pool: &ConnectionPool, record: &IdentityRecord) {
+    let pool = pool.clone();
+    let record = record.clone();
+    tokio::spawn(async move {
+        if let Err(err) = mark_requested(&pool, &record).await {
+            warn!(
+                "Prune | Failed to mark {} as requested: {}",
+                record.id(),
+                err
+            );
+        }
+    });
+}
+
+/// Identities added more than `older_than` ago, never requested (see `mark_requested`),
+/// and without any `Proof`, `Hold` or `Resolve` edge.
+/// They are removed unless `dry_run`. Returns them either way.
+pub async fn prune_unreachable(
+    pool: &ConnectionPool,
+    older_than: Duration,
+    dry_run: bool,
+) -> Result<Vec<IdentityRecord>, Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let aql = Aql::new(&[
+        ("identities", Identity::COLLECTION_NAME),
+        ("contracts", Contract::COLLECTION_NAME),
+    ])
+    .bind_collection("proofs", Proof::COLLECTION_NAME)
+    .bind_collection("holds", Hold::COLLECTION_NAME)
+    .bind_collection("resolves", Resolve::COLLECTION_NAME)
+    .clause("FOR d IN @@identities")
+    .clause("FILTER d.added_at < @before AND d.requested_at == null")
+    .clause(
+        "FILTER LENGTH(FOR v IN 1..1 ANY d @@proofs, @@holds, @@resolves LIMIT 1 RETURN 1) == 0",
+    )
+    // Checked and removed by one query, so an edge connected meanwhile is never left dangling.
+    .clause_if(dry_run, "RETURN d")
+    .clause_if(!dry_run, "REMOVE d IN @@identities RETURN OLD")
+    .bind("before", json!(naive_now() - older_than));
+    Ok(db.aql_query(aql.query()).await?)
+}
+
+/// Periodically run `prune_unreachable`. See `C.db.prune_interval`.
+pub fn spawn_unreachable_pruner(pool: ConnectionPool) {
+    if C.db.prune_interval == 0 {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(C.db.prune_interval);
+    let older_than = Duration::seconds(C.db.prune_after as i64);
+    let dry_run = C.db.prune_dry_run;
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match prune_unreachable(&pool, older_than, dry_run).await {
+                Ok(found) if found.is_empty() => {}
+                Ok(found) if dry_run => info!(
+                    "Prune | {} unreachable identities would be removed (dry run)",
+                    found.len()
+                ),
+                Ok(found) => info!("Prune | Removed {} unreachable identities", found.len()),
+                Err(err) => warn!("Prune | Failed: {}", err),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{arangopool::new_connection_pool, new_db_connection, Edge, Vertex};
+    use aragog::{DatabaseConnection, DatabaseRecord};
+    use fake::{Fake, Faker};
+
+    /// `create_or_update` always stamps `added_at` now, so old ones are seeded directly.
+    async fn create_added_days_ago(
+        db: &DatabaseConnection,
+        days: i64,
+    ) -> Result<IdentityRecord, Error> {
+        let identity = Identity {
+            added_at: naive_now() - Duration::days(days),
+            ..Faker.fake()
+        };
+        Ok(DatabaseRecord::create(identity, db).await?.into())
+    }
+
+    #[tokio::test]
+    async fn test_prune_unreachable() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let isolated = create_added_days_ago(&db, 400).await?;
+        let requested = create_added_days_ago(&db, 400).await?;
+        mark_requested(&pool, &requested).await?;
+        // Survives being refetched.
+        Identity {
+            platform: requested.platform,
+            identity: requested.identity.clone(),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        let connected = create_added_days_ago(&db, 400).await?;
+        let fresh = Identity::create_dummy(&db).await?;
+        let proof: Proof = Faker.fake();
+        proof.connect(&db, &connected, &fresh).await?;
+
+        let is_found = |found: &[IdentityRecord], record: &IdentityRecord| {
+            found.iter().any(|f| f.id() == record.id())
+        };
+        let found = prune_unreachable(&pool, Duration::days(365), true).await?;
+        assert!(is_found(&found, &isolated));
+        assert!(!is_found(&found, &requested));
+        assert!(!is_found(&found, &connected));
+        assert!(!is_found(&found, &fresh));
+        // Nothing removed in a dry run.
+        assert!(
+            Identity::find_by_platform_identity(&db, &isolated.platform, &isolated.identity)
+                .await?
+                .is_some()
+        );
+
+        let pruned = prune_unreachable(&pool, Duration::days(365), false).await?;
+        assert!(is_found(&pruned, &isolated));
+        assert!(
+            Identity::find_by_platform_identity(&db, &isolated.platform, &isolated.identity)
+                .await?
+                .is_none()
+        );
+        assert!(
+            Identity::find_by_platform_identity(&db, &requested.platform, &requested.identity)
+                .await?
+                .is_some()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+//! Trust (anti-Sybil) scoring of identities.
+//! Deployments can swap in their own `Scorer` by putting an `ActiveScorer` into GraphQL schema data.
+
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        aql::Aql,
+        edge::Proof,
+        vertex::{IdentityRecord, IdentityWithSource, NeighborOptions},
+        ConnectionPool,
+    },
+    upstream::{DataSource, Platform},
+    util::naive_now,
+};
+use aragog::Record;
+use chrono::Duration;
+use std::sync::Arc;
+
+/// Neighbors within this depth are given to `Scorer`.
+pub const SCORE_DEPTH: u16 = 3;
+
+/// Computes a trust score of an identity from its neighborhood.
+pub trait Scorer: Send + Sync {
+    /// `neighbors`: found within `SCORE_DEPTH`. Higher is more trustworthy.
+    fn score(&self, identity: &IdentityRecord, neighbors: &[IdentityWithSource]) -> f64;
+}
+
+/// Scorer in use. `DefaultScorer` if not given in schema data.
+#[derive(Clone)]
+pub struct ActiveScorer(pub Arc<dyn Scorer>);
+
+impl Default for ActiveScorer {
+    fn default() -> Self {
+        Self(Arc::new(DefaultScorer))
+    }
+}
+
+/// Scores in `[0, 1]`, as a weighted sum of:
+/// - how many distinct upstreams confirm its connections;
+/// - how many direct connections it has;
+/// - how close it is to a trusted root (a NextID persona, or a connection proved by NextID).
+pub struct DefaultScorer;
+
+impl DefaultScorer {
+    const SOURCES_WEIGHT: f64 = 0.3;
+    const EDGES_WEIGHT: f64 = 0.3;
+    const ROOT_WEIGHT: f64 = 0.4;
+    /// Distinct sources to get a full `SOURCES_WEIGHT`.
+    const ENOUGH_SOURCES: f64 = 3.0;
+    /// Direct connections to get a full `EDGES_WEIGHT`.
+    const ENOUGH_EDGES: f64 = 5.0;
+
+    fn is_trusted_root(platform: Platform) -> bool {
+        platform == Platform::NextID
+    }
+}
+
+impl Scorer for DefaultScorer {
+    fn score(&self, identity: &IdentityRecord, neighbors: &[IdentityWithSource]) -> f64 {
+        let mut sources: Vec<DataSource> = vec![];
+        for source in neighbors.iter().flat_map(|n| n.sources.iter()) {
+            if !sources.contains(source) {
+                sources.push(*source);
+            }
+        }
+        let edges: usize = neighbors
+            .iter()
+            .filter(|n| n.depth == 1)
+            .map(|n| n.sources.len())
+            .sum();
+        let distance_to_root = if Self::is_trusted_root(identity.platform) {
+            Some(0)
+        } else {
+            neighbors
+                .iter()
+                .filter(|n| {
+                    Self::is_trusted_root(n.identity.platform)
+                        || (n.depth == 1 && n.sources.contains(&DataSource::NextID))
+                })
+                .map(|n| n.depth)
+                .min()
+        };
+
+        let sources_score = (sources.len() as f64 / Self::ENOUGH_SOURCES).min(1.0);
+        let edges_score = (edges as f64 / Self::ENOUGH_EDGES).min(1.0);
+        let root_score = distance_to_root.map_or(0.0, |distance| 1.0 / (distance as f64 + 1.0));
+
+        Self::SOURCES_WEIGHT * sources_score
+            + Self::EDGES_WEIGHT * edges_score
+            + Self::ROOT_WEIGHT * root_score
+    }
+}
+
+/// Could `identity` and `neighbors` be a proof ring, before counting proofs among them?
+/// i.e. a small cluster of brand-new identities without any trusted root. See `C.proof_ring`.
+fn is_ring_candidate(identity: &IdentityRecord, neighbors: &[IdentityWithSource]) -> bool {
+    let config = &C.proof_ring;
+    let size = neighbors.len() + 1;
+    if config.max_size == 0 || size < 2 || size > config.max_size {
+        return false;
+    }
+    let new_since = naive_now() - Duration::seconds(config.new_within as i64);
+    std::iter::once(identity)
+        .chain(neighbors.iter().map(|n| &n.identity))
+        .all(|i| i.added_at >= new_since && !DefaultScorer::is_trusted_root(i.platform))
+        && !neighbors
+            .iter()
+            .any(|n| n.sources.contains(&DataSource::NextID))
+}
+
+impl IdentityRecord {
+    async fn neighborhood(&self, pool: &ConnectionPool) -> Result<Vec<IdentityWithSource>, Error> {
+        self.neighbors(
+            pool,
+            NeighborOptions {
+                depth: SCORE_DEPTH,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Trust score of this identity given by `scorer`.
+    /// Lowered by `C.proof_ring.score_factor` if it is in a proof ring. See `is_suspicious`.
+    pub async fn score(&self, pool: &ConnectionPool, scorer: &dyn Scorer) -> Result<f64, Error> {
+        let neighbors = self.neighborhood(pool).await?;
+        let score = scorer.score(self, &neighbors);
+        if self.in_proof_ring(pool, &neighbors).await? {
+            Ok(score * C.proof_ring.score_factor)
+        } else {
+            Ok(score)
+        }
+    }
+
+    /// Is this identity in a proof ring, i.e. a small cluster of brand-new identities
+    /// proving each other (e.g. mutually or circularly) to look well-connected?
+    /// See `C.proof_ring`.
+    pub async fn is_suspicious(&self, pool: &ConnectionPool) -> Result<bool, Error> {
+        let neighbors = self.neighborhood(pool).await?;
+        self.in_proof_ring(pool, &neighbors).await
+    }
+
+    async fn in_proof_ring(
+        &self,
+        pool: &ConnectionPool,
+        neighbors: &[IdentityWithSource],
+    ) -> Result<bool, Error> {
+        if !is_ring_candidate(self, neighbors) {
+            return Ok(false);
+        }
+        let ids: Vec<&str> = std::iter::once(self.id().as_str())
+            .chain(neighbors.iter().map(|n| n.identity.id().as_str()))
+            .collect();
+        let size = ids.len();
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        // Proofs of the same direction between the same pair count once.
+        let aql = Aql::new(&[("proofs", Proof::COLLECTION_NAME)])
+            .clause("RETURN COUNT(")
+            .clause("  FOR p IN @@proofs")
+            .clause("    FILTER p._from IN @ids AND p._to IN @ids")
+            .clause("    COLLECT from = p._from, to = p._to")
+            .clause("    RETURN 1")
+            .clause(")")
+            .bind("ids", ids);
+        let found: Vec<usize> = conn.database().aql_query(aql.query()).await?;
+        let proofs = found.first().copied().unwrap_or(0);
+
+        let density = proofs as f64 / (size * (size - 1)) as f64;
+        Ok(density >= C.proof_ring.min_density)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{
+        arangopool::new_connection_pool,
+        edge::Proof,
+        new_db_connection,
+        vertex::{Identity, Vertex},
+        Edge,
+    };
+    use fake::{Fake, Faker};
+
+    #[tokio::test]
+    async fn test_default_scorer() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // persona --NextID--> twitter
+        //   |  \--NextID--> github --Keybase--> keybase
+        //   \--NextID--> ethereum
+        let persona = Identity {
+            platform: Platform::NextID,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        let mut connected = vec![];
+        for platform in [Platform::Twitter, Platform::Github, Platform::Ethereum] {
+            let identity = Identity {
+                platform,
+                ..Faker.fake()
+            }
+            .create_or_update(&db)
+            .await?;
+            Proof {
+                source: DataSource::NextID,
+                ..Faker.fake()
+            }
+            .connect(&db, &persona, &identity)
+            .await?;
+            connected.push(identity);
+        }
+        let keybase = Identity {
+            platform: Platform::Keybase,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        Proof {
+            source: DataSource::Keybase,
+            ..Faker.fake()
+        }
+        .connect(&db, &connected[1], &keybase)
+        .await?;
+        let isolated = Identity::create_dummy(&db).await?;
+
+        let well_connected = connected[0].score(&pool, &DefaultScorer).await?;
+        let alone = isolated.score(&pool, &DefaultScorer).await?;
+        assert_eq!(0.0, alone);
+        assert!(well_connected > alone);
+        assert!(well_connected <= 1.0);
+        // Trusted root itself scores even higher.
+        assert!(persona.score(&pool, &DefaultScorer).await? > well_connected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_proof_ring() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let mut ring = vec![];
+        for _ in 0..3 {
+            ring.push(Identity::create_dummy(&db).await?);
+        }
+        // a <-> b <-> c <-> a
+        for (from, to) in [(0, 1), (1, 2), (2, 0)] {
+            for (from, to) in [(from, to), (to, from)] {
+                Proof {
+                    source: DataSource::Keybase,
+                    ..Faker.fake()
+                }
+                .connect(&db, &ring[from], &ring[to])
+                .await?;
+            }
+        }
+        for identity in ring.iter() {
+            assert!(identity.is_suspicious(&pool).await?);
+        }
+        let suspicious = ring[0].score(&pool, &DefaultScorer).await?;
+        let neighbors = ring[0].neighborhood(&pool).await?;
+        assert_eq!(
+            suspicious,
+            DefaultScorer.score(&ring[0], &neighbors) * C.proof_ring.score_factor
+        );
+
+        // A chain proving one way isn't interlinked enough.
+        let mut chain = vec![];
+        for _ in 0..3 {
+            chain.push(Identity::create_dummy(&db).await?);
+        }
+        for (from, to) in [(0, 1), (1, 2)] {
+            Proof {
+                source: DataSource::Keybase,
+                ..Faker.fake()
+            }
+            .connect(&db, &chain[from], &chain[to])
+            .await?;
+        }
+        assert!(!chain[0].is_suspicious(&pool).await?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        edge::{Hold, Proof, Resolve},
+        vertex::{Contract, Identity},
+        ConnectionPool, Freshness,
+    },
+    util::naive_now,
+};
+use aragog::Record;
+use arangors_lite::{AqlQuery, Database};
+use chrono::Duration;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::RwLock;
+use tracing::warn;
+
+lazy_static! {
+    /// Latest report sampled by `spawn_staleness_sampler`.
+    static ref LATEST: RwLock<Vec<Staleness>> = RwLock::new(vec![]);
+}
+
+/// How many records of an entity type are currently outdated (i.e. `is_outdated()`).
+#[derive(Clone, Debug, PartialEq, async_graphql::SimpleObject)]
+pub struct Staleness {
+    /// Collection name, e.g. `Identities`.
+    pub entity: String,
+    pub total: u64,
+    pub outdated: u64,
+    /// `outdated / total`. `0` if there's no record at all.
+    pub fraction: f64,
+}
+
+#[derive(Deserialize)]
+struct Counts {
+    total: u64,
+    outdated: u64,
+}
+
+/// Every entity type with its freshness window.
+fn entities() -> Vec<(&'static str, Duration)> {
+    vec![
+        (Identity::COLLECTION_NAME, Identity::outdated_in()),
+        (Contract::COLLECTION_NAME, Contract::outdated_in()),
+        (Proof::COLLECTION_NAME, Proof::outdated_in()),
+        (Hold::COLLECTION_NAME, Hold::outdated_in()),
+        (Resolve::COLLECTION_NAME, Resolve::outdated_in()),
+    ]
+}
+
+/// Count records in `collection` not updated within `outdated_in`.
+pub async fn sample_collection(
+    db: &Database,
+    collection: &str,
+    outdated_in: Duration,
+) -> Result<Staleness, Error> {
+    let aql_str = r"
+        RETURN {
+          total: LENGTH(@@collection_name),
+          outdated: COUNT(
+            FOR d IN @@collection_name
+              FILTER d.updated_at < @fresh_since
+              RETURN 1
+          )
+        }";
+    let aql = AqlQuery::new(aql_str)
+        .bind_var("@collection_name", collection)
+        .bind_var("fresh_since", json!(naive_now() - outdated_in))
+        .batch_size(1)
+        .count(false);
+
+    let counts: Vec<Counts> = db.aql_query(aql).await?;
+    let Counts { total, outdated } = counts.into_iter().next().ok_or(Error::NoResult)?;
+    Ok(Staleness {
+        entity: collection.to_string(),
+        total,
+        outdated,
+        fraction: if total == 0 {
+            0.0
+        } else {
+            outdated as f64 / total as f64
+        },
+    })
+}
+
+/// Staleness of every entity type, computed right now.
+pub async fn staleness_report(pool: &ConnectionPool) -> Result<Vec<Staleness>, Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let mut report = vec![];
+    for (collection, outdated_in) in entities() {
+        report.push(sample_collection(db, collection, outdated_in).await?);
+    }
+    Ok(report)
+}
+
+/// Latest report sampled by `spawn_staleness_sampler`. Empty before the first sample.
+pub fn latest_staleness() -> Vec<Staleness> {
+    LATEST.read().unwrap().clone()
+}
+
+/// Periodically re-sample `staleness_report` for `/metrics`. See `C.metrics`.
+pub fn spawn_staleness_sampler(pool: ConnectionPool) {
+    let interval = std::time::Duration::from_secs(C.metrics.staleness_interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match staleness_report(&pool).await {
+                Ok(report) => *LATEST.write().unwrap() = report,
+                Err(err) => warn!("Staleness | Failed to sample: {}", err),
+            }
+        }
+    });
+}
+
+/// Render `report` in Prometheus text exposition format.
+pub fn render_metrics(report: &[Staleness]) -> String {
+    let mut lines = vec![
+        "# HELP relation_server_outdated_ratio Fraction of records which are outdated and should be refetched.".to_string(),
+        "# TYPE relation_server_outdated_ratio gauge".to_string(),
+    ];
+    lines.extend(report.iter().map(|s| {
+        format!(
+            "relation_server_outdated_ratio{{entity=\"{}\"}} {}",
+            s.entity, s.fraction
+        )
+    }));
+    lines.push("# HELP relation_server_records Number of records.".to_string());
+    lines.push("# TYPE relation_server_records gauge".to_string());
+    lines.extend(report.iter().map(|s| {
+        format!(
+            "relation_server_records{{entity=\"{}\"}} {}",
+            s.entity, s.total
+        )
+    }));
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::new_raw_db_connection;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_sample_collection() -> Result<(), Error> {
+        let db = new_raw_db_connection().await?;
+        let collection = format!("StalenessTest{}", Uuid::new_v4().simple());
+        db.create_collection(&collection).await?;
+
+        let fresh = json!({ "updated_at": naive_now() });
+        let stale = json!({ "updated_at": naive_now() - Duration::hours(2) });
+        let aql = AqlQuery::new("FOR d IN @docs INSERT d INTO @@collection_name")
+            .bind_var("@collection_name", collection.as_str())
+            .bind_var("docs", json!([fresh, stale, stale, stale]));
+        let _: Vec<serde_json::Value> = db.aql_query(aql).await?;
+
+        let found = sample_collection(&db, &collection, Duration::hours(1)).await;
+        db.drop_collection(&collection).await?;
+
+        let found = found?;
+        assert_eq!(4, found.total);
+        assert_eq!(3, found.outdated);
+        assert_eq!(0.75, found.fraction);
+        assert!(render_metrics(&[found]).contains(&format!(
+            "relation_server_outdated_ratio{{entity=\"{}\"}} 0.75",
+            collection
+        )));
+
+        Ok(())
+    }
+}
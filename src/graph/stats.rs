@@ -0,0 +1,176 @@
+use crate::{
+    cache::cache,
+    config::C,
+    error::Error,
+    graph::{
+        edge::{Hold, Proof, Resolve},
+        vertex::{Contract, Identity},
+        ConnectionPool,
+    },
+};
+use aragog::Record;
+use arangors_lite::{AqlQuery, Database};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
+
+/// Key of the latest `Stats` in `cache`. See `cached_stats`.
+const CACHE_KEY: &str = "stats";
+
+/// How many records share the same value of a field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct Count {
+    /// e.g. `twitter` for identities per platform.
+    pub key: String,
+    pub count: u64,
+}
+
+/// Totals of the whole dataset.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, async_graphql::SimpleObject)]
+pub struct Stats {
+    /// Identities per platform.
+    pub identities: Vec<Count>,
+    /// Edges (`Proof`, `Hold` and `Resolve`) per data source.
+    pub edges: Vec<Count>,
+    /// Contracts per chain.
+    pub contracts: Vec<Count>,
+}
+
+/// Count records in `collection` grouped by `field`. Ordered by key.
+pub async fn count_by(db: &Database, collection: &str, field: &str) -> Result<Vec<Count>, Error> {
+    let aql_str = r"
+        FOR d IN @@collection_name
+          COLLECT key = d[@field] WITH COUNT INTO count
+          SORT key
+          RETURN { key: TO_STRING(key), count }";
+    let aql = AqlQuery::new(aql_str)
+        .bind_var("@collection_name", collection)
+        .bind_var("field", field)
+        .batch_size(1)
+        .count(false);
+
+    Ok(db.aql_query(aql).await?)
+}
+
+/// Sum up counts of the same key. Ordered by key.
+fn merge_counts(counts: Vec<Count>) -> Vec<Count> {
+    let mut merged: Vec<Count> = vec![];
+    for count in counts.into_iter() {
+        match merged.iter_mut().find(|c| c.key == count.key) {
+            Some(found) => found.count += count.count,
+            None => merged.push(count),
+        }
+    }
+    merged.sort_by(|a, b| a.key.cmp(&b.key));
+    merged
+}
+
+/// Compute `Stats` right now.
+pub async fn stats(pool: &ConnectionPool) -> Result<Stats, Error> {
+    let conn = pool
+        .get()
+        .await
+        .map_err(|err| Error::PoolError(err.to_string()))?;
+    let db = conn.database();
+
+    let mut edges = vec![];
+    for collection in [
+        Proof::COLLECTION_NAME,
+        Hold::COLLECTION_NAME,
+        Resolve::COLLECTION_NAME,
+    ] {
+        edges.extend(count_by(db, collection, "source").await?);
+    }
+    Ok(Stats {
+        identities: count_by(db, Identity::COLLECTION_NAME, "platform").await?,
+        edges: merge_counts(edges),
+        contracts: count_by(db, Contract::COLLECTION_NAME, "chain").await?,
+    })
+}
+
+/// Same as `stats`, but reuses the latest result (of any instance) within `C.metrics.stats_ttl`.
+/// Computed right now if the cache is unavailable.
+pub async fn cached_stats(pool: &ConnectionPool) -> Result<Stats, Error> {
+    let ttl = Duration::from_secs(C.metrics.stats_ttl);
+    let cache = match cache().await {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            warn!("Stats | Cache unavailable: {}", err);
+            None
+        }
+    };
+    if let Some(cache) = cache {
+        match cache.get(CACHE_KEY).await {
+            Ok(Some(cached)) => match serde_json::from_str(&cached) {
+                Ok(cached) => return Ok(cached),
+                Err(err) => warn!("Stats | Cached one is unreadable: {}", err),
+            },
+            Ok(None) => {}
+            Err(err) => warn!("Stats | Failed to read cache: {}", err),
+        }
+    }
+
+    let computed = stats(pool).await?;
+    if let Some(cache) = cache.filter(|_| !ttl.is_zero()) {
+        if let Err(err) = cache
+            .set(CACHE_KEY, &serde_json::to_string(&computed)?, ttl)
+            .await
+        {
+            warn!("Stats | Failed to write cache: {}", err);
+        }
+    }
+    Ok(computed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::new_raw_db_connection;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_count_by() -> Result<(), Error> {
+        let db = new_raw_db_connection().await?;
+        let collection = format!("StatsTest{}", Uuid::new_v4().simple());
+        db.create_collection(&collection).await?;
+
+        let docs = json!([
+            { "platform": "twitter" },
+            { "platform": "github" },
+            { "platform": "twitter" },
+            { "platform": "ethereum" },
+            { "platform": "twitter" },
+        ]);
+        let aql = AqlQuery::new("FOR d IN @docs INSERT d INTO @@collection_name")
+            .bind_var("@collection_name", collection.as_str())
+            .bind_var("docs", docs);
+        let _: Vec<serde_json::Value> = db.aql_query(aql).await?;
+
+        let found = count_by(&db, &collection, "platform").await;
+        db.drop_collection(&collection).await?;
+
+        let count = |key: &str, count: u64| Count {
+            key: key.into(),
+            count,
+        };
+        assert_eq!(
+            found?,
+            vec![
+                count("ethereum", 1),
+                count("github", 1),
+                count("twitter", 3)
+            ]
+        );
+        assert_eq!(
+            merge_counts(vec![
+                count("keybase", 2),
+                count("dotbit", 1),
+                count("keybase", 3)
+            ]),
+            vec![count("dotbit", 1), count("keybase", 5)]
+        );
+
+        Ok(())
+    }
+}
@@ -1,6 +1,20 @@
 #[cfg(test)]
 mod tests {
-    use crate::graph::{new_db_connection, new_raw_db_connection};
+    use crate::{
+        error::Error,
+        graph::{
+            aql::Aql,
+            arangopool::new_connection_pool,
+            edge::Proof,
+            new_db_connection, new_raw_db_connection,
+            vertex::{Identity, NeighborOptions},
+            Edge, ReadConsistency,
+        },
+    };
+    use arangors_lite::AqlQuery;
+    use fake::{Fake, Faker};
+    use serde_json::Value;
+    use uuid::Uuid;
 
     #[tokio::test]
     async fn test_new_db_connection() {
@@ -15,4 +29,64 @@ mod tests {
     async fn test_new_raw_db_connection() {
         new_raw_db_connection().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_read_consistency() -> Result<(), Error> {
+        let db = new_raw_db_connection().await?;
+        // More than a batch of the cursor API (1000 rows by default).
+        let aql = Aql::new(&[])
+            .clause("FOR i IN 1..@n RETURN i")
+            .bind("n", 2500);
+        let expected: Vec<u32> = (1..=2500).collect();
+        for consistency in [ReadConsistency::Leader, ReadConsistency::Follower] {
+            let found: Vec<u32> = consistency.query(&db, &aql).await?;
+            assert_eq!(found, expected, "{:?}", consistency);
+        }
+
+        let invalid = Aql::new(&[]).clause("RETURN @missing");
+        assert!(ReadConsistency::Follower
+            .query::<Value>(&db, &invalid)
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cursor_drained_across_batches() -> Result<(), Error> {
+        let db = new_raw_db_connection().await?;
+        let collection = format!("CursorTest{}", Uuid::new_v4().simple());
+        db.create_collection(&collection).await?;
+        let aql = AqlQuery::new("FOR i IN 1..25 INSERT { i } INTO @@collection_name")
+            .bind_var("@collection_name", collection.as_str());
+        let _: Vec<Value> = db.aql_query(aql).await?;
+
+        // Every query here uses `batch_size(1)`: one row per batch.
+        let aql = AqlQuery::new("FOR d IN @@collection_name SORT d.i RETURN d.i")
+            .bind_var("@collection_name", collection.as_str())
+            .batch_size(1)
+            .count(false);
+        let found: Result<Vec<u32>, _> = db.aql_query(aql).await;
+        db.drop_collection(&collection).await?;
+        assert_eq!(found?, (1..=25).collect::<Vec<u32>>());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_more_than_one_batch() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let center = Identity::create_dummy(&db).await?;
+        for _ in 0..5 {
+            let neighbor = Identity::create_dummy(&db).await?;
+            let proof: Proof = Faker.fake();
+            proof.connect(&db, &center, &neighbor).await?;
+        }
+
+        let neighbors = center.neighbors(&pool, NeighborOptions::default()).await?;
+        assert_eq!(5, neighbors.len());
+
+        Ok(())
+    }
 }
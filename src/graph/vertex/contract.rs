@@ -1,7 +1,13 @@
 use crate::{
     error::Error,
     graph::edge::Hold,
-    graph::{ConnectionPool, Vertex},
+    graph::{
+        aql::Aql,
+        export::MAX_PAGE_SIZE,
+        vertex::{Identity, IdentityRecord},
+        ConnectionPool, Freshness, Vertex,
+    },
+    upstream::cost::record_db_writes,
     util::naive_now,
 };
 use aragog::{
@@ -134,6 +140,7 @@ pub enum Chain {
     /// Gnosis Chain provides stability, scalability and an extendable beacon chain framework.
     /// Established in 2018 as the xDai Chain, the updated Gnosis Chain gives devs the tools and resources they need to create enhanced user experiences and optimized applications.
     /// https://developers.gnosischain.com
+    /// Same chain as `Xdai`, under which its records are. See `canonical`.
     #[serde(rename = "gnosis")]
     #[strum(serialize = "gnosis")]
     #[graphql(name = "gnosis")]
@@ -184,6 +191,8 @@ pub enum ChainType {
     Arweave,
     /// Basiclly an EVM, but with different address serializer, transaction packaging and genesis contracts.
     Conflux,
+    /// Of a chain we don't model yet.
+    Unknown,
 }
 impl Default for ChainType {
     fn default() -> Self {
@@ -217,7 +226,16 @@ impl Chain {
             Arbitrum => ChainType::EVM(42161),
             Optimism => ChainType::EVM(10),
             Crossbell => ChainType::EVM(3737),
-            Unknown => todo!(),
+            Unknown => ChainType::Unknown,
+        }
+    }
+
+    /// The one of chains known by many names (Gnosis, formerly xDai) records are saved
+    /// and looked up under. Use it on chains given by upstreams or clients.
+    pub fn canonical(self) -> Self {
+        match self {
+            Chain::Gnosis => Chain::Xdai,
+            chain => chain,
         }
     }
 }
@@ -281,7 +299,7 @@ impl ContractCategory {
             ENS => Some(Chain::Ethereum),
             ERC721 => Some(Chain::Ethereum),
             ERC1155 => Some(Chain::Ethereum),
-            POAP => Some(Chain::Ethereum),
+            POAP => Some(Chain::Xdai),
             _ => None,
         }
     }
@@ -384,6 +402,16 @@ impl Default for Contract {
     }
 }
 
+impl Freshness for Contract {
+    fn outdated_in() -> Duration {
+        Duration::hours(1)
+    }
+
+    fn fetched_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
 impl Contract {
     pub async fn find_by_chain_address(
         db: &DatabaseConnection,
@@ -401,6 +429,20 @@ impl Contract {
             Ok(Some(result.first().unwrap().to_owned().into()))
         }
     }
+
+    /// Contracts on any chain whose `symbol` is `symbol`, compared case-insensitively
+    /// since upstreams give them as they like (e.g. `USDC` and `usdc`).
+    pub async fn find_by_symbol(
+        db: &DatabaseConnection,
+        symbol: &str,
+    ) -> Result<Vec<ContractRecord>, Error> {
+        let aql = Aql::new(&[("contracts", Self::COLLECTION_NAME)])
+            .clause("FOR d IN @@contracts")
+            .clause("FILTER d.symbol != null AND LOWER(d.symbol) == @symbol")
+            .clause("RETURN d")
+            .bind("symbol", symbol.to_lowercase());
+        Ok(db.database().aql_query(aql.query()).await?)
+    }
 }
 
 #[async_trait::async_trait]
@@ -417,12 +459,14 @@ impl Vertex<ContractRecord> for Contract {
                 let mut to_be_created = self.clone();
                 to_be_created.updated_at = naive_now();
                 let created = DatabaseRecord::create(to_be_created, db).await?;
+                record_db_writes(1);
                 Ok(created.into())
             }
             Some(mut found) => {
                 found.updated_at = naive_now();
                 found.symbol = self.symbol.clone();
                 found.save(db).await?;
+                record_db_writes(1);
                 Ok(found)
             }
         }
@@ -444,17 +488,48 @@ impl Vertex<ContractRecord> for Contract {
 
     /// Outdated in 1 hour
     fn is_outdated(&self) -> bool {
-        let outdated_in = Duration::hours(1);
-        self.updated_at
-            .checked_add_signed(outdated_in)
-            .unwrap()
-            .lt(&naive_now())
+        self.is_stale()
     }
 }
 
 #[derive(Clone, Deserialize, Serialize, Default, Debug)]
 pub struct ContractRecord(pub DatabaseRecord<Contract>);
 
+impl ContractRecord {
+    /// Identities holding this contract (only token `id` of it, if given), ordered by key.
+    /// `limit` is capped at `MAX_PAGE_SIZE`, and `offset` of them are skipped.
+    pub async fn holders(
+        &self,
+        pool: &ConnectionPool,
+        id: Option<&str>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<IdentityRecord>, Error> {
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        let aql = Aql::new(&[
+            ("identities", Identity::COLLECTION_NAME),
+            ("contracts", Contract::COLLECTION_NAME),
+        ])
+        .clause("FOR vertex, edge IN 1..1 INBOUND @contract GRAPH @graph_name")
+        .clause("FILTER @id == null OR edge.id == @id")
+        .clause("COLLECT holder = vertex._id")
+        .clause("SORT holder")
+        .clause("LIMIT @offset, @limit")
+        .clause("RETURN DOCUMENT(holder)")
+        .bind("contract", self.id().as_str())
+        .bind("graph_name", "identities_contracts_graph")
+        .bind("id", id)
+        .bind("offset", offset)
+        .bind("limit", limit.clamp(1, MAX_PAGE_SIZE));
+        Ok(db.aql_query(aql.query()).await?)
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize, Default, Debug)]
 pub struct ToContractRecord {
     /// NFT_ID of ENS is a hash of domain. So domain can be used as NFT_ID.
@@ -529,6 +604,35 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_find_by_symbol() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let symbol = format!("USDC{}", Uuid::new_v4().simple()).to_uppercase();
+        let mut seeded = vec![];
+        for (chain, symbol) in [
+            (Chain::Ethereum, symbol.clone()),
+            (Chain::Polygon, symbol.to_lowercase()),
+        ] {
+            let contract = Contract {
+                chain,
+                symbol: Some(symbol),
+                ..Faker.fake()
+            };
+            seeded.push(contract.create_or_update(&db).await?);
+        }
+
+        for query in [symbol.clone(), symbol.to_lowercase()] {
+            let found = Contract::find_by_symbol(&db, &query).await?;
+            assert_eq!(found.len(), 2);
+            for contract in seeded.iter() {
+                assert!(found.iter().any(|f| f.key() == contract.key()));
+            }
+        }
+        assert!(Contract::find_by_symbol(&db, "USD").await?.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_contracts_hashmap() -> Result<(), Error> {
         let pool = new_connection_pool().await?;
@@ -541,4 +645,37 @@ mod tests {
         println!("{:#?}", result);
         Ok(())
     }
+
+    #[test]
+    fn test_rss3_networks() {
+        use std::str::FromStr;
+        use strum::IntoEnumIterator;
+
+        // `network`s an RSS3 note can be on. See https://docs.rss3.io
+        for network in [
+            "ethereum",
+            "ethereum_classic",
+            "binance_smart_chain",
+            "polygon",
+            "zksync",
+            "xdai",
+            "arweave",
+            "arbitrum",
+            "optimism",
+            "crossbell",
+        ] {
+            let chain = Chain::from_str(network).unwrap_or_default();
+            assert_ne!(chain, Chain::Unknown, "{} is not modeled", network);
+        }
+        assert_eq!(Chain::Gnosis.canonical(), Chain::Xdai);
+        assert_eq!(
+            ContractCategory::POAP.default_chain().unwrap(),
+            Chain::from_str("gnosis").unwrap().canonical()
+        );
+        // Never panics, even for those we don't model.
+        for chain in Chain::iter() {
+            chain.chain_type();
+        }
+        assert!(matches!(ChainType::default(), ChainType::Unknown));
+    }
 }
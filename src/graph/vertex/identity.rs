@@ -1,18 +1,16 @@
 use crate::{
+    config::{ConfigFreshness, UnchangedUpdate, C},
     error::Error,
-    graph::ConnectionPool,
+    graph::{aql::Aql, ConnectionPool, Freshness, ReadConsistency},
     graph::{
-        edge::{Hold, HoldRecord, Proof, ProofRecord},
+        edge::{resolve::DomainNameSystem, Hold, HoldRecord, Proof, ProofRecord, Resolve},
         vertex::vec_string_to_vec_datasource,
-        vertex::Vertex,
+        vertex::{contract::ContractCategory, Contract, Vertex},
     },
-    upstream::{DataSource, Platform},
+    upstream::{cost::record_db_writes, DataSource, Platform},
     util::naive_now,
 };
-use aragog::{
-    query::{Comparison, Filter},
-    DatabaseAccess, DatabaseConnection, DatabaseRecord, Record,
-};
+use aragog::{query::Comparison, DatabaseAccess, DatabaseConnection, DatabaseRecord, Record};
 use arangors_lite::AqlQuery;
 use array_tool::vec::Uniq;
 use async_trait::async_trait;
@@ -21,7 +19,11 @@ use dataloader::BatchFn;
 use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_value, json, value::Value};
-use std::collections::HashMap;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+};
+use strum_macros::{Display, EnumString};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -42,6 +44,10 @@ pub struct Identity {
     /// e.g. for `Twitter`, this is the user's `screen_name`.
     /// For `ethereum`, this is the reversed ENS name set by user.
     pub display_name: Option<String>,
+    /// Where `display_name` comes from, if there're multiple candidates (e.g. wallets).
+    /// Decides which one wins. See `C.upstream.display_name_priority`.
+    #[serde(default)]
+    pub display_name_source: Option<NameSource>,
     /// URL to target identity profile page on `platform` (if any).
     pub profile_url: Option<String>,
     /// URL to avatar (if any is recorded and given by target platform).
@@ -58,12 +64,90 @@ pub struct Identity {
     pub updated_at: NaiveDateTime,
 }
 
+/// Where a `display_name` comes from.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    async_graphql::Enum,
+)]
+pub enum NameSource {
+    /// ENS reverse record.
+    #[strum(serialize = "ens")]
+    #[serde(rename = "ens")]
+    #[graphql(name = "ens")]
+    ENS,
+    /// .bit reverse record.
+    #[strum(serialize = "dotbit")]
+    #[serde(rename = "dotbit")]
+    #[graphql(name = "dotbit")]
+    Dotbit,
+    /// Lens handle owned.
+    #[strum(serialize = "lens")]
+    #[serde(rename = "lens")]
+    #[graphql(name = "lens")]
+    Lens,
+    /// Handle of a social account proven to be the same person.
+    #[strum(serialize = "social")]
+    #[serde(rename = "social")]
+    #[graphql(name = "social")]
+    Social,
+}
+
+/// Rank of `source` in `priority`, the smaller the better. Unlisted ones (or `None`) go last.
+fn name_rank(priority: &[NameSource], source: Option<NameSource>) -> usize {
+    source
+        .and_then(|s| priority.iter().position(|p| *p == s))
+        .unwrap_or(priority.len())
+}
+
+/// Should `incoming` replace `current` as `(display_name, display_name_source)`?
+/// - An empty name (reverse record cleared) only clears one from the same source.
+/// - Otherwise the one with higher priority wins, or the newer one if they're the same.
+/// - An empty or sourceless `current` is always replaced.
+pub(crate) fn display_name_wins(
+    priority: &[NameSource],
+    incoming: (&str, Option<NameSource>),
+    current: (Option<&str>, Option<NameSource>),
+) -> bool {
+    let (name, source) = incoming;
+    let (current_name, current_source) = current;
+    if current_source.is_none() || current_name.map_or(true, str::is_empty) {
+        return true;
+    }
+    if name.is_empty() {
+        return source == current_source;
+    }
+    name_rank(priority, source) <= name_rank(priority, current_source)
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Path {
     pub vertices: Vec<IdentityRecord>,
     pub edges: Vec<ProofRecord>,
 }
 
+/// A neighbor found by `neighbor_page`: the sources of edges reaching it, and the shortest depth.
+#[derive(Debug, Clone, Deserialize)]
+struct NeighborRow {
+    identity: IdentityRecord,
+    sources: Vec<String>,
+    depth: u16,
+}
+
+/// A traversal path together with its length (amount of edges).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct PathWithDepth {
+    path: Path,
+    depth: u16,
+}
+
 impl Default for Identity {
     fn default() -> Self {
         Self {
@@ -71,6 +155,7 @@ impl Default for Identity {
             platform: Platform::Twitter,
             identity: Default::default(),
             display_name: Default::default(),
+            display_name_source: None,
             profile_url: None,
             avatar_url: None,
             created_at: None,
@@ -86,24 +171,51 @@ impl PartialEq for Identity {
     }
 }
 
+impl Freshness for Identity {
+    fn outdated_in() -> Duration {
+        Duration::hours(ConfigFreshness::current().identity_hours as i64)
+    }
+
+    fn fetched_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
 impl Identity {
+    /// If every field we store is the same as `other`'s, except `added_at` / `updated_at`.
+    /// Unlike `==`, which compares `uuid` only.
+    fn same_content(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+            && self.platform == other.platform
+            && self.identity == other.identity
+            && self.display_name == other.display_name
+            && self.display_name_source == other.display_name_source
+            && self.profile_url == other.profile_url
+            && self.avatar_url == other.avatar_url
+            && self.created_at == other.created_at
+    }
+
     /// Find record by given platform and identity.
+    /// On case-insensitive platforms (see `Platform::is_case_insensitive`), `identity` in any casing
+    /// matches. Those stored before they were folded are still found by their exact form.
     pub async fn find_by_platform_identity(
         db: &DatabaseConnection,
         platform: &Platform,
         identity: &str,
     ) -> Result<Option<IdentityRecord>, Error> {
-        let query = Self::query().filter(
-            Filter::new(Comparison::field("platform").equals_str(platform))
-                .and(Comparison::field("identity").equals_str(identity)),
-        );
-        let query_result = Self::get(&query, db).await?;
-
-        if query_result.len() == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(query_result.first().unwrap().to_owned().into()))
+        let mut identities = vec![platform.fold_identity(identity)];
+        if identities[0] != identity {
+            identities.push(identity.to_string());
         }
+        let aql = Aql::new(&[("identities", Self::COLLECTION_NAME)])
+            .clause("FOR d IN @@identities")
+            .clause("FILTER d.platform == @platform AND d.identity IN @identities")
+            .clause("LIMIT 1")
+            .clause("RETURN d")
+            .bind("platform", json!(platform))
+            .bind("identities", identities);
+        let result: Vec<IdentityRecord> = db.database().aql_query(aql.query()).await?;
+        Ok(result.into_iter().next())
 
         /* Use connection pool
         let db = pool.db().await?;
@@ -126,34 +238,92 @@ impl Identity {
         */
     }
 
+    /// Twitter identities are keyed by numeric user ID, with the handle (`screen_name`) as `display_name`.
+    /// Find one by either form (`@` prefix of handle is optional).
+    /// Handle is matched as-is or lowercased, so that `PlatformDisplayName` index can be used.
+    /// Prefers the numeric-ID-keyed record, then falls back to legacy ones keyed by (lowercased) handle.
+    pub async fn find_twitter(
+        db: &DatabaseConnection,
+        id_or_handle: &str,
+    ) -> Result<Option<IdentityRecord>, Error> {
+        let id_or_handle = id_or_handle.trim().trim_start_matches('@');
+        if is_twitter_id(id_or_handle) {
+            return Self::find_by_platform_identity(db, &Platform::Twitter, id_or_handle).await;
+        }
+
+        let handles = vec![id_or_handle.to_string(), id_or_handle.to_lowercase()];
+        let aql = r"FOR v IN @@collection_name
+        FILTER v.platform == @platform AND v.display_name IN @handles
+        RETURN v";
+        let aql = AqlQuery::new(aql)
+            .bind_var("@collection_name", Identity::COLLECTION_NAME)
+            .bind_var("platform", Platform::Twitter.to_string())
+            .bind_var("handles", json!(handles))
+            .batch_size(1)
+            .count(false);
+        let found: Vec<IdentityRecord> = db.database().aql_query(aql).await?;
+        if let Some(record) = found.into_iter().find(|r| is_twitter_id(&r.identity)) {
+            return Ok(Some(record));
+        }
+
+        Self::find_by_platform_identity(db, &Platform::Twitter, &id_or_handle.to_lowercase()).await
+    }
+
+    /// Find records of `identity` on each of `platforms`. Ordered as `platforms`.
     pub async fn find_by_platforms_identity(
         pool: &ConnectionPool,
         platforms: &Vec<Platform>,
         identity: &str,
     ) -> Result<Vec<IdentityRecord>, Error> {
-        // let db = pool.db().await?;
+        // Folded or as it is, on each platform. See `find_by_platform_identity`.
+        let pairs: Vec<(Platform, String)> = platforms
+            .iter()
+            .map(|platform| (*platform, identity.to_string()))
+            .collect();
+        let mut result = Self::find_by_platform_identity_pairs(pool, &pairs).await?;
+        // Same order as `platforms`.
+        result.sort_by_key(|record| platforms.iter().position(|p| *p == record.platform));
+        Ok(result)
+    }
+
+    /// Find records of every `(platform, identity)` in `pairs` at once.
+    /// Those not found are omitted. Not in any particular order.
+    pub async fn find_by_platform_identity_pairs(
+        pool: &ConnectionPool,
+        pairs: &[(Platform, String)],
+    ) -> Result<Vec<IdentityRecord>, Error> {
+        if pairs.is_empty() {
+            return Ok(vec![]);
+        }
         let conn = pool
             .get()
             .await
             .map_err(|err| Error::PoolError(err.to_string()))?;
         let db = conn.database();
 
-        let platform_array: Vec<Value> = platforms
+        // Stored either folded or as it is. See `find_by_platform_identity`.
+        let candidates: HashSet<(Platform, String)> = pairs
+            .iter()
+            .flat_map(|(platform, identity)| {
+                [
+                    (*platform, platform.fold_identity(identity)),
+                    (*platform, identity.clone()),
+                ]
+            })
+            .collect();
+        let candidates: Vec<Value> = candidates
             .into_iter()
-            .map(|field| json!(field.to_string()))
+            .map(|(platform, identity)| json!([platform, identity]))
             .collect();
-
-        let aql = r"FOR v IN @@collection_name
-        FILTER v.identity == @identity AND v.platform IN @platform
-        RETURN v";
-        let aql = AqlQuery::new(aql)
-            .bind_var("@collection_name", Identity::COLLECTION_NAME)
-            .bind_var("identity", identity)
-            .bind_var("platform", platform_array)
-            .batch_size(1)
-            .count(false);
-        let result: Vec<IdentityRecord> = db.aql_query(aql).await?;
-        Ok(result)
+        // Looked up pair by pair, so that the (platform, identity) index is used.
+        let aql = Aql::new(&[])
+            .bind_collection("identities", Identity::COLLECTION_NAME)
+            .clause("FOR p IN @pairs")
+            .clause("FOR v IN @@identities")
+            .clause("FILTER v.platform == p[0] AND v.identity == p[1]")
+            .clause("RETURN v")
+            .bind("pairs", candidates);
+        Ok(db.aql_query(aql.query()).await?)
     }
 
     #[allow(unused)]
@@ -202,6 +372,7 @@ impl Vertex<IdentityRecord> for Identity {
             None => {
                 // Create
                 let mut to_be_created = self.clone();
+                to_be_created.identity = self.platform.fold_identity(&self.identity);
                 to_be_created.uuid = to_be_created.uuid.or(Some(Uuid::new_v4()));
                 to_be_created.added_at = naive_now();
                 to_be_created.updated_at = naive_now();
@@ -210,7 +381,10 @@ impl Vertex<IdentityRecord> for Identity {
 
                 // Must do this to avoid "future cannot be sent between threads safely" complain from compiler.
                 match DatabaseRecord::create(to_be_created, db).await {
-                    Ok(created) => return Ok(created.into()),
+                    Ok(created) => {
+                        record_db_writes(1);
+                        return Ok(created.into());
+                    }
                     // An exception is raised from ArangoDB complaining about unique index violation.
                     // Refetch it later (after leaving this block).
                     // Since `bool` is `Send`able.
@@ -223,10 +397,18 @@ impl Vertex<IdentityRecord> for Identity {
                 };
 
                 if need_refetch {
+                    // Someone else created it first. Theirs (and its `uuid`) wins.
                     let found =
                         Self::find_by_platform_identity(db, &self.platform, &self.identity).await?;
-                    // FIXME: `.except()` below DOES have chance to be triggered. Really should take a look at the whole fn.
-                    Ok(found.expect("Not found after an race condition in create_or_update"))
+                    found.ok_or_else(|| {
+                        Error::General(
+                            format!(
+                                "Identity {}/{} not found after a conflict in create_or_update",
+                                self.platform, self.identity
+                            ),
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                        )
+                    })
                 } else {
                     Err(Error::General("Impossible: no refetch triggered nor record found / created in create_or_update".into(), StatusCode::INTERNAL_SERVER_ERROR))
                 }
@@ -234,13 +416,39 @@ impl Vertex<IdentityRecord> for Identity {
 
             Some(mut found) => {
                 // Update
-                found.display_name = self.display_name.clone().or(found.display_name.clone());
+                // `uuid` of an existing identity is never replaced, since downstream systems key on it.
+                // Those legacy records without one get `self.uuid` (and keep it afterwards).
+                let recorded = found.0.record.clone();
+                found.uuid = found.uuid.or(self.uuid).or_else(|| Some(Uuid::new_v4()));
+                if let Some(name) = self.display_name.as_deref() {
+                    if display_name_wins(
+                        &C.upstream.display_name_priority,
+                        (name, self.display_name_source),
+                        (found.display_name.as_deref(), found.display_name_source),
+                    ) {
+                        found.display_name = Some(name.to_string());
+                        found.display_name_source = self.display_name_source;
+                    }
+                }
                 found.profile_url = self.profile_url.clone();
                 found.avatar_url = self.avatar_url.clone();
                 found.created_at = self.created_at.or(found.created_at);
+                if found.same_content(&recorded) {
+                    // Nothing changed. Don't rewrite the whole record for it.
+                    match C.upstream.unchanged_update {
+                        UnchangedUpdate::Skip => return Ok(found),
+                        UnchangedUpdate::Touch => {
+                            found.updated_at = naive_now();
+                            touch(db, &found).await?;
+                            record_db_writes(1);
+                            return Ok(found);
+                        }
+                    }
+                }
                 found.updated_at = naive_now();
 
                 found.save(db).await?;
+                record_db_writes(1);
                 Ok(found)
             }
         }
@@ -261,14 +469,21 @@ impl Vertex<IdentityRecord> for Identity {
 
     /// Judge if this record is outdated and should be refetched.
     fn is_outdated(&self) -> bool {
-        let outdated_in = Duration::hours(1);
-        self.updated_at
-            .checked_add_signed(outdated_in)
-            .unwrap()
-            .lt(&naive_now())
+        self.is_stale()
     }
 }
 
+/// Write `updated_at` of `record`, leaving every other field as it's stored.
+async fn touch(db: &DatabaseConnection, record: &IdentityRecord) -> Result<(), Error> {
+    let aql = Aql::new(&[])
+        .clause("UPDATE @key WITH { updated_at: @updated_at } IN @@identities")
+        .bind_collection("identities", Identity::COLLECTION_NAME)
+        .bind("key", record.key().as_str())
+        .bind("updated_at", json!(record.updated_at));
+    let _: Vec<Value> = db.database().aql_query(aql.query()).await?;
+    Ok(())
+}
+
 /// Result struct queried from graph database.
 /// Useful by GraphQL side to wrap more function / traits.
 #[derive(Clone, Deserialize, Serialize, Default, Debug)]
@@ -286,6 +501,283 @@ pub struct ToIdentityRecord {
 pub struct IdentityWithSource {
     pub identity: IdentityRecord,
     pub sources: Vec<DataSource>,
+    /// At which depth this neighbor is found. `1` means connected directly.
+    /// The shortest one if it can be reached through multiple paths.
+    pub depth: u16,
+}
+
+/// A page of neighbors ordered by `_key`, so pages stay stable while records are updated.
+/// See `IdentityRecord::neighbor_page`.
+#[derive(Clone, Debug, Default)]
+pub struct NeighborPage {
+    pub neighbors: Vec<IdentityWithSource>,
+    /// More neighbors follow the last one of this page.
+    pub has_next_page: bool,
+}
+
+/// Opaque cursor pointing at a neighbor, i.e. its `_key` encoded.
+pub fn neighbor_cursor(neighbor: &IdentityWithSource) -> String {
+    base64::encode(neighbor.identity.key())
+}
+
+/// `_key` encoded in a cursor given by `neighbor_cursor`.
+fn parse_neighbor_cursor(cursor: &str) -> Result<String, Error> {
+    base64::decode(cursor)
+        .ok()
+        .and_then(|key| String::from_utf8(key).ok())
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| Error::ParamError(format!("Invalid cursor: {}", cursor)))
+}
+
+/// Range of `created_at` of the connecting `Proof`.
+/// Either end can be omitted to make it open-ended.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CreatedAtRange {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+/// Key to sort neighbors by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum NeighborSortKey {
+    /// When the neighbor identity is added into this database.
+    #[graphql(name = "added_at")]
+    AddedAt,
+
+    /// When the neighbor identity is re-fetched by us.
+    #[graphql(name = "updated_at")]
+    UpdatedAt,
+
+    /// Platform name of the neighbor identity.
+    #[graphql(name = "platform")]
+    Platform,
+
+    /// How many distinct upstreams confirm the connection to the neighbor.
+    #[graphql(name = "confidence")]
+    Confidence,
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum SortOrder {
+    #[default]
+    #[graphql(name = "asc")]
+    Asc,
+
+    #[graphql(name = "desc")]
+    Desc,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NeighborSort {
+    pub by: NeighborSortKey,
+    pub order: SortOrder,
+}
+
+impl NeighborSort {
+    fn compare(&self, a: &IdentityWithSource, b: &IdentityWithSource) -> Ordering {
+        let ordering = match self.by {
+            NeighborSortKey::AddedAt => a.identity.added_at.cmp(&b.identity.added_at),
+            NeighborSortKey::UpdatedAt => a.identity.updated_at.cmp(&b.identity.updated_at),
+            NeighborSortKey::Platform => a
+                .identity
+                .platform
+                .to_string()
+                .cmp(&b.identity.platform.to_string()),
+            NeighborSortKey::Confidence => a.sources.len().cmp(&b.sources.len()),
+        };
+        match self.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// How `neighbors` traverses and sorts. By default, neighbors connected directly,
+/// through any upstream, not sorted, read from leader.
+#[derive(Clone, Copy, Debug)]
+pub struct NeighborOptions {
+    /// Depth of traversal. `1` means connected directly.
+    pub depth: u16,
+    /// Only through proofs created in this range.
+    pub created_between: Option<CreatedAtRange>,
+    /// Ignored by `neighbor_page`, which is ordered by `_key`.
+    pub sort: Option<NeighborSort>,
+    /// See `ReadConsistency`.
+    pub consistency: ReadConsistency,
+    /// If `false`, edges from `DataSource::Unknown` (e.g. left by incomplete imports) are not traversed.
+    pub include_unknown_sources: bool,
+}
+
+impl Default for NeighborOptions {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            created_between: None,
+            sort: None,
+            consistency: ReadConsistency::default(),
+            include_unknown_sources: true,
+        }
+    }
+}
+
+/// Numeric Twitter user ID, as opposed to a handle (which must contain a letter or `_`).
+fn is_twitter_id(identity: &str) -> bool {
+    !identity.is_empty() && identity.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Which logical identity `identity` is, by the same rules records are found by
+/// (see `find_by_platform_identity` and `find_twitter`). A legacy Twitter record keyed by handle is
+/// the one keyed by numeric ID with that handle as `display_name`, if `handles` (handle => numeric ID) has it.
+fn equivalence_key(identity: &Identity, handles: &HashMap<String, String>) -> (Platform, String) {
+    if identity.platform == Platform::Twitter && !is_twitter_id(&identity.identity) {
+        let handle = identity.identity.trim_start_matches('@').to_lowercase();
+        let key = handles.get(&handle).cloned().unwrap_or(handle);
+        return (Platform::Twitter, key);
+    }
+    (
+        identity.platform,
+        identity.platform.fold_identity(&identity.identity),
+    )
+}
+
+/// Merge neighbors which are the same identity recorded more than once (e.g. a Twitter user
+/// keyed by numeric ID and its legacy duplicate keyed by handle), and drop those the same as `root`.
+/// Only the response is deduplicated: records are left as they are.
+/// The preferred record (keyed by numeric ID for Twitter) is kept at the place of the first one,
+/// found at the shortest depth of them, through all their sources.
+pub fn collapse_equivalents(
+    root: &Identity,
+    neighbors: Vec<IdentityWithSource>,
+) -> Vec<IdentityWithSource> {
+    let handles: HashMap<String, String> = neighbors
+        .iter()
+        .map(|n| &n.identity.0.record)
+        .chain(std::iter::once(root))
+        .filter(|i| i.platform == Platform::Twitter && is_twitter_id(&i.identity))
+        .filter_map(|i| {
+            let handle = i.display_name.as_deref()?.trim_start_matches('@');
+            Some((handle.to_lowercase(), i.identity.clone()))
+        })
+        .collect();
+    let is_preferred = |i: &Identity| i.platform != Platform::Twitter || is_twitter_id(&i.identity);
+    let root_key = equivalence_key(root, &handles);
+
+    let mut collapsed: Vec<IdentityWithSource> = vec![];
+    let mut index: HashMap<(Platform, String), usize> = HashMap::new();
+    for neighbor in neighbors {
+        let key = equivalence_key(&neighbor.identity, &handles);
+        if key == root_key {
+            continue;
+        }
+        match index.get(&key) {
+            None => {
+                index.insert(key, collapsed.len());
+                collapsed.push(neighbor);
+            }
+            Some(&at) => {
+                let kept = &mut collapsed[at];
+                if !is_preferred(&kept.identity) && is_preferred(&neighbor.identity) {
+                    kept.identity = neighbor.identity;
+                }
+                kept.depth = kept.depth.min(neighbor.depth);
+                for source in neighbor.sources {
+                    if !kept.sources.contains(&source) {
+                        kept.sources.push(source);
+                    }
+                }
+            }
+        }
+    }
+    collapsed
+}
+
+/// An ENS domain with its subdomains, nested to any depth.
+#[derive(Clone, Debug, PartialEq, Eq, async_graphql::SimpleObject)]
+pub struct DomainGroup {
+    /// e.g. `vitalik.eth`
+    pub name: String,
+    /// e.g. `blog.vitalik.eth`. Sorted by name.
+    pub subdomains: Vec<DomainGroup>,
+}
+
+/// Nest each of `names` under its closest parent domain which is also in `names`.
+/// e.g. `[a.b.eth, b.eth, c.a.b.eth]` => `b.eth { a.b.eth { c.a.b.eth } }`.
+pub fn group_domains(names: Vec<String>) -> Vec<DomainGroup> {
+    let names: BTreeSet<String> = names.into_iter().map(|name| name.to_lowercase()).collect();
+    let parent_of = |name: &str| -> Option<String> {
+        let mut rest = name;
+        while let Some((_, parent)) = rest.split_once('.') {
+            if names.contains(parent) {
+                return Some(parent.to_string());
+            }
+            rest = parent;
+        }
+        None
+    };
+
+    let mut children: HashMap<Option<String>, Vec<String>> = HashMap::new();
+    for name in names.iter() {
+        children
+            .entry(parent_of(name))
+            .or_default()
+            .push(name.clone());
+    }
+
+    fn build(
+        parent: Option<String>,
+        children: &HashMap<Option<String>, Vec<String>>,
+    ) -> Vec<DomainGroup> {
+        children
+            .get(&parent)
+            .map(|names| {
+                names
+                    .iter()
+                    .map(|name| DomainGroup {
+                        name: name.clone(),
+                        subdomains: build(Some(name.clone()), children),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+    build(None, &children)
+}
+
+/// Sort neighbors by given key. Ties (and everything, if `sort` is `None`)
+/// are ordered by `platform` and `identity` so the result is always deterministic.
+fn sort_neighbors(neighbors: &mut [IdentityWithSource], sort: Option<NeighborSort>) {
+    neighbors.sort_by(|a, b| {
+        sort.map(|s| s.compare(a, b))
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| {
+                a.identity
+                    .platform
+                    .to_string()
+                    .cmp(&b.identity.platform.to_string())
+            })
+            .then_with(|| a.identity.identity.cmp(&b.identity.identity))
+    });
+}
+
+/// First avatar found in `cluster` by platform `priority`. Platforms not listed come last.
+fn pick_avatar(priority: &[Platform], cluster: &[&Identity]) -> Option<String> {
+    let rank = |platform: &Platform| {
+        priority
+            .iter()
+            .position(|p| p == platform)
+            .unwrap_or(priority.len())
+    };
+    cluster
+        .iter()
+        .filter(|identity| {
+            identity
+                .avatar_url
+                .as_ref()
+                .map_or(false, |url| !url.is_empty())
+        })
+        // Stable: same platform ones stay in `cluster` order.
+        .min_by_key(|identity| rank(&identity.platform))
+        .and_then(|identity| identity.avatar_url.clone())
 }
 
 #[derive(Clone, Deserialize, Serialize, Default, Debug)]
@@ -354,6 +846,36 @@ impl BatchFn<String, Option<IdentityRecord>> for IdentityLoadFn {
     }
 }
 
+/// Loads identities by `(platform, identity)`.
+pub struct PlatformIdentityLoadFn {
+    pub pool: ConnectionPool,
+}
+
+#[async_trait::async_trait]
+impl BatchFn<(Platform, String), Option<IdentityRecord>> for PlatformIdentityLoadFn {
+    async fn load(
+        &mut self,
+        keys: &[(Platform, String)],
+    ) -> HashMap<(Platform, String), Option<IdentityRecord>> {
+        debug!("Loading Identity for: {:?}", keys);
+        let found = Identity::find_by_platform_identity_pairs(&self.pool, keys)
+            .await
+            .unwrap_or_default();
+        keys.iter()
+            .map(|(platform, identity)| {
+                let folded = platform.fold_identity(identity);
+                let record = found
+                    .iter()
+                    .find(|r| {
+                        r.platform == *platform && (r.identity == folded || &r.identity == identity)
+                    })
+                    .cloned();
+                ((*platform, identity.clone()), record)
+            })
+            .collect()
+    }
+}
+
 /// It already returns Dataloader friendly output given the NFT IDs.
 async fn get_identities(
     pool: &ConnectionPool,
@@ -451,12 +973,12 @@ async fn get_from_to_record(
 }
 
 impl IdentityRecord {
-    /// Returns all neighbors of this identity. Depth and upstream data souce can be specified.
+    /// Returns all neighbors of this identity, traversed as `options` says.
+    /// Use `NeighborOptions::default()` if unsure.
     pub async fn neighbors(
         &self,
         pool: &ConnectionPool,
-        depth: u16,
-        _source: Option<DataSource>,
+        options: NeighborOptions,
     ) -> Result<Vec<IdentityWithSource>, Error> {
         // let db = pool.db().await?;
         let conn = pool
@@ -465,33 +987,26 @@ impl IdentityRecord {
             .map_err(|err| Error::PoolError(err.to_string()))?;
         let db = conn.database();
 
-        let aql_str = r"
-        WITH @@collection_name FOR d IN @@collection_name
-          FILTER d._id == @id
-          LIMIT 1
-          FOR vertex, edge, path
-            IN 1..@depth
-            ANY d GRAPH @graph_name
-            RETURN path";
+        let aql = self
+            .neighbor_traversal(Proof::COLLECTION_NAME, &options)
+            .clause("RETURN { path: path, depth: LENGTH(path.edges) }");
+        // The cursor is drained (following `hasMore`), so every path is read.
+        let resp: Vec<Value> = options.consistency.query(db, &aql).await?;
 
-        let aql = AqlQuery::new(aql_str)
-            .bind_var("@collection_name", Identity::COLLECTION_NAME)
-            .bind_var("graph_name", "identities_proofs_graph")
-            .bind_var("id", self.id().as_str())
-            .bind_var("depth", depth)
-            .batch_size(1)
-            .count(false);
-
-        let resp: Vec<Value> = db.aql_query(aql).await?;
         let mut identity_map: HashMap<String, IdentityRecord> = HashMap::new();
         let mut sources_map: HashMap<String, Vec<String>> = HashMap::new();
+        let mut depth_map: HashMap<String, u16> = HashMap::new();
         for p in resp {
-            let path: Path = from_value(p)?;
+            let PathWithDepth { path, depth } = from_value(p)?;
 
             let last = path.vertices.last().unwrap().to_owned();
             let last_edge = path.edges.last().unwrap().to_owned();
             let key = last.id().to_string();
             identity_map.entry(key.clone()).or_insert(last);
+            depth_map
+                .entry(key.clone())
+                .and_modify(|found| *found = (*found).min(depth))
+                .or_insert(depth);
             sources_map
                 .entry(key.clone())
                 .or_insert_with(|| Vec::new())
@@ -510,6 +1025,7 @@ impl IdentityRecord {
                         let id = IdentityWithSource {
                             sources: _sources,
                             identity: v.to_owned(),
+                            depth: depth_map.get(k).copied().unwrap_or_default(),
                         };
                         identity_sources.push(id);
                     }
@@ -517,9 +1033,127 @@ impl IdentityRecord {
                 None => continue,
             };
         }
+        sort_neighbors(&mut identity_sources, options.sort);
         Ok(identity_sources)
     }
 
+    /// Paths (`vertex`, `edge`, `path`) from this identity through `edge_collection`,
+    /// filtered as `neighbors` does. What to return is left to the clauses following it.
+    fn neighbor_traversal(&self, edge_collection: &str, options: &NeighborOptions) -> Aql {
+        let NeighborOptions {
+            depth,
+            created_between,
+            include_unknown_sources,
+            ..
+        } = *options;
+        let range = created_between.unwrap_or_default();
+        let mut aql = Aql::new(&[("identities", Identity::COLLECTION_NAME)])
+            .clause("FOR d IN @@identities")
+            .clause("FILTER d._id == @id")
+            .clause("LIMIT 1")
+            .clause("FOR vertex, edge, path IN 1..@depth ANY d @@edges")
+            // `null` is less than anything in AQL. Exclude those connections without `created_at` explicitly.
+            .clause_if(created_between.is_some(), "FILTER edge.created_at != null")
+            .clause_if(
+                range.from.is_some(),
+                "FILTER edge.created_at >= @created_from",
+            )
+            .clause_if(range.to.is_some(), "FILTER edge.created_at <= @created_to")
+            .clause_if(
+                !include_unknown_sources,
+                "FILTER path.edges[*].source NONE == @unknown",
+            )
+            .bind_collection("edges", edge_collection)
+            .bind("id", self.id().as_str())
+            .bind("depth", depth);
+        if let Some(from) = range.from {
+            aql = aql.bind("created_from", json!(from));
+        }
+        if let Some(to) = range.to {
+            aql = aql.bind("created_to", json!(to));
+        }
+        if !include_unknown_sources {
+            aql = aql.bind("unknown", json!(DataSource::Unknown));
+        }
+        aql
+    }
+
+    /// Same as `neighbors`, but `first` of them (ordered by `_key`) after the cursor `after`.
+    /// Paging is done in AQL, so only the page itself is read.
+    pub async fn neighbor_page(
+        &self,
+        pool: &ConnectionPool,
+        options: NeighborOptions,
+        first: usize,
+        after: Option<&str>,
+    ) -> Result<NeighborPage, Error> {
+        let after = after.map(parse_neighbor_cursor).transpose()?;
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        // One more than asked to tell if there's a next page.
+        let mut aql = self
+            .neighbor_traversal(Proof::COLLECTION_NAME, &options)
+            .clause_if(after.is_some(), "FILTER vertex._key > @after")
+            .clause("COLLECT key = vertex._key INTO found = { vertex, source: edge.source, depth: LENGTH(path.edges) }")
+            .clause("SORT key")
+            .clause("LIMIT @limit")
+            .clause("RETURN { identity: FIRST(found[*].vertex), sources: UNIQUE(found[*].source), depth: MIN(found[*].depth) }")
+            .bind("limit", first + 1);
+        if let Some(after) = after.as_deref() {
+            aql = aql.bind("after", after);
+        }
+        let mut rest: Vec<IdentityWithSource> = vec![];
+        for row in options.consistency.query::<NeighborRow>(db, &aql).await? {
+            rest.push(IdentityWithSource {
+                identity: row.identity,
+                sources: vec_string_to_vec_datasource(row.sources)?,
+                depth: row.depth,
+            });
+        }
+
+        let has_next_page = rest.len() > first;
+        rest.truncate(first);
+        Ok(NeighborPage {
+            neighbors: rest,
+            has_next_page,
+        })
+    }
+
+    /// Shortest path through proofs from this identity to `to`.
+    /// `None` if they're not connected within `max_depth`.
+    pub async fn connection_path(
+        &self,
+        pool: &ConnectionPool,
+        to: &IdentityRecord,
+        max_depth: u16,
+    ) -> Result<Option<Path>, Error> {
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        // The first step has no edge.
+        let aql = Aql::new(&[("identities", Identity::COLLECTION_NAME)])
+            .clause("LET steps = (")
+            .clause("  FOR vertex, edge IN ANY SHORTEST_PATH @from TO @to GRAPH @graph_name")
+            .clause("    RETURN { vertex, edge }")
+            .clause(")")
+            .clause("FILTER LENGTH(steps) > 0 AND LENGTH(steps) - 1 <= @depth")
+            .clause("RETURN { vertices: steps[*].vertex, edges: SLICE(steps, 1)[*].edge }")
+            .bind("graph_name", "identities_proofs_graph")
+            .bind("from", self.id().as_str())
+            .bind("to", to.id().as_str())
+            .bind("depth", max_depth);
+
+        let found: Vec<Path> = db.aql_query(aql.query()).await?;
+        Ok(found.into_iter().next())
+    }
+
     // Return lens owned by wallet address.
     pub async fn lens_owned_by(
         &self,
@@ -532,24 +1166,17 @@ impl IdentityRecord {
             .map_err(|err| Error::PoolError(err.to_string()))?;
         let db = conn.database();
 
-        let aql_str = r"
-          WITH @@collection_name
-            FOR d IN @@collection_name
-            FILTER d._id == @id AND d.platform == @platform
-            LIMIT 1
-            FOR vertex
-                IN 1..1 ANY d GRAPH @graph_name
-                RETURN DISTINCT vertex";
-
-        let aql = AqlQuery::new(aql_str)
-            .bind_var("@collection_name", Identity::COLLECTION_NAME)
-            .bind_var("graph_name", "identities_proofs_graph")
-            .bind_var("id", self.id().as_str())
-            .bind_var("platform", "lens")
-            .batch_size(1)
-            .count(false);
+        let aql = Aql::new(&[("identities", Identity::COLLECTION_NAME)])
+            .clause("FOR d IN @@identities")
+            .clause("FILTER d._id == @id AND d.platform == @platform")
+            .clause("LIMIT 1")
+            .clause("FOR vertex IN 1..1 ANY d GRAPH @graph_name")
+            .clause("RETURN DISTINCT vertex")
+            .bind("graph_name", "identities_proofs_graph")
+            .bind("id", self.id().as_str())
+            .bind("platform", json!(Platform::Lens));
 
-        let result = db.aql_query::<IdentityRecord>(aql).await?;
+        let result = db.aql_query::<IdentityRecord>(aql.query()).await?;
 
         if result.len() == 0 {
             Ok(None)
@@ -559,11 +1186,13 @@ impl IdentityRecord {
     }
 
     // Return all neighbors of this identity with path<ProofRecord>
+    // Edges from `DataSource::Unknown` are skipped unless `include_unknown_sources`.
     pub async fn neighbors_with_traversal(
         &self,
         pool: &ConnectionPool,
         depth: u16,
         source: Option<DataSource>,
+        include_unknown_sources: bool,
     ) -> Result<Vec<ProofRecord>, Error> {
         // Using graph speed up FILTER
         // let db = pool.db().await?;
@@ -583,6 +1212,7 @@ impl IdentityRecord {
                   FOR vertex, edge, path
                     IN 1..@depth
                     ANY d GRAPH @graph_name
+                    FILTER @include_unknown OR path.edges[*].`source` NONE == @unknown
                     RETURN DISTINCT edge";
 
                 aql = AqlQuery::new(aql_str)
@@ -590,6 +1220,8 @@ impl IdentityRecord {
                     .bind_var("graph_name", "identities_proofs_graph")
                     .bind_var("id", self.id().as_str())
                     .bind_var("depth", depth)
+                    .bind_var("include_unknown", include_unknown_sources)
+                    .bind_var("unknown", DataSource::Unknown.to_string().as_str())
                     .batch_size(1)
                     .count(false);
             }
@@ -623,8 +1255,144 @@ impl IdentityRecord {
         Ok(paths)
     }
 
+    /// Wallet identities (see `Platform::is_wallet`) connected to this identity
+    /// within `depth`, with the sources connecting them.
+    pub async fn wallets(
+        &self,
+        pool: &ConnectionPool,
+        depth: u16,
+    ) -> Result<Vec<IdentityWithSource>, Error> {
+        Ok(self
+            .neighbors(
+                pool,
+                NeighborOptions {
+                    depth,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .into_iter()
+            .filter(|neighbor| neighbor.identity.platform.is_wallet())
+            .collect())
+    }
+
+    /// Avatar of this identity and those connected to it within `depth`, from the platform
+    /// coming first in `C.upstream.avatar_priority` which has one.
+    /// Among the same platform, its own avatar wins, then the nearest ones.
+    pub async fn best_avatar(
+        &self,
+        pool: &ConnectionPool,
+        depth: u16,
+    ) -> Result<Option<String>, Error> {
+        let mut neighbors = self
+            .neighbors(
+                pool,
+                NeighborOptions {
+                    depth,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        neighbors.sort_by_key(|neighbor| neighbor.depth);
+        let cluster: Vec<&Identity> = std::iter::once(&self.0.record)
+            .chain(neighbors.iter().map(|neighbor| &neighbor.identity.0.record))
+            .collect();
+        Ok(pick_avatar(&C.upstream.avatar_priority, &cluster))
+    }
+
+    /// ENS domains owned by (`Hold`) or resolving to (`Resolve`) this identity,
+    /// with all known subdomains of them (no matter who owns those) grouped under them.
+    pub async fn ens_domains(&self, pool: &ConnectionPool) -> Result<Vec<DomainGroup>, Error> {
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        let aql_str = r"
+        WITH @@hold_collection_name, @@resolve_collection_name, @@contract_collection_name
+        LET root_holds = (
+          FOR h IN @@hold_collection_name
+            FILTER h._from == @id
+            FILTER DOCUMENT(h._to).category == @category
+            RETURN h
+        )
+        LET root_resolves = (
+          FOR r IN @@resolve_collection_name
+            FILTER r._to == @id AND r.system == @system
+            RETURN r
+        )
+        LET roots = UNION_DISTINCT(root_holds[*].id, root_resolves[*].name)
+        // Subdomains live in the same contract as their roots.
+        // Starting from it avoids scanning every `Hold` / `Resolve`.
+        LET contracts = UNION_DISTINCT(root_holds[*]._to, root_resolves[*]._from)
+        LET names = UNION_DISTINCT(
+          (FOR c IN contracts
+            FOR h IN @@hold_collection_name
+              FILTER h._to == c
+              RETURN h.id),
+          (FOR c IN contracts
+            FOR r IN @@resolve_collection_name
+              FILTER r._from == c AND r.system == @system
+              RETURN r.name)
+        )
+        FOR name IN names
+          FILTER name IN roots OR LENGTH(
+            FOR root IN roots
+              FILTER RIGHT(name, LENGTH(root) + 1) == CONCAT('.', root)
+              LIMIT 1
+              RETURN true
+          ) > 0
+          RETURN DISTINCT name";
+        let aql = AqlQuery::new(aql_str)
+            .bind_var("@hold_collection_name", Hold::COLLECTION_NAME)
+            .bind_var("@resolve_collection_name", Resolve::COLLECTION_NAME)
+            .bind_var("@contract_collection_name", Contract::COLLECTION_NAME)
+            .bind_var("id", self.id().as_str())
+            .bind_var("category", json!(ContractCategory::ENS))
+            .bind_var("system", DomainNameSystem::ENS.to_string())
+            .batch_size(1)
+            .count(false);
+
+        let names: Vec<String> = db.aql_query(aql).await?;
+        Ok(group_domains(names))
+    }
+
+    /// ENS name resolving to this identity (see `Resolve`), the latest fetched one if many.
+    /// `None` if `self.platform != Ethereum`.
+    pub async fn resolved_name(&self, pool: &ConnectionPool) -> Result<Option<String>, Error> {
+        if self.platform != Platform::Ethereum {
+            return Ok(None);
+        }
+        let conn = pool
+            .get()
+            .await
+            .map_err(|err| Error::PoolError(err.to_string()))?;
+        let db = conn.database();
+
+        let aql = Aql::new(&[("resolves", Resolve::COLLECTION_NAME)])
+            .clause("FOR r IN @@resolves")
+            .clause("FILTER r._to == @id AND r.system == @system")
+            .clause("SORT r.updated_at DESC")
+            .clause("LIMIT 1")
+            .clause("RETURN r.name")
+            .bind("id", self.id().as_str())
+            .bind("system", DomainNameSystem::ENS.to_string());
+        let names: Vec<String> = db.aql_query(aql.query()).await?;
+        Ok(names.into_iter().next())
+    }
+
     /// Returns all Contracts owned by this identity. Empty list if `self.platform != Ethereum`.
-    pub async fn nfts(&self, pool: &ConnectionPool) -> Result<Vec<HoldRecord>, Error> {
+    /// `categories`: only returns NFTs in these categories if given.
+    /// `source`: only returns those reported by this upstream if given.
+    /// `include_expired`: whether names with their registration expired are returned. See `Hold::is_expired`.
+    pub async fn nfts(
+        &self,
+        pool: &ConnectionPool,
+        categories: Option<Vec<ContractCategory>>,
+        source: Option<DataSource>,
+        include_expired: bool,
+    ) -> Result<Vec<HoldRecord>, Error> {
         if self.0.record.platform != Platform::Ethereum {
             return Ok(vec![]);
         }
@@ -636,17 +1404,38 @@ impl IdentityRecord {
             .map_err(|err| Error::PoolError(err.to_string()))?;
         let db = conn.database();
 
-        let aql_str = r"WITH @@edge_collection_name
-            FOR d in @@edge_collection_name
-            FILTER d._from == @id
-            RETURN d";
-        let aql = AqlQuery::new(aql_str)
-            .bind_var("@edge_collection_name", Hold::COLLECTION_NAME)
-            .bind_var("id", self.id().as_str())
-            .batch_size(1)
-            .count(false);
+        let aql = Aql::new(&[
+            ("holds", Hold::COLLECTION_NAME),
+            ("contracts", Contract::COLLECTION_NAME),
+        ])
+        .clause("FOR d IN @@holds")
+        .clause("FILTER d._from == @id")
+        .clause_if(
+            categories.is_some(),
+            "FILTER DOCUMENT(d._to).category IN @categories",
+        )
+        .clause_if(source.is_some(), "FILTER d.source == @source")
+        .clause_if(
+            !include_expired,
+            "FILTER d.expires_at == null OR d.expires_at > @now",
+        )
+        .clause("RETURN d")
+        .bind("id", self.id().as_str());
+        let aql = if include_expired {
+            aql
+        } else {
+            aql.bind("now", json!(naive_now()))
+        };
+        let aql = match categories {
+            None => aql,
+            Some(categories) => aql.bind("categories", json!(categories)),
+        };
+        let aql = match source {
+            None => aql,
+            Some(source) => aql.bind("source", json!(source)),
+        };
 
-        let result = db.aql_query::<HoldRecord>(aql).await?;
+        let result = db.aql_query::<HoldRecord>(aql.query()).await?;
         Ok(result)
     }
 }
@@ -655,19 +1444,29 @@ impl IdentityRecord {
 mod tests {
 
     use crate::graph::vertex::identity::get_identities;
-    use aragog::DatabaseConnection;
+    use aragog::{DatabaseConnection, Record};
     use fake::{Dummy, Fake, Faker};
+    use serde_json::{json, Value};
     use tokio::join;
     use uuid::Uuid;
 
-    use super::{Identity, IdentityRecord};
+    use super::{
+        display_name_wins, group_domains, CreatedAtRange, DomainGroup, Identity, IdentityRecord,
+        NameSource, NeighborOptions, NeighborSort, NeighborSortKey, SortOrder,
+    };
     use crate::{
+        config::{with_freshness, ConfigFreshness},
         error::Error,
         graph::arangopool::new_connection_pool,
         graph::new_db_connection,
-        graph::{edge::Proof, Edge, Vertex},
-        upstream::Platform,
-        util::naive_now,
+        graph::{
+            aql::Aql,
+            edge::{resolve::DomainNameSystem, Hold, Proof, Resolve},
+            vertex::Contract,
+            Edge, Vertex,
+        },
+        upstream::{DataFetcher, DataSource, Platform},
+        util::{naive_now, timestamp_to_naive},
     };
 
     impl Identity {
@@ -685,6 +1484,7 @@ mod tests {
                 platform: Platform::Twitter,
                 identity: config.fake(),
                 display_name: config.fake(),
+                display_name_source: None,
                 profile_url: Some(config.fake()),
                 avatar_url: Some(config.fake()),
                 created_at: Some(config.fake()),
@@ -764,6 +1564,207 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_update_is_persisted() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+
+        let mut identity: Identity = Faker.fake();
+        let created = identity.create_or_update(&db).await?;
+
+        identity.avatar_url = Some(format!("https://example.com/{}.png", Uuid::new_v4()));
+        identity.create_or_update(&db).await?;
+
+        let stored =
+            Identity::find_by_platform_identity(&db, &identity.platform, &identity.identity)
+                .await?
+                .expect("stored");
+        assert_eq!(stored.key(), created.key());
+        assert_eq!(stored.avatar_url, identity.avatar_url);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_update() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let identity = Identity {
+            avatar_url: None,
+            ..Faker.fake()
+        };
+        let created = identity.create_or_update(&db).await?;
+        // Stored without `avatar_url` at all, which a rewrite of the record would bring back.
+        let unset = Aql::new(&[])
+            .clause(
+                "UPDATE @key WITH { avatar_url: null } IN @@identities OPTIONS { keepNull: false }",
+            )
+            .bind_collection("identities", Identity::COLLECTION_NAME)
+            .bind("key", created.key().as_str());
+        let _: Vec<Value> = db.database().aql_query(unset.query()).await?;
+
+        let updated = identity.create_or_update(&db).await?;
+        assert!(updated.updated_at > created.updated_at);
+        let stored = Aql::new(&[])
+            .clause("RETURN DOCUMENT(@@identities, @key)")
+            .bind_collection("identities", Identity::COLLECTION_NAME)
+            .bind("key", created.key().as_str());
+        let stored: Vec<Value> = db.database().aql_query(stored.query()).await?;
+        assert!(stored[0].get("avatar_url").is_none());
+        assert_eq!(
+            stored[0]["updated_at"],
+            json!(updated.updated_at),
+            "updated_at is bumped"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_case_folding() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let suffix = Uuid::new_v4().simple().to_string().to_uppercase();
+
+        // Case-insensitive: stored lowercased, found by any casing.
+        let github = Identity {
+            platform: Platform::Github,
+            identity: format!("MixedCase{}", suffix),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        assert_eq!(
+            github.identity,
+            format!("mixedcase{}", suffix.to_lowercase())
+        );
+        for query in [
+            format!("MixedCase{}", suffix),
+            format!("MIXEDCASE{}", suffix),
+            github.identity.clone(),
+        ] {
+            let found = Identity::find_by_platform_identity(&db, &Platform::Github, &query)
+                .await?
+                .expect("found in any casing");
+            assert_eq!(found.key(), github.key());
+        }
+
+        // Case-sensitive: stored and matched as is.
+        let nextid = Identity {
+            platform: Platform::NextID,
+            identity: format!("0xPubKey{}", suffix),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        assert_eq!(nextid.identity, format!("0xPubKey{}", suffix));
+        assert!(
+            Identity::find_by_platform_identity(&db, &Platform::NextID, &nextid.identity)
+                .await?
+                .is_some()
+        );
+        assert!(Identity::find_by_platform_identity(
+            &db,
+            &Platform::NextID,
+            &nextid.identity.to_lowercase()
+        )
+        .await?
+        .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_display_name_priority() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            display_name: Some("vitalik.eth".into()),
+            display_name_source: Some(NameSource::ENS),
+            ..Faker.fake()
+        };
+        wallet.create_or_update(&db).await?;
+
+        // Lens handle found later doesn't replace the ENS reverse record...
+        let updated = Identity {
+            display_name: Some("vitalik.lens".into()),
+            display_name_source: Some(NameSource::Lens),
+            ..wallet.clone()
+        }
+        .create_or_update(&db)
+        .await?;
+        assert_eq!(updated.display_name.as_deref(), Some("vitalik.eth"));
+        assert_eq!(updated.display_name_source, Some(NameSource::ENS));
+
+        // ... until the reverse record is cleared.
+        let cleared = Identity {
+            display_name: Some("".into()),
+            ..wallet.clone()
+        }
+        .create_or_update(&db)
+        .await?;
+        assert_eq!(cleared.display_name.as_deref(), Some(""));
+        let updated = Identity {
+            display_name: Some("vitalik.lens".into()),
+            display_name_source: Some(NameSource::Lens),
+            ..wallet.clone()
+        }
+        .create_or_update(&db)
+        .await?;
+        assert_eq!(updated.display_name.as_deref(), Some("vitalik.lens"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_name_wins() {
+        use NameSource::*;
+        let default = [ENS, Dotbit, Lens, Social];
+        let lens_first = [Lens, ENS];
+
+        assert!(!display_name_wins(
+            &default,
+            ("a.lens", Some(Lens)),
+            (Some("a.eth"), Some(ENS))
+        ));
+        assert!(display_name_wins(
+            &lens_first,
+            ("a.lens", Some(Lens)),
+            (Some("a.eth"), Some(ENS))
+        ));
+        // Same source: the newer one.
+        assert!(display_name_wins(
+            &default,
+            ("b.eth", Some(ENS)),
+            (Some("a.eth"), Some(ENS))
+        ));
+        // Unlisted ones lose to listed ones.
+        assert!(!display_name_wins(
+            &lens_first,
+            ("a.bit", Some(Dotbit)),
+            (Some("a.eth"), Some(ENS))
+        ));
+        // Empty or sourceless ones are always replaced.
+        assert!(display_name_wins(
+            &default,
+            ("a", Some(Social)),
+            (Some(""), Some(ENS))
+        ));
+        assert!(display_name_wins(
+            &default,
+            ("a", Some(Social)),
+            (Some("a.eth"), None)
+        ));
+        // Clearing only clears names from the same source.
+        assert!(!display_name_wins(
+            &default,
+            ("", Some(ENS)),
+            (Some("a.lens"), Some(Lens))
+        ));
+        assert!(display_name_wins(
+            &default,
+            ("", Some(ENS)),
+            (Some("a.eth"), Some(ENS))
+        ));
+    }
+
     #[tokio::test]
     async fn test_find_by_uuid() -> Result<(), Error> {
         let db = new_db_connection().await?;
@@ -789,6 +1790,43 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_lens_identity() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let handle = format!("{}.lens", Uuid::new_v4().simple());
+        let lens = Identity {
+            platform: Platform::Lens,
+            identity: handle.clone(),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+
+        let found = Identity::find_by_platform_identity(&db, &Platform::Lens, &handle)
+            .await?
+            .expect("Record not found");
+        assert_eq!(found.uuid, lens.uuid);
+        assert_eq!(found.platform, Platform::Lens);
+        assert_eq!("lens".parse::<Platform>()?, Platform::Lens);
+        assert_eq!(serde_json::to_value(Platform::Lens)?, "lens");
+
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        let proof: Proof = Faker.fake();
+        proof.connect(&db, &wallet, &lens).await?;
+        let owner = lens.lens_owned_by(&pool).await?.expect("owner not found");
+        assert_eq!(owner.id(), wallet.id());
+        // Only the lens one has an owner.
+        assert!(wallet.lens_owned_by(&pool).await?.is_none());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_find_by_display_name() -> Result<(), Error> {
         // let db = new_db_connection().await?;
@@ -833,7 +1871,15 @@ mod tests {
         proof1_raw.connect(&db, &id1, &id2).await?;
         proof2_raw.connect(&db, &id1, &id3).await?;
         proof3_raw.connect(&db, &id2, &id4).await?;
-        let neighbors = id1.neighbors(&pool, 2, None).await?;
+        let neighbors = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    depth: 2,
+                    ..Default::default()
+                },
+            )
+            .await?;
         assert_eq!(3, neighbors.len());
         // assert!(neighbors
         //     .iter()
@@ -841,6 +1887,574 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_neighbors_depth() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // ID1 --Proof--> ID2 --Proof--> ID3 --Proof--> ID4
+        let id1 = Identity::create_dummy(&db).await?;
+        let id2 = Identity::create_dummy(&db).await?;
+        let id3 = Identity::create_dummy(&db).await?;
+        let id4 = Identity::create_dummy(&db).await?;
+        for (from, to) in [(&id1, &id2), (&id2, &id3), (&id3, &id4)] {
+            let proof: Proof = Faker.fake();
+            proof.connect(&db, from, to).await?;
+        }
+
+        let neighbors = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    depth: 3,
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(3, neighbors.len());
+        for (expected, depth) in [(&id2, 1), (&id3, 2), (&id4, 3)] {
+            let found = neighbors
+                .iter()
+                .find(|n| n.identity.key() == expected.key())
+                .expect("Neighbor not found");
+            assert_eq!(depth, found.depth);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_path() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // ID1 --Proof--> ID2 <--Proof-- ID3
+        let id1 = Identity::create_dummy(&db).await?;
+        let id2 = Identity::create_dummy(&db).await?;
+        let id3 = Identity::create_dummy(&db).await?;
+        for (from, to) in [(&id1, &id2), (&id3, &id2)] {
+            let proof: Proof = Faker.fake();
+            proof.connect(&db, from, to).await?;
+        }
+
+        let path = id1
+            .connection_path(&pool, &id3, 3)
+            .await?
+            .expect("Path not found");
+        assert_eq!(2, path.edges.len());
+        let keys: Vec<&String> = path.vertices.iter().map(|v| v.key()).collect();
+        assert_eq!(keys, vec![id1.key(), id2.key(), id3.key()]);
+
+        // Too far.
+        assert!(id1.connection_path(&pool, &id3, 1).await?.is_none());
+        // Not connected at all.
+        let isolated = Identity::create_dummy(&db).await?;
+        assert!(id1.connection_path(&pool, &isolated, 3).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wallets() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // ETH1 <--Proof-- ID1 --Proof--> ID2 --Proof--> ETH2
+        let id1 = Identity::create_dummy(&db).await?;
+        let id2 = Identity::create_dummy(&db).await?;
+        let eth1 = Identity {
+            platform: Platform::Ethereum,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        let eth2 = Identity {
+            platform: Platform::Ethereum,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        for (from, to) in [(&id1, &eth1), (&id1, &id2), (&id2, &eth2)] {
+            let proof: Proof = Faker.fake();
+            proof.connect(&db, from, to).await?;
+        }
+
+        let wallets = id1.wallets(&pool, 2).await?;
+        assert_eq!(2, wallets.len());
+        for eth in [&eth1, &eth2] {
+            assert!(wallets.iter().any(|w| w.identity.key() == eth.key()));
+        }
+        assert!(wallets.iter().all(|w| !w.sources.is_empty()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uuid_stability() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let identity: Identity = Faker.fake();
+        let created = identity.create_or_update(&db).await?;
+        let uuid = created.uuid;
+        assert!(uuid.is_some());
+
+        // Re-fetched data comes with a brand new UUID, which should not replace the existing one.
+        let refetched = Identity {
+            uuid: Some(Uuid::new_v4()),
+            display_name: Some("updated".into()),
+            ..identity.clone()
+        }
+        .create_or_update(&db)
+        .await?;
+        assert_eq!(uuid, refetched.uuid);
+        assert_eq!(Some("updated".to_string()), refetched.display_name);
+
+        // Racing creations of a new identity all end up with the same record and UUID.
+        let racing: Identity = Faker.fake();
+        let attempts = (0..5).map(|_| Identity {
+            uuid: Some(Uuid::new_v4()),
+            ..racing.clone()
+        });
+        let results = futures::future::join_all(
+            attempts
+                .collect::<Vec<_>>()
+                .iter()
+                .map(|attempt| attempt.create_or_update(&db)),
+        )
+        .await;
+        let uuids: Vec<Option<Uuid>> = results
+            .into_iter()
+            .map(|result| result.map(|record| record.uuid))
+            .collect::<Result<_, _>>()?;
+        assert!(uuids.windows(2).all(|w| w[0] == w[1]));
+        let stored = Identity::find_by_platform_identity(&db, &racing.platform, &racing.identity)
+            .await?
+            .unwrap();
+        assert_eq!(uuids[0], stored.uuid);
+
+        // Found by its UUID after all of above.
+        let found = Identity::find_by_uuid(&db, uuid.unwrap()).await?.unwrap();
+        assert_eq!(created.key(), found.key());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_twitter_by_id_or_handle() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let id = format!("{}", Faker.fake::<u64>());
+        let handle = format!("Handle_{}", Uuid::new_v4().simple());
+        let created = Identity {
+            platform: Platform::Twitter,
+            identity: id.clone(),
+            display_name: Some(handle.clone()),
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+
+        for query in [id.clone(), handle.clone(), format!("@{}", handle)] {
+            let found = Identity::find_twitter(&db, &query).await?;
+            assert_eq!(
+                Some(created.key()),
+                found.as_ref().map(|f| f.key()),
+                "{}",
+                query
+            );
+        }
+        assert!(Identity::find_twitter(&db, "no_such_handle_at_all")
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_domains() {
+        let group = |name: &str, subdomains: Vec<DomainGroup>| DomainGroup {
+            name: name.into(),
+            subdomains,
+        };
+        let names = [
+            "c.a.b.eth",
+            "b.eth",
+            "x.eth",
+            "a.b.eth",
+            "D.b.eth",
+            "e.f.x.eth",
+        ];
+
+        assert_eq!(
+            group_domains(names.iter().map(|n| n.to_string()).collect()),
+            vec![
+                group(
+                    "b.eth",
+                    vec![
+                        group("a.b.eth", vec![group("c.a.b.eth", vec![])]),
+                        group("d.b.eth", vec![])
+                    ]
+                ),
+                // Intermediate `f.x.eth` is unknown.
+                group("x.eth", vec![group("e.f.x.eth", vec![])]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ens_domains() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let root = format!("{}.eth", Faker.fake::<String>().to_lowercase());
+        let blog = format!("blog.{}", root);
+        let deep = format!("old.blog.{}", root);
+        let shop = format!("shop.{}", root);
+        // wallet --Hold--> root, and subdomains resolving to others.
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        let contract = Contract::create_dummy(&db).await?;
+        Hold {
+            id: root.clone(),
+            ..Faker.fake()
+        }
+        .connect(&db, &wallet, &contract)
+        .await?;
+        for name in [&blog, &deep, &shop] {
+            let other = Identity::create_dummy(&db).await?;
+            Resolve {
+                uuid: Uuid::new_v4(),
+                source: DataSource::TheGraph,
+                system: DomainNameSystem::ENS,
+                name: name.clone(),
+                fetcher: DataFetcher::RelationService,
+                updated_at: naive_now(),
+            }
+            .connect(&db, &*contract, &*other)
+            .await?;
+        }
+
+        let groups = wallet.ens_domains(&pool).await?;
+        assert_eq!(1, groups.len());
+        assert_eq!(root, groups[0].name);
+        let subdomains = &groups[0].subdomains;
+        assert_eq!(
+            vec![&blog, &shop],
+            subdomains.iter().map(|g| &g.name).collect::<Vec<_>>()
+        );
+        assert_eq!(deep, subdomains[0].subdomains[0].name);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nfts_by_source() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        let wallet = Identity {
+            platform: Platform::Ethereum,
+            ..Faker.fake()
+        }
+        .create_or_update(&db)
+        .await?;
+        for source in [DataSource::Rss3, DataSource::Knn3] {
+            let contract = Contract::create_dummy(&db).await?;
+            Hold {
+                source,
+                ..Faker.fake()
+            }
+            .connect(&db, &wallet, &contract)
+            .await?;
+        }
+
+        assert_eq!(wallet.nfts(&pool, None, None, true).await?.len(), 2);
+        for source in [DataSource::Rss3, DataSource::Knn3] {
+            let found = wallet.nfts(&pool, None, Some(source), true).await?;
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].source, source);
+        }
+        assert!(wallet
+            .nfts(&pool, None, Some(DataSource::TheGraph), true)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_best_avatar() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // github (avatar) --> twitter (avatar) --> ethereum (no avatar)
+        let identity = |platform: Platform, avatar_url: Option<String>| Identity {
+            platform,
+            avatar_url,
+            ..Faker.fake()
+        };
+        let github = identity(Platform::Github, Some("https://github.test/a.png".into()))
+            .create_or_update(&db)
+            .await?;
+        let twitter = identity(Platform::Twitter, Some("https://twitter.test/a.png".into()))
+            .create_or_update(&db)
+            .await?;
+        let wallet = identity(Platform::Ethereum, None)
+            .create_or_update(&db)
+            .await?;
+        for (from, to) in [(&github, &twitter), (&twitter, &wallet)] {
+            let proof: Proof = Faker.fake();
+            proof.connect(&db, from, to).await?;
+        }
+
+        // Ethereum comes first by default, but has none.
+        for from in [&github, &twitter, &wallet] {
+            assert_eq!(
+                from.best_avatar(&pool, 3).await?,
+                Some("https://twitter.test/a.png".into())
+            );
+        }
+
+        let cluster = [&github.0.record, &twitter.0.record, &wallet.0.record];
+        assert_eq!(
+            super::pick_avatar(&[Platform::Github], &cluster),
+            Some("https://github.test/a.png".into())
+        );
+        assert_eq!(super::pick_avatar(&[], &cluster[2..]), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_without_unknown_sources() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // ID2 <--(keybase)-- ID1 --(unknown)--> ID3 --(keybase)--> ID4
+        let id1 = Identity::create_dummy(&db).await?;
+        let id2 = Identity::create_dummy(&db).await?;
+        let id3 = Identity::create_dummy(&db).await?;
+        let id4 = Identity::create_dummy(&db).await?;
+        for (from, to, source) in [
+            (&id1, &id2, DataSource::Keybase),
+            (&id1, &id3, DataSource::Unknown),
+            (&id3, &id4, DataSource::Keybase),
+        ] {
+            Proof {
+                source,
+                ..Faker.fake()
+            }
+            .connect(&db, from, to)
+            .await?;
+        }
+
+        for (include_unknown_sources, expected) in [
+            (true, vec![id2.key(), id3.key(), id4.key()]),
+            // ID4 is only reachable through the unknown one.
+            (false, vec![id2.key()]),
+        ] {
+            let mut found: Vec<String> = id1
+                .neighbors(
+                    &pool,
+                    NeighborOptions {
+                        depth: 2,
+                        include_unknown_sources,
+                        ..Default::default()
+                    },
+                )
+                .await?
+                .iter()
+                .map(|n| n.identity.key().to_string())
+                .collect();
+            found.sort();
+            let mut expected: Vec<String> = expected.iter().map(|k| k.to_string()).collect();
+            expected.sort();
+            assert_eq!(expected, found);
+
+            let edges = id1
+                .neighbors_with_traversal(&pool, 2, None, include_unknown_sources)
+                .await?;
+            assert_eq!(expected.len(), edges.len());
+            assert_eq!(
+                include_unknown_sources,
+                edges.iter().any(|e| e.source == DataSource::Unknown)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_created_between() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // ID2 <--(2022-01-01)-- ID1 --(2022-06-01)--> ID3
+        //                        |
+        //                        +--(no created_at)--> ID4
+        let id1 = Identity::create_dummy(&db).await?;
+        let id2 = Identity::create_dummy(&db).await?;
+        let id3 = Identity::create_dummy(&db).await?;
+        let id4 = Identity::create_dummy(&db).await?;
+        let jan = timestamp_to_naive(1640995200, 0);
+        let mar = timestamp_to_naive(1646092800, 0);
+        let jun = timestamp_to_naive(1654041600, 0);
+        Proof {
+            created_at: Some(jan),
+            ..Faker.fake()
+        }
+        .connect(&db, &id1, &id2)
+        .await?;
+        Proof {
+            created_at: Some(jun),
+            ..Faker.fake()
+        }
+        .connect(&db, &id1, &id3)
+        .await?;
+        Proof {
+            created_at: None,
+            ..Faker.fake()
+        }
+        .connect(&db, &id1, &id4)
+        .await?;
+
+        let since_mar = CreatedAtRange {
+            from: Some(mar),
+            to: None,
+        };
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    created_between: Some(since_mar),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(1, found.len());
+        assert_eq!(found.first().unwrap().identity.key(), id3.key());
+
+        let until_mar = CreatedAtRange {
+            from: None,
+            to: Some(mar),
+        };
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    created_between: Some(until_mar),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(1, found.len());
+        assert_eq!(found.first().unwrap().identity.key(), id2.key());
+
+        let whole_year = CreatedAtRange {
+            from: Some(jan),
+            to: Some(jun),
+        };
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    created_between: Some(whole_year),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(2, found.len());
+
+        let found = id1.neighbors(&pool, NeighborOptions::default()).await?;
+        assert_eq!(3, found.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_sorted() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let pool = new_connection_pool().await?;
+        // ID2 <--(keybase, nextid)-- ID1 --(keybase)--> ID3
+        //                             |
+        //                             +--(keybase)--> ID4
+        let id1 = Identity::create_dummy(&db).await?;
+        let id2 = Identity::create_dummy(&db).await?;
+        let id3 = Identity::create_dummy(&db).await?;
+        let id4 = Identity::create_dummy(&db).await?;
+        for (to, source) in [
+            (&id2, DataSource::Keybase),
+            (&id2, DataSource::NextID),
+            (&id3, DataSource::Keybase),
+            (&id4, DataSource::Keybase),
+        ] {
+            Proof {
+                source,
+                ..Faker.fake()
+            }
+            .connect(&db, &id1, to)
+            .await?;
+        }
+
+        let sorted = |by, order| Some(NeighborSort { by, order });
+
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    sort: sorted(NeighborSortKey::AddedAt, SortOrder::Asc),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(3, found.len());
+        assert!(found
+            .windows(2)
+            .all(|w| w[0].identity.added_at <= w[1].identity.added_at));
+
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    sort: sorted(NeighborSortKey::UpdatedAt, SortOrder::Desc),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert!(found
+            .windows(2)
+            .all(|w| w[0].identity.updated_at >= w[1].identity.updated_at));
+
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    sort: sorted(NeighborSortKey::Platform, SortOrder::Asc),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert!(found
+            .windows(2)
+            .all(|w| w[0].identity.platform.to_string() <= w[1].identity.platform.to_string()));
+
+        let found = id1
+            .neighbors(
+                &pool,
+                NeighborOptions {
+                    sort: sorted(NeighborSortKey::Confidence, SortOrder::Desc),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(found.first().unwrap().identity.key(), id2.key());
+        assert_eq!(2, found.first().unwrap().sources.len());
+
+        // Deterministic even without a sort key.
+        let first = id1.neighbors(&pool, NeighborOptions::default()).await?;
+        let second = id1.neighbors(&pool, NeighborOptions::default()).await?;
+        assert_eq!(
+            first.iter().map(|n| n.identity.key()).collect::<Vec<_>>(),
+            second.iter().map(|n| n.identity.key()).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_neighbors_with_traversal() -> Result<(), Error> {
         let pool = new_connection_pool().await?;
@@ -849,7 +2463,7 @@ mod tests {
             .expect("Record not found");
         println!("{:#?}", found);
         let neighbors = found
-            .neighbors_with_traversal(&pool, 3, None)
+            .neighbors_with_traversal(&pool, 3, None, true)
             .await
             .unwrap();
         println!("{:#?}", neighbors);
@@ -880,4 +2494,25 @@ mod tests {
         println!("{:#?}", result);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_outdated_in_configured() -> Result<(), Error> {
+        let db = new_db_connection().await?;
+        let created = Identity::create_dummy(&db).await?;
+        assert!(!created.is_outdated());
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let outdated = with_freshness(
+            ConfigFreshness {
+                identity_hours: 0,
+                hold_hours: 0,
+                resolve_days: 0,
+            },
+            async { created.is_outdated() },
+        )
+        .await;
+        assert!(outdated);
+
+        Ok(())
+    }
 }
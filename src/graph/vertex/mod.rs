@@ -6,7 +6,12 @@ use crate::upstream::DataSource;
 use aragog::{DatabaseConnection, Record};
 use async_trait::async_trait;
 pub use contract::{Contract, ContractRecord};
-pub use identity::{FromToLoadFn, Identity, IdentityLoadFn, IdentityRecord, IdentityWithSource};
+pub(crate) use identity::display_name_wins;
+pub use identity::{
+    collapse_equivalents, neighbor_cursor, CreatedAtRange, DomainGroup, FromToLoadFn, Identity,
+    IdentityLoadFn, IdentityRecord, IdentityWithSource, NameSource, NeighborOptions, NeighborPage,
+    NeighborSort, NeighborSortKey, PlatformIdentityLoadFn, SortOrder,
+};
 use uuid::Uuid;
 
 use crate::error::Error;
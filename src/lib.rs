@@ -1,6 +1,7 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod cache;
 pub mod config;
 pub mod controller;
 pub mod error;
@@ -8,12 +8,12 @@ use crate::graph::edge::Proof;
 use crate::graph::vertex::Identity;
 use crate::graph::{create_identity_to_identity_record, new_db_connection};
 use crate::upstream::{DataSource, Fetcher, Platform, TargetProcessedList};
-use crate::util::{make_client, naive_now, parse_body, timestamp_to_naive};
+use crate::util::{make_client_with_timeout, naive_now, parse_body, timestamp_to_naive};
 use async_trait::async_trait;
 use futures::future::join_all;
 use serde::Deserialize;
-use std::str::FromStr;
-use tracing::{error, info};
+use std::{str::FromStr, time::Duration};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 #[derive(Deserialize, Debug)]
@@ -51,9 +51,14 @@ impl Fetcher for Aggregation {
 
         match target {
             Target::Identity(platform, identity) => {
-                fetch_connections_by_platform_identity(platform, identity).await
+                fetch_connections_by_platform_identity(
+                    &C.upstream.aggregation_service.url,
+                    platform,
+                    identity,
+                )
+                .await
             }
-            Target::NFT(_, _, _, _) => todo!(),
+            Target::NFT(_, _, _, _) => Ok(vec![]),
         }
     }
 
@@ -62,11 +67,15 @@ impl Fetcher for Aggregation {
     }
 }
 
+/// `url`: Aggregation service search API endpoint. See `C.upstream.aggregation_service.url`.
 async fn fetch_connections_by_platform_identity(
+    url: &str,
     platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
-    let client = make_client();
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.aggregation_service.timeout_seconds,
+    ));
     let mut page = 1;
 
     let mut next_targets: TargetProcessedList = Vec::new();
@@ -74,7 +83,7 @@ async fn fetch_connections_by_platform_identity(
     loop {
         let uri: http::Uri = match format!(
             "{}?platform={}&identity={}&page={}&size=100",
-            C.upstream.aggregation_service.url, platform, identity, page
+            url, platform, identity, page
         )
         .parse()
         {
@@ -111,6 +120,29 @@ async fn fetch_connections_by_platform_identity(
     Ok(next_targets)
 }
 
+/// Our `DataSource` of the provider where Aggregation service got a record from.
+/// Those we don't know are attributed to `Aggregation` itself.
+fn sub_source(source: &str) -> DataSource {
+    let normalized = source.trim().to_lowercase().replace(['-', ' '], "_");
+    let found = match normalized.as_str() {
+        "sybil_list" | "uniswap" => DataSource::SybilList,
+        "next_id" => DataSource::NextID,
+        "thegraph" | "ens" => DataSource::TheGraph,
+        "ethleaderboard" | "eth_leaderboard" => DataSource::EthLeaderboard,
+        "cyber_connect" => DataSource::CyberConnect,
+        "dot_bit" => DataSource::Dotbit,
+        other => DataSource::from_str(other).unwrap_or(DataSource::Unknown),
+    };
+    if found == DataSource::Unknown {
+        warn!(
+            "AggregationService | Unknown sub-source {}. Attributed to aggregation.",
+            source
+        );
+        return DataSource::Aggregation;
+    }
+    found
+}
+
 async fn save_item(p: Record) -> Result<TargetProcessedList, Error> {
     let db = new_db_connection().await?;
     let mut targets = Vec::new();
@@ -129,6 +161,7 @@ async fn save_item(p: Record) -> Result<TargetProcessedList, Error> {
         identity: p.sns_handle.clone().to_lowercase(),
         created_at: None,
         display_name: Some(p.sns_handle.clone()),
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -151,6 +184,7 @@ async fn save_item(p: Record) -> Result<TargetProcessedList, Error> {
         created_at: None,
         // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
         display_name: None,
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -166,8 +200,9 @@ async fn save_item(p: Record) -> Result<TargetProcessedList, Error> {
 
     let pf: Proof = Proof {
         uuid: Uuid::new_v4(),
-        source: DataSource::from_str(p.source.as_str()).unwrap_or(DataSource::Unknown),
+        source: sub_source(&p.source),
         record_id: Some(p.id.clone()),
+        proof_url: None,
         created_at: Some(timestamp_to_naive(
             p.create_timestamp.parse::<i64>().unwrap() / 1000,
             create_ms_time,
@@ -176,7 +211,11 @@ async fn save_item(p: Record) -> Result<TargetProcessedList, Error> {
             p.modify_timestamp.parse::<i64>().unwrap() / 1000,
             update_ms_time,
         ),
-        fetcher: DataFetcher::AggregationService,
+        fetcher: C
+            .upstream
+            .aggregation_service
+            .fetcher
+            .unwrap_or(DataFetcher::AggregationService),
     };
 
     let _ = create_identity_to_identity_record(&db, &from, &to, &pf).await;
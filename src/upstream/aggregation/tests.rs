@@ -1,11 +1,17 @@
 use crate::{
     error::Error,
+    graph::edge::Proof,
     graph::new_db_connection,
     graph::vertex::{
         contract::{Chain, ContractCategory},
         Contract, Identity,
     },
+    upstream::mock::{self, Fixture},
     upstream::{aggregation::Aggregation, Target},
+    upstream::{
+        aggregation::{fetch_connections_by_platform_identity, sub_source},
+        DataFetcher, DataSource,
+    },
     upstream::{Fetcher, Platform},
     util::timestamp_to_naive,
 };
@@ -50,3 +56,80 @@ async fn test_parse_timestamp() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_aggregation_fetcher_attribution() -> Result<(), Error> {
+    const SEARCH_PATH: &str = "/v1/identity/search";
+    let base = mock::serve(vec![Fixture::ok(
+        SEARCH_PATH,
+        include_str!("../fixtures/aggregation/search.json"),
+    )]);
+    let url = format!("{}{}", base, SEARCH_PATH);
+
+    fetch_connections_by_platform_identity(&url, &Platform::Twitter, "fixture_agg").await?;
+
+    let db = new_db_connection().await?;
+    let from = Identity::find_by_platform_identity(&db, &Platform::Twitter, "fixture_agg")
+        .await?
+        .expect("Record not found");
+    let to = Identity::find_by_platform_identity(
+        &db,
+        &Platform::Ethereum,
+        "0x00000000000000000000000000000000000a6601",
+    )
+    .await?
+    .expect("Record not found");
+    let proof = Proof::find_by_from_to(
+        &db,
+        &from,
+        &to,
+        &DataSource::SybilList,
+        &Some("fixture-aggregation-0001".into()),
+    )
+    .await?
+    .expect("Record not found");
+    assert_eq!(proof.fetcher, DataFetcher::AggregationService);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_aggregation_sub_sources() -> Result<(), Error> {
+    const SEARCH_PATH: &str = "/v1/identity/search";
+    let base = mock::serve(vec![Fixture::ok(
+        SEARCH_PATH,
+        include_str!("../fixtures/aggregation/search_sub_sources.json"),
+    )]);
+    let url = format!("{}{}", base, SEARCH_PATH);
+
+    fetch_connections_by_platform_identity(&url, &Platform::Twitter, "fixture_agg_sub").await?;
+
+    let db = new_db_connection().await?;
+    let from = Identity::find_by_platform_identity(&db, &Platform::Twitter, "fixture_agg_sub")
+        .await?
+        .expect("Record not found");
+    for (address, source, record_id) in [
+        (
+            "0x00000000000000000000000000000000000a6611",
+            DataSource::SybilList,
+            "fixture-aggregation-sub-0001",
+        ),
+        (
+            "0x00000000000000000000000000000000000a6612",
+            DataSource::NextID,
+            "fixture-aggregation-sub-0002",
+        ),
+    ] {
+        let to = Identity::find_by_platform_identity(&db, &Platform::Ethereum, address)
+            .await?
+            .expect("Record not found");
+        Proof::find_by_from_to(&db, &from, &to, &source, &Some(record_id.into()))
+            .await?
+            .expect("Record not found");
+    }
+
+    assert_eq!(sub_source("Keybase"), DataSource::Keybase);
+    assert_eq!(sub_source("some_new_provider"), DataSource::Aggregation);
+
+    Ok(())
+}
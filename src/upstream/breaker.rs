@@ -0,0 +1,173 @@
+//! Per-upstream circuit breaker, so a down upstream isn't asked (and waited for)
+//! by every query until it recovers. See `C.upstream.breaker`.
+
+use crate::{
+    config::C,
+    error::Error,
+    upstream::{DataSource, UPSTREAMS},
+};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+lazy_static! {
+    /// Breaker of each upstream failed at least once.
+    static ref BREAKERS: Mutex<HashMap<DataSource, Breaker>> = Mutex::new(HashMap::new());
+}
+
+/// State of the circuit breaker of an upstream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, async_graphql::Enum)]
+pub enum BreakerState {
+    /// Asked as usual.
+    Closed,
+    /// Failed too many times in a row. Skipped until `C.upstream.breaker.open_seconds` passes.
+    Open,
+    /// Waited long enough. The next fetch tells if it's recovered.
+    HalfOpen,
+}
+
+#[derive(Default)]
+struct Breaker {
+    /// Failures in a row.
+    failures: u32,
+    /// When it's tripped. `None` if closed.
+    opened_at: Option<Instant>,
+    /// A trial fetch of half-open breaker is running.
+    probing: bool,
+}
+
+impl Breaker {
+    fn state(&self) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(at) if at.elapsed() < open_for() => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+}
+
+fn open_for() -> Duration {
+    Duration::from_secs(C.upstream.breaker.open_seconds)
+}
+
+/// Does `err` tell that the upstream is unavailable, instead of having nothing for a target?
+fn is_outage(err: &Error) -> bool {
+    match err {
+        Error::Timeout(_) | Error::HttpClientError(_) | Error::TooManyRequests(_) => true,
+        Error::General(_, status) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+/// A fetch of `source` let through by `allow`. Give its result to `record`.
+/// If it's dropped unrecorded (cancelled by a deadline, or the client going away) while it's
+/// the trial of a half-open breaker, the next fetch is let through to try instead.
+pub struct Admission {
+    source: DataSource,
+    /// Is this the trial fetch of a half-open breaker?
+    probe: bool,
+}
+
+impl Admission {
+    /// Record the result of this fetch.
+    pub fn record<T>(mut self, result: &Result<T, Error>) {
+        self.probe = false;
+        record(self.source, result);
+    }
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        if !self.probe {
+            return;
+        }
+        if let Some(breaker) = BREAKERS.lock().unwrap().get_mut(&self.source) {
+            breaker.probing = false;
+        }
+    }
+}
+
+/// Should `source` be fetched now? Only one trial fetch is let through by a half-open breaker.
+pub fn allow(source: DataSource) -> Option<Admission> {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = match breakers.get_mut(&source) {
+        None => {
+            return Some(Admission {
+                source,
+                probe: false,
+            })
+        }
+        Some(breaker) => breaker,
+    };
+    let probe = match breaker.state() {
+        BreakerState::Closed => false,
+        BreakerState::Open => return None,
+        BreakerState::HalfOpen if breaker.probing => return None,
+        BreakerState::HalfOpen => true,
+    };
+    breaker.probing = probe;
+    Some(Admission { source, probe })
+}
+
+/// Record the result of a fetch of `source` let through by `allow`.
+fn record<T>(source: DataSource, result: &Result<T, Error>) {
+    let threshold = C.upstream.breaker.failure_threshold;
+    if threshold == 0 {
+        return;
+    }
+    let mut breakers = BREAKERS.lock().unwrap();
+    match result {
+        Err(err) if is_outage(err) => {
+            let breaker = breakers.entry(source).or_default();
+            breaker.failures += 1;
+            if breaker.probing || breaker.failures >= threshold {
+                warn!(
+                    "Breaker | {} failed {} times in a row ({}), skipped for {}s",
+                    source,
+                    breaker.failures,
+                    err,
+                    open_for().as_secs()
+                );
+                breaker.opened_at = Some(Instant::now());
+                breaker.probing = false;
+            }
+        }
+        // It answers, even if with nothing.
+        _ => {
+            if let Some(breaker) = breakers.remove(&source) {
+                if breaker.opened_at.is_some() {
+                    info!("Breaker | {} is recovered", source);
+                }
+            }
+        }
+    }
+}
+
+/// Current breaker state of `source`.
+pub fn state_of(source: DataSource) -> BreakerState {
+    BREAKERS
+        .lock()
+        .unwrap()
+        .get(&source)
+        .map_or(BreakerState::Closed, Breaker::state)
+}
+
+/// Breaker state of every upstream `fetch_one` asks.
+pub fn upstream_health() -> HashMap<DataSource, BreakerState> {
+    UPSTREAMS
+        .iter()
+        .map(|(source, _)| (*source, state_of(*source)))
+        .collect()
+}
+
+/// Trip the breaker of `source` as if it happened at `at`.
+#[cfg(test)]
+pub(crate) fn open_since(source: DataSource, at: Instant) {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = breakers.entry(source).or_default();
+    breaker.opened_at = Some(at);
+    breaker.probing = false;
+}
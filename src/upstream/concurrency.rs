@@ -0,0 +1,37 @@
+//! Per-upstream limit of concurrent fetches, since some upstreams tolerate only a few at a time.
+//! See `C.upstream.concurrency`.
+
+use crate::{config::C, upstream::DataSource};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+lazy_static! {
+    /// Semaphore of each limited upstream. Created when first used.
+    static ref PERMITS: Mutex<HashMap<DataSource, Option<Arc<Semaphore>>>> = Mutex::new(HashMap::new());
+}
+
+fn semaphore_of(max: usize) -> Option<Arc<Semaphore>> {
+    (max > 0).then(|| Arc::new(Semaphore::new(max)))
+}
+
+/// Wait until `source` can be fetched once more. Held permit is released when dropped.
+/// `None` if `source` is unlimited.
+pub async fn acquire(source: DataSource) -> Option<OwnedSemaphorePermit> {
+    let semaphore = PERMITS
+        .lock()
+        .unwrap()
+        .entry(source)
+        .or_insert_with(|| semaphore_of(C.upstream.concurrency.get(&source).copied().unwrap_or(0)))
+        .clone()?;
+    // Never closed.
+    semaphore.acquire_owned().await.ok()
+}
+
+/// Override the limit of `source` given in config. `0` means unlimited.
+/// Fetches running with permits of the old limit are not counted in the new one.
+pub(crate) fn set_limit(source: DataSource, max: usize) {
+    PERMITS.lock().unwrap().insert(source, semaphore_of(max));
+}
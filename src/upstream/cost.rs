@@ -0,0 +1,91 @@
+//! What a crawl costs: upstream requests, database writes and time spent.
+//! Counted only within `with_cost`, i.e. by `fetch_all`, and reported to its caller.
+
+use crate::upstream::DataSource;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+tokio::task_local! {
+    /// Counter of the crawl running in current task. See `with_cost`.
+    static COST: Arc<Counter>;
+}
+
+#[derive(Default)]
+struct Counter {
+    requests: Mutex<HashMap<DataSource, u32>>,
+    db_writes: AtomicU64,
+    truncated: AtomicBool,
+}
+
+/// Cost of a crawl.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlCost {
+    /// Fetches sent to each upstream. Those skipped by an open breaker are not counted.
+    pub requests: HashMap<DataSource, u32>,
+    /// Vertices and edges created or updated.
+    pub db_writes: u64,
+    /// Time spent, in milliseconds.
+    pub elapsed_ms: u64,
+    /// Stopped at the deadline (see `C.upstream.crawl.deadline`) before everything reachable is fetched.
+    pub truncated: bool,
+}
+
+impl CrawlCost {
+    /// Add up the cost of another crawl.
+    pub fn merge(&mut self, other: &CrawlCost) {
+        for (source, count) in other.requests.iter() {
+            *self.requests.entry(*source).or_default() += count;
+        }
+        self.db_writes += other.db_writes;
+        self.elapsed_ms += other.elapsed_ms;
+        self.truncated |= other.truncated;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty() && self.db_writes == 0 && self.elapsed_ms == 0 && !self.truncated
+    }
+}
+
+/// Run `fut`, counting what it costs. Returns the cost besides the output of `fut`.
+pub async fn with_cost<F: Future>(fut: F) -> (F::Output, CrawlCost) {
+    let counter = Arc::new(Counter::default());
+    let started_at = Instant::now();
+    let output = COST.scope(counter.clone(), fut).await;
+    let cost = CrawlCost {
+        requests: counter.requests.lock().unwrap().clone(),
+        db_writes: counter.db_writes.load(Ordering::SeqCst),
+        elapsed_ms: started_at.elapsed().as_millis() as u64,
+        truncated: counter.truncated.load(Ordering::SeqCst),
+    };
+    (output, cost)
+}
+
+/// Count a fetch sent to `source`. Does nothing outside `with_cost`.
+pub fn record_request(source: DataSource) {
+    let _ = COST.try_with(|counter| {
+        *counter.requests.lock().unwrap().entry(source).or_default() += 1;
+    });
+}
+
+/// Count `count` vertices / edges written. Does nothing outside `with_cost`.
+pub fn record_db_writes(count: u64) {
+    let _ = COST.try_with(|counter| {
+        counter.db_writes.fetch_add(count, Ordering::SeqCst);
+    });
+}
+
+/// Mark the crawl as stopped before it's done. Does nothing outside `with_cost`.
+pub fn record_truncated() {
+    let _ = COST.try_with(|counter| {
+        counter.truncated.store(true, Ordering::SeqCst);
+    });
+}
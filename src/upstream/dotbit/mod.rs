@@ -7,12 +7,16 @@ use crate::graph::edge::Edge;
 use crate::graph::edge::Resolve;
 use crate::graph::edge::{hold::Hold, resolve::DomainNameSystem};
 use crate::graph::vertex::Vertex;
-use crate::graph::{new_db_connection, vertex::Identity};
-use crate::upstream::{DataFetcher, DataSource, Fetcher, Platform, Target, TargetProcessedList};
-use crate::util::{make_client, naive_now, parse_body, timestamp_to_naive};
+use crate::graph::{
+    new_db_connection,
+    vertex::{Identity, NameSource},
+};
+use crate::upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList};
+use crate::util::{make_client_with_timeout, naive_now, parse_body, timestamp_to_naive};
 use async_trait::async_trait;
 use hyper::{Body, Method, Request};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use tracing::warn;
 use uuid::Uuid;
 
@@ -27,9 +31,14 @@ impl Fetcher for DotBit {
 
         match target {
             Target::Identity(platform, identity) => {
-                fetch_connections_by_platform_identity(platform, identity).await
+                fetch_connections_by_platform_identity(
+                    &C.upstream.dotbit_service.url,
+                    platform,
+                    identity,
+                )
+                .await
             }
-            Target::NFT(_, _, _, _) => todo!(),
+            Target::NFT(_, _, _, _) => Ok(vec![]),
         }
     }
 
@@ -151,18 +160,23 @@ pub struct AccountListResponse {
 
 const UNKNOWN_OWNER: &str = "0x0000000000000000000000000000000000000000";
 
+/// `url`: JSON-RPC endpoint of .bit account indexer. See `C.upstream.dotbit_service.url`.
 async fn fetch_connections_by_platform_identity(
+    url: &str,
     platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
     match *platform {
-        Platform::Dotbit => fetch_connections_by_account_info(platform, identity).await,
-        Platform::Ethereum => fetch_hold_acc_and_reverse_record_by_addrs(platform, identity).await,
+        Platform::Dotbit => fetch_connections_by_account_info(url, platform, identity).await,
+        Platform::Ethereum => {
+            fetch_hold_acc_and_reverse_record_by_addrs(url, platform, identity).await
+        }
         _ => Ok(vec![]),
     }
 }
 
 async fn fetch_connections_by_account_info(
+    url: &str,
     _platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
@@ -177,10 +191,12 @@ async fn fetch_connections_by_account_info(
     };
     let json_params = serde_json::to_vec(&params)?;
 
-    let client = make_client();
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.dotbit_service.timeout_seconds,
+    ));
     let req = Request::builder()
         .method(Method::POST)
-        .uri(C.upstream.dotbit_service.url.clone())
+        .uri(url)
         .body(Body::from(json_params))
         .expect("request builder");
 
@@ -213,6 +229,7 @@ async fn fetch_connections_by_account_info(
         identity: account_info.owner_key.to_lowercase().clone(),
         created_at: Some(created_at_naive),
         display_name: None,
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -225,6 +242,7 @@ async fn fetch_connections_by_account_info(
         identity: identity.to_string(),
         created_at: Some(created_at_naive),
         display_name: Some(identity.to_string()),
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -237,8 +255,9 @@ async fn fetch_connections_by_account_info(
         transaction: Some(out_point.tx_hash),
         id: out_point.index.to_string(),
         created_at: Some(created_at_naive),
+        expires_at: None,
         updated_at: naive_now(),
-        fetcher: DataFetcher::RelationService,
+        fetcher: C.upstream.dotbit_service.fetcher.unwrap_or_default(),
     };
 
     create_identity_to_identity_hold_record(&db, &from, &to, &hold).await?;
@@ -250,10 +269,26 @@ async fn fetch_connections_by_account_info(
 }
 
 async fn fetch_hold_acc_and_reverse_record_by_addrs(
+    url: &str,
+    _platform: &Platform,
+    identity: &str,
+) -> Result<TargetProcessedList, Error> {
+    let mut targets = fetch_account_list_by_addrs(url, _platform, identity).await?;
+    for target in fetch_reverse_record(url, _platform, identity).await? {
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+/// An address may hold many .bit accounts. The one designated by its reverse record is the primary,
+/// which becomes `display_name` of this address (like ENS reverse lookup does).
+async fn fetch_reverse_record(
+    url: &str,
     _platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
-    fetch_account_list_by_addrs(_platform, identity).await?;
     // das_reverseRecord
     let request_params = get_req_params_by_platform(_platform, identity);
     let params = ReverseRecordRequest {
@@ -264,10 +299,12 @@ async fn fetch_hold_acc_and_reverse_record_by_addrs(
     };
     let json_params = serde_json::to_vec(&params)?;
 
-    let client = make_client();
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.dotbit_service.timeout_seconds,
+    ));
     let req = Request::builder()
         .method(Method::POST)
-        .uri(C.upstream.dotbit_service.url.clone())
+        .uri(url)
         .body(Body::from(json_params))
         .expect("request builder");
 
@@ -289,7 +326,8 @@ async fn fetch_hold_acc_and_reverse_record_by_addrs(
         platform: Platform::Ethereum,
         identity: identity.to_string().to_lowercase(),
         created_at: None,
-        display_name: None,
+        display_name: Some(result_data.account.clone()),
+        display_name_source: Some(NameSource::Dotbit),
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -302,6 +340,7 @@ async fn fetch_hold_acc_and_reverse_record_by_addrs(
         identity: result_data.account.clone(),
         created_at: None,
         display_name: Some(result_data.account.clone()),
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -314,8 +353,9 @@ async fn fetch_hold_acc_and_reverse_record_by_addrs(
         transaction: None,
         id: "".to_string(),
         created_at: None,
+        expires_at: None,
         updated_at: naive_now(),
-        fetcher: DataFetcher::RelationService,
+        fetcher: C.upstream.dotbit_service.fetcher.unwrap_or_default(),
     };
 
     let eth_record = eth_identity.create_or_update(&db).await?;
@@ -327,7 +367,7 @@ async fn fetch_hold_acc_and_reverse_record_by_addrs(
         source: DataSource::Dotbit,
         system: DomainNameSystem::DotBit,
         name: result_data.account.clone(),
-        fetcher: DataFetcher::RelationService,
+        fetcher: C.upstream.dotbit_service.fetcher.unwrap_or_default(),
         updated_at: naive_now(),
     };
     resolve.connect(&db, &dotbit_record, &eth_record).await?;
@@ -339,6 +379,7 @@ async fn fetch_hold_acc_and_reverse_record_by_addrs(
 }
 
 async fn fetch_account_list_by_addrs(
+    url: &str,
     _platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
@@ -352,10 +393,12 @@ async fn fetch_account_list_by_addrs(
     };
     let json_params = serde_json::to_vec(&params)?;
 
-    let client = make_client();
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.dotbit_service.timeout_seconds,
+    ));
     let req = Request::builder()
         .method(Method::POST)
-        .uri(C.upstream.dotbit_service.url.clone())
+        .uri(url)
         .body(Body::from(json_params))
         .expect("request builder");
 
@@ -374,6 +417,7 @@ async fn fetch_account_list_by_addrs(
         identity: identity.to_string().to_lowercase().clone(),
         created_at: None,
         display_name: None,
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -381,6 +425,7 @@ async fn fetch_account_list_by_addrs(
     };
     let from_record = from.create_or_update(&db).await?;
 
+    let mut targets: TargetProcessedList = vec![];
     for i in resp.result.data.unwrap().account_list.into_iter() {
         let to: Identity = Identity {
             uuid: Some(Uuid::new_v4()),
@@ -388,6 +433,7 @@ async fn fetch_account_list_by_addrs(
             identity: i.account.to_string(),
             created_at: None,
             display_name: Some(i.account.to_string()),
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: None,
@@ -400,15 +446,17 @@ async fn fetch_account_list_by_addrs(
             transaction: None,
             id: "".to_string(),
             created_at: None,
+            expires_at: None,
             updated_at: naive_now(),
-            fetcher: DataFetcher::RelationService,
+            fetcher: C.upstream.dotbit_service.fetcher.unwrap_or_default(),
         };
 
         let to_record = to.create_or_update(&db).await?;
         hold.connect(&db, &from_record, &to_record).await?;
+        targets.push(Target::Identity(Platform::Dotbit, i.account));
     }
 
-    Ok(vec![])
+    Ok(targets)
 }
 
 fn get_req_params_by_platform(_platform: &Platform, identity: &str) -> RequestTypeKeyInfoParams {
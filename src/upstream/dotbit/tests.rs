@@ -1,4 +1,8 @@
-use crate::graph::edge::Hold;
+use crate::graph::edge::{resolve::DomainNameSystem, Hold, Resolve};
+use crate::upstream::dotbit::{
+    fetch_account_list_by_addrs, fetch_connections_by_account_info, fetch_reverse_record,
+};
+use crate::upstream::mock::{self, Fixture};
 use crate::upstream::Target;
 use crate::{error::Error, upstream::dotbit::DotBit, upstream::Fetcher};
 use crate::{
@@ -38,12 +42,10 @@ async fn test_smoke_dotbit_reverse_record() -> Result<(), Error> {
     DotBit::fetch(&target2).await?;
     let db = new_db_connection().await?;
 
-    assert_eq!(
-        Identity::find_by_platform_identity(&db, &target2.platform()?, &target2.identity()?)
-            .await?
-            .is_none(),
-        true
-    );
+    // Ethereum addresses are case-insensitive: found by either form.
+    Identity::find_by_platform_identity(&db, &target2.platform()?, &target2.identity()?)
+        .await?
+        .expect("Record not found");
     Identity::find_by_platform_identity(
         &db,
         &target2.platform()?,
@@ -58,3 +60,104 @@ async fn test_smoke_dotbit_reverse_record() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_reverse_record_sets_display_name() -> Result<(), Error> {
+    // Replay of `das_reverseRecord` for an address holding many accounts.
+    let url = mock::serve(vec![Fixture::ok(
+        "/",
+        include_str!("../fixtures/dotbit/reverse_record.json"),
+    )]);
+    let address = "0x0000000000000000000000000000000000d07b17";
+
+    let result = fetch_reverse_record(&url, &Platform::Ethereum, address).await?;
+    assert_eq!(
+        result,
+        vec![Target::Identity(
+            Platform::Dotbit,
+            "fixture-primary.bit".into()
+        )]
+    );
+
+    let db = new_db_connection().await?;
+    let wallet = Identity::find_by_platform_identity(&db, &Platform::Ethereum, address)
+        .await?
+        .expect("Record not found");
+    assert_eq!(wallet.display_name, Some("fixture-primary.bit".into()));
+    let resolve =
+        Resolve::find_by_name_system(&db, "fixture-primary.bit", &DomainNameSystem::DotBit)
+            .await?
+            .expect("Resolve not found");
+    assert_eq!(resolve.id_to(), wallet.id());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_account_list_of_address() -> Result<(), Error> {
+    // Replay of `das_accountList`.
+    let url = mock::serve(vec![Fixture::ok(
+        "/",
+        include_str!("../fixtures/dotbit/account_list.json"),
+    )]);
+    let address = "0x0000000000000000000000000000000000D07B1A";
+
+    let result = fetch_account_list_by_addrs(&url, &Platform::Ethereum, address).await?;
+    let accounts = ["fixture-first.bit", "fixture-second.bit"];
+    assert_eq!(
+        result,
+        accounts
+            .iter()
+            .map(|account| Target::Identity(Platform::Dotbit, account.to_string()))
+            .collect::<Vec<_>>()
+    );
+
+    let db = new_db_connection().await?;
+    let wallet =
+        Identity::find_by_platform_identity(&db, &Platform::Ethereum, &address.to_lowercase())
+            .await?
+            .expect("Record not found");
+    for account in accounts {
+        let account = Identity::find_by_platform_identity(&db, &Platform::Dotbit, account)
+            .await?
+            .expect("Record not found");
+        Hold::find_by_from_to_id(&db, &wallet, &account, "")
+            .await?
+            .expect("Hold not found");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_account_info_of_name() -> Result<(), Error> {
+    // Replay of `das_accountInfo`.
+    let url = mock::serve(vec![Fixture::ok(
+        "/",
+        include_str!("../fixtures/dotbit/account_info.json"),
+    )]);
+    let owner = "0x0000000000000000000000000000000000d07b1c";
+
+    let result =
+        fetch_connections_by_account_info(&url, &Platform::Dotbit, "fixture-owned.bit").await?;
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].platform()?, Platform::Ethereum);
+    assert_eq!(result[0].identity()?.to_lowercase(), owner);
+
+    let db = new_db_connection().await?;
+    let wallet = Identity::find_by_platform_identity(&db, &Platform::Ethereum, owner)
+        .await?
+        .expect("Record not found");
+    let account = Identity::find_by_platform_identity(&db, &Platform::Dotbit, "fixture-owned.bit")
+        .await?
+        .expect("Record not found");
+    let hold = Hold::find_by_from_to_id(&db, &wallet, &account, "0")
+        .await?
+        .expect("Hold not found");
+    assert_eq!(
+        hold.transaction.as_deref(),
+        Some("0x00000000000000000000000000000000000000000000000000000000000d07b1")
+    );
+
+    Ok(())
+}
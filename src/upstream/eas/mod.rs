@@ -0,0 +1,286 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    config::{ConfigEasSchema, C},
+    error::Error,
+    graph::{create_identity_to_identity_record, edge::Proof, new_db_connection, vertex::Identity},
+    upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList},
+    util::{naive_now, timestamp_to_naive, with_timeout},
+};
+use aragog::DatabaseConnection;
+use async_trait::async_trait;
+use futures::future::join_all;
+use gql_client::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+const QUERY_BY_RECIPIENT: &str = r#"
+        query AttestationsByRecipient($recipient: String!, $schemas: [String!]) {
+            attestations(where: {
+                recipient: { equals: $recipient, mode: insensitive },
+                schemaId: { in: $schemas }
+            }) {
+                id
+                attester
+                recipient
+                schemaId
+                revoked
+                time
+                decodedDataJson
+            }
+        }
+    "#;
+
+#[derive(Serialize)]
+struct QueryVars {
+    recipient: String,
+    schemas: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct QueryResponse {
+    attestations: Vec<Attestation>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    /// Attestation UID.
+    pub id: String,
+    pub attester: String,
+    /// Ethereum address this attestation is about.
+    pub recipient: String,
+    pub schema_id: String,
+    pub revoked: bool,
+    /// Unix timestamp (seconds) when this attestation is made.
+    pub time: i64,
+    /// JSON string. See `DecodedItem`.
+    pub decoded_data_json: String,
+}
+
+/// An item in `decodedDataJson`, which is an array of those.
+/// e.g. `{"name": "handle", "type": "string", "value": {"name": "handle", "type": "string", "value": "alice"}}`
+#[derive(Deserialize, Debug)]
+struct DecodedItem {
+    name: String,
+    value: DecodedValue,
+}
+
+#[derive(Deserialize, Debug)]
+struct DecodedValue {
+    value: serde_json::Value,
+}
+
+impl Attestation {
+    /// Value of `field` in this attestation's data, as a string.
+    pub fn decoded_field(&self, field: &str) -> Result<Option<String>, Error> {
+        let items: Vec<DecodedItem> = serde_json::from_str(&self.decoded_data_json)?;
+        Ok(items
+            .into_iter()
+            .find(|item| item.name == field)
+            .and_then(|item| match item.value.value {
+                serde_json::Value::String(value) => Some(value),
+                serde_json::Value::Null => None,
+                value => Some(value.to_string()),
+            })
+            .filter(|value| !value.is_empty()))
+    }
+
+    /// The identity this attestation links its recipient to.
+    /// `None` if it is not made with one of `schemas`, is not made by an attester trusted
+    /// by the schema (see `ConfigEasSchema::attesters`), or has no such identity in its data.
+    pub fn linked_identity(&self, schemas: &[ConfigEasSchema]) -> Result<Option<Target>, Error> {
+        let schema = match schemas
+            .iter()
+            .find(|s| s.uid.to_lowercase() == self.schema_id.to_lowercase())
+        {
+            Some(schema) => schema,
+            None => return Ok(None),
+        };
+        if !schema.trusts(&self.attester, &self.recipient) {
+            debug!(
+                "EAS | Attestation {} by untrusted attester {} is skipped.",
+                self.id, self.attester
+            );
+            return Ok(None);
+        }
+        Ok(self.decoded_field(&schema.field)?.map(|identity| {
+            Target::Identity(schema.platform, normalize(schema.platform, identity))
+        }))
+    }
+}
+
+fn normalize(platform: Platform, identity: String) -> String {
+    match platform {
+        Platform::Ethereum => identity.to_lowercase(),
+        _ => identity,
+    }
+}
+
+pub struct Eas {}
+
+#[async_trait]
+impl Fetcher for Eas {
+    async fn fetch(target: &Target) -> Result<TargetProcessedList, Error> {
+        if !Self::can_fetch(target) {
+            return Ok(vec![]);
+        }
+
+        match target {
+            Target::Identity(_, identity) => {
+                let futures = C.upstream.eas.deployments.iter().map(|deployment| {
+                    fetch_attestations_by_recipient(
+                        &deployment.url,
+                        &C.upstream.eas.schemas,
+                        identity,
+                    )
+                });
+                // A failing deployment fails the fetch, so that it's counted by breaker and outcomes.
+                let results: Vec<TargetProcessedList> = join_all(futures)
+                    .await
+                    .into_iter()
+                    .collect::<Result<_, _>>()?;
+                Ok(results.into_iter().flatten().collect())
+            }
+            Target::NFT(_, _, _, _) => Ok(vec![]),
+        }
+    }
+
+    fn can_fetch(target: &Target) -> bool {
+        !C.upstream.eas.schemas.is_empty() && target.in_platform_supported(vec![Platform::Ethereum])
+    }
+}
+
+/// `url`: GraphQL endpoint of an EAS deployment. See `C.upstream.eas.deployments`.
+async fn fetch_attestations_by_recipient(
+    url: &str,
+    schemas: &[ConfigEasSchema],
+    address: &str,
+) -> Result<TargetProcessedList, Error> {
+    let client = Client::new(url);
+    let vars = QueryVars {
+        recipient: address.to_string(),
+        schemas: schemas.iter().map(|s| s.uid.clone()).collect(),
+    };
+    let resp = with_timeout(
+        "EAS",
+        C.upstream.eas.timeout_seconds,
+        client.query_with_vars::<QueryResponse, QueryVars>(QUERY_BY_RECIPIENT, vars),
+    )
+    .await?
+    .map_err(|err| {
+        warn!("EAS {} | Failed to fetch {}: {}", url, address, err);
+        Error::General(
+            format!("EAS fetch error: {}", err),
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+        )
+    })?;
+    let attestations = match resp {
+        Some(resp) => resp.attestations,
+        None => {
+            info!("EAS {} | No result for {}", url, address);
+            return Ok(vec![]);
+        }
+    };
+
+    let db = new_db_connection().await?;
+    let mut next_targets: TargetProcessedList = vec![];
+    for attestation in attestations.into_iter() {
+        match save_attestation(&db, schemas, &attestation).await {
+            Ok(Some(target)) => next_targets.push(target),
+            Ok(None) => {}
+            Err(err) => warn!(
+                "EAS {} | Failed to save attestation {}: {}",
+                url, attestation.id, err
+            ),
+        }
+    }
+
+    Ok(next_targets)
+}
+
+/// Record a `Proof` edge for an attestation.
+/// A revoked attestation removes the edge recorded before instead.
+async fn save_attestation(
+    db: &DatabaseConnection,
+    schemas: &[ConfigEasSchema],
+    attestation: &Attestation,
+) -> Result<Option<Target>, Error> {
+    let linked = match attestation.linked_identity(schemas)? {
+        Some(Target::Identity(platform, identity)) => (platform, identity),
+        _ => return Ok(None),
+    };
+    let recipient = attestation.recipient.to_lowercase();
+
+    if attestation.revoked {
+        info!("EAS | Attestation {} is revoked.", attestation.id);
+        remove_revoked(db, &recipient, &linked, attestation).await?;
+        return Ok(None);
+    }
+
+    let from: Identity = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::Ethereum,
+        identity: recipient,
+        created_at: None,
+        display_name: None,
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: None,
+        updated_at: naive_now(),
+    };
+
+    let to: Identity = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: linked.0,
+        identity: linked.1.clone(),
+        created_at: None,
+        display_name: None,
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: None,
+        updated_at: naive_now(),
+    };
+
+    let proof: Proof = Proof {
+        uuid: Uuid::new_v4(),
+        source: DataSource::Eas,
+        record_id: Some(attestation.id.clone()),
+        proof_url: None,
+        created_at: Some(timestamp_to_naive(attestation.time, 0)),
+        updated_at: naive_now(),
+        fetcher: C.upstream.eas.fetcher.unwrap_or_default(),
+    };
+
+    create_identity_to_identity_record(db, &from, &to, &proof).await?;
+
+    Ok(Some(Target::Identity(linked.0, linked.1)))
+}
+
+async fn remove_revoked(
+    db: &DatabaseConnection,
+    recipient: &str,
+    linked: &(Platform, String),
+    attestation: &Attestation,
+) -> Result<(), Error> {
+    let from = Identity::find_by_platform_identity(db, &Platform::Ethereum, recipient).await?;
+    let to = Identity::find_by_platform_identity(db, &linked.0, &linked.1).await?;
+    if let (Some(from), Some(to)) = (from, to) {
+        let found = Proof::find_by_from_to(
+            db,
+            &from,
+            &to,
+            &DataSource::Eas,
+            &Some(attestation.id.clone()),
+        )
+        .await?;
+        if let Some(mut edge) = found {
+            edge.delete(db).await?;
+        }
+    }
+    Ok(())
+}
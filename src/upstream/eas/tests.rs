@@ -0,0 +1,121 @@
+use crate::{
+    config::ConfigEasSchema,
+    error::Error,
+    graph::{edge::Proof, new_db_connection, vertex::Identity},
+    upstream::{
+        eas::{fetch_attestations_by_recipient, Attestation},
+        mock::{self, Fixture},
+        DataSource, Platform, Target,
+    },
+};
+
+const GRAPHQL_PATH: &str = "/graphql";
+const RECIPIENT: &str = "0x0000000000000000000000000000000000f1c7e3";
+const ATTESTER: &str = "0x0000000000000000000000000000000000a77e57";
+const SCHEMA_UID: &str = "0x00000000000000000000000000000000000000000000000000000000005c4e3a";
+
+fn schemas() -> Vec<ConfigEasSchema> {
+    vec![ConfigEasSchema {
+        uid: SCHEMA_UID.into(),
+        platform: Platform::Twitter,
+        field: "handle".into(),
+        attesters: vec![ATTESTER.into()],
+    }]
+}
+
+#[test]
+fn test_decode_attestation() -> Result<(), Error> {
+    let attestation: Attestation = serde_json::from_str(
+        r#"{
+            "id": "0x01",
+            "attester": "0x0000000000000000000000000000000000A77E57",
+            "recipient": "0x0000000000000000000000000000000000F1C7E3",
+            "schemaId": "0x00000000000000000000000000000000000000000000000000000000005C4E3A",
+            "revoked": false,
+            "time": 1690000000,
+            "decodedDataJson": "[{\"name\":\"handle\",\"type\":\"string\",\"signature\":\"string handle\",\"value\":{\"name\":\"handle\",\"type\":\"string\",\"value\":\"alice\"}},{\"name\":\"since\",\"type\":\"uint64\",\"signature\":\"uint64 since\",\"value\":{\"name\":\"since\",\"type\":\"uint64\",\"value\":1680000000}}]"
+        }"#,
+    )?;
+
+    assert_eq!(attestation.decoded_field("handle")?, Some("alice".into()));
+    assert_eq!(
+        attestation.decoded_field("since")?,
+        Some("1680000000".into())
+    );
+    assert_eq!(attestation.decoded_field("missing")?, None);
+    assert_eq!(
+        attestation.linked_identity(&schemas())?,
+        Some(Target::Identity(Platform::Twitter, "alice".into()))
+    );
+    // Not an identity-linking schema.
+    assert_eq!(attestation.linked_identity(&[])?, None);
+    // Not by a trusted attester.
+    let mut untrusted = schemas();
+    untrusted[0].attesters = vec!["0x0000000000000000000000000000000000000bad".into()];
+    assert_eq!(attestation.linked_identity(&untrusted)?, None);
+    // No attester configured: self-attested ones only.
+    let mut self_attested = schemas();
+    self_attested[0].attesters = vec![];
+    assert_eq!(attestation.linked_identity(&self_attested)?, None);
+    let attestation = Attestation {
+        attester: attestation.recipient.clone(),
+        ..attestation
+    };
+    assert_eq!(
+        attestation.linked_identity(&self_attested)?,
+        Some(Target::Identity(Platform::Twitter, "alice".into()))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eas_replay() -> Result<(), Error> {
+    let url = format!(
+        "{}{}",
+        mock::serve(vec![Fixture::ok(
+            GRAPHQL_PATH,
+            include_str!("../fixtures/eas/attestations.json"),
+        )]),
+        GRAPHQL_PATH
+    );
+
+    let result = fetch_attestations_by_recipient(&url, &schemas(), RECIPIENT).await?;
+    // Revoked and untrusted attestations are skipped.
+    assert_eq!(
+        result,
+        vec![Target::Identity(Platform::Twitter, "fixture_eas".into())]
+    );
+
+    let db = new_db_connection().await?;
+    let from = Identity::find_by_platform_identity(&db, &Platform::Ethereum, RECIPIENT)
+        .await?
+        .expect("Record not found");
+    let to = Identity::find_by_platform_identity(&db, &Platform::Twitter, "fixture_eas")
+        .await?
+        .expect("Record not found");
+    Proof::find_by_from_to(&db, &from, &to, &DataSource::Eas, &None)
+        .await?
+        .expect("Record not found");
+    for skipped in ["fixture_eas_revoked", "fixture_eas_untrusted"] {
+        assert!(
+            Identity::find_by_platform_identity(&db, &Platform::Twitter, skipped)
+                .await?
+                .is_none()
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_eas_deployment_error() {
+    let url = format!(
+        "{}{}",
+        mock::serve(vec![Fixture::ok("/other", "{}")]),
+        GRAPHQL_PATH
+    );
+    assert!(fetch_attestations_by_recipient(&url, &schemas(), RECIPIENT)
+        .await
+        .is_err());
+}
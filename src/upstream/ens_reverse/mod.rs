@@ -4,11 +4,16 @@ mod tests;
 use crate::{
     config::C,
     error::Error,
-    graph::{new_db_connection, vertex::Identity, Vertex},
-    util::{make_client, parse_body},
+    graph::{
+        new_db_connection,
+        vertex::{Identity, NameSource},
+        Vertex,
+    },
+    util::{make_client_with_timeout, parse_body},
 };
 use async_trait::async_trait;
 use serde::Deserialize;
+use std::time::Duration;
 use tracing::info;
 
 use super::{Fetcher, Platform, Target, TargetProcessedList};
@@ -31,7 +36,7 @@ impl Fetcher for ENSReverseLookup {
             return Ok(vec![]);
         }
         let wallet = target.identity().unwrap().to_lowercase();
-        let record = fetch_record(&wallet).await?;
+        let record = fetch_record(&C.upstream.ens_reverse.url, &wallet).await?;
         // If reverse lookup record is reset to empty by user,
         // our cache should also be cleared.
         // Reach this by setting `display_name` into `Some("")`.
@@ -43,6 +48,7 @@ impl Fetcher for ENSReverseLookup {
         identity.platform = Platform::Ethereum;
         identity.identity = wallet.clone();
         identity.display_name = Some(reverse_ens);
+        identity.display_name_source = Some(NameSource::ENS);
         let db = new_db_connection().await?;
         identity.create_or_update(&db).await?;
 
@@ -54,13 +60,24 @@ impl Fetcher for ENSReverseLookup {
     }
 }
 
-async fn fetch_record(wallet: &str) -> Result<Response, Error> {
-    let client = make_client();
-    let url: http::Uri = format!("{}{}", C.upstream.ens_reverse.url, wallet)
-        .parse()
-        .map_err(|err: http::uri::InvalidUri| {
-            Error::ParamError(format!("URI Format error: {}", err))
-        })?;
+/// Current ENS reverse record of `wallet`, looked up at `base_url`. `None` if it has none.
+pub(crate) async fn reverse_record_of(
+    base_url: &str,
+    wallet: &str,
+) -> Result<Option<String>, Error> {
+    let record = fetch_record(base_url, &wallet.to_lowercase()).await?;
+    Ok(record.reverse_record.filter(|name| !name.is_empty()))
+}
+
+async fn fetch_record(base_url: &str, wallet: &str) -> Result<Response, Error> {
+    let client =
+        make_client_with_timeout(Duration::from_secs(C.upstream.ens_reverse.timeout_seconds));
+    let url: http::Uri =
+        format!("{}{}", base_url, wallet)
+            .parse()
+            .map_err(|err: http::uri::InvalidUri| {
+                Error::ParamError(format!("URI Format error: {}", err))
+            })?;
 
     let mut resp = client.get(url).await?;
     if !resp.status().is_success() {
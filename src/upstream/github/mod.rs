@@ -0,0 +1,212 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    config::C,
+    error::Error,
+    graph::{create_identity_to_identity_record, edge::Proof, new_db_connection, vertex::Identity},
+    upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList},
+    util::{make_client_with_timeout, naive_now, parse_body, read_body},
+};
+use async_trait::async_trait;
+use hyper::{Body, Method, Request, Response};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// See https://docs.github.com/en/rest/users/users#get-a-user
+#[derive(Deserialize, Debug)]
+pub struct GithubUser {
+    pub login: String,
+    /// Numeric user ID, which never changes even if `login` is renamed.
+    /// Recorded in `Proof.record_id`.
+    pub id: u64,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub html_url: String,
+    pub bio: Option<String>,
+    pub blog: Option<String>,
+}
+
+pub struct Github {}
+
+#[async_trait]
+impl Fetcher for Github {
+    async fn fetch(target: &Target) -> Result<TargetProcessedList, Error> {
+        if !Self::can_fetch(target) {
+            return Ok(vec![]);
+        }
+
+        match target {
+            Target::Identity(_, identity) => {
+                fetch_user(
+                    &C.upstream.github.url,
+                    C.upstream.github.token.as_deref(),
+                    identity,
+                )
+                .await
+            }
+            Target::NFT(_, _, _, _) => Ok(vec![]),
+        }
+    }
+
+    fn can_fetch(target: &Target) -> bool {
+        target.in_platform_supported(vec![Platform::Github])
+    }
+}
+
+/// Find all Ethereum addresses (`0x` + 40 hex chars) in `text`. Lowercased and deduplicated.
+pub fn extract_addresses(text: &str) -> Vec<String> {
+    let bytes = text.as_bytes();
+    let is_hex = |i: usize| bytes.get(i).map_or(false, |b| b.is_ascii_hexdigit());
+    let mut found: Vec<String> = vec![];
+    let mut i = 0;
+    while i + 42 <= bytes.len() {
+        let starts = bytes[i] == b'0'
+            && (bytes[i + 1] == b'x' || bytes[i + 1] == b'X')
+            && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric());
+        if starts && (i + 2..i + 42).all(is_hex) && !is_hex(i + 42) {
+            let address = text[i..i + 42].to_lowercase();
+            if !found.contains(&address) {
+                found.push(address);
+            }
+            i += 42;
+        } else {
+            i += 1;
+        }
+    }
+    found
+}
+
+async fn request(url: &str, token: Option<&str>, accept: &str) -> Result<Response<Body>, Error> {
+    let mut builder = Request::builder()
+        .method(Method::GET)
+        .uri(url)
+        // Required by GitHub API.
+        .header("User-Agent", "relation_server")
+        .header("Accept", accept);
+    if let Some(token) = token {
+        builder = builder.header("Authorization", format!("token {}", token));
+    }
+    let req = builder
+        .body(Body::empty())
+        .map_err(|err| Error::ParamError(format!("GitHub request error: {}", err)))?;
+
+    let client = make_client_with_timeout(Duration::from_secs(C.upstream.github.timeout_seconds));
+    Ok(client.request(req).await?)
+}
+
+/// Content of user's profile README (`github.com/LOGIN/LOGIN`). `None` if there isn't one.
+async fn fetch_profile_readme(
+    url: &str,
+    token: Option<&str>,
+    login: &str,
+) -> Result<Option<String>, Error> {
+    let mut resp = request(
+        &format!("{}/repos/{}/{}/readme", url, login, login),
+        token,
+        "application/vnd.github.raw",
+    )
+    .await?;
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+
+    let body = read_body(&mut resp).await?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+/// `url`: GitHub REST API endpoint. See `C.upstream.github.url`.
+/// `identity`: login name, or numeric user ID.
+async fn fetch_user(
+    url: &str,
+    token: Option<&str>,
+    identity: &str,
+) -> Result<TargetProcessedList, Error> {
+    let user_url = if identity.chars().all(|c| c.is_ascii_digit()) {
+        format!("{}/user/{}", url, identity)
+    } else {
+        format!("{}/users/{}", url, identity)
+    };
+    let mut resp = request(&user_url, token, "application/vnd.github+json").await?;
+    if !resp.status().is_success() {
+        warn!(
+            "GitHub | Failed to fetch user {}: {}",
+            identity,
+            resp.status()
+        );
+        return Err(Error::General(
+            format!("GitHub user fetch error: {}", resp.status()),
+            resp.status(),
+        ));
+    }
+    let user: GithubUser = parse_body(&mut resp).await?;
+
+    let readme = match fetch_profile_readme(url, token, &user.login).await {
+        Ok(readme) => readme,
+        Err(err) => {
+            warn!("GitHub | Failed to fetch README of {}: {}", user.login, err);
+            None
+        }
+    };
+    let profile_text = [user.bio.as_deref(), user.blog.as_deref(), readme.as_deref()]
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let addresses = extract_addresses(&profile_text);
+    if addresses.is_empty() {
+        info!("GitHub | No address found in profile of {}", user.login);
+        return Ok(vec![]);
+    }
+
+    let db = new_db_connection().await?;
+    // Recorded by lowercased login, the same as other upstreams (e.g. Keybase) do,
+    // so they end up in the same identity. Numeric user ID is kept in `Proof.record_id`.
+    let from: Identity = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::Github,
+        identity: user.login.to_lowercase(),
+        created_at: None,
+        display_name: Some(user.name.clone().unwrap_or_else(|| user.login.clone())),
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: user.avatar_url.clone(),
+        profile_url: Some(user.html_url.clone()),
+        updated_at: naive_now(),
+    };
+
+    let mut next_targets: TargetProcessedList = vec![];
+    for address in addresses.into_iter() {
+        let to: Identity = Identity {
+            uuid: Some(Uuid::new_v4()),
+            platform: Platform::Ethereum,
+            identity: address.clone(),
+            created_at: None,
+            // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
+            display_name: None,
+            display_name_source: None,
+            added_at: naive_now(),
+            avatar_url: None,
+            profile_url: None,
+            updated_at: naive_now(),
+        };
+
+        let proof: Proof = Proof {
+            uuid: Uuid::new_v4(),
+            source: DataSource::Github,
+            record_id: Some(format!("{}/{}", user.id, address)),
+            proof_url: Some(user.html_url.clone()),
+            created_at: None,
+            updated_at: naive_now(),
+            fetcher: C.upstream.github.fetcher.unwrap_or_default(),
+        };
+
+        create_identity_to_identity_record(&db, &from, &to, &proof).await?;
+        next_targets.push(Target::Identity(Platform::Ethereum, address));
+    }
+
+    Ok(next_targets)
+}
@@ -0,0 +1,76 @@
+use crate::{
+    error::Error,
+    graph::{edge::Proof, new_db_connection, vertex::Identity},
+    upstream::{
+        github::{extract_addresses, fetch_user},
+        mock::{self, Fixture},
+        DataSource, Platform, Target,
+    },
+};
+
+const LOGIN: &str = "fixture-gh";
+const USER_ID: &str = "98765432";
+const ADDRESS_IN_BIO: &str = "0x0000000000000000000000000000000000f1c7e6";
+const ADDRESS_IN_README: &str = "0x0000000000000000000000000000000000f1c7e7";
+
+#[test]
+fn test_extract_addresses() {
+    assert_eq!(
+        extract_addresses(
+            "eth: 0x0000000000000000000000000000000000F1C7E6, \
+             again 0x0000000000000000000000000000000000f1c7e6; \
+             too long 0x0000000000000000000000000000000000f1c7e600, \
+             too short 0x1234"
+        ),
+        vec![ADDRESS_IN_BIO.to_string()]
+    );
+    assert!(extract_addresses("").is_empty());
+}
+
+#[tokio::test]
+async fn test_github_replay() -> Result<(), Error> {
+    let url = mock::serve(vec![
+        Fixture::ok(
+            &format!("/users/{}", LOGIN),
+            include_str!("../fixtures/github/user.json"),
+        ),
+        Fixture::ok(
+            &format!("/repos/{}/{}/readme", LOGIN, LOGIN),
+            include_str!("../fixtures/github/readme.md"),
+        ),
+    ]);
+
+    let result = fetch_user(&url, None, LOGIN).await?;
+    assert_eq!(
+        result,
+        vec![
+            Target::Identity(Platform::Ethereum, ADDRESS_IN_BIO.into()),
+            Target::Identity(Platform::Ethereum, ADDRESS_IN_README.into()),
+        ]
+    );
+
+    let db = new_db_connection().await?;
+    // Recorded by login (lowercased), with numeric ID kept in proofs.
+    let user = Identity::find_by_platform_identity(&db, &Platform::Github, LOGIN)
+        .await?
+        .expect("Record not found");
+    assert_eq!(user.display_name, Some("Fixture GH".into()));
+    for address in [ADDRESS_IN_BIO, ADDRESS_IN_README] {
+        let wallet = Identity::find_by_platform_identity(&db, &Platform::Ethereum, address)
+            .await?
+            .expect("Record not found");
+        let record_id = Some(format!("{}/{}", USER_ID, address));
+        Proof::find_by_from_to(&db, &user, &wallet, &DataSource::Github, &record_id)
+            .await?
+            .expect("Record not found");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_github_user_not_found() {
+    let url = mock::serve(vec![]);
+
+    assert!(fetch_user(&url, None, "nobody").await.is_err());
+}
@@ -6,14 +6,15 @@ use crate::error::Error;
 use crate::graph::create_identity_to_identity_record;
 use crate::graph::{edge::Proof, new_db_connection, vertex::Identity};
 use crate::upstream::{DataSource, Fetcher, Platform, TargetProcessedList};
-use crate::util::{make_client, naive_now, parse_body};
+use crate::util::{make_client_with_timeout, naive_now, parse_body, retry_request};
 use async_trait::async_trait;
 use serde::Deserialize;
+use std::time::Duration;
 
-use std::str::FromStr;
+use tracing::debug;
 use uuid::Uuid;
 
-use super::{DataFetcher, Target};
+use super::Target;
 
 #[derive(Deserialize, Debug)]
 pub struct KeybaseResponse {
@@ -84,9 +85,14 @@ impl Fetcher for Keybase {
 
         match target {
             Target::Identity(platform, identity) => {
-                fetch_connections_by_platform_identity(platform, identity).await
+                fetch_connections_by_platform_identity(
+                    &C.upstream.keybase_service.url,
+                    platform,
+                    identity,
+                )
+                .await
             }
-            Target::NFT(_, _, _, _) => todo!(),
+            Target::NFT(_, _, _, _) => Ok(vec![]),
         }
     }
 
@@ -95,22 +101,41 @@ impl Fetcher for Keybase {
     }
 }
 
+/// Our platform of a Keybase `proof_type`. `None` if we don't have one for it.
+/// See https://keybase.io/docs/api/1.0/call/user/lookup
+fn keybase_platform(proof_type: &str) -> Option<Platform> {
+    match proof_type {
+        "twitter" => Some(Platform::Twitter),
+        "github" => Some(Platform::Github),
+        "reddit" => Some(Platform::Reddit),
+        "dns" => Some(Platform::DNS),
+        // Websites, Hacker News, Facebook, Mastodon, etc.
+        _ => None,
+    }
+}
+
+/// `url`: Keybase user lookup API endpoint. See `C.upstream.keybase_service.url`.
 async fn fetch_connections_by_platform_identity(
+    url: &str,
     platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
-    let client = make_client();
-    let uri: http::Uri = match format!(
-        "{}?{}={}&fields=proofs_summary",
-        C.upstream.keybase_service.url, platform, identity
-    )
-    .parse()
-    {
-        Ok(n) => n,
-        Err(err) => return Err(Error::ParamError(format!("Uri format Error: {}", err))),
-    };
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.keybase_service.timeout_seconds,
+    ));
+    let uri: http::Uri =
+        match format!("{}?{}={}&fields=proofs_summary", url, platform, identity).parse() {
+            Ok(n) => n,
+            Err(err) => return Err(Error::ParamError(format!("Uri format Error: {}", err))),
+        };
 
-    let mut resp = client.get(uri).await?;
+    let retry = &C.upstream.keybase_service.retry;
+    let mut resp = retry_request(
+        || client.get(uri.clone()),
+        retry.max_attempts,
+        retry.base_delay(),
+    )
+    .await?;
     if !resp.status().is_success() {
         let body: ErrorResponse = parse_body(&mut resp).await?;
         return Err(Error::General(
@@ -145,21 +170,30 @@ async fn fetch_connections_by_platform_identity(
             identity: user_id.clone(),
             created_at: None,
             display_name: Some(user_name.clone()),
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: None,
             updated_at: naive_now(),
         };
 
-        if Platform::from_str(p.proof_type.as_str()).is_err() {
-            continue;
-        }
+        let to_platform = match keybase_platform(&p.proof_type) {
+            Some(platform) => platform,
+            None => {
+                debug!(
+                    "Keybase | Proof type {} is not supported. Skipped.",
+                    p.proof_type
+                );
+                continue;
+            }
+        };
         let to: Identity = Identity {
             uuid: Some(Uuid::new_v4()),
-            platform: Platform::from_str(p.proof_type.as_str()).unwrap(),
+            platform: to_platform,
             identity: p.nametag.clone().to_lowercase(),
             created_at: None,
             display_name: Some(p.nametag.clone()),
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: None,
@@ -170,17 +204,15 @@ async fn fetch_connections_by_platform_identity(
             uuid: Uuid::new_v4(),
             source: DataSource::Keybase,
             record_id: Some(p.proof_id.clone()),
+            proof_url: Some(p.proof_url.clone()),
             created_at: None,
             updated_at: naive_now(),
-            fetcher: DataFetcher::RelationService,
+            fetcher: C.upstream.keybase_service.fetcher.unwrap_or_default(),
         };
 
         create_identity_to_identity_record(&db, &from, &to, &pf).await?;
 
-        next_targets.push(Target::Identity(
-            Platform::from_str(&p.proof_type).unwrap(),
-            p.nametag,
-        ));
+        next_targets.push(Target::Identity(to_platform, p.nametag));
     }
 
     Ok(next_targets)
@@ -1,21 +1,188 @@
+use http::StatusCode;
+use std::time::Duration;
+
 use crate::{
+    config::C,
     error::Error,
+    graph::edge::Proof,
     graph::new_db_connection,
     graph::vertex::Identity,
-    upstream::{keybase::Keybase, Target},
-    upstream::{Fetcher, Platform},
+    upstream::keybase::{fetch_connections_by_platform_identity, keybase_platform},
+    upstream::mock::{self, Fixture},
+    upstream::{DataSource, Platform, Target},
     util::naive_now,
 };
 
+const LOOKUP_PATH: &str = "/_/api/1.0/user/lookup.json";
+
+fn mock_keybase(fixture: Fixture) -> String {
+    format!("{}{}", mock::serve(vec![fixture]), LOOKUP_PATH)
+}
+
+#[tokio::test]
+async fn test_keybase_retries_flaky_upstream() -> Result<(), Error> {
+    let url = mock_keybase(
+        Fixture::ok(
+            LOOKUP_PATH,
+            include_str!("../fixtures/keybase/user_lookup.json"),
+        )
+        .with_failures(2),
+    );
+
+    let result =
+        fetch_connections_by_platform_identity(&url, &Platform::Github, "fixture_fss").await?;
+    assert!(result.contains(&Target::Identity(Platform::Github, "fixture_fss".into())));
+
+    Ok(())
+}
+
 #[tokio::test]
-async fn test_smoke_keybase() -> Result<(), Error> {
-    let target = Target::Identity(Platform::Github, "fengshanshan".into());
-    Keybase::fetch(&target).await?;
+async fn test_keybase_replay() -> Result<(), Error> {
+    let url = mock_keybase(Fixture::ok(
+        LOOKUP_PATH,
+        include_str!("../fixtures/keybase/user_lookup.json"),
+    ));
+
+    let result =
+        fetch_connections_by_platform_identity(&url, &Platform::Github, "fixture_fss").await?;
+    // `generic_web_site` proof is not a supported platform, thus skipped.
+    assert_eq!(
+        result,
+        vec![
+            Target::Identity(Platform::Github, "fixture_fss".into()),
+            Target::Identity(Platform::Twitter, "Fixture_FSS".into()),
+        ]
+    );
+
     let db = new_db_connection().await?;
-    let found = Identity::find_by_platform_identity(&db, &target.platform()?, &target.identity()?)
+    let keybase = Identity::find_by_platform_identity(
+        &db,
+        &Platform::Keybase,
+        "d5a4a3c4d4e5b1c0a2f0e58e0d1e5f19",
+    )
+    .await?
+    .expect("Record not found");
+    assert_eq!(keybase.display_name, Some("fixture_fss".into()));
+    let twitter = Identity::find_by_platform_identity(&db, &Platform::Twitter, "fixture_fss")
         .await?
         .expect("Record not found");
+    assert_eq!(twitter.display_name, Some("Fixture_FSS".into()));
+    assert!((twitter.updated_at.timestamp() - naive_now().timestamp()).abs() < 3);
+
+    let proof = Proof::find_by_from_to(
+        &db,
+        &keybase,
+        &twitter,
+        &DataSource::Keybase,
+        &Some("6c7f2b2ad3a8e7b2d4a1d910".into()),
+    )
+    .await?
+    .expect("Record not found");
+    assert_eq!(
+        proof.proof_url,
+        Some("https://twitter.com/Fixture_FSS/status/1477960772995100672".into())
+    );
 
-    assert!((found.updated_at.timestamp() - naive_now().timestamp()).abs() < 3);
     Ok(())
 }
+
+#[tokio::test]
+async fn test_keybase_replay_reddit() -> Result<(), Error> {
+    let url = mock_keybase(Fixture::ok(
+        LOOKUP_PATH,
+        include_str!("../fixtures/keybase/user_lookup_reddit.json"),
+    ));
+
+    let result =
+        fetch_connections_by_platform_identity(&url, &Platform::Reddit, "fixture_redditor").await?;
+    assert_eq!(
+        result,
+        vec![Target::Identity(
+            Platform::Reddit,
+            "Fixture_Redditor".into()
+        )]
+    );
+
+    let db = new_db_connection().await?;
+    let keybase = Identity::find_by_platform_identity(
+        &db,
+        &Platform::Keybase,
+        "7c2e9b1f4a3d5e6f8091a2b3c4d5e6f7",
+    )
+    .await?
+    .expect("Record not found");
+    let reddit = Identity::find_by_platform_identity(&db, &Platform::Reddit, "fixture_redditor")
+        .await?
+        .expect("Record not found");
+    assert_eq!(reddit.display_name, Some("Fixture_Redditor".into()));
+    assert!(Proof::find_by_from_to(
+        &db,
+        &keybase,
+        &reddit,
+        &DataSource::Keybase,
+        &Some("b4d2f1b9cae1d2e3f4a51620".into()),
+    )
+    .await?
+    .is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keybase_replay_not_found() {
+    let url = mock_keybase(Fixture::ok(
+        LOOKUP_PATH,
+        include_str!("../fixtures/keybase/user_not_found.json"),
+    ));
+
+    let result = fetch_connections_by_platform_identity(&url, &Platform::Github, "nobody").await;
+    assert!(matches!(result, Err(Error::NoResult)));
+}
+
+#[tokio::test]
+async fn test_keybase_replay_upstream_error() {
+    let url = mock_keybase(Fixture::with_status(
+        LOOKUP_PATH,
+        StatusCode::INTERNAL_SERVER_ERROR,
+        r#"{"message": "internal error"}"#,
+    ));
+
+    let result =
+        fetch_connections_by_platform_identity(&url, &Platform::Github, "fixture_fss").await;
+    assert!(matches!(
+        result,
+        Err(Error::General(_, StatusCode::INTERNAL_SERVER_ERROR))
+    ));
+}
+
+#[tokio::test]
+async fn test_keybase_times_out() {
+    let timeout = C.upstream.keybase_service.timeout_seconds;
+    // Hangs longer than `timeout_seconds`. Timeouts aren't retried.
+    let url = mock_keybase(
+        Fixture::ok(
+            LOOKUP_PATH,
+            include_str!("../fixtures/keybase/user_lookup.json"),
+        )
+        .with_delay(Duration::from_secs(timeout + 5)),
+    );
+
+    let result =
+        fetch_connections_by_platform_identity(&url, &Platform::Github, "fixture_fss").await;
+    assert!(matches!(result, Err(Error::Timeout(_))));
+}
+
+#[test]
+fn test_keybase_platform() {
+    for (proof_type, platform) in [
+        ("twitter", Some(Platform::Twitter)),
+        ("github", Some(Platform::Github)),
+        ("reddit", Some(Platform::Reddit)),
+        ("dns", Some(Platform::DNS)),
+        ("generic_web_site", None),
+        ("hackernews", None),
+        ("some_future_service", None),
+    ] {
+        assert_eq!(keybase_platform(proof_type), platform, "{}", proof_type);
+    }
+}
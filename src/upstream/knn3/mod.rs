@@ -5,8 +5,8 @@ use crate::config::C;
 use crate::graph::edge::hold::Hold;
 use crate::graph::vertex::{contract::Chain, contract::ContractCategory, Contract};
 
-use crate::upstream::{DataFetcher, DataSource, Fetcher, Platform, Target, TargetProcessedList};
-use crate::util::naive_now;
+use crate::upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList};
+use crate::util::{naive_now, with_timeout};
 use crate::{
     error::Error,
     graph::{create_identity_to_contract_record, new_db_connection, vertex::Identity},
@@ -58,21 +58,30 @@ impl Fetcher for Knn3 {
         }
 
         match target {
-            Target::Identity(_, identity) => fetch_ens_by_eth_wallet(identity).await,
-            Target::NFT(_, _, _, id) => fetch_eth_wallet_by_ens(id).await,
+            Target::Identity(_, identity) => {
+                fetch_ens_by_eth_wallet(&C.upstream.knn3_service.url, identity).await
+            }
+            Target::NFT(_, _, _, id) => {
+                fetch_eth_wallet_by_ens(&C.upstream.knn3_service.url, id).await
+            }
         }
     }
 
-    fn can_fetch(_target: &Target) -> bool {
-        // TODO: temporarily disable KNN3 fetcher
-        false
-        // target.in_platform_supported(vec![Platform::Ethereum])
-        //     || target.in_nft_supported(vec![ContractCategory::ENS], vec![Chain::Ethereum])
+    fn can_fetch(target: &Target) -> bool {
+        can_fetch_if(C.upstream.knn3_service.enabled, target)
     }
 }
 
+/// `enabled`: see `C.upstream.knn3_service.enabled`.
+fn can_fetch_if(enabled: bool, target: &Target) -> bool {
+    enabled
+        && (target.in_platform_supported(vec![Platform::Ethereum])
+            || target.in_nft_supported(vec![ContractCategory::ENS], vec![Chain::Ethereum]))
+}
+
 /// Use ethereum address to fetch NFTs (especially ENS).
-async fn fetch_ens_by_eth_wallet(identity: &str) -> Result<TargetProcessedList, Error> {
+/// `url`: KNN3 GraphQL endpoint. See `C.upstream.knn3_service.url`.
+async fn fetch_ens_by_eth_wallet(url: &str, identity: &str) -> Result<TargetProcessedList, Error> {
     let query = r#"
         query EnsByAddressQuery($addr: String!){
             addrs(where: { address: $addr }) {
@@ -81,12 +90,17 @@ async fn fetch_ens_by_eth_wallet(identity: &str) -> Result<TargetProcessedList,
         }
     "#;
 
-    let client = Client::new(C.upstream.knn3_service.url.clone());
+    let client = Client::new(url);
     let vars = EthQueryVars {
         addr: &identity.to_lowercase(), // Yes, KNN3 is case-sensitive.
     };
 
-    let resp: Result<Option<EthQueryResponse>, _> = client.query_with_vars(query, vars).await;
+    let resp: Result<Option<EthQueryResponse>, _> = with_timeout(
+        "KNN3",
+        C.upstream.knn3_service.timeout_seconds,
+        client.query_with_vars(query, vars),
+    )
+    .await?;
     if resp.is_err() {
         warn!(
             "KNN3 fetch | Failed to fetch addrs: {}, err: {:?}",
@@ -113,6 +127,7 @@ async fn fetch_ens_by_eth_wallet(identity: &str) -> Result<TargetProcessedList,
             created_at: None,
             // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
             display_name: None,
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: None,
@@ -132,8 +147,9 @@ async fn fetch_ens_by_eth_wallet(identity: &str) -> Result<TargetProcessedList,
             id: ens.to_string(),
             source: DataSource::Knn3,
             created_at: None,
+            expires_at: None,
             updated_at: naive_now(),
-            fetcher: DataFetcher::RelationService,
+            fetcher: C.upstream.knn3_service.fetcher.unwrap_or_default(),
         };
         create_identity_to_contract_record(&db, &from, &to, &ownership).await?;
     }
@@ -151,7 +167,9 @@ async fn fetch_ens_by_eth_wallet(identity: &str) -> Result<TargetProcessedList,
         .collect())
 }
 
-async fn fetch_eth_wallet_by_ens(id: &str) -> Result<TargetProcessedList, Error> {
+/// Use ENS to fetch ethereum addresses holding it.
+/// `url`: KNN3 GraphQL endpoint. See `C.upstream.knn3_service.url`.
+async fn fetch_eth_wallet_by_ens(url: &str, id: &str) -> Result<TargetProcessedList, Error> {
     let query = r#"
         query AddressByENSQuery($ens: [String]){
             addrs(where: { ens: $ens }) {
@@ -159,13 +177,16 @@ async fn fetch_eth_wallet_by_ens(id: &str) -> Result<TargetProcessedList, Error>
             }
         }
     "#;
-    let client = Client::new(C.upstream.knn3_service.url.clone());
+    let client = Client::new(url);
     let vars = ENSQueryVars {
         ens: vec![id.to_string()],
     };
-    let response = client
-        .query_with_vars::<EnsQueryResponse, _>(query, vars)
-        .await;
+    let response = with_timeout(
+        "KNN3",
+        C.upstream.knn3_service.timeout_seconds,
+        client.query_with_vars::<EnsQueryResponse, _>(query, vars),
+    )
+    .await?;
     if response.is_err() {
         warn!(
             "KNN3 fetch | Failed to fetch addrs using ENS: {}, error: {:?}",
@@ -181,20 +202,17 @@ async fn fetch_eth_wallet_by_ens(id: &str) -> Result<TargetProcessedList, Error>
         return Ok(vec![]);
     }
 
-    // NOTE: not sure if this result must have one and only one.
-    let address = result.addrs.first().unwrap().address.clone();
-    let from = Identity {
-        uuid: Some(Uuid::new_v4()),
-        platform: Platform::Ethereum,
-        identity: address.to_lowercase(),
-        // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
-        display_name: None,
-        profile_url: None,
-        avatar_url: None,
-        created_at: None,
-        added_at: naive_now(),
-        updated_at: naive_now(),
-    };
+    // KNN3 may return more than one address for an ENS. Record all of them,
+    // in a deterministic order.
+    let mut addresses: Vec<String> = result
+        .addrs
+        .into_iter()
+        .map(|addr| addr.address.to_lowercase())
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+
+    let db = new_db_connection().await?;
     let to = Contract {
         uuid: Uuid::new_v4(),
         updated_at: naive_now(),
@@ -203,17 +221,35 @@ async fn fetch_eth_wallet_by_ens(id: &str) -> Result<TargetProcessedList, Error>
         chain: Chain::Ethereum,
         symbol: None,
     };
-    let hold = Hold {
-        uuid: Uuid::new_v4(),
-        transaction: None,
-        id: id.into(),
-        source: DataSource::Knn3,
-        created_at: None,
-        updated_at: naive_now(),
-        fetcher: DataFetcher::RelationService,
-    };
-    let db = new_db_connection().await?;
-    create_identity_to_contract_record(&db, &from, &to, &hold).await?;
+    for address in addresses.iter() {
+        let from = Identity {
+            uuid: Some(Uuid::new_v4()),
+            platform: Platform::Ethereum,
+            identity: address.clone(),
+            // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
+            display_name: None,
+            display_name_source: None,
+            profile_url: None,
+            avatar_url: None,
+            created_at: None,
+            added_at: naive_now(),
+            updated_at: naive_now(),
+        };
+        let hold = Hold {
+            uuid: Uuid::new_v4(),
+            transaction: None,
+            id: id.into(),
+            source: DataSource::Knn3,
+            created_at: None,
+            expires_at: None,
+            updated_at: naive_now(),
+            fetcher: C.upstream.knn3_service.fetcher.unwrap_or_default(),
+        };
+        create_identity_to_contract_record(&db, &from, &to, &hold).await?;
+    }
 
-    Ok(vec![Target::Identity(Platform::Ethereum, address)])
+    Ok(addresses
+        .into_iter()
+        .map(|address| Target::Identity(Platform::Ethereum, address))
+        .collect())
 }
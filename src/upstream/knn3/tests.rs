@@ -7,7 +7,11 @@ use crate::{
         vertex::Identity,
         vertex::{contract::ContractCategory, Contract},
     },
-    upstream::{knn3::Knn3, Fetcher, Platform, Target},
+    upstream::{
+        knn3::{can_fetch_if, fetch_eth_wallet_by_ens, Knn3},
+        mock::{self, Fixture},
+        Fetcher, Platform, Target,
+    },
 };
 
 #[tokio::test]
@@ -55,3 +59,66 @@ async fn test_knn3_fail_get_result() -> Result<(), Error> {
     assert_eq!(res.len(), 0);
     Ok(())
 }
+
+#[tokio::test]
+async fn test_knn3_ens_with_multiple_addresses() -> Result<(), Error> {
+    let url = mock::serve(vec![Fixture::ok(
+        "/",
+        include_str!("../fixtures/knn3/addrs_by_ens.json"),
+    )]);
+    let ens = "fixture-multiple.eth";
+
+    let result = fetch_eth_wallet_by_ens(&url, ens).await?;
+    let owners = vec![
+        "0x0000000000000000000000000000000000f1c7e4",
+        "0x0000000000000000000000000000000000f1c7e5",
+    ];
+    assert_eq!(
+        result,
+        owners
+            .iter()
+            .map(|owner| Target::Identity(Platform::Ethereum, owner.to_string()))
+            .collect::<Vec<_>>()
+    );
+
+    let db = new_db_connection().await?;
+    let contract = Contract::find_by_chain_address(
+        &db,
+        &Chain::Ethereum,
+        &ContractCategory::ENS.default_contract_address().unwrap(),
+    )
+    .await?
+    .expect("Record not found");
+    for owner in owners {
+        let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, owner)
+            .await?
+            .expect("Record not found");
+        Hold::find_by_from_to_id(&db, &owner, &contract, ens)
+            .await?
+            .expect("Record not found");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_can_fetch_if_enabled() {
+    let wallet = Target::Identity(
+        Platform::Ethereum,
+        "0xd8da6bf26964af9d7eed9e03e53415d37aa96045".into(),
+    );
+    let ens = Target::NFT(
+        Chain::Ethereum,
+        ContractCategory::ENS,
+        ContractCategory::ENS.default_contract_address().unwrap(),
+        "vitalik.eth".into(),
+    );
+    let twitter = Target::Identity(Platform::Twitter, "test".into());
+
+    assert!(can_fetch_if(true, &wallet));
+    assert!(can_fetch_if(true, &ens));
+    assert!(!can_fetch_if(true, &twitter));
+
+    assert!(!can_fetch_if(false, &wallet));
+    assert!(!can_fetch_if(false, &ens));
+}
@@ -0,0 +1,92 @@
+//! Detection of upstreams which silently stopped working,
+//! e.g. their API changed and every request fails.
+//! An upstream "produces" whenever it responds successfully, even if nothing is found.
+
+use crate::{
+    config::C,
+    upstream::{DataSource, UPSTREAMS},
+    util::naive_now,
+};
+use chrono::{Duration, NaiveDateTime};
+use std::{collections::HashMap, sync::Mutex};
+use tracing::warn;
+
+lazy_static! {
+    /// When each upstream last produced anything.
+    static ref LAST_PRODUCED: Mutex<HashMap<DataSource, NaiveDateTime>> = Mutex::new(HashMap::new());
+    /// Since when we are watching. Upstreams never producing anything are quiet since then.
+    static ref WATCHING_SINCE: NaiveDateTime = naive_now();
+}
+
+/// Liveness of an upstream.
+#[derive(Clone, Debug, PartialEq, async_graphql::SimpleObject)]
+pub struct SourceLiveness {
+    pub source: DataSource,
+    /// When it last produced anything. Second-based unix timestamp.
+    /// `null` if it hasn't since this server started.
+    pub last_produced_at: Option<i64>,
+    /// Has it produced nothing for longer than `upstream.liveness.quiet_after` in config?
+    pub quiet: bool,
+}
+
+/// Record that `source` produced something just now.
+pub fn record_produced(source: DataSource) {
+    record_produced_at(source, naive_now());
+}
+
+pub(crate) fn record_produced_at(source: DataSource, at: NaiveDateTime) {
+    let mut last_produced = LAST_PRODUCED.lock().unwrap();
+    let last = last_produced.entry(source).or_insert(at);
+    *last = (*last).max(at);
+}
+
+/// Liveness of every upstream `fetch_one` asks, except those turned off in config,
+/// which never produce anything.
+pub fn source_liveness() -> Vec<SourceLiveness> {
+    UPSTREAMS
+        .iter()
+        .filter(|(source, _)| is_configured(*source))
+        .map(|(source, _)| liveness_of(*source))
+        .collect()
+}
+
+/// Is `source` turned on in config? See `can_fetch` of each of them.
+fn is_configured(source: DataSource) -> bool {
+    match source {
+        DataSource::Knn3 => C.upstream.knn3_service.enabled,
+        DataSource::Eas => !C.upstream.eas.schemas.is_empty(),
+        DataSource::Poap => !C.upstream.poap.url.is_empty(),
+        DataSource::SpaceId => !C.upstream.space_id.url.is_empty(),
+        _ => true,
+    }
+}
+
+pub(crate) fn liveness_of(source: DataSource) -> SourceLiveness {
+    let quiet_after = Duration::seconds(C.upstream.liveness.quiet_after as i64);
+    let last = LAST_PRODUCED.lock().unwrap().get(&source).copied();
+    SourceLiveness {
+        source,
+        last_produced_at: last.map(|at| at.timestamp()),
+        quiet: naive_now() - last.unwrap_or(*WATCHING_SINCE) > quiet_after,
+    }
+}
+
+/// Periodically warn about quiet upstreams. See `C.upstream.liveness`.
+pub fn spawn_liveness_monitor() {
+    if C.upstream.liveness.check_interval == 0 {
+        return;
+    }
+    let interval = std::time::Duration::from_secs(C.upstream.liveness.check_interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for quiet in source_liveness().into_iter().filter(|l| l.quiet) {
+                warn!(
+                    "Liveness | {} has produced nothing for more than {}s (last at: {:?}). Is its API changed?",
+                    quiet.source, C.upstream.liveness.quiet_after, quiet.last_produced_at
+                );
+            }
+        }
+    });
+}
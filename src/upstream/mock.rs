@@ -0,0 +1,119 @@
+//! Local mock server replaying recorded upstream responses,
+//! so fetchers can be tested offline and deterministically.
+//! Recorded responses live in `src/upstream/fixtures/`.
+
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+
+/// A canned response served on `path` (query string is ignored).
+#[derive(Clone, Debug)]
+pub struct Fixture {
+    pub path: String,
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Wait this long before responding.
+    pub delay: Option<Duration>,
+    /// Respond `503` to this many requests first.
+    pub failures: Arc<AtomicUsize>,
+}
+
+impl Fixture {
+    /// `200 OK` with `body`.
+    pub fn ok(path: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self::with_status(path, StatusCode::OK, body)
+    }
+
+    pub fn with_status(path: &str, status: StatusCode, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            path: path.to_string(),
+            status,
+            headers: vec![],
+            body: body.into(),
+            delay: None,
+            failures: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Also respond with this header (e.g. `Content-Encoding`).
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Respond only after `delay`, like an upstream which hangs.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Respond `503` to the first `times` requests, like a flaky upstream.
+    pub fn with_failures(self, times: usize) -> Self {
+        self.failures.store(times, Ordering::SeqCst);
+        self
+    }
+}
+
+/// Start a mock upstream serving given fixtures, `404` on anything else.
+/// Returns its base URL (`http://127.0.0.1:PORT`, no trailing slash).
+/// Must be called inside a tokio runtime.
+pub fn serve(fixtures: Vec<Fixture>) -> String {
+    let fixtures = Arc::new(fixtures);
+    let make_svc = make_service_fn(move |_| {
+        let fixtures = fixtures.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let found = fixtures
+                    .iter()
+                    .find(|f| f.path == req.uri().path())
+                    .cloned();
+                async move {
+                    if let Some(delay) = found.as_ref().and_then(|f| f.delay) {
+                        tokio::time::sleep(delay).await;
+                    }
+                    let failing = found.as_ref().map_or(false, |f| {
+                        f.failures
+                            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                            .is_ok()
+                    });
+                    let resp = match found {
+                        Some(_) if failing => Response::builder()
+                            .status(StatusCode::SERVICE_UNAVAILABLE)
+                            .body(Body::empty()),
+                        Some(fixture) => fixture
+                            .headers
+                            .iter()
+                            .fold(
+                                Response::builder()
+                                    .status(fixture.status)
+                                    .header("content-type", "application/json"),
+                                |builder, (name, value)| builder.header(name, value),
+                            )
+                            .body(Body::from(fixture.body)),
+                        None => Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .body(Body::empty()),
+                    };
+                    Ok::<_, Infallible>(resp.unwrap())
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    format!("http://{}", address)
+}
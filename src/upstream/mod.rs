@@ -1,41 +1,138 @@
 // Upstreams
 mod aggregation;
+pub mod breaker;
+pub mod concurrency;
+pub mod cost;
 mod dotbit;
-mod ens_reverse;
+mod eas;
+pub(crate) mod ens_reverse;
+mod github;
 mod keybase;
 mod knn3;
+pub mod liveness;
+#[cfg(test)]
+pub(crate) mod mock;
+pub mod nft_metadata;
+mod poap;
 mod proof_client;
+pub mod refetch;
 mod rss3;
+mod spaceid;
 mod sybil_list;
 
 #[cfg(test)]
 mod tests;
-mod the_graph;
+pub mod the_graph;
 mod types;
+mod web_proof;
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use crate::{
+    cache::cache,
+    config::C,
     error::Error,
+    graph::{vertex::Identity, with_db_budget, Freshness},
     upstream::{
-        aggregation::Aggregation, dotbit::DotBit, ens_reverse::ENSReverseLookup, keybase::Keybase,
-        knn3::Knn3, proof_client::ProofClient, rss3::Rss3, sybil_list::SybilList,
-        the_graph::TheGraph,
+        aggregation::Aggregation, cost::CrawlCost, dotbit::DotBit, eas::Eas,
+        ens_reverse::ENSReverseLookup, github::Github, keybase::Keybase, knn3::Knn3,
+        liveness::record_produced, poap::Poap, proof_client::ProofClient, rss3::Rss3,
+        spaceid::SpaceId, sybil_list::SybilList, the_graph::TheGraph, web_proof::WebProof,
     },
-    util::hashset_append,
+    util::naive_now,
 };
 use async_trait::async_trait;
-use futures::future::join_all;
-use tracing::{info, warn};
+use futures::future::{join_all, BoxFuture};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
 
-pub(crate) use types::{DataFetcher, DataSource, Platform, Target, TargetProcessedList};
+pub(crate) use types::{
+    validate_identity, Algorithm, Curve, DataFetcher, DataSource, Platform, Target,
+    TargetProcessedList,
+};
 
 lazy_static! {
     /// Global processing queue to prevent duplicated query. i.e. multiple same request from frontend.
-    pub static ref FETCHING: Arc<Mutex<HashSet<Target>>> = Arc::new(Mutex::new(HashSet::new()));
+    /// Keyed by normalized target (see `Target::normalized`).
+    /// Value is when the crawl of this target is started.
+    pub static ref FETCHING: Arc<Mutex<HashMap<Target, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    /// Targets being fetched by `fetch_one` right now, with how many fetches of each are running.
+    /// Covers every target a crawl walks through, not only the initial one in `FETCHING`.
+    /// Keyed by normalized target, too.
+    static ref IN_FLIGHT: Mutex<HashMap<Target, usize>> = Mutex::new(HashMap::new());
+
+    /// Wallets whose reverse records are fetched by `trigger_reverse_lookup`, and when.
+    static ref REVERSE_TRIGGERED: Mutex<HashMap<String, Instant>> = Mutex::new(HashMap::new());
+}
+
+/// Marks a target as in flight until dropped. See `IN_FLIGHT`.
+pub(crate) struct InFlight(Target);
+
+impl InFlight {
+    pub(crate) fn enter(target: &Target) -> Self {
+        let target = target.normalized();
+        *IN_FLIGHT.lock().unwrap().entry(target.clone()).or_insert(0) += 1;
+        Self(target)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        if let Some(count) = in_flight.get_mut(&self.0) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// Is this target being crawled right now, either as the initial target of a crawl or in the middle of one?
+pub fn is_fetching(target: &Target) -> bool {
+    let target = target.normalized();
+    FETCHING.lock().unwrap().contains_key(&target)
+        || IN_FLIGHT.lock().unwrap().contains_key(&target)
+}
+
+/// Evict those entries in `FETCHING` which are started more than `max_age` ago,
+/// so a hanging crawl can't block further crawls of the same target forever.
+/// Returns evicted targets.
+pub fn evict_stale_fetching(max_age: Duration) -> Vec<Target> {
+    let mut fetching = FETCHING.lock().unwrap();
+    let stale: Vec<Target> = fetching
+        .iter()
+        .filter(|(_, started_at)| started_at.elapsed() > max_age)
+        .map(|(target, _)| target.clone())
+        .collect();
+    for target in stale.iter() {
+        fetching.remove(target);
+        warn!(
+            "{} is fetching for more than {}s. Evicted from fetching registry.",
+            target,
+            max_age.as_secs()
+        );
+    }
+    stale
+}
+
+/// Periodically evict stale entries in `FETCHING`. See `C.upstream.crawl`.
+pub fn spawn_fetching_watchdog() {
+    let max_age = Duration::from_secs(C.upstream.crawl.max_age);
+    let interval = Duration::from_secs(C.upstream.crawl.sweep_interval);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            evict_stale_fetching(max_age);
+        }
+    });
 }
 
 /// Fetcher defines how to fetch data from upstream.
@@ -48,75 +145,413 @@ pub trait Fetcher {
     fn can_fetch(target: &Target) -> bool;
 }
 
-/// Find all available (platform, identity) in all `Upstream`s.
-pub async fn fetch_all(initial_target: Target) -> Result<(), Error> {
-    if FETCHING.lock().unwrap().contains(&initial_target) {
-        info!("{} is fetching. Skipped.", initial_target);
-        return Ok(());
+/// Find all available (platform, identity) in all `Upstream`s,
+/// at most `C.upstream.crawl.max_depth` hops away from `initial_target`.
+/// Returns what the crawl costs, which is empty if `initial_target` is being fetched already.
+pub async fn fetch_all(initial_target: Target) -> Result<CrawlCost, Error> {
+    fetch_all_with_sources(initial_target, SourceSelection::default()).await
+}
+
+/// Same as `fetch_all`, but stops at `max_depth` hops away from `initial_target`.
+/// `0` means unlimited.
+pub async fn fetch_all_with_depth(
+    initial_target: Target,
+    max_depth: u16,
+) -> Result<CrawlCost, Error> {
+    fetch_all_limited(initial_target, SourceSelection::default(), max_depth).await
+}
+
+/// Same as `fetch_all`, but only asks selected upstreams.
+pub async fn fetch_all_with_sources(
+    initial_target: Target,
+    sources: SourceSelection,
+) -> Result<CrawlCost, Error> {
+    fetch_all_limited(initial_target, sources, C.upstream.crawl.max_depth).await
+}
+
+async fn fetch_all_limited(
+    initial_target: Target,
+    sources: SourceSelection,
+    max_depth: u16,
+) -> Result<CrawlCost, Error> {
+    initial_target.validate()?;
+    evict_stale_fetching(Duration::from_secs(C.upstream.crawl.max_age));
+    let started_at = Instant::now();
+    // The same one however it's cased, as `crawl` tells targets apart.
+    let key = initial_target.normalized();
+    {
+        let mut fetching = FETCHING.lock().unwrap();
+        if fetching.contains_key(&key) {
+            info!("{} is fetching. Skipped.", initial_target);
+            return Ok(CrawlCost::default());
+        }
+        fetching.insert(key.clone(), started_at);
+    }
+    if !claim_crawl(&key).await {
+        info!(
+            "{} is fetching by another instance. Skipped.",
+            initial_target
+        );
+        release_fetching(&key, started_at);
+        return Ok(CrawlCost::default());
     }
+    let ((_, peak_connections), cost) = cost::with_cost(with_db_budget(
+        C.upstream.crawl.max_db_connections,
+        crawl(
+            initial_target.clone(),
+            C.upstream.crawl.strategy,
+            max_depth,
+            Some(Duration::from_secs(C.upstream.crawl.deadline)).filter(|d| !d.is_zero()),
+            |target| {
+                let sources = &sources;
+                async move {
+                    let outcome = fetch_one_with_sources(&target, sources).await?;
+                    outcome.warn_failures(&target);
+                    Ok(outcome.found)
+                }
+            },
+        ),
+    ))
+    .await;
+    debug!(
+        "{} | Crawl used at most {} DB connections at the same time.",
+        initial_target, peak_connections
+    );
 
-    FETCHING.lock().unwrap().insert(initial_target.clone());
-    // queues of this session.
-    let mut up_next = HashSet::from([initial_target.clone()]);
-    let mut processed: HashSet<Target> = HashSet::new();
+    release_crawl(&key).await;
+    release_fetching(&key, started_at);
+    Ok(cost)
+}
 
-    while up_next.len() > 0 {
-        let futures: Vec<_> = up_next
-            .iter()
-            .filter(|target| !processed.contains(target))
-            .map(|target| fetch_one(target))
-            .collect();
-        let mut result: Vec<Target> = join_all(futures)
-            .await
-            .into_iter()
-            .flat_map(|handle_result| match handle_result {
-                Ok(result) => result,
+fn release_fetching(target: &Target, started_at: Instant) {
+    let mut fetching = FETCHING.lock().unwrap();
+    // This entry may be evicted as stale and re-inserted by another crawl. Keep that one.
+    if fetching.get(target) == Some(&started_at) {
+        fetching.remove(target);
+    }
+}
+
+fn crawl_cache_key(target: &Target) -> String {
+    format!("crawl:{}", target.to_log_string(false))
+}
+
+/// Claim crawling `target` among all instances sharing `cache`, for at most `C.upstream.crawl.max_age`.
+/// Always succeeds if the cache is unavailable, the same as if it's not shared.
+async fn claim_crawl(target: &Target) -> bool {
+    let claimed = match cache().await {
+        Ok(cache) => {
+            cache
+                .set_if_absent(
+                    &crawl_cache_key(target),
+                    &naive_now().timestamp().to_string(),
+                    Duration::from_secs(C.upstream.crawl.max_age),
+                )
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    claimed.unwrap_or_else(|err| {
+        warn!("{} | Failed to claim crawl in cache: {}", target, err);
+        true
+    })
+}
+
+async fn release_crawl(target: &Target) {
+    let released = match cache().await {
+        Ok(cache) => cache.invalidate(&crawl_cache_key(target)).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = released {
+        warn!("{} | Failed to release crawl in cache: {}", target, err);
+    }
+}
+
+/// How `fetch_all` walks through the targets found.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum CrawlStrategy {
+    /// Breadth-first. All targets found in the same round are fetched concurrently.
+    #[default]
+    #[serde(rename = "bfs")]
+    BreadthFirst,
+    /// Depth-first. The latest found target is fetched first, one at a time.
+    #[serde(rename = "dfs")]
+    DepthFirst,
+}
+
+/// Walk through all targets reachable from `initial_target` using `fetch`,
+/// at most `max_depth` hops away from it (`0` means unlimited).
+/// Once it has run for `deadline`, fetches in progress are dropped and nothing more is fetched.
+/// The crawl is then recorded as truncated (see `CrawlCost`).
+/// Returns processed targets in the order they are fetched.
+async fn crawl<F, Fut>(
+    initial_target: Target,
+    strategy: CrawlStrategy,
+    max_depth: u16,
+    deadline: Option<Duration>,
+    fetch: F,
+) -> Vec<Target>
+where
+    F: Fn(Target) -> Fut,
+    Fut: Future<Output = Result<TargetProcessedList, Error>>,
+{
+    let mut processed: Vec<Target> = Vec::new();
+    // Every target ever queued in this session. Normalized, so the same one found in different casing is fetched once.
+    let mut seen: HashSet<Target> = HashSet::from([initial_target.normalized()]);
+    // With how many hops away from `initial_target`.
+    let mut up_next: VecDeque<(Target, u16)> = VecDeque::from([(initial_target, 0)]);
+
+    let started_at = Instant::now();
+
+    while !up_next.is_empty() {
+        let remaining = deadline.map(|deadline| deadline.saturating_sub(started_at.elapsed()));
+        if remaining == Some(Duration::ZERO) {
+            warn!(
+                "Crawl | Deadline reached. {} targets left unfetched.",
+                up_next.len()
+            );
+            cost::record_truncated();
+            break;
+        }
+        let batch: Vec<(Target, u16)> = match strategy {
+            CrawlStrategy::BreadthFirst => up_next.drain(..).collect(),
+            CrawlStrategy::DepthFirst => up_next.pop_back().into_iter().collect(),
+        };
+        let fetches = join_all(batch.iter().map(|(target, _)| fetch(target.clone())));
+        let results = match remaining {
+            None => fetches.await,
+            Some(remaining) => match tokio::time::timeout(remaining, fetches).await {
+                Ok(results) => results,
+                Err(_) => {
+                    warn!(
+                        "Crawl | Deadline reached. {} targets dropped while fetching, {} left unfetched.",
+                        batch.len(),
+                        up_next.len()
+                    );
+                    cost::record_truncated();
+                    break;
+                }
+            },
+        };
+
+        for ((target, depth), result) in batch.into_iter().zip(results) {
+            let found = match result {
+                Ok(found) => found,
                 Err(err) => {
                     warn!("Error happened in fetching task: {}", err);
                     vec![]
                 }
-            })
-            .collect();
-        result.dedup();
+            };
+            processed.push(target);
+            if max_depth != 0 && depth >= max_depth {
+                debug!(
+                    "Crawl | Max depth {} reached. Not going further.",
+                    max_depth
+                );
+                continue;
+            }
 
-        // Add previous up_next into processed, and replace with new up_next
-        hashset_append(&mut processed, up_next.into_iter().collect());
-        up_next = HashSet::from_iter(result.into_iter());
+            // Deduplicated by the normalized form, but fetched as it's found.
+            let found: Vec<(Target, u16)> = found
+                .into_iter()
+                .filter(|t| seen.insert(t.normalized()))
+                .map(|t| (t, depth + 1))
+                .collect();
+            match strategy {
+                CrawlStrategy::BreadthFirst => up_next.extend(found),
+                // Reversed, so the first found one is popped first.
+                CrawlStrategy::DepthFirst => up_next.extend(found.into_iter().rev()),
+            }
+        }
     }
 
-    FETCHING.lock().unwrap().remove(&initial_target);
-    Ok(())
+    processed
+}
+
+/// Fetch function of an upstream. See `Fetcher::fetch`.
+pub(crate) type FetchFn =
+    for<'a> fn(&'a Target) -> BoxFuture<'a, Result<TargetProcessedList, Error>>;
+
+/// All upstreams `fetch_one` asks, with the data source each of them stands for.
+pub(crate) const UPSTREAMS: &[(DataSource, FetchFn)] = &[
+    (DataSource::Aggregation, fetch_via::<Aggregation>),
+    (DataSource::SybilList, fetch_via::<SybilList>),
+    (DataSource::Keybase, fetch_via::<Keybase>),
+    (DataSource::NextID, fetch_via::<ProofClient>),
+    (DataSource::Rss3, fetch_via::<Rss3>),
+    (DataSource::Knn3, fetch_via::<Knn3>),
+    (DataSource::TheGraph, fetch_via::<TheGraph>),
+    (DataSource::ENSReverse, fetch_via::<ENSReverseLookup>),
+    (DataSource::Dotbit, fetch_via::<DotBit>),
+    (DataSource::WebProof, fetch_via::<WebProof>),
+    (DataSource::Eas, fetch_via::<Eas>),
+    (DataSource::Github, fetch_via::<Github>),
+    (DataSource::Poap, fetch_via::<Poap>),
+    (DataSource::SpaceId, fetch_via::<SpaceId>),
+];
+
+/// `Fetcher::can_fetch` of the upstream `source` stands for.
+/// `true` for those not in `UPSTREAMS`.
+pub(crate) fn can_fetch(source: DataSource, target: &Target) -> bool {
+    match source {
+        DataSource::Aggregation => Aggregation::can_fetch(target),
+        DataSource::SybilList => SybilList::can_fetch(target),
+        DataSource::Keybase => Keybase::can_fetch(target),
+        DataSource::NextID => ProofClient::can_fetch(target),
+        DataSource::Rss3 => Rss3::can_fetch(target),
+        DataSource::Knn3 => Knn3::can_fetch(target),
+        DataSource::TheGraph => TheGraph::can_fetch(target),
+        DataSource::ENSReverse => ENSReverseLookup::can_fetch(target),
+        DataSource::Dotbit => DotBit::can_fetch(target),
+        DataSource::WebProof => WebProof::can_fetch(target),
+        DataSource::Eas => Eas::can_fetch(target),
+        DataSource::Github => Github::can_fetch(target),
+        DataSource::Poap => Poap::can_fetch(target),
+        DataSource::SpaceId => SpaceId::can_fetch(target),
+        _ => true,
+    }
+}
+
+fn fetch_via<F: Fetcher>(target: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    F::fetch(target)
+}
+
+/// Which upstreams to ask during a crawl. All of them by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SourceSelection {
+    /// Ask these upstreams only. `None` means all.
+    pub only: Option<Vec<DataSource>>,
+    /// Never ask these upstreams.
+    pub exclude: Vec<DataSource>,
+}
+
+impl SourceSelection {
+    pub fn includes(&self, source: &DataSource) -> bool {
+        self.only
+            .as_ref()
+            .map_or(true, |only| only.contains(source))
+            && !self.exclude.contains(source)
+    }
+}
+
+/// What `fetch_one` got from upstreams.
+#[derive(Debug, Default)]
+pub struct FetchOutcome {
+    /// Targets found by all upstreams succeeded, for next iter.
+    pub found: TargetProcessedList,
+    /// Upstreams which failed, with why. Those succeeded are not affected by them.
+    pub failures: Vec<(DataSource, Error)>,
+}
+
+impl FetchOutcome {
+    /// Log each one of `failures` at `warn`.
+    pub fn warn_failures(&self, target: &Target) {
+        for (source, err) in self.failures.iter() {
+            warn!(
+                "Error happened when fetching {} from {}: {}",
+                target, source, err
+            );
+        }
+    }
 }
 
 /// Find one (platform, identity) pair in all upstreams.
-/// Returns amount of identities just fetched for next iter.
-pub async fn fetch_one(target: &Target) -> Result<Vec<Target>, Error> {
-    let mut up_next: TargetProcessedList = join_all(vec![
-        Aggregation::fetch(target),
-        SybilList::fetch(target),
-        Keybase::fetch(target),
-        ProofClient::fetch(target),
-        Rss3::fetch(target),
-        Knn3::fetch(target),
-        TheGraph::fetch(target),
-        ENSReverseLookup::fetch(target),
-        DotBit::fetch(target),
-    ])
-    .await
-    .into_iter()
-    .flat_map(|res| {
-        match res {
-            Ok(up_next_list) => up_next_list,
-            Err(err) => {
-                warn!("Error happened when fetching {}: {}", target, err);
-                vec![] // Don't break the procedure
-            }
+/// Fails only if `target` is invalid. Failures of upstreams are in `FetchOutcome::failures`.
+pub async fn fetch_one(target: &Target) -> Result<FetchOutcome, Error> {
+    fetch_one_with_sources(target, &SourceSelection::default()).await
+}
+
+/// Same as `fetch_one`, but only asks selected upstreams.
+pub async fn fetch_one_with_sources(
+    target: &Target,
+    sources: &SourceSelection,
+) -> Result<FetchOutcome, Error> {
+    target.validate()?;
+    let _in_flight = InFlight::enter(target);
+    fetch_one_from(target, UPSTREAMS, sources).await
+}
+
+async fn fetch_one_from(
+    target: &Target,
+    upstreams: &[(DataSource, FetchFn)],
+    sources: &SourceSelection,
+) -> Result<FetchOutcome, Error> {
+    let results = join_all(
+        upstreams
+            .iter()
+            .filter(|(source, _)| sources.includes(source))
+            .map(|(source, fetch)| async move {
+                // Skipped as if it has nothing, while it's known to be down.
+                let admission = match breaker::allow(*source) {
+                    Some(admission) => admission,
+                    None => {
+                        debug!("Breaker | {} is open, skip fetching {}", source, target);
+                        return (*source, Ok(vec![]));
+                    }
+                };
+                let _permit = concurrency::acquire(*source).await;
+                cost::record_request(*source);
+                let result = fetch(target).await;
+                admission.record(&result);
+                // Any successful response counts, even if nothing is found.
+                // Not when it isn't asked at all, e.g. the target isn't supported there.
+                if result.is_ok() && can_fetch(*source, target) {
+                    record_produced(*source);
+                }
+                (*source, result)
+            }),
+    )
+    .await;
+
+    let mut outcome = FetchOutcome::default();
+    for (source, result) in results {
+        match result {
+            Ok(up_next_list) => outcome.found.extend(up_next_list),
+            // Don't break the procedure
+            Err(err) => outcome.failures.push((source, err)),
         }
-    })
-    .collect();
-    up_next.dedup();
+    }
+    // Empty identities given by upstreams are junk, never crawl them.
+    outcome.found.retain(|next| next.validate().is_ok());
+    outcome.found.dedup();
+
+    Ok(outcome)
+}
+
+/// Fetch reverse records (ENS / .bit) of a wallet lacking a display name, in the background.
+/// Each wallet is tried at most once per `Identity::outdated_in()`.
+/// Returns whether a fetch is spawned.
+pub fn trigger_reverse_lookup(address: &str) -> bool {
+    let cooldown = Identity::outdated_in().to_std().unwrap_or_default();
+    {
+        let mut triggered = REVERSE_TRIGGERED.lock().unwrap();
+        triggered.retain(|_, at| at.elapsed() < cooldown);
+        if triggered.contains_key(address) {
+            return false;
+        }
+        triggered.insert(address.to_string(), Instant::now());
+    }
+
+    let target = Target::Identity(Platform::Ethereum, address.to_string());
+    info!(
+        "{} has no display name. Fetching reverse records...",
+        target
+    );
+    tokio::spawn(async move {
+        let sources = SourceSelection {
+            only: Some(vec![DataSource::ENSReverse, DataSource::Dotbit]),
+            exclude: vec![],
+        };
+        match fetch_one_with_sources(&target, &sources).await {
+            Ok(outcome) => outcome.warn_failures(&target),
+            Err(err) => warn!("Failed to fetch reverse records of {}: {}", target, err),
+        }
+    });
+    true
+}
 
-    Ok(up_next)
+/// Is a reverse lookup of this wallet triggered within the cooldown? See `trigger_reverse_lookup`.
+pub(crate) fn reverse_lookup_triggered(address: &str) -> bool {
+    REVERSE_TRIGGERED.lock().unwrap().contains_key(address)
 }
 
 /// Prefetch all prefetchable upstreams, e.g. SybilList.
@@ -0,0 +1,243 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    cache::cache,
+    config::C,
+    error::Error,
+    util::{make_client_with_timeout, parse_body},
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
+
+const IPFS_SCHEME: &str = "ipfs://";
+const ARWEAVE_SCHEME: &str = "ar://";
+/// Placeholder of token ID in ERC-1155 URIs. See `substitute_id`.
+const ID_PLACEHOLDER: &str = "{id}";
+
+/// NFT metadata returned by `tokenURI`.
+/// See also: https://eips.ethereum.org/EIPS/eip-721 (Metadata JSON Schema)
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct NFTMetadata {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// May also be a content-addressed URI. Use `to_gateway_url` before fetching.
+    #[serde(default)]
+    pub image: Option<String>,
+    #[serde(default)]
+    pub animation_url: Option<String>,
+    #[serde(default)]
+    pub external_url: Option<String>,
+}
+
+/// Translate a `tokenURI` into a list of fetchable HTTP URLs, in the order they should be tried.
+/// - `ipfs://CID/path` (and legacy `ipfs://ipfs/CID/path`) => each IPFS gateway.
+/// - `ar://TX_ID` => each Arweave gateway.
+/// - Anything else is returned as-is.
+pub fn gateway_urls(
+    token_uri: &str,
+    ipfs_gateways: &[String],
+    arweave_gateways: &[String],
+) -> Vec<String> {
+    if let Some(path) = token_uri.strip_prefix(IPFS_SCHEME) {
+        let path = path.trim_start_matches("ipfs/");
+        ipfs_gateways
+            .iter()
+            .map(|gateway| join_gateway(gateway, path))
+            .collect()
+    } else if let Some(path) = token_uri.strip_prefix(ARWEAVE_SCHEME) {
+        arweave_gateways
+            .iter()
+            .map(|gateway| join_gateway(gateway, path))
+            .collect()
+    } else {
+        vec![token_uri.to_string()]
+    }
+}
+
+/// Translate a content-addressed URI into an URL using the first configured gateway.
+/// Useful for those URIs given to clients directly (e.g. `image`).
+pub fn to_gateway_url(uri: &str) -> String {
+    gateway_urls(
+        uri,
+        &C.upstream.nft_metadata.ipfs_gateways,
+        &C.upstream.nft_metadata.arweave_gateways,
+    )
+    .into_iter()
+    .next()
+    .unwrap_or_else(|| uri.to_string())
+}
+
+/// Fetch NFT metadata of given `tokenURI` through configured gateways.
+pub async fn fetch_metadata(token_uri: &str) -> Result<NFTMetadata, Error> {
+    fetch_metadata_via(
+        token_uri,
+        &C.upstream.nft_metadata.ipfs_gateways,
+        &C.upstream.nft_metadata.arweave_gateways,
+    )
+    .await
+}
+
+/// Token ID (decimal, or `0x` hex) as ERC-1155 clients substitute `{id}` with:
+/// lowercase hex, zero-padded to 64 characters. `None` if it's not a number.
+fn id_hex(id: &str) -> Option<String> {
+    let hex = match id.strip_prefix("0x") {
+        Some(hex) if !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            hex.trim_start_matches('0').to_lowercase()
+        }
+        Some(_) => return None,
+        None if !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()) => {
+            // Long division by 16, since IDs are uint256.
+            let mut digits: Vec<u32> = id.bytes().map(|d| (d - b'0') as u32).collect();
+            let mut hex = vec![];
+            while digits.iter().any(|d| *d != 0) {
+                let mut remainder = 0;
+                for d in digits.iter_mut() {
+                    let current = remainder * 10 + *d;
+                    *d = current / 16;
+                    remainder = current % 16;
+                }
+                hex.push(std::char::from_digit(remainder, 16).unwrap());
+            }
+            hex.iter().rev().collect()
+        }
+        None => return None,
+    };
+    if hex.len() > 64 {
+        return None;
+    }
+    Some(format!("{:0>64}", hex))
+}
+
+/// `token_uri` of token `id`. ERC-1155 contracts may give one URI for all their tokens,
+/// in which `{id}` is replaced by clients. See https://eips.ethereum.org/EIPS/eip-1155#metadata
+pub fn substitute_id(token_uri: &str, id: &str) -> String {
+    match id_hex(id) {
+        Some(hex) if token_uri.contains(ID_PLACEHOLDER) => token_uri.replace(ID_PLACEHOLDER, &hex),
+        _ => token_uri.to_string(),
+    }
+}
+
+/// Cache key of metadata of token `id` of `contract`. The URI it's fetched from is in it,
+/// so tokens sharing a (substituted) base URI never get the metadata of each other.
+fn cache_key(contract: &str, id: &str, uri: &str) -> String {
+    format!("nft_metadata:{}:{}:{}", contract.to_lowercase(), id, uri)
+}
+
+/// Metadata of token `id` of `contract`, whose `tokenURI` (or ERC-1155 `uri`) is `token_uri`.
+/// Reuses the one fetched within `C.upstream.nft_metadata.cache_ttl`.
+pub async fn fetch_token_metadata(
+    contract: &str,
+    id: &str,
+    token_uri: &str,
+) -> Result<NFTMetadata, Error> {
+    fetch_token_metadata_via(
+        contract,
+        id,
+        token_uri,
+        &C.upstream.nft_metadata.ipfs_gateways,
+        &C.upstream.nft_metadata.arweave_gateways,
+        Duration::from_secs(C.upstream.nft_metadata.cache_ttl),
+    )
+    .await
+}
+
+/// Same as `fetch_token_metadata`, but through given gateways and cached for `ttl`.
+/// Fetched right now if the cache is unavailable.
+pub async fn fetch_token_metadata_via(
+    contract: &str,
+    id: &str,
+    token_uri: &str,
+    ipfs_gateways: &[String],
+    arweave_gateways: &[String],
+    ttl: Duration,
+) -> Result<NFTMetadata, Error> {
+    let uri = substitute_id(token_uri, id);
+    let key = cache_key(contract, id, &uri);
+    let cache = match cache().await {
+        Ok(cache) => Some(cache).filter(|_| !ttl.is_zero()),
+        Err(err) => {
+            warn!("NFT metadata | Cache unavailable: {}", err);
+            None
+        }
+    };
+    if let Some(cache) = cache {
+        match cache.get(&key).await {
+            Ok(Some(cached)) => match serde_json::from_str(&cached) {
+                Ok(cached) => return Ok(cached),
+                Err(err) => warn!("NFT metadata | Cached one is unreadable: {}", err),
+            },
+            Ok(None) => {}
+            Err(err) => warn!("NFT metadata | Failed to read cache: {}", err),
+        }
+    }
+
+    let metadata = fetch_metadata_via(&uri, ipfs_gateways, arweave_gateways).await?;
+    if let Some(cache) = cache {
+        if let Err(err) = cache
+            .set(&key, &serde_json::to_string(&metadata)?, ttl)
+            .await
+        {
+            warn!("NFT metadata | Failed to write cache: {}", err);
+        }
+    }
+    Ok(metadata)
+}
+
+/// Fetch NFT metadata of given `tokenURI`, falling back to next gateway on failure.
+pub async fn fetch_metadata_via(
+    token_uri: &str,
+    ipfs_gateways: &[String],
+    arweave_gateways: &[String],
+) -> Result<NFTMetadata, Error> {
+    let urls = gateway_urls(token_uri, ipfs_gateways, arweave_gateways);
+    let mut last_error = Error::NoResult;
+
+    for url in urls.into_iter() {
+        match fetch_from_url(&url).await {
+            Ok(mut metadata) => {
+                metadata.image = metadata.image.map(|image| {
+                    gateway_urls(&image, ipfs_gateways, arweave_gateways)
+                        .into_iter()
+                        .next()
+                        .unwrap_or(image)
+                });
+                return Ok(metadata);
+            }
+            Err(err) => {
+                warn!("NFT metadata | Failed to fetch {}: {}", url, err);
+                last_error = err;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+async fn fetch_from_url(url: &str) -> Result<NFTMetadata, Error> {
+    let client =
+        make_client_with_timeout(Duration::from_secs(C.upstream.nft_metadata.timeout_seconds));
+    let uri: http::Uri = url.parse().map_err(|err: http::uri::InvalidUri| {
+        Error::ParamError(format!("URI Format error: {}", err))
+    })?;
+
+    let mut resp = client.get(uri).await?;
+    if !resp.status().is_success() {
+        return Err(Error::General(
+            format!("NFT metadata fetch error: {}", resp.status()),
+            resp.status(),
+        ));
+    }
+    parse_body(&mut resp).await
+}
+
+fn join_gateway(gateway: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        gateway.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
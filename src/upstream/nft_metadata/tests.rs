@@ -0,0 +1,155 @@
+use hyper::StatusCode;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    error::Error,
+    upstream::mock::{self, Fixture},
+    upstream::nft_metadata::{
+        fetch_metadata_via, fetch_token_metadata_via, gateway_urls, substitute_id,
+    },
+};
+
+const METADATA: &str =
+    r#"{"name": "Test NFT #1", "description": "A test", "image": "ipfs://QmImage/1.png"}"#;
+
+/// Start a local gateway which serves `body` on `path`, and `404` on anything else.
+/// Returns its base URL.
+fn mock_gateway(path: &str, status: StatusCode, body: &str) -> String {
+    format!(
+        "{}/",
+        mock::serve(vec![Fixture::with_status(path, status, body)])
+    )
+}
+
+#[test]
+fn test_gateway_urls() {
+    let ipfs = vec![
+        "https://ipfs.io/ipfs/".to_string(),
+        "https://gw.test/ipfs".to_string(),
+    ];
+    let arweave = vec!["https://arweave.net/".to_string()];
+
+    assert_eq!(
+        gateway_urls("ipfs://QmHash/1.json", &ipfs, &arweave),
+        vec![
+            "https://ipfs.io/ipfs/QmHash/1.json".to_string(),
+            "https://gw.test/ipfs/QmHash/1.json".to_string()
+        ]
+    );
+    assert_eq!(
+        gateway_urls("ipfs://ipfs/QmHash", &ipfs, &arweave),
+        vec![
+            "https://ipfs.io/ipfs/QmHash".to_string(),
+            "https://gw.test/ipfs/QmHash".to_string()
+        ]
+    );
+    assert_eq!(
+        gateway_urls("ar://TxID", &ipfs, &arweave),
+        vec!["https://arweave.net/TxID".to_string()]
+    );
+    assert_eq!(
+        gateway_urls("https://example.com/1.json", &ipfs, &arweave),
+        vec!["https://example.com/1.json".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn test_ipfs_with_fallback_gateway() -> Result<(), Error> {
+    let broken = mock_gateway("/QmHash/1.json", StatusCode::BAD_GATEWAY, "");
+    let working = mock_gateway("/QmHash/1.json", StatusCode::OK, METADATA);
+
+    let metadata =
+        fetch_metadata_via("ipfs://QmHash/1.json", &[broken.clone(), working], &[]).await?;
+    assert_eq!(metadata.name, Some("Test NFT #1".into()));
+    // `image` should be translated using the first gateway.
+    assert_eq!(metadata.image, Some(format!("{}QmImage/1.png", broken)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_arweave() -> Result<(), Error> {
+    let gateway = mock_gateway("/TxID", StatusCode::OK, METADATA);
+
+    let metadata = fetch_metadata_via("ar://TxID", &[], &[gateway]).await?;
+    assert_eq!(metadata.description, Some("A test".into()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_all_gateways_failed() {
+    let broken = mock_gateway("/TxID", StatusCode::INTERNAL_SERVER_ERROR, "");
+
+    assert!(fetch_metadata_via("ar://TxID", &[], &[broken])
+        .await
+        .is_err());
+}
+
+#[test]
+fn test_substitute_id() {
+    let uri = "ipfs://QmBase/{id}.json";
+    assert_eq!(
+        substitute_id(uri, "1"),
+        format!("ipfs://QmBase/{:0>64}.json", "1")
+    );
+    assert_eq!(
+        substitute_id(uri, "314592"),
+        format!("ipfs://QmBase/{:0>64}.json", "4cce0")
+    );
+    assert_eq!(
+        substitute_id(uri, "0x4CCE0"),
+        format!("ipfs://QmBase/{:0>64}.json", "4cce0")
+    );
+    // uint256 max.
+    assert_eq!(
+        substitute_id(
+            uri,
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        ),
+        format!("ipfs://QmBase/{}.json", "f".repeat(64))
+    );
+    // Nothing to substitute or not a number: as-is.
+    assert_eq!(
+        substitute_id("ipfs://QmHash/1.json", "1"),
+        "ipfs://QmHash/1.json"
+    );
+    assert_eq!(substitute_id(uri, "abc.eth"), uri);
+}
+
+#[tokio::test]
+async fn test_erc1155_ids_sharing_base_uri() -> Result<(), Error> {
+    let contract = format!("0x{}", Uuid::new_v4().simple());
+    let base_uri = "ipfs://QmBase/{id}.json";
+    let gateway = format!(
+        "{}/",
+        mock::serve(vec![
+            Fixture::ok(
+                &format!("/QmBase/{:0>64}.json", "1"),
+                r#"{"name": "Token #1"}"#
+            ),
+            Fixture::ok(
+                &format!("/QmBase/{:0>64}.json", "2"),
+                r#"{"name": "Token #2"}"#
+            ),
+        ])
+    );
+    let ttl = Duration::from_secs(60);
+
+    for (id, name) in [("1", "Token #1"), ("2", "Token #2")] {
+        let metadata =
+            fetch_token_metadata_via(&contract, id, base_uri, &[gateway.clone()], &[], ttl).await?;
+        assert_eq!(metadata.name.as_deref(), Some(name));
+    }
+
+    // Served from cache, still each of its own.
+    let broken = mock_gateway("/", StatusCode::BAD_GATEWAY, "");
+    for (id, name) in [("1", "Token #1"), ("2", "Token #2")] {
+        let metadata =
+            fetch_token_metadata_via(&contract, id, base_uri, &[broken.clone()], &[], ttl).await?;
+        assert_eq!(metadata.name.as_deref(), Some(name));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        bulk::{connect_in_bulk_or_each, Batch},
+        edge::Hold,
+        new_db_connection,
+        vertex::{
+            contract::{Chain, ContractCategory},
+            Contract, Identity,
+        },
+    },
+    upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList},
+    util::{make_client_with_timeout, naive_now, parse_body},
+};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use hyper::{Body, Method, Request};
+use serde::Deserialize;
+use std::{str::FromStr, time::Duration};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// POAP contract. The same address on every chain it's deployed.
+pub const POAP_CONTRACT: &str = "0x22c1f6050e56d2876009903609a2cc3fef83b415";
+
+/// See https://documentation.poap.tech/reference/getactionsscan-5
+#[derive(Deserialize, Debug)]
+pub struct PoapToken {
+    pub event: PoapEvent,
+    #[serde(rename = "tokenId")]
+    pub token_id: String,
+    pub owner: String,
+    /// `xdai` or `mainnet`.
+    pub chain: String,
+    /// When it's minted, as `YYYY-MM-DD HH:MM:SS`.
+    pub created: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PoapEvent {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+pub struct Poap {}
+
+#[async_trait]
+impl Fetcher for Poap {
+    async fn fetch(target: &Target) -> Result<TargetProcessedList, Error> {
+        if !Self::can_fetch(target) {
+            return Ok(vec![]);
+        }
+
+        match target {
+            Target::Identity(_, identity) => {
+                fetch_poaps(
+                    &C.upstream.poap.url,
+                    C.upstream.poap.api_key.as_deref(),
+                    identity,
+                )
+                .await
+            }
+            Target::NFT(_, _, _, _) => Ok(vec![]),
+        }
+    }
+
+    /// Disabled unless `C.upstream.poap.url` is given.
+    fn can_fetch(target: &Target) -> bool {
+        !C.upstream.poap.url.is_empty() && target.in_platform_supported(vec![Platform::Ethereum])
+    }
+}
+
+fn chain_of(chain: &str) -> Chain {
+    match chain {
+        "mainnet" => Chain::Ethereum,
+        _ => Chain::from_str(chain).unwrap_or(Chain::Xdai).canonical(),
+    }
+}
+
+/// `url`: POAP API endpoint. See `C.upstream.poap.url`.
+/// Each POAP held by `address` is recorded as a hold of POAP contract, whose ID is the event ID.
+async fn fetch_poaps(
+    url: &str,
+    api_key: Option<&str>,
+    address: &str,
+) -> Result<TargetProcessedList, Error> {
+    let address = address.to_lowercase();
+    let mut builder = Request::builder()
+        .method(Method::GET)
+        .uri(format!("{}/actions/scan/{}", url, address))
+        .header("Accept", "application/json");
+    if let Some(api_key) = api_key {
+        builder = builder.header("X-API-Key", api_key);
+    }
+    let req = builder
+        .body(Body::empty())
+        .map_err(|err| Error::ParamError(format!("POAP request error: {}", err)))?;
+    let client = make_client_with_timeout(Duration::from_secs(C.upstream.poap.timeout_seconds));
+    let mut resp = client.request(req).await?;
+    if !resp.status().is_success() {
+        warn!(
+            "POAP | Failed to fetch POAPs of {}: {}",
+            address,
+            resp.status()
+        );
+        return Err(Error::General(
+            format!("POAP fetch error: {}", resp.status()),
+            resp.status(),
+        ));
+    }
+    let tokens: Vec<PoapToken> = parse_body(&mut resp).await?;
+    if tokens.is_empty() {
+        info!("POAP | No POAP held by {}", address);
+        return Ok(vec![]);
+    }
+
+    let owner = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::Ethereum,
+        identity: address.clone(),
+        created_at: None,
+        // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
+        display_name: None,
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: None,
+        updated_at: naive_now(),
+    };
+
+    let mut batch = Batch::new();
+    let mut next_targets: TargetProcessedList = vec![];
+    for token in tokens.into_iter() {
+        if token.owner.to_lowercase() != address {
+            continue;
+        }
+        let chain = chain_of(&token.chain);
+        let contract = Contract {
+            uuid: Uuid::new_v4(),
+            category: ContractCategory::POAP,
+            address: POAP_CONTRACT.into(),
+            chain,
+            symbol: Some("POAP".into()),
+            updated_at: naive_now(),
+        };
+        let hold = Hold {
+            uuid: Uuid::new_v4(),
+            source: DataSource::Poap,
+            transaction: None,
+            id: token.event.id.to_string(),
+            created_at: token.created.as_deref().and_then(|created| {
+                NaiveDateTime::parse_from_str(created, "%Y-%m-%d %H:%M:%S").ok()
+            }),
+            expires_at: None,
+            updated_at: naive_now(),
+            fetcher: C.upstream.poap.fetcher.unwrap_or_default(),
+        };
+        let target = Target::NFT(
+            chain,
+            ContractCategory::POAP,
+            POAP_CONTRACT.into(),
+            hold.id.clone(),
+        );
+        if !next_targets.contains(&target) {
+            next_targets.push(target);
+        }
+        batch = batch.hold(owner.clone(), contract, hold);
+    }
+
+    let db = new_db_connection().await?;
+    let failed = connect_in_bulk_or_each(&db, &batch).await?;
+    if failed > 0 {
+        warn!("POAP | {} POAPs of {} not recorded", failed, address);
+    }
+
+    Ok(next_targets)
+}
@@ -0,0 +1,80 @@
+use crate::{
+    error::Error,
+    graph::{
+        arangopool::new_connection_pool,
+        edge::Hold,
+        new_db_connection,
+        vertex::{
+            contract::{Chain, ContractCategory},
+            Contract, Identity,
+        },
+    },
+    upstream::{
+        mock::{self, Fixture},
+        poap::{fetch_poaps, POAP_CONTRACT},
+        DataSource, Platform, Target,
+    },
+};
+
+const OWNER: &str = "0x0000000000000000000000000000000000f1c7e4";
+
+#[tokio::test]
+async fn test_poap_replay() -> Result<(), Error> {
+    let url = mock::serve(vec![Fixture::ok(
+        &format!("/actions/scan/{}", OWNER),
+        include_str!("../fixtures/poap/scan.json"),
+    )]);
+
+    let result = fetch_poaps(&url, Some("fixture-key"), OWNER).await?;
+    // Recorded by event ID instead of token ID.
+    assert_eq!(
+        result,
+        vec![
+            Target::NFT(
+                Chain::Xdai,
+                ContractCategory::POAP,
+                POAP_CONTRACT.into(),
+                "60001".into()
+            ),
+            Target::NFT(
+                Chain::Ethereum,
+                ContractCategory::POAP,
+                POAP_CONTRACT.into(),
+                "60002".into()
+            ),
+        ]
+    );
+
+    let db = new_db_connection().await?;
+    let pool = new_connection_pool().await?;
+    let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, OWNER)
+        .await?
+        .expect("Record not found");
+    let contract = Contract::find_by_chain_address(&db, &Chain::Xdai, POAP_CONTRACT)
+        .await?
+        .expect("Record not found");
+    assert_eq!(contract.category, ContractCategory::POAP);
+    let hold = Hold::find_by_from_to_id(&db, &owner, &contract, "60001")
+        .await?
+        .expect("Record not found");
+    assert_eq!(hold.source, DataSource::Poap);
+    assert_eq!(
+        hold.created_at.map(|at| at.to_string()),
+        Some("2022-03-01 18:30:00".into())
+    );
+
+    let poaps = owner
+        .nfts(&pool, Some(vec![ContractCategory::POAP]), None, true)
+        .await?;
+    assert_eq!(poaps.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_poap_upstream_error() {
+    // Nothing recorded: mock server returns 404.
+    let url = mock::serve(vec![]);
+
+    assert!(fetch_poaps(&url, None, OWNER).await.is_err());
+}
@@ -6,17 +6,20 @@ use crate::config::C;
 use crate::error::Error;
 use crate::graph::{edge::Proof, new_db_connection, vertex::Identity};
 use crate::graph::{Edge, Vertex};
-use crate::upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList};
-use crate::util::{make_client, naive_now, parse_body, timestamp_to_naive};
+use crate::upstream::{
+    Algorithm, Curve, DataSource, Fetcher, Platform, Target, TargetProcessedList,
+};
+use crate::util::{
+    make_client_with_timeout, naive_now, parse_body, timestamp_to_naive, verify_signature,
+    HttpClient,
+};
 
 use async_trait::async_trait;
 use serde::Deserialize;
-use std::str::FromStr;
-use tracing::{debug, error, info};
+use std::{collections::HashSet, str::FromStr, time::Duration};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use super::DataFetcher;
-
 /// https://github.com/nextdotid/proof-server/blob/master/docs/api.apib
 #[derive(Deserialize, Debug)]
 pub struct ProofQueryResponse {
@@ -48,6 +51,33 @@ pub struct ProofQueryResponsePagination {
     pub next: u32,
 }
 
+/// https://github.com/nextdotid/proof-server/blob/master/docs/api.apib
+/// Every proof ever created / deleted by a persona, signed by it.
+#[derive(Deserialize, Debug)]
+pub struct ProofChainResponse {
+    pub pagination: ProofQueryResponsePagination,
+    pub proof_chain: Vec<ProofChainItem>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProofChainItem {
+    pub action: String,
+    pub platform: String,
+    pub identity: String,
+    /// Base64-encoded signature of `signature_payload` by the persona.
+    pub signature: String,
+    /// What's signed, a JSON of this proof.
+    pub signature_payload: String,
+}
+
+/// Fields of `ProofChainItem.signature_payload` which are trusted once the signature is verified.
+#[derive(Deserialize, Debug)]
+struct SignedPayload {
+    action: String,
+    platform: String,
+    identity: String,
+}
+
 #[derive(Deserialize, Debug)]
 pub struct ErrorResponse {
     pub message: String,
@@ -64,9 +94,14 @@ impl Fetcher for ProofClient {
 
         match target {
             Target::Identity(platform, identity) => {
-                fetch_connections_by_platform_identity(platform, identity).await
+                fetch_connections_by_platform_identity(
+                    &C.upstream.proof_service.url,
+                    platform,
+                    identity,
+                )
+                .await
             }
-            Target::NFT(_, _, _, _) => todo!(),
+            Target::NFT(_, _, _, _) => Ok(vec![]),
         }
     }
 
@@ -81,15 +116,91 @@ impl Fetcher for ProofClient {
     }
 }
 
+/// `(platform, identity)` of every proof of `persona` which is still alive in its proof chain,
+/// as signed by `persona`. Lowercased.
+/// Those with an invalid signature are skipped.
+async fn fetch_verified_proofs(
+    client: &HttpClient,
+    url: &str,
+    persona: &str,
+) -> Result<HashSet<(String, String)>, Error> {
+    let mut verified: HashSet<(String, String)> = HashSet::new();
+    let mut page = 1;
+    loop {
+        let uri: http::Uri = format!(
+            "{}/v1/proof/chain?public_key={}&page={}",
+            url, persona, page
+        )
+        .parse()
+        .map_err(|_err| Error::ParamError("Uri format Error".to_string()))?;
+        let mut resp = client.get(uri).await?;
+        if !resp.status().is_success() {
+            let body: ErrorResponse = parse_body(&mut resp).await?;
+            return Err(Error::General(
+                format!("Proof Chain Get Error: {}", body.message),
+                resp.status(),
+            ));
+        }
+        let body: ProofChainResponse = parse_body(&mut resp).await?;
+
+        // From the oldest to the newest, so a deletion overrides the creation before it.
+        for item in body.proof_chain.into_iter() {
+            if let Err(err) = verify_signature(
+                Algorithm::EllipticCurve,
+                Curve::Secp256K1,
+                &item.signature_payload,
+                &item.signature,
+                persona,
+            ) {
+                warn!(
+                    "NextID {} | Skipped proof of {}/{}: {}",
+                    persona, item.platform, item.identity, err
+                );
+                continue;
+            }
+            // Only what's signed counts, not the fields beside it.
+            let signed: SignedPayload = match serde_json::from_str(&item.signature_payload) {
+                Ok(signed) => signed,
+                Err(err) => {
+                    warn!(
+                        "NextID {} | Skipped proof of {}/{}: unknown payload ({})",
+                        persona, item.platform, item.identity, err
+                    );
+                    continue;
+                }
+            };
+            let key = (
+                signed.platform.to_lowercase(),
+                signed.identity.to_lowercase(),
+            );
+            match signed.action.as_str() {
+                "create" => verified.insert(key),
+                "delete" => verified.remove(&key),
+                _ => false,
+            };
+        }
+
+        if body.pagination.next == 0 || body.pagination.next <= page {
+            break;
+        }
+        page = body.pagination.next;
+    }
+    Ok(verified)
+}
+
+/// `url`: NextID proof service endpoint. See `C.upstream.proof_service.url`.
 async fn fetch_connections_by_platform_identity(
+    url: &str,
     platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
-    let client = make_client();
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.proof_service.timeout_seconds,
+    ));
 
     let uri: http::Uri = format!(
         "{}/v1/proof?platform={}&identity={}",
-        C.upstream.proof_service.url, platform, identity
+        url, platform, identity
     )
     .parse()
     .map_err(|_err| Error::ParamError("Uri format Error".to_string()))?;
@@ -117,10 +228,19 @@ async fn fetch_connections_by_platform_identity(
         }
     };
     let next_id_identity = proofs.avatar;
+    // Not trusted unless signed by the persona itself.
+    let verified = fetch_verified_proofs(&client, url, &next_id_identity).await?;
     let db = new_db_connection().await?;
     let mut next_targets: TargetProcessedList = vec![];
 
     for p in proofs.proofs.into_iter() {
+        if !verified.contains(&(p.platform.to_lowercase(), p.identity.to_lowercase())) {
+            warn!(
+                "NextID {} | Skipped proof of {}/{}: not signed in its proof chain",
+                next_id_identity, p.platform, p.identity
+            );
+            continue;
+        }
         let from: Identity = Identity {
             uuid: Some(Uuid::new_v4()),
             platform: Platform::NextID,
@@ -130,6 +250,7 @@ async fn fetch_connections_by_platform_identity(
                 0,
             )),
             display_name: Some(next_id_identity.clone()),
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: None,
@@ -162,6 +283,7 @@ async fn fetch_connections_by_platform_identity(
             } else {
                 Some(p.identity.clone())
             },
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: None,
@@ -175,12 +297,13 @@ async fn fetch_connections_by_platform_identity(
             uuid: Uuid::new_v4(),
             source: DataSource::NextID,
             record_id: None,
+            proof_url: None,
             created_at: Some(timestamp_to_naive(
                 p.created_at.to_string().parse().unwrap(),
                 0,
             )),
             updated_at: naive_now(),
-            fetcher: DataFetcher::RelationService,
+            fetcher: C.upstream.proof_service.fetcher.unwrap_or_default(),
         };
         pf.connect(&db, &from_record, &to_record).await?;
     }
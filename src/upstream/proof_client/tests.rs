@@ -1,8 +1,16 @@
-use crate::upstream::Target;
+use crate::upstream::{
+    mock::{self, Fixture},
+    proof_client::fetch_connections_by_platform_identity,
+    Target,
+};
 use crate::{error::Error, upstream::proof_client::ProofClient, upstream::Fetcher};
 use crate::{
     graph::new_db_connection, graph::vertex::Identity, upstream::Platform, util::naive_now,
+    util::personal_message_hash,
 };
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use serde_json::{json, Value};
+use uuid::Uuid;
 
 #[tokio::test]
 async fn test_smoke() -> Result<(), Error> {
@@ -21,3 +29,88 @@ async fn test_smoke() -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Sign a proof chain item of `platform/identity` by `secret_key`.
+fn chain_item(secret_key: &SecretKey, persona: &str, platform: &str, identity: &str) -> Value {
+    let payload = json!({
+        "action": "create",
+        "platform": platform,
+        "identity": identity,
+        "persona": persona,
+        "created_at": "1660000000",
+    })
+    .to_string();
+    let message = Message::from_slice(&personal_message_hash(&payload)).unwrap();
+    let signature = Secp256k1::new().sign_ecdsa(&message, secret_key);
+    json!({
+        "action": "create",
+        "platform": platform,
+        "identity": identity,
+        "signature": base64::encode(signature.serialize_compact()),
+        "signature_payload": payload,
+    })
+}
+
+fn proof_of(platform: &str, identity: &str) -> Value {
+    json!({
+        "platform": platform,
+        "identity": identity,
+        "created_at": "1660000000",
+        "last_checked_at": "1660000000",
+        "is_valid": true,
+        "invalid_reason": "",
+    })
+}
+
+#[tokio::test]
+async fn test_replay_verified_proofs() -> Result<(), Error> {
+    let secret_key = SecretKey::from_slice(&rand::random::<[u8; 32]>()).unwrap();
+    let persona = format!(
+        "0x{}",
+        hex::encode(PublicKey::from_secret_key(&Secp256k1::new(), &secret_key).serialize())
+    );
+    let good = format!("good_{}", Uuid::new_v4().simple());
+    let tampered = format!("tampered_{}", Uuid::new_v4().simple());
+
+    // Signed for another identity, then claimed for `tampered`.
+    let mut forged = chain_item(&secret_key, &persona, "github", "someone_else");
+    forged["identity"] = json!(tampered);
+    forged["signature_payload"] = json!(forged["signature_payload"]
+        .as_str()
+        .unwrap()
+        .replace("someone_else", &tampered));
+    let pagination = json!({ "total": 2, "per": 20, "current": 1, "next": 0 });
+    let proofs = json!({
+        "pagination": pagination,
+        "ids": [{
+            "avatar": persona,
+            "proofs": [proof_of("twitter", &good), proof_of("github", &tampered)],
+        }],
+    });
+    let chain = json!({
+        "pagination": pagination,
+        "proof_chain": [chain_item(&secret_key, &persona, "twitter", &good), forged],
+    });
+    let url = mock::serve(vec![
+        Fixture::ok("/v1/proof", proofs.to_string()),
+        Fixture::ok("/v1/proof/chain", chain.to_string()),
+    ]);
+
+    let targets = fetch_connections_by_platform_identity(&url, &Platform::NextID, &persona).await?;
+    assert_eq!(
+        targets,
+        vec![Target::Identity(Platform::Twitter, good.clone())]
+    );
+
+    let db = new_db_connection().await?;
+    Identity::find_by_platform_identity(&db, &Platform::Twitter, &good)
+        .await?
+        .expect("Record not found");
+    assert!(
+        Identity::find_by_platform_identity(&db, &Platform::Github, &tampered)
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! Background refetches of outdated records found by queries. Instead of a crawl spawned per query,
+//! targets are queued (once each, however many queries find them outdated) and crawled
+//! by a fixed number of workers. See `C.upstream.refetch`.
+
+use crate::{
+    config::C,
+    error::Error,
+    upstream::{cost::CrawlCost, fetch_all_with_sources, SourceSelection, Target},
+};
+use futures::future::{BoxFuture, FutureExt};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{mpsc, OnceCell};
+use tracing::{debug, warn};
+
+lazy_static! {
+    /// Queue of `enqueue_refetch`, set up by `spawn_refetch_workers`.
+    static ref QUEUE: OnceCell<RefetchQueue> = OnceCell::new();
+}
+
+/// How a worker crawls a target.
+pub type RefetchFn = Arc<
+    dyn Fn(Target, SourceSelection) -> BoxFuture<'static, Result<CrawlCost, Error>> + Send + Sync,
+>;
+
+/// A target with the upstreams to ask, i.e. a queued crawl.
+type Job = (Target, SourceSelection);
+
+/// A bounded queue of targets to crawl, with the workers crawling them.
+pub struct RefetchQueue {
+    sender: mpsc::Sender<Job>,
+    /// Crawls queued or running. The same one is never queued twice,
+    /// while a target can be queued again with other upstreams to ask.
+    pending: Arc<Mutex<HashSet<Job>>>,
+}
+
+/// Removes a job from `pending` once it's done, however it ends (e.g. by panicking).
+struct PendingGuard {
+    pending: Arc<Mutex<HashSet<Job>>>,
+    job: Job,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.pending.lock().unwrap().remove(&self.job);
+    }
+}
+
+impl RefetchQueue {
+    /// Starts `workers` workers crawling by `fetch_all_with_sources`.
+    pub fn start(workers: usize, queue_size: usize) -> Self {
+        Self::start_with(
+            workers,
+            queue_size,
+            Arc::new(|target: Target, sources: SourceSelection| {
+                fetch_all_with_sources(target, sources).boxed()
+            }),
+        )
+    }
+
+    /// Same as `start`, but crawling by `fetch`.
+    pub fn start_with(workers: usize, queue_size: usize, fetch: RefetchFn) -> Self {
+        let (sender, receiver) = mpsc::channel(queue_size.max(1));
+        let receiver = Arc::new(tokio::sync::Mutex::new(receiver));
+        let pending: Arc<Mutex<HashSet<Job>>> = Arc::new(Mutex::new(HashSet::new()));
+        for _ in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            let pending = pending.clone();
+            let fetch = fetch.clone();
+            tokio::spawn(async move {
+                loop {
+                    let job = receiver.lock().await.recv().await;
+                    let (target, sources) = match job {
+                        Some(job) => job,
+                        // Queue is dropped.
+                        None => return,
+                    };
+                    let _done = PendingGuard {
+                        pending: pending.clone(),
+                        job: (target.clone(), sources.clone()),
+                    };
+                    // In a task of its own, so a panicking crawl doesn't take the worker down.
+                    match tokio::spawn(fetch(target.clone(), sources)).await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(err)) => warn!("Refetch | Failed to refetch {}: {}", target, err),
+                        Err(err) => warn!("Refetch | Refetching {} panicked: {}", target, err),
+                    }
+                }
+            });
+        }
+        Self { sender, pending }
+    }
+
+    /// Queue a crawl of `target`, asking `sources` only.
+    /// Returns `false` if it's queued (or being crawled) already, or the queue is full.
+    pub fn enqueue(&self, target: Target, sources: SourceSelection) -> bool {
+        let job = (target, sources);
+        if !self.pending.lock().unwrap().insert(job.clone()) {
+            debug!("Refetch | {} is queued already", job.0);
+            return false;
+        }
+        match self.sender.try_send(job.clone()) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("Refetch | Dropped {}: {}", job.0, err);
+                self.pending.lock().unwrap().remove(&job);
+                false
+            }
+        }
+    }
+
+    /// Is `target` queued or being crawled, asking whichever upstreams?
+    pub fn is_pending(&self, target: &Target) -> bool {
+        self.pending
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(pending, _)| pending == target)
+    }
+}
+
+/// Start the workers `enqueue_refetch` queues targets for. See `C.upstream.refetch`.
+pub fn spawn_refetch_workers() {
+    let queue = RefetchQueue::start(C.upstream.refetch.workers, C.upstream.refetch.queue_size);
+    if QUEUE.set(queue).is_err() {
+        warn!("Refetch | Workers are started already");
+    }
+}
+
+/// Refetch `target` in the background. See `enqueue_refetch_with_sources`.
+pub fn enqueue_refetch(target: Target) -> bool {
+    enqueue_refetch_with_sources(target, SourceSelection::default())
+}
+
+/// Refetch `target` in the background, asking `sources` only.
+/// Crawled right away (in a task of its own) if workers aren't started, e.g. in tests.
+pub fn enqueue_refetch_with_sources(target: Target, sources: SourceSelection) -> bool {
+    match QUEUE.get() {
+        Some(queue) => queue.enqueue(target, sources),
+        None => {
+            tokio::spawn(async move {
+                if let Err(err) = fetch_all_with_sources(target.clone(), sources).await {
+                    warn!("Refetch | Failed to refetch {}: {}", target, err);
+                }
+            });
+            true
+        }
+    }
+}
@@ -7,22 +7,20 @@ use crate::{
         create_identity_to_contract_record, create_identity_to_identity_record,
         edge::{hold::Hold, proof::Proof},
         new_db_connection,
-        vertex::{contract::Chain, contract::ContractCategory, Contract, Identity},
+        vertex::{contract::Chain, contract::ContractCategory, Contract, Identity, NameSource},
     },
     upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList},
-    util::{make_client, naive_now, parse_body},
+    util::{make_client_with_timeout, naive_now, parse_body, retry_request},
 };
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDateTime};
 use futures::future::join_all;
 use http::uri::InvalidUri;
 use serde::Deserialize;
-use std::str::FromStr;
-use tracing::{error, info};
+use std::{str::FromStr, time::Duration};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use super::DataFetcher;
-
 #[derive(Deserialize, Debug)]
 pub struct Rss3Response {
     pub total: i64,
@@ -73,6 +71,28 @@ pub struct MetaData {
     pub standard: Option<String>,
     pub contract_address: Option<String>,
     pub handle: Option<String>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Attribute {
+    pub trait_type: String,
+    pub value: serde_json::Value,
+}
+
+impl MetaData {
+    /// POAP event this token is minted for.
+    /// Tokens of the same event share it, so it identifies a POAP better than token ID.
+    fn poap_event_id(&self) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|attr| attr.trait_type == "eventId")
+            .map(|attr| match &attr.value {
+                serde_json::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            })
+    }
 }
 
 pub struct Rss3 {}
@@ -85,29 +105,151 @@ impl Fetcher for Rss3 {
         }
 
         match target {
-            Target::Identity(platform, identity) => fetch_nfts_by_account(platform, identity).await,
-            Target::NFT(_, _, _, _) => todo!(),
+            Target::Identity(platform, identity) => {
+                fetch_nfts_by_account(&C.upstream.rss3_service.url, platform, identity).await
+            }
+            Target::NFT(chain, category, address, nft_id) => {
+                fetch_accounts_by_nft(
+                    &C.upstream.rss3_service.url,
+                    chain,
+                    category,
+                    address,
+                    nft_id,
+                )
+                .await
+            }
         }
     }
 
     fn can_fetch(target: &Target) -> bool {
         target.in_platform_supported(vec![Platform::Ethereum])
+            || target.in_nft_supported(
+                vec![
+                    ContractCategory::ERC721,
+                    ContractCategory::ERC1155,
+                    ContractCategory::POAP,
+                ],
+                vec![Chain::Ethereum, Chain::Polygon, Chain::Xdai],
+            )
+    }
+}
+
+/// Is `p` a note of the NFT `nft_id` (event ID for POAPs) in contract `address`?
+fn is_note_of(p: &ResultItem, address: &str, nft_id: &str) -> bool {
+    p.tag == "collectible"
+        && p.actions.iter().any(|a| {
+            let metadata = &a.metadata;
+            let id = match a.tag_type.as_str() {
+                "poap" => metadata.poap_event_id(),
+                _ => metadata.id.clone(),
+            };
+            a.tag == "collectible"
+                && metadata
+                    .contract_address
+                    .as_ref()
+                    .map_or(false, |addr| addr.eq_ignore_ascii_case(address))
+                && id.as_deref() == Some(nft_id)
+        })
+}
+
+/// Find holders of an NFT, recording their `Hold`s as `fetch_nfts_by_account` does.
+/// `url`: RSS3 notes API endpoint. See `C.upstream.rss3_service.url`.
+async fn fetch_accounts_by_nft(
+    url: &str,
+    chain: &Chain,
+    category: &ContractCategory,
+    address: &str,
+    nft_id: &str,
+) -> Result<TargetProcessedList, Error> {
+    let client =
+        make_client_with_timeout(Duration::from_secs(C.upstream.rss3_service.timeout_seconds));
+    // POAPs are recorded (and looked up) by their event ID.
+    let id_param = match category {
+        ContractCategory::POAP => "event_id",
+        _ => "token_id",
+    };
+    let uri: http::Uri = format!(
+        "{}?tag=collectible&network={}&contract_address={}&{}={}&include_poap=true&refresh=true",
+        url, chain, address, id_param, nft_id
+    )
+    .parse()
+    .map_err(|_err: InvalidUri| Error::ParamError(format!("Uri format Error {}", _err)))?;
+
+    let retry = &C.upstream.rss3_service.retry;
+    let mut resp = retry_request(
+        || client.get(uri.clone()),
+        retry.max_attempts,
+        retry.base_delay(),
+    )
+    .await?;
+
+    if !resp.status().is_success() {
+        error!("Rss3 fetch error, statusCode: {}", resp.status());
+        return Err(Error::General(
+            "Rss3 Result Get Error".to_string(),
+            resp.status(),
+        ));
     }
+
+    let body: Rss3Response = parse_body(&mut resp).await?;
+    if body.total == 0 {
+        info!("Rss3 Response is empty");
+        return Err(Error::General(
+            "Rss3 Response is empty".to_string(),
+            resp.status(),
+        ));
+    }
+
+    let mut holders: Vec<String> = vec![];
+    let futures: Vec<_> = body
+        .result
+        .into_iter()
+        .filter(|p| is_note_of(p, address, nft_id))
+        .map(|p| {
+            let owner = p.owner.to_lowercase();
+            if !holders.contains(&owner) {
+                holders.push(owner);
+            }
+            save_item(p)
+        })
+        .collect();
+    for result in join_all(futures).await {
+        if let Err(err) = result {
+            error!(
+                "Rss3 Fetch data | Failed to save holder of {}: {}",
+                address, err
+            );
+        }
+    }
+
+    Ok(holders
+        .into_iter()
+        .map(|owner| Target::Identity(Platform::Ethereum, owner))
+        .collect())
 }
 
+/// `url`: RSS3 notes API endpoint. See `C.upstream.rss3_service.url`.
 async fn fetch_nfts_by_account(
+    url: &str,
     _platform: &Platform,
     identity: &str,
 ) -> Result<TargetProcessedList, Error> {
-    let client = make_client();
+    let client =
+        make_client_with_timeout(Duration::from_secs(C.upstream.rss3_service.timeout_seconds));
     let uri: http::Uri = format!(
         "{}/{}?tag=collectible&tag=social&include_poap=true&refresh=true",
-        C.upstream.rss3_service.url, identity
+        url, identity
     )
     .parse()
     .map_err(|_err: InvalidUri| Error::ParamError(format!("Uri format Error {}", _err)))?;
 
-    let mut resp = client.get(uri).await?;
+    let retry = &C.upstream.rss3_service.retry;
+    let mut resp = retry_request(
+        || client.get(uri.clone()),
+        retry.max_attempts,
+        retry.base_delay(),
+    )
+    .await?;
 
     if !resp.status().is_success() {
         error!("Rss3 fetch error, statusCode: {}", resp.status());
@@ -136,14 +278,25 @@ async fn fetch_nfts_by_account(
     let next_targets: TargetProcessedList = join_all(futures)
         .await
         .into_iter()
-        .flat_map(|result| result.unwrap_or_default())
+        .flat_map(|result| {
+            result.unwrap_or_else(|err| {
+                warn!("Rss3 Fetch data | Skipped a note of {}: {}", identity, err);
+                vec![]
+            })
+        })
         .collect();
 
     Ok(next_targets)
 }
 
+/// Fails (instead of panicking) on malformed notes, so only that one is skipped.
 async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
-    let creataed_at = DateTime::parse_from_rfc3339(&p.timestamp).unwrap();
+    let creataed_at = DateTime::parse_from_rfc3339(&p.timestamp).map_err(|err| {
+        Error::ParamError(format!(
+            "Rss3 note {}: invalid timestamp {}: {}",
+            p.hash, p.timestamp, err
+        ))
+    })?;
     let created_at_naive = NaiveDateTime::from_timestamp(creataed_at.timestamp(), 0);
     let db = new_db_connection().await?;
 
@@ -154,6 +307,7 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
         created_at: Some(created_at_naive),
         // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
         display_name: None,
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -182,13 +336,18 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
         if social_platform != Platform::Lens {
             return Ok(vec![]);
         }
-        let handle = real_action.metadata.handle.as_ref().unwrap().to_string();
+        let handle = real_action
+            .metadata
+            .handle
+            .clone()
+            .ok_or_else(|| Error::ParamMissing(format!("Rss3 note {}: handle", p.hash)))?;
         let to_identity: Identity = Identity {
             uuid: Some(Uuid::new_v4()),
             platform: social_platform,
             identity: handle.clone(),
             created_at: Some(created_at_naive),
             display_name: Some(handle.clone()),
+            display_name_source: None,
             added_at: naive_now(),
             avatar_url: None,
             profile_url: Some("https://lenster.xyz/u/".to_owned() + &handle),
@@ -199,11 +358,18 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
             uuid: Uuid::new_v4(),
             source: DataSource::Rss3,
             record_id: Some(p.hash),
+            proof_url: None,
             created_at: Some(created_at_naive),
             updated_at: naive_now(),
-            fetcher: DataFetcher::RelationService,
+            fetcher: C.upstream.rss3_service.fetcher.unwrap_or_default(),
         };
 
+        // Its Lens handle names the wallet, unless a name with higher priority is there.
+        let from = Identity {
+            display_name: Some(handle.clone()),
+            display_name_source: Some(NameSource::Lens),
+            ..from
+        };
         create_identity_to_identity_record(&db, &from, &to_identity, &pf).await?;
 
         return Ok(vec![Target::Identity(Platform::Lens, handle.clone())]);
@@ -215,15 +381,21 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
         return Ok(vec![]);
     }
 
-    let mut nft_category =
-        ContractCategory::from_str(real_action.metadata.standard.as_ref().unwrap().as_str())
-            .unwrap_or_default();
+    // Unknown (or absent) standards are recorded as `ContractCategory::Unknown`.
+    let mut nft_category = real_action
+        .metadata
+        .standard
+        .as_deref()
+        .and_then(|standard| ContractCategory::from_str(standard).ok())
+        .unwrap_or_default();
 
     if real_action.tag_type == "poap".to_string() {
         nft_category = ContractCategory::POAP;
     }
 
-    let chain = Chain::from_str(p.network.as_str()).unwrap_or_default();
+    let chain = Chain::from_str(p.network.as_str())
+        .unwrap_or_default()
+        .canonical();
     if chain == Chain::Unknown {
         error!("Rss3 Fetch data | Unknown Chain, original data: {:?}", p);
         return Ok(vec![]);
@@ -232,9 +404,15 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
         .metadata
         .contract_address
         .as_ref()
-        .unwrap()
+        .ok_or_else(|| Error::ParamMissing(format!("Rss3 note {}: contract_address", p.hash)))?
         .to_lowercase();
-    let nft_id = real_action.metadata.id.as_ref().unwrap();
+    // POAPs are recorded by their event ID.
+    let nft_id = match nft_category {
+        ContractCategory::POAP => real_action.metadata.poap_event_id(),
+        _ => None,
+    }
+    .or(real_action.metadata.id.clone())
+    .ok_or_else(|| Error::ParamMissing(format!("Rss3 note {}: NFT ID", p.hash)))?;
 
     let to: Contract = Contract {
         uuid: Uuid::new_v4(),
@@ -251,8 +429,9 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
         transaction: Some(p.hash),
         id: nft_id.clone(),
         created_at: Some(created_at_naive),
+        expires_at: None,
         updated_at: naive_now(),
-        fetcher: DataFetcher::RelationService,
+        fetcher: C.upstream.rss3_service.fetcher.unwrap_or_default(),
     };
     create_identity_to_contract_record(&db, &from, &to, &hold).await?;
 
@@ -260,6 +439,6 @@ async fn save_item(p: ResultItem) -> Result<TargetProcessedList, Error> {
         chain,
         nft_category,
         contract_addr.clone(),
-        nft_id.clone(),
+        nft_id,
     )])
 }
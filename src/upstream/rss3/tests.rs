@@ -1,35 +1,202 @@
 use crate::{
     error::Error,
     graph::edge::Hold,
-    graph::new_db_connection,
-    graph::vertex::{contract::Chain, Contract, Identity},
-    upstream::rss3::Rss3,
+    graph::vertex::{contract::Chain, contract::ContractCategory, Contract, Identity},
+    graph::{arangopool::new_connection_pool, new_db_connection},
+    upstream::mock::{self, Fixture},
+    upstream::rss3::{fetch_accounts_by_nft, fetch_nfts_by_account},
     upstream::Platform,
-    upstream::{Fetcher, Target},
+    upstream::Target,
 };
 
+const NOTES_PATH: &str = "/v1/notes";
+const OWNER: &str = "0x0000000000000000000000000000000000f1c7e1";
+const CONTRACT: &str = "0x000000000000000000000000000000000f1c7e7e";
+const POAP_OWNER: &str = "0x0000000000000000000000000000000000f1c7e2";
+const POAP_CONTRACT: &str = "0x22c1f6050e56d2876009903609a2cc3fef83b415";
+const HELD_CONTRACT: &str = "0x000000000000000000000000000000000f1c7e7f";
+const MALFORMED_OWNER: &str = "0x0000000000000000000000000000000000f1c7e3";
+const MALFORMED_CONTRACT: &str = "0x000000000000000000000000000000000f1c7e80";
+
 #[tokio::test]
-async fn test_smoke_nft_rss3() -> Result<(), Error> {
-    let target = Target::Identity(
-        Platform::Ethereum,
-        "0x934b510d4c9103e6a87aef13b816fb080286d649".to_lowercase(),
+async fn test_rss3_replay() -> Result<(), Error> {
+    let base = mock::serve(vec![Fixture::ok(
+        &format!("{}/{}", NOTES_PATH, OWNER),
+        include_str!("../fixtures/rss3/notes.json"),
+    )]);
+    let url = format!("{}{}", base, NOTES_PATH);
+
+    let result = fetch_nfts_by_account(&url, &Platform::Ethereum, OWNER).await?;
+    // Note owned by others is skipped.
+    assert_eq!(
+        result,
+        vec![
+            Target::NFT(
+                Chain::Ethereum,
+                ContractCategory::ERC721,
+                CONTRACT.into(),
+                "87".into()
+            ),
+            Target::Identity(Platform::Lens, "fixture.lens".into()),
+        ]
     );
-    let _ = Rss3::fetch(&target).await?;
+
     let db = new_db_connection().await?;
+    let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, OWNER)
+        .await?
+        .expect("Record not found");
+    let contract = Contract::find_by_chain_address(&db, &Chain::Ethereum, CONTRACT)
+        .await?
+        .expect("Record not found");
+    Hold::find_by_from_to_id(&db, &owner, &contract, "87")
+        .await?
+        .expect("Record not found");
+    assert!(Hold::find_by_from_to_id(&db, &owner, &contract, "88")
+        .await?
+        .is_none());
+    Identity::find_by_platform_identity(&db, &Platform::Lens, "fixture.lens")
+        .await?
+        .expect("Record not found");
+
+    Ok(())
+}
 
-    let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, &target.identity()?)
+#[tokio::test]
+async fn test_rss3_replay_upstream_error() {
+    // Nothing recorded: mock server returns 404.
+    let url = format!("{}{}", mock::serve(vec![]), NOTES_PATH);
+
+    assert!(fetch_nfts_by_account(&url, &Platform::Ethereum, OWNER)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_rss3_replay_malformed_notes() -> Result<(), Error> {
+    let base = mock::serve(vec![Fixture::ok(
+        &format!("{}/{}", NOTES_PATH, MALFORMED_OWNER),
+        include_str!("../fixtures/rss3/malformed.json"),
+    )]);
+    let url = format!("{}{}", base, NOTES_PATH);
+
+    let result = fetch_nfts_by_account(&url, &Platform::Ethereum, MALFORMED_OWNER).await?;
+    // The one with a bad timestamp is skipped. Unknown (or absent) standards don't stop the others.
+    assert_eq!(
+        result,
+        vec![
+            Target::NFT(
+                Chain::Ethereum,
+                ContractCategory::Unknown,
+                MALFORMED_CONTRACT.into(),
+                "2".into()
+            ),
+            Target::NFT(
+                Chain::Ethereum,
+                ContractCategory::Unknown,
+                MALFORMED_CONTRACT.into(),
+                "3".into()
+            ),
+        ]
+    );
+
+    let db = new_db_connection().await?;
+    let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, MALFORMED_OWNER)
         .await?
         .expect("Record not found");
-    let contract = Contract::find_by_chain_address(
-        &db,
+    let contract = Contract::find_by_chain_address(&db, &Chain::Ethereum, MALFORMED_CONTRACT)
+        .await?
+        .expect("Record not found");
+    assert!(Hold::find_by_from_to_id(&db, &owner, &contract, "1")
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rss3_replay_poap() -> Result<(), Error> {
+    let base = mock::serve(vec![Fixture::ok(
+        &format!("{}/{}", NOTES_PATH, POAP_OWNER),
+        include_str!("../fixtures/rss3/poap.json"),
+    )]);
+    let url = format!("{}{}", base, NOTES_PATH);
+
+    let result = fetch_nfts_by_account(&url, &Platform::Ethereum, POAP_OWNER).await?;
+    // Recorded by event ID instead of token ID.
+    assert_eq!(
+        result,
+        vec![Target::NFT(
+            Chain::Xdai,
+            ContractCategory::POAP,
+            POAP_CONTRACT.into(),
+            "54321".into()
+        )]
+    );
+
+    let db = new_db_connection().await?;
+    let pool = new_connection_pool().await?;
+    let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, POAP_OWNER)
+        .await?
+        .expect("Record not found");
+
+    let poaps = owner
+        .nfts(&pool, Some(vec![ContractCategory::POAP]), None, true)
+        .await?;
+    assert_eq!(poaps.len(), 1);
+    assert_eq!(poaps[0].id, "54321");
+    assert!(owner
+        .nfts(&pool, Some(vec![ContractCategory::ERC721]), None, true)
+        .await?
+        .is_empty());
+    assert_eq!(owner.nfts(&pool, None, None, true).await?.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rss3_replay_nft_holders() -> Result<(), Error> {
+    let base = mock::serve(vec![Fixture::ok(
+        NOTES_PATH,
+        include_str!("../fixtures/rss3/nft_holders.json"),
+    )]);
+    let url = format!("{}{}", base, NOTES_PATH);
+
+    let result = fetch_accounts_by_nft(
+        &url,
         &Chain::Ethereum,
-        "0x596cfe8d6709a86d51ff0c18ebf0e66561b08ae3",
+        &ContractCategory::ERC1155,
+        HELD_CONTRACT,
+        "42",
     )
-    .await?
-    .unwrap();
+    .await?;
+    // Notes of other tokens in the same contract are skipped.
+    assert_eq!(
+        result,
+        vec![
+            Target::Identity(
+                Platform::Ethereum,
+                "0x0000000000000000000000000000000000f1c7e3".into()
+            ),
+            Target::Identity(
+                Platform::Ethereum,
+                "0x0000000000000000000000000000000000f1c7e4".into()
+            ),
+        ]
+    );
 
-    let _ = Hold::find_by_from_to_id(&db, &owner, &contract, "87")
-        .await
+    let db = new_db_connection().await?;
+    let holder = Identity::find_by_platform_identity(
+        &db,
+        &Platform::Ethereum,
+        "0x0000000000000000000000000000000000f1c7e4",
+    )
+    .await?
+    .expect("Record not found");
+    let contract = Contract::find_by_chain_address(&db, &Chain::Ethereum, HELD_CONTRACT)
+        .await?
+        .expect("Record not found");
+    Hold::find_by_from_to_id(&db, &holder, &contract, "42")
+        .await?
         .expect("Record not found");
 
     Ok(())
@@ -0,0 +1,191 @@
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    config::C,
+    error::Error,
+    graph::{
+        edge::{resolve::DomainNameSystem, Edge, Resolve},
+        new_db_connection,
+        vertex::{Identity, Vertex},
+    },
+    upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList},
+    util::{make_client_with_timeout, naive_now, parse_body},
+};
+use async_trait::async_trait;
+use hyper::{Body, Method, Request};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// What SPACE ID answers for unregistered (or unresolved) names.
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// See https://docs.space.id/developer-guide/web3-name-sdk/api
+#[derive(Deserialize, Debug)]
+pub struct AddressResponse {
+    pub code: i32,
+    pub msg: String,
+    pub address: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NameResponse {
+    pub code: i32,
+    pub msg: String,
+    pub name: Option<String>,
+}
+
+pub struct SpaceId {}
+
+#[async_trait]
+impl Fetcher for SpaceId {
+    async fn fetch(target: &Target) -> Result<TargetProcessedList, Error> {
+        if !Self::can_fetch(target) {
+            return Ok(vec![]);
+        }
+
+        match target {
+            Target::Identity(Platform::SpaceId, name) => {
+                fetch_address_of_name(&C.upstream.space_id.url, name).await
+            }
+            Target::Identity(_, address) => {
+                fetch_name_of_address(&C.upstream.space_id.url, address).await
+            }
+            Target::NFT(_, _, _, _) => Ok(vec![]),
+        }
+    }
+
+    /// Disabled unless `C.upstream.space_id.url` is given.
+    fn can_fetch(target: &Target) -> bool {
+        !C.upstream.space_id.url.is_empty()
+            && target.in_platform_supported(vec![Platform::SpaceId, Platform::Ethereum])
+    }
+}
+
+/// GET `{url}/v1/{method}?tld=bnb&{key}={value}`, answered with `code: 0` if it succeeds.
+async fn get<T: for<'de> Deserialize<'de>>(
+    url: &str,
+    method: &str,
+    key: &str,
+    value: &str,
+) -> Result<T, Error> {
+    let query: String = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("tld", "bnb")
+        .append_pair(key, value)
+        .finish();
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(format!("{}/v1/{}?{}", url, method, query))
+        .body(Body::empty())
+        .map_err(|err| Error::ParamError(format!("SPACE ID request error: {}", err)))?;
+    let client = make_client_with_timeout(Duration::from_secs(C.upstream.space_id.timeout_seconds));
+    let mut resp = client.request(req).await?;
+    if !resp.status().is_success() {
+        warn!(
+            "SPACE ID | Failed to {} of {}: {}",
+            method,
+            value,
+            resp.status()
+        );
+        return Err(Error::General(
+            format!("SPACE ID fetch error: {}", resp.status()),
+            resp.status(),
+        ));
+    }
+    parse_body(&mut resp).await
+}
+
+/// `url`: SPACE ID API endpoint. See `C.upstream.space_id.url`.
+/// Records the wallet `name` (e.g. `abc.bnb`) resolves to.
+async fn fetch_address_of_name(url: &str, name: &str) -> Result<TargetProcessedList, Error> {
+    let name = DomainNameSystem::SpaceID.normalize(name)?;
+    if !name.ends_with(".bnb") {
+        return Ok(vec![]);
+    }
+    let resp: AddressResponse = get(url, "getAddress", "domain", &name).await?;
+    if resp.code != 0 {
+        warn!("SPACE ID | Failed to resolve {}: {}", name, resp.msg);
+        return Err(Error::NoResult);
+    }
+    let address = match resp.address.map(|address| address.to_lowercase()) {
+        Some(address) if !address.is_empty() && address != ZERO_ADDRESS => address,
+        _ => {
+            info!("SPACE ID | {} resolves to nothing", name);
+            return Ok(vec![]);
+        }
+    };
+
+    save_resolve(&name, &address).await?;
+    Ok(vec![Target::Identity(Platform::Ethereum, address)])
+}
+
+/// `url`: SPACE ID API endpoint. See `C.upstream.space_id.url`.
+/// Records the primary name of `address` (its reverse record), which resolves to it.
+async fn fetch_name_of_address(url: &str, address: &str) -> Result<TargetProcessedList, Error> {
+    let address = address.to_lowercase();
+    let resp: NameResponse = get(url, "getName", "address", &address).await?;
+    if resp.code != 0 {
+        warn!(
+            "SPACE ID | Failed to reverse lookup {}: {}",
+            address, resp.msg
+        );
+        return Err(Error::NoResult);
+    }
+    let name = match resp.name.filter(|name| !name.is_empty()) {
+        Some(name) => DomainNameSystem::SpaceID.normalize(&name)?,
+        None => {
+            info!("SPACE ID | No .bnb name of {}", address);
+            return Ok(vec![]);
+        }
+    };
+
+    save_resolve(&name, &address).await?;
+    Ok(vec![Target::Identity(Platform::SpaceId, name)])
+}
+
+/// `name` --Resolve--> `address`.
+async fn save_resolve(name: &str, address: &str) -> Result<(), Error> {
+    let db = new_db_connection().await?;
+    let wallet = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::Ethereum,
+        identity: address.to_string(),
+        created_at: None,
+        // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
+        display_name: None,
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: None,
+        updated_at: naive_now(),
+    };
+    let space_id = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::SpaceId,
+        identity: name.to_string(),
+        created_at: None,
+        display_name: Some(name.to_string()),
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: None,
+        updated_at: naive_now(),
+    };
+    let resolve = Resolve {
+        uuid: Uuid::new_v4(),
+        source: DataSource::SpaceId,
+        system: DomainNameSystem::SpaceID,
+        name: name.to_string(),
+        fetcher: C.upstream.space_id.fetcher.unwrap_or_default(),
+        updated_at: naive_now(),
+    };
+
+    let wallet_record = wallet.create_or_update(&db).await?;
+    let space_id_record = space_id.create_or_update(&db).await?;
+    resolve
+        .connect(&db, &space_id_record, &wallet_record)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use crate::{
+    error::Error,
+    graph::{
+        edge::{resolve::DomainNameSystem, Resolve},
+        new_db_connection,
+        vertex::Identity,
+    },
+    upstream::{
+        mock::{self, Fixture},
+        spaceid::{fetch_address_of_name, fetch_name_of_address},
+        DataSource, Platform, Target,
+    },
+};
+
+#[tokio::test]
+async fn test_address_of_name() -> Result<(), Error> {
+    // Replay of `getAddress`.
+    let url = mock::serve(vec![Fixture::ok(
+        "/v1/getAddress",
+        include_str!("../fixtures/spaceid/address.json"),
+    )]);
+    let address = "0x0000000000000000000000000000000000005bad";
+
+    let result = fetch_address_of_name(&url, "Fixture-Forward.bnb").await?;
+    assert_eq!(
+        result,
+        vec![Target::Identity(Platform::Ethereum, address.into())]
+    );
+
+    let db = new_db_connection().await?;
+    let wallet = Identity::find_by_platform_identity(&db, &Platform::Ethereum, address)
+        .await?
+        .expect("Record not found");
+    let name = Identity::find_by_platform_identity(&db, &Platform::SpaceId, "fixture-forward.bnb")
+        .await?
+        .expect("Record not found");
+    let resolve =
+        Resolve::find_by_name_system(&db, "fixture-forward.bnb", &DomainNameSystem::SpaceID)
+            .await?
+            .expect("Resolve not found");
+    assert_eq!(resolve.source, DataSource::SpaceId);
+    assert_eq!(resolve.id_from(), name.id());
+    assert_eq!(resolve.id_to(), wallet.id());
+
+    // Not a `.bnb` name: nothing asked.
+    assert!(fetch_address_of_name(&mock::serve(vec![]), "vitalik.eth")
+        .await?
+        .is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_name_of_address() -> Result<(), Error> {
+    // Replay of `getName`.
+    let url = mock::serve(vec![Fixture::ok(
+        "/v1/getName",
+        include_str!("../fixtures/spaceid/name.json"),
+    )]);
+    let address = "0x0000000000000000000000000000000000005BAE";
+
+    let result = fetch_name_of_address(&url, address).await?;
+    assert_eq!(
+        result,
+        vec![Target::Identity(
+            Platform::SpaceId,
+            "fixture-primary.bnb".into()
+        )]
+    );
+
+    let db = new_db_connection().await?;
+    let wallet =
+        Identity::find_by_platform_identity(&db, &Platform::Ethereum, &address.to_lowercase())
+            .await?
+            .expect("Record not found");
+    let resolve =
+        Resolve::find_by_name_system(&db, "fixture-primary.bnb", &DomainNameSystem::SpaceID)
+            .await?
+            .expect("Resolve not found");
+    assert_eq!(resolve.id_to(), wallet.id());
+
+    // No primary name.
+    let url = mock::serve(vec![Fixture::ok(
+        "/v1/getName",
+        include_str!("../fixtures/spaceid/no_name.json"),
+    )]);
+    assert!(fetch_name_of_address(&url, address).await?.is_empty());
+
+    Ok(())
+}
@@ -5,15 +5,20 @@ mod tests;
 use crate::config::C;
 use crate::error::Error;
 use crate::graph::edge::ProofRecord;
-use crate::graph::{edge::Proof, new_db_connection, vertex::Identity};
+use crate::graph::{
+    edge::Proof,
+    new_db_connection,
+    vertex::{Identity, NameSource},
+};
 use crate::graph::{Edge, Vertex};
 use crate::upstream::{DataSource, Fetcher, Platform, TargetProcessedList};
-use crate::util::{make_client, naive_now, parse_body, timestamp_to_naive};
+use crate::util::{make_client_with_timeout, naive_now, parse_body, timestamp_to_naive};
 use aragog::query::{Comparison, Filter, QueryResult};
 use aragog::{DatabaseConnection, DatabaseRecord, EdgeRecord, Record};
 use async_trait::async_trait;
 use http::StatusCode;
 use serde::Deserialize;
+use std::time::Duration;
 use tracing::{debug, info};
 
 use serde_json::{Map, Value};
@@ -22,7 +27,7 @@ use uuid::Uuid;
 
 use futures::future::join_all;
 
-use super::{DataFetcher, Target};
+use super::Target;
 
 #[derive(Deserialize, Debug)]
 pub struct SybilListItem {
@@ -63,8 +68,10 @@ async fn save_item(
         platform: Platform::Ethereum,
         identity: eth_wallet_address.to_lowercase(),
         created_at: None,
-        // Don't use ETH's wallet as display_name, use ENS reversed lookup instead.
-        display_name: None,
+        // Named after the Twitter handle it is verified with,
+        // unless a name with higher priority (e.g. ENS reverse) is there.
+        display_name: Some(item.twitter.handle.clone()),
+        display_name_source: Some(NameSource::Social),
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -78,6 +85,7 @@ async fn save_item(
         identity: item.twitter.handle.to_lowercase(),
         created_at: None,
         display_name: Some(item.twitter.handle.clone()),
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -90,12 +98,13 @@ async fn save_item(
         uuid: Uuid::new_v4(),
         source: DataSource::SybilList,
         record_id: Some(item.twitter.tweet_id),
+        proof_url: None,
         created_at: Some(timestamp_to_naive(
             item.twitter.timestamp / 1000,
             create_ms_time,
         )), // millisecond
         updated_at: naive_now(),
-        fetcher: DataFetcher::RelationService,
+        fetcher: C.upstream.sybil_service.fetcher.unwrap_or_default(),
     };
 
     proof.connect(db, &from_record, &to_record).await.ok()?;
@@ -104,7 +113,9 @@ async fn save_item(
 
 /// Trigger a refetch from github.
 pub async fn prefetch() -> Result<(), Error> {
-    let client = make_client();
+    let client = make_client_with_timeout(Duration::from_secs(
+        C.upstream.sybil_service.timeout_seconds,
+    ));
     let uri: http::Uri = (C.upstream.sybil_service.url).parse().unwrap();
 
     let mut resp = client.get(uri).await?;
@@ -158,7 +169,7 @@ impl Fetcher for SybilList {
                         .and(Comparison::field("source").equals_str(DataSource::SybilList));
                 let result: QueryResult<EdgeRecord<Proof>> = EdgeRecord::<Proof>::query()
                     .filter(filter)
-                    .call(&db)
+                    .call(&*db)
                     .await?;
 
                 if result.len() == 0 {
@@ -166,7 +177,8 @@ impl Fetcher for SybilList {
                     Ok(vec![])
                 } else {
                     let found: ProofRecord = result.first().unwrap().clone().into();
-                    let next_target: DatabaseRecord<Identity> = found.record.to_record(&db).await?;
+                    let next_target: DatabaseRecord<Identity> =
+                        found.record.to_record(&*db).await?;
 
                     Ok(vec![Target::Identity(
                         next_target.platform,
@@ -179,7 +191,7 @@ impl Fetcher for SybilList {
                     .and(Comparison::field("source").equals_str(DataSource::SybilList));
                 let result: QueryResult<EdgeRecord<Proof>> = EdgeRecord::<Proof>::query()
                     .filter(filter)
-                    .call(&db)
+                    .call(&*db)
                     .await?;
 
                 if result.len() == 0 {
@@ -188,7 +200,7 @@ impl Fetcher for SybilList {
                 } else {
                     let found: ProofRecord = result.first().unwrap().clone().into();
                     let next_target: DatabaseRecord<Identity> =
-                        found.record.from_record(&db).await?;
+                        found.record.from_record(&*db).await?;
 
                     Ok(vec![Target::Identity(
                         next_target.platform,
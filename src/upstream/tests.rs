@@ -1,10 +1,39 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::config::C;
 use crate::error::Error;
-use crate::upstream::{fetch_all, fetch_one, Platform, Target};
+use crate::graph::{
+    new_db_connection,
+    vertex::contract::{Chain, ContractCategory},
+    vertex::{Identity, Vertex},
+    with_db_budget,
+};
+use crate::upstream::{
+    breaker::{self, BreakerState},
+    concurrency,
+    cost::{with_cost, CrawlCost},
+    crawl, evict_stale_fetching, fetch_all, fetch_one, fetch_one_from,
+    liveness::{liveness_of, record_produced_at},
+    refetch::RefetchQueue,
+    CrawlStrategy, DataSource, FetchFn, Platform, SourceSelection, Target, TargetProcessedList,
+    FETCHING, UPSTREAMS,
+};
+use crate::util::naive_now;
+use fake::{Fake, Faker};
+use futures::future::{BoxFuture, FutureExt};
+use uuid::Uuid;
 
 #[tokio::test]
 async fn test_fetch_one_result() -> Result<(), Error> {
     let result = fetch_one(&Target::Identity(Platform::Twitter, "yeiwb".into())).await?;
-    assert_ne!(result.len(), 0);
+    assert_ne!(result.found.len(), 0);
 
     Ok(())
 }
@@ -15,3 +44,620 @@ async fn test_fetch_all() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_evict_stale_fetching() -> Result<(), Error> {
+    let max_age = Duration::from_secs(C.upstream.crawl.max_age);
+    let stuck = Target::Identity(Platform::Unknown, "stuck_crawl".into());
+    let alive = Target::Identity(Platform::Unknown, "alive_crawl".into());
+    {
+        let mut fetching = FETCHING.lock().unwrap();
+        fetching.insert(
+            stuck.clone(),
+            Instant::now() - max_age - Duration::from_secs(1),
+        );
+        fetching.insert(alive.clone(), Instant::now());
+    }
+
+    assert_eq!(evict_stale_fetching(max_age), vec![stuck.clone()]);
+    assert!(!FETCHING.lock().unwrap().contains_key(&stuck));
+    assert!(FETCHING.lock().unwrap().contains_key(&alive));
+    FETCHING.lock().unwrap().remove(&alive);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch_all_after_stuck_crawl() -> Result<(), Error> {
+    let max_age = Duration::from_secs(C.upstream.crawl.max_age);
+    let target = Target::Identity(Platform::Unknown, "stuck_then_refetch".into());
+    FETCHING.lock().unwrap().insert(
+        target.clone(),
+        Instant::now() - max_age - Duration::from_secs(1),
+    );
+
+    // A skipped crawl leaves the registry as-is; a started one cleans its entry up after.
+    fetch_all(target.clone()).await?;
+    assert!(!FETCHING.lock().unwrap().contains_key(&target));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch_all_dedupes_casing() -> Result<(), Error> {
+    let running = Target::Identity(Platform::Twitter, "dedupe_casing".into());
+    let started_at = Instant::now();
+    FETCHING.lock().unwrap().insert(running.clone(), started_at);
+
+    // Skipped as the same one is being crawled.
+    let cost = fetch_all(Target::Identity(Platform::Twitter, "Dedupe_Casing".into())).await?;
+    assert_eq!(cost, CrawlCost::default());
+    assert_eq!(FETCHING.lock().unwrap().get(&running), Some(&started_at));
+    FETCHING.lock().unwrap().remove(&running);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_crawl_strategy() {
+    let t = |name: &str| Target::Identity(Platform::Twitter, name.into());
+    //     A
+    //    / \
+    //   B   C
+    //   |   |
+    //   D   E
+    let graph: Arc<HashMap<Target, Vec<Target>>> = Arc::new(HashMap::from([
+        (t("a"), vec![t("b"), t("c")]),
+        (t("b"), vec![t("d"), t("a")]),
+        (t("c"), vec![t("e")]),
+    ]));
+    let fetch = |target: Target| {
+        let graph = graph.clone();
+        async move { Ok::<_, Error>(graph.get(&target).cloned().unwrap_or_default()) }
+    };
+
+    let bfs = crawl(t("a"), CrawlStrategy::BreadthFirst, 0, None, fetch).await;
+    assert_eq!(bfs, vec![t("a"), t("b"), t("c"), t("d"), t("e")]);
+
+    let dfs = crawl(t("a"), CrawlStrategy::DepthFirst, 0, None, fetch).await;
+    assert_eq!(dfs, vec![t("a"), t("b"), t("d"), t("c"), t("e")]);
+}
+
+#[tokio::test]
+async fn test_crawl_max_depth() {
+    // 0 -> 1 -> 2 -> ... -> 9
+    let t = |n: u16| Target::Identity(Platform::Twitter, format!("chain_{}", n));
+    let fetch = |target: Target| async move {
+        let n: u16 = target.identity()?.trim_start_matches("chain_").parse()?;
+        Ok::<_, Error>(if n < 9 { vec![t(n + 1)] } else { vec![] })
+    };
+
+    for strategy in [CrawlStrategy::BreadthFirst, CrawlStrategy::DepthFirst] {
+        let processed = crawl(t(0), strategy, 3, None, fetch).await;
+        assert_eq!(processed, vec![t(0), t(1), t(2), t(3)]);
+    }
+    assert_eq!(
+        crawl(t(0), CrawlStrategy::BreadthFirst, 0, None, fetch)
+            .await
+            .len(),
+        10
+    );
+}
+
+#[tokio::test]
+async fn test_crawl_deadline() {
+    // An endless chain, each one taking 100ms to fetch.
+    let t = |n: u32| Target::Identity(Platform::Twitter, format!("slow_{}", n));
+    let fetch = |target: Target| async move {
+        let n: u32 = target.identity()?.trim_start_matches("slow_").parse()?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        Ok::<_, Error>(vec![t(n + 1)])
+    };
+
+    let started_at = Instant::now();
+    let (processed, cost) = with_cost(crawl(
+        t(0),
+        CrawlStrategy::BreadthFirst,
+        0,
+        Some(Duration::from_millis(550)),
+        fetch,
+    ))
+    .await;
+    // The 6th fetch is dropped half way.
+    assert!(started_at.elapsed() < Duration::from_millis(700));
+    assert_eq!(processed, (0..5).map(t).collect::<Vec<_>>());
+    assert!(cost.truncated);
+
+    // Crawls done in time are not truncated.
+    let (processed, cost) = with_cost(crawl(
+        t(0),
+        CrawlStrategy::BreadthFirst,
+        2,
+        Some(Duration::from_secs(10)),
+        fetch,
+    ))
+    .await;
+    assert_eq!(processed.len(), 3);
+    assert!(!cost.truncated);
+}
+
+#[tokio::test]
+async fn test_crawl_dedup_by_normalized_target() {
+    let t = |name: &str| Target::Identity(Platform::Twitter, name.into());
+    let fetched = Arc::new(std::sync::Mutex::new(vec![]));
+    let fetch = |target: Target| {
+        let fetched = fetched.clone();
+        async move {
+            fetched.lock().unwrap().push(target.clone());
+            if target == t("root") {
+                Ok::<_, Error>(vec![t("Vitalik"), t("vitalik"), t("VITALIK")])
+            } else {
+                Ok(vec![t("Root")])
+            }
+        }
+    };
+
+    crawl(t("root"), CrawlStrategy::BreadthFirst, 0, None, fetch).await;
+    // The first one found, as it is.
+    assert_eq!(*fetched.lock().unwrap(), vec![t("root"), t("Vitalik")]);
+}
+
+fn from_keybase(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async {
+        Ok(vec![Target::Identity(
+            Platform::Twitter,
+            "from_keybase".into(),
+        )])
+    })
+}
+
+fn from_rss3(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async {
+        Ok(vec![Target::Identity(
+            Platform::Twitter,
+            "from_rss3".into(),
+        )])
+    })
+}
+
+#[tokio::test]
+async fn test_fetch_one_with_sources() -> Result<(), Error> {
+    let registry: &[(DataSource, FetchFn)] = &[
+        (DataSource::Keybase, from_keybase),
+        (DataSource::Rss3, from_rss3),
+    ];
+    let target = Target::Identity(Platform::Github, "test".into());
+    let keybase = Target::Identity(Platform::Twitter, "from_keybase".into());
+    let rss3 = Target::Identity(Platform::Twitter, "from_rss3".into());
+
+    let only = SourceSelection {
+        only: Some(vec![DataSource::Keybase]),
+        exclude: vec![],
+    };
+    assert_eq!(
+        fetch_one_from(&target, registry, &only).await?.found,
+        vec![keybase.clone()]
+    );
+
+    let exclude = SourceSelection {
+        only: None,
+        exclude: vec![DataSource::Keybase],
+    };
+    assert_eq!(
+        fetch_one_from(&target, registry, &exclude).await?.found,
+        vec![rss3.clone()]
+    );
+
+    let all = fetch_one_from(&target, registry, &SourceSelection::default()).await?;
+    assert_eq!(all.found, vec![keybase, rss3]);
+    assert!(all.failures.is_empty());
+
+    Ok(())
+}
+
+fn from_broken(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async { Err(Error::NoResult) })
+}
+
+#[tokio::test]
+async fn test_fetch_one_failures() -> Result<(), Error> {
+    let registry: &[(DataSource, FetchFn)] = &[
+        (DataSource::Keybase, from_keybase),
+        (DataSource::SybilList, from_broken),
+        (DataSource::Rss3, from_rss3),
+    ];
+    let target = Target::Identity(Platform::Github, "test".into());
+
+    let outcome = fetch_one_from(&target, registry, &SourceSelection::default()).await?;
+    // Others still count.
+    assert_eq!(outcome.found.len(), 2);
+    assert_eq!(outcome.failures.len(), 1);
+    assert_eq!(outcome.failures[0].0, DataSource::SybilList);
+    assert!(matches!(outcome.failures[0].1, Error::NoResult));
+
+    Ok(())
+}
+
+/// How many times `from_down` is asked.
+static DOWN_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+fn from_down(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    DOWN_CALLS.fetch_add(1, Ordering::SeqCst);
+    Box::pin(async { Err(Error::Timeout("from_down".into())) })
+}
+
+#[tokio::test]
+async fn test_breaker_opens() -> Result<(), Error> {
+    // Not asked by anything else.
+    let source = DataSource::EthLeaderboard;
+    let registry: &[(DataSource, FetchFn)] = &[(source, from_down)];
+    let target = Target::Identity(Platform::Github, "test".into());
+
+    for _ in 0..C.upstream.breaker.failure_threshold {
+        let outcome = fetch_one_from(&target, registry, &SourceSelection::default()).await?;
+        assert_eq!(outcome.failures.len(), 1);
+    }
+    assert_eq!(breaker::state_of(source), BreakerState::Open);
+    let called = DOWN_CALLS.load(Ordering::SeqCst);
+
+    // Skipped without asking it.
+    let outcome = fetch_one_from(&target, registry, &SourceSelection::default()).await?;
+    assert!(outcome.found.is_empty());
+    assert!(outcome.failures.is_empty());
+    assert_eq!(DOWN_CALLS.load(Ordering::SeqCst), called);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_breaker_half_open() -> Result<(), Error> {
+    let source = DataSource::CyberConnect;
+    let target = Target::Identity(Platform::Github, "test".into());
+    let long_ago = Instant::now()
+        .checked_sub(Duration::from_secs(C.upstream.breaker.open_seconds + 1))
+        .unwrap();
+
+    // A trial dropped before it's done (e.g. cancelled) lets the next one try.
+    breaker::open_since(source, long_ago);
+    let trial = breaker::allow(source).expect("trial let through");
+    assert!(breaker::allow(source).is_none());
+    drop(trial);
+    assert!(breaker::allow(source).is_some());
+
+    // Still down: tripped again after one trial.
+    breaker::open_since(source, long_ago);
+    assert_eq!(breaker::state_of(source), BreakerState::HalfOpen);
+    let down: &[(DataSource, FetchFn)] = &[(source, from_down)];
+    let outcome = fetch_one_from(&target, down, &SourceSelection::default()).await?;
+    assert_eq!(outcome.failures.len(), 1);
+    assert_eq!(breaker::state_of(source), BreakerState::Open);
+
+    // Recovered: closed after one trial.
+    breaker::open_since(source, long_ago);
+    let up: &[(DataSource, FetchFn)] = &[(source, from_keybase)];
+    let outcome = fetch_one_from(&target, up, &SourceSelection::default()).await?;
+    assert_eq!(outcome.found.len(), 1);
+    assert_eq!(breaker::state_of(source), BreakerState::Closed);
+    assert!(breaker::upstream_health().contains_key(&DataSource::Keybase));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unsupported_targets() -> Result<(), Error> {
+    // Nobody fetches these.
+    let unsupported = [
+        Target::Identity(Platform::Unknown, "unsupported".into()),
+        Target::NFT(
+            Chain::Arweave,
+            ContractCategory::Unknown,
+            "0x0000000000000000000000000000000000000000".into(),
+            "1".into(),
+        ),
+    ];
+    for target in unsupported.iter() {
+        for (source, fetch) in UPSTREAMS {
+            assert!(
+                fetch(target).await?.is_empty(),
+                "{} fetched {}",
+                source,
+                target
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_unknown_enum_value() -> Result<(), Error> {
+    let source: DataSource = serde_json::from_str(r#""some_future_source""#)?;
+    assert_eq!(source, DataSource::Unknown);
+    let platform: Platform = serde_json::from_str(r#""some_future_platform""#)?;
+    assert_eq!(platform, Platform::Unknown);
+
+    // Known values are intact.
+    let source: DataSource = serde_json::from_str(r#""keybase""#)?;
+    assert_eq!(source, DataSource::Keybase);
+    let platform: Platform = serde_json::from_str(r#""unknown""#)?;
+    assert_eq!(platform, Platform::Unknown);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_empty_identity() -> Result<(), Error> {
+    assert!(matches!(
+        Target::new_identity(Platform::Twitter, " "),
+        Err(Error::ParamMissing(_))
+    ));
+    assert!(Target::default().validate().is_err());
+    assert!(matches!(
+        fetch_all(Target::default()).await,
+        Err(Error::ParamMissing(_))
+    ));
+    assert!(!FETCHING.lock().unwrap().contains_key(&Target::default()));
+    assert!(matches!(
+        fetch_one(&Target::Identity(Platform::Github, "".into())).await,
+        Err(Error::ParamMissing(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_crawl_db_budget() -> Result<(), Error> {
+    let root = Target::Identity(Platform::Unknown, "budget_root".into());
+    let (processed, peak) = with_db_budget(
+        3,
+        crawl(
+            root.clone(),
+            CrawlStrategy::BreadthFirst,
+            0,
+            None,
+            |target| {
+                let root = root.clone();
+                async move {
+                    let _db = new_db_connection().await?;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    if target == root {
+                        // A wide crawl: 12 targets are fetched concurrently in the next round.
+                        Ok((0..12)
+                            .map(|i| Target::Identity(Platform::Unknown, format!("budget_{}", i)))
+                            .collect())
+                    } else {
+                        Ok(vec![])
+                    }
+                }
+            },
+        ),
+    )
+    .await;
+
+    assert_eq!(13, processed.len());
+    assert!(peak >= 1);
+    assert!(peak <= 3, "{} connections used at the same time", peak);
+
+    Ok(())
+}
+
+fn from_nowhere(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async { Ok(vec![]) })
+}
+
+fn from_failing(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async { Err(Error::NoResult) })
+}
+
+#[tokio::test]
+async fn test_source_going_quiet() -> Result<(), Error> {
+    // Not asked by any other test.
+    let source = DataSource::Unknown;
+    let target = Target::Identity(Platform::Github, "test".into());
+    let long_ago = naive_now() - chrono::Duration::days(2);
+    record_produced_at(source, long_ago);
+
+    // Failures don't count.
+    let quiet: &[(DataSource, FetchFn)] = &[(source, from_failing)];
+    fetch_one_from(&target, quiet, &SourceSelection::default()).await?;
+    let liveness = liveness_of(source);
+    assert_eq!(liveness.last_produced_at, Some(long_ago.timestamp()));
+    assert!(liveness.quiet);
+
+    // Successful responses do, even if nothing is found.
+    let producing: &[(DataSource, FetchFn)] = &[(source, from_nowhere)];
+    fetch_one_from(&target, producing, &SourceSelection::default()).await?;
+    let liveness = liveness_of(source);
+    assert!(liveness.last_produced_at.unwrap() > long_ago.timestamp());
+    assert!(!liveness.quiet);
+
+    Ok(())
+}
+
+static LIMITED_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static LIMITED_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn from_limited(_: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async {
+        let now = LIMITED_IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+        LIMITED_PEAK.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        LIMITED_IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+        Ok(vec![])
+    })
+}
+
+#[tokio::test]
+async fn test_source_concurrency() {
+    // Not asked by any other test.
+    let source = DataSource::RPCServer;
+    concurrency::set_limit(source, 2);
+    let registry: &[(DataSource, FetchFn)] = &[(source, from_limited)];
+    let root = Target::Identity(Platform::Twitter, "limited_root".into());
+
+    crawl(
+        root.clone(),
+        CrawlStrategy::BreadthFirst,
+        0,
+        None,
+        |target| {
+            let root = root.clone();
+            async move {
+                fetch_one_from(&target, registry, &SourceSelection::default()).await?;
+                if target == root {
+                    // A wide crawl: 10 targets are fetched concurrently in the next round.
+                    Ok((0..10)
+                        .map(|i| Target::Identity(Platform::Twitter, format!("limited_{}", i)))
+                        .collect())
+                } else {
+                    Ok(vec![])
+                }
+            }
+        },
+    )
+    .await;
+
+    assert_eq!(LIMITED_PEAK.load(Ordering::SeqCst), 2);
+}
+
+/// Records the identity fetched. The root one leads to 2 more.
+fn from_writing(target: &Target) -> BoxFuture<'_, Result<TargetProcessedList, Error>> {
+    Box::pin(async move {
+        let db = new_db_connection().await?;
+        let identity = Identity {
+            platform: Platform::Github,
+            identity: target.identity()?,
+            ..Faker.fake()
+        };
+        identity.create_or_update(&db).await?;
+        if target.identity()?.ends_with("_root") {
+            Ok((0..2)
+                .map(|i| {
+                    Target::Identity(Platform::Github, format!("cost_{}_{}", i, Uuid::new_v4()))
+                })
+                .collect())
+        } else {
+            Ok(vec![])
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_crawl_cost() -> Result<(), Error> {
+    // Not asked by any other test.
+    let registry: &[(DataSource, FetchFn)] = &[
+        (DataSource::Knn3, from_writing),
+        (DataSource::Eas, from_nowhere),
+    ];
+    let root = Target::Identity(Platform::Github, format!("cost_{}_root", Uuid::new_v4()));
+    let (processed, cost) = with_cost(crawl(
+        root,
+        CrawlStrategy::BreadthFirst,
+        0,
+        None,
+        |target| async move {
+            Ok(
+                fetch_one_from(&target, registry, &SourceSelection::default())
+                    .await?
+                    .found,
+            )
+        },
+    ))
+    .await;
+
+    assert_eq!(processed.len(), 3);
+    assert_eq!(cost.requests.get(&DataSource::Knn3), Some(&3));
+    assert_eq!(cost.requests.get(&DataSource::Eas), Some(&3));
+    assert_eq!(cost.requests.len(), 2);
+    assert_eq!(cost.db_writes, 3);
+
+    // Nothing is counted outside of a crawl.
+    let (_, idle) = with_cost(async {}).await;
+    assert!(idle.requests.is_empty());
+    assert_eq!(idle.db_writes, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refetch_queue_dedupes_pending() {
+    let fetched = Arc::new(AtomicUsize::new(0));
+    let counter = fetched.clone();
+    let queue = RefetchQueue::start_with(
+        2,
+        10,
+        Arc::new(move |_: Target, _: SourceSelection| {
+            let counter = counter.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, Error>(CrawlCost::default())
+            }
+            .boxed()
+        }),
+    );
+    let target = Target::Identity(
+        Platform::Twitter,
+        format!("refetch{}", Uuid::new_v4().simple()),
+    );
+
+    assert!(queue.enqueue(target.clone(), SourceSelection::default()));
+    // Queued already: not again.
+    assert!(!queue.enqueue(target.clone(), SourceSelection::default()));
+    let started_at = Instant::now();
+    while queue.is_pending(&target) {
+        assert!(
+            started_at.elapsed() < Duration::from_secs(5),
+            "refetch never finishes"
+        );
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(fetched.load(Ordering::SeqCst), 1);
+
+    // Done: can be queued again.
+    assert!(queue.enqueue(target.clone(), SourceSelection::default()));
+    // Asking only some upstreams doesn't hold a full refetch back, nor the other way around.
+    let reverse = SourceSelection {
+        only: Some(vec![DataSource::ENSReverse]),
+        exclude: vec![],
+    };
+    assert!(queue.enqueue(target.clone(), reverse.clone()));
+    assert!(!queue.enqueue(target.clone(), reverse));
+}
+
+#[tokio::test]
+async fn test_refetch_queue_survives_panic() {
+    let queue = RefetchQueue::start_with(
+        1,
+        10,
+        Arc::new(|target: Target, _: SourceSelection| {
+            async move {
+                if target.identity().unwrap().starts_with("panic") {
+                    panic!("refetch panicked");
+                }
+                Ok::<_, Error>(CrawlCost::default())
+            }
+            .boxed()
+        }),
+    );
+    let panicking = Target::Identity(
+        Platform::Twitter,
+        format!("panic{}", Uuid::new_v4().simple()),
+    );
+    let fine = Target::Identity(
+        Platform::Twitter,
+        format!("refetch{}", Uuid::new_v4().simple()),
+    );
+
+    assert!(queue.enqueue(panicking.clone(), SourceSelection::default()));
+    assert!(queue.enqueue(fine.clone(), SourceSelection::default()));
+    let started_at = Instant::now();
+    // The only worker goes on after the panic, and the panicked one isn't left pending.
+    while queue.is_pending(&panicking) || queue.is_pending(&fine) {
+        assert!(
+            started_at.elapsed() < Duration::from_secs(5),
+            "refetch never finishes"
+        );
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
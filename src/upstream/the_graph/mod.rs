@@ -14,8 +14,8 @@ use crate::{
         },
         Edge, Vertex,
     },
-    upstream::{DataFetcher, DataSource, Fetcher, Platform, Target, TargetProcessedList},
-    util::{naive_now, parse_timestamp},
+    upstream::{DataSource, Fetcher, Platform, Target, TargetProcessedList},
+    util::{self, naive_now, parse_timestamp},
 };
 use aragog::DatabaseConnection;
 use async_trait::async_trait;
@@ -48,6 +48,15 @@ struct Domain {
     resolvedAddress: Option<Account>,
     /// Owner info
     owner: Account,
+    /// Registration of this name. Subdomains have none.
+    registration: Option<Registration>,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(non_snake_case)]
+struct Registration {
+    /// Expiration timestamp (in seconds)
+    expiryDate: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -89,6 +98,9 @@ const QUERY_BY_ENS: &str = r#"
                 owner{
                   id
                 }
+                registration {
+                  expiryDate
+                }
               }
         }
     "#;
@@ -111,10 +123,31 @@ const QUERY_BY_WALLET: &str = r#"
                 owner {
                   id
                 }
+                registration {
+                  expiryDate
+                }
               }
         }
     "#;
 
+const QUERY_NAME_BY_NAMEHASH: &str = r#"
+        query ENSNameByNamehash($target: String!){
+            domains(where: { id: $target }) {
+                name
+              }
+        }
+    "#;
+
+#[derive(Deserialize, Debug)]
+struct NameResponse {
+    domains: Vec<DomainName>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DomainName {
+    name: String,
+}
+
 pub struct TheGraph {}
 
 #[async_trait]
@@ -124,7 +157,7 @@ impl Fetcher for TheGraph {
             return Ok(vec![]);
         }
 
-        perform_fetch(target).await
+        perform_fetch(&C.upstream.the_graph.ens, target).await
     }
 
     fn can_fetch(target: &Target) -> bool {
@@ -137,7 +170,9 @@ impl Fetcher for TheGraph {
 /// See also: https://github.com/ensdomains/ens-subgraph/issues/25
 /// Consider deploy a self-hosted reverse lookup service like:
 /// https://github.com/fafrd/ens-reverse-lookup
-async fn perform_fetch(target: &Target) -> Result<TargetProcessedList, Error> {
+///
+/// `url`: ENS subgraph endpoint. See `C.upstream.the_graph.ens`.
+async fn perform_fetch(url: &str, target: &Target) -> Result<TargetProcessedList, Error> {
     let query: String;
     let target_var: String;
     match target {
@@ -147,16 +182,20 @@ async fn perform_fetch(target: &Target) -> Result<TargetProcessedList, Error> {
         }
         Target::NFT(_chain, _category, _contract_addr, ens_name) => {
             query = QUERY_BY_ENS.to_string();
-            target_var = ens_name.clone();
+            // TheGraph has names in their normalized form.
+            target_var = DomainNameSystem::ENS.normalize(ens_name)?;
         }
     }
 
-    let client = Client::new(&C.upstream.the_graph.ens);
+    let client = Client::new(url);
     let vars = QueryVars { target: target_var };
 
-    let resp = client
-        .query_with_vars::<QueryResponse, QueryVars>(&query, vars)
-        .await;
+    let resp = util::with_timeout(
+        "TheGraph",
+        C.upstream.the_graph.timeout_seconds,
+        client.query_with_vars::<QueryResponse, QueryVars>(&query, vars),
+    )
+    .await?;
 
     if resp.is_err() {
         warn!(
@@ -175,7 +214,15 @@ async fn perform_fetch(target: &Target) -> Result<TargetProcessedList, Error> {
     let db = new_db_connection().await?;
     let mut next_targets: TargetProcessedList = vec![];
 
-    for domain in res.domains.into_iter() {
+    for mut domain in res.domains.into_iter() {
+        // Stored normalized, so variants of a name are one record.
+        domain.name = match DomainNameSystem::ENS.normalize(&domain.name) {
+            Ok(name) => name,
+            Err(err) => {
+                warn!("TheGraph {} | Skipped: {}", target, err);
+                continue;
+            }
+        };
         // Create own record
         let contract_record = create_or_update_own(&db, &domain).await?;
 
@@ -191,6 +238,7 @@ async fn perform_fetch(target: &Target) -> Result<TargetProcessedList, Error> {
                     identity: address.clone(),
                     created_at: None,
                     display_name: None,
+                    display_name_source: None,
                     added_at: naive_now(),
                     avatar_url: None,
                     profile_url: None,
@@ -203,7 +251,7 @@ async fn perform_fetch(target: &Target) -> Result<TargetProcessedList, Error> {
                     source: DataSource::TheGraph,
                     system: DomainNameSystem::ENS,
                     name: domain.name.clone(),
-                    fetcher: DataFetcher::RelationService,
+                    fetcher: C.upstream.the_graph.fetcher.unwrap_or_default(),
                     updated_at: naive_now(),
                 };
 
@@ -240,6 +288,52 @@ async fn perform_fetch(target: &Target) -> Result<TargetProcessedList, Error> {
     Ok(next_targets)
 }
 
+/// ENS name whose namehash (i.e. token ID) is `namehash`, normalized. `None` if TheGraph doesn't know it.
+///
+/// `url`: ENS subgraph endpoint. See `C.upstream.the_graph.ens`.
+pub async fn name_of_namehash(url: &str, namehash: &str) -> Result<Option<String>, Error> {
+    // TheGraph has domain IDs (namehashes) lowercased.
+    let namehash = namehash.to_lowercase();
+    let client = Client::new(url);
+    let vars = QueryVars {
+        target: namehash.clone(),
+    };
+    let resp = util::with_timeout(
+        "TheGraph",
+        C.upstream.the_graph.timeout_seconds,
+        client.query_with_vars::<NameResponse, QueryVars>(QUERY_NAME_BY_NAMEHASH, vars),
+    )
+    .await?;
+    let domains = match resp {
+        Ok(Some(resp)) => resp.domains,
+        Ok(None) => vec![],
+        Err(err) => {
+            warn!("TheGraph {} | Failed to fetch name: {}", namehash, err);
+            return Ok(None);
+        }
+    };
+
+    for domain in domains.into_iter() {
+        let name = match DomainNameSystem::ENS.normalize(&domain.name) {
+            Ok(name) => name,
+            Err(err) => {
+                warn!("TheGraph {} | Skipped: {}", namehash, err);
+                continue;
+            }
+        };
+        // Names of unknown labels come as `[labelhash].eth`, which don't hash back.
+        if util::namehash(&name) == namehash {
+            return Ok(Some(name));
+        }
+        warn!(
+            "TheGraph {} | Skipped {}: namehash mismatched",
+            namehash, name
+        );
+    }
+    info!("TheGraph {} | No name found", namehash);
+    Ok(None)
+}
+
 /// Focus on `Hold` record.
 async fn create_or_update_own(
     db: &DatabaseConnection,
@@ -250,12 +344,17 @@ async fn create_or_update_own(
         .first() // TODO: really?
         .map(|event| event.transactionID.clone());
     let ens_created_at = parse_timestamp(&domain.createdAt).ok();
+    let ens_expires_at = domain
+        .registration
+        .as_ref()
+        .and_then(|registration| parse_timestamp(&registration.expiryDate).ok());
     let owner = Identity {
         uuid: Some(Uuid::new_v4()),
         platform: Platform::Ethereum,
         identity: domain.owner.id.clone(),
         created_at: None,
         display_name: None,
+        display_name_source: None,
         added_at: naive_now(),
         avatar_url: None,
         profile_url: None,
@@ -275,8 +374,9 @@ async fn create_or_update_own(
         id: domain.name.clone(),
         source: DataSource::TheGraph,
         created_at: ens_created_at,
+        expires_at: ens_expires_at,
         updated_at: naive_now(),
-        fetcher: DataFetcher::RelationService,
+        fetcher: C.upstream.the_graph.fetcher.unwrap_or_default(),
     };
     let (_owner_record, contract_record, _hold_record) =
         create_identity_to_contract_record(db, &owner, &conrtract, &ownership).await?;
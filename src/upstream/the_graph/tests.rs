@@ -1,14 +1,19 @@
 use crate::{
     error::Error,
     graph::{
-        edge::Hold,
+        arangopool::new_connection_pool,
+        edge::{resolve::DomainNameSystem, Hold, HoldRecord, Resolve},
         new_db_connection,
         vertex::contract::Chain,
         vertex::Identity,
         vertex::{contract::ContractCategory, Contract},
     },
-    upstream::{the_graph::TheGraph, DataFetcher, DataSource, Fetcher, Platform, Target},
-    util::parse_timestamp,
+    upstream::{
+        mock::{self, Fixture},
+        the_graph::{name_of_namehash, perform_fetch, TheGraph},
+        DataFetcher, DataSource, Fetcher, Platform, Target,
+    },
+    util::{namehash, parse_timestamp},
 };
 
 #[tokio::test]
@@ -104,3 +109,138 @@ async fn test_find_wallet_by_ens() -> Result<(), Error> {
 
     Ok(())
 }
+
+const FIXTURE_NAME: &str = "fixture-thegraph.eth";
+const FIXTURE_OWNER: &str = "0x0000000000000000000000000000000000e650e4";
+const FIXTURE_RESOLVED: &str = "0x0000000000000000000000000000000000e650e5";
+
+fn serve_domains() -> String {
+    mock::serve(vec![Fixture::ok(
+        "/",
+        include_str!("../fixtures/the_graph/domains.json"),
+    )])
+}
+
+#[tokio::test]
+async fn test_replay_domains_of_wallet() -> Result<(), Error> {
+    let url = serve_domains();
+    let target = Target::Identity(Platform::Ethereum, FIXTURE_OWNER.into());
+
+    let targets = perform_fetch(&url, &target).await?;
+    // Names are normalized.
+    assert_eq!(
+        targets,
+        vec![Target::NFT(
+            Chain::Ethereum,
+            ContractCategory::ENS,
+            ContractCategory::ENS.default_contract_address().unwrap(),
+            FIXTURE_NAME.into(),
+        )]
+    );
+
+    let db = new_db_connection().await?;
+    let hold = Hold::find_by_id_chain_address(
+        &db,
+        FIXTURE_NAME,
+        &Chain::Ethereum,
+        &ContractCategory::ENS.default_contract_address().unwrap(),
+    )
+    .await?
+    .expect("Record not found");
+    assert_eq!(hold.source, DataSource::TheGraph);
+    assert_eq!(hold.created_at, parse_timestamp("1497775154").ok());
+    let resolve = Resolve::find_by_name_system(&db, FIXTURE_NAME, &DomainNameSystem::ENS)
+        .await?
+        .expect("Record not found");
+    let resolved = Identity::find_by_platform_identity(&db, &Platform::Ethereum, FIXTURE_RESOLVED)
+        .await?
+        .expect("Record not found");
+    assert_eq!(resolve.key_to(), resolved.key());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_owner_of_name() -> Result<(), Error> {
+    let url = serve_domains();
+    let target = Target::NFT(
+        Chain::Ethereum,
+        ContractCategory::ENS,
+        ContractCategory::ENS.default_contract_address().unwrap(),
+        "Fixture-TheGraph.eth".into(),
+    );
+
+    let targets = perform_fetch(&url, &target).await?;
+    // Both the owner and the wallet it resolves to are up next.
+    assert_eq!(
+        targets,
+        vec![
+            Target::Identity(Platform::Ethereum, FIXTURE_OWNER.into()),
+            Target::Identity(Platform::Ethereum, FIXTURE_RESOLVED.into()),
+        ]
+    );
+
+    let db = new_db_connection().await?;
+    Identity::find_by_platform_identity(&db, &Platform::Ethereum, FIXTURE_OWNER)
+        .await?
+        .expect("Record not found");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_expired_name() -> Result<(), Error> {
+    let url = serve_domains();
+    let target = Target::Identity(Platform::Ethereum, FIXTURE_OWNER.into());
+    perform_fetch(&url, &target).await?;
+
+    let db = new_db_connection().await?;
+    let pool = new_connection_pool().await?;
+    let hold = Hold::find_by_id_chain_address(
+        &db,
+        FIXTURE_NAME,
+        &Chain::Ethereum,
+        &ContractCategory::ENS.default_contract_address().unwrap(),
+    )
+    .await?
+    .expect("Record not found");
+    // Registration in the fixture expired in 2020.
+    assert_eq!(hold.expires_at, parse_timestamp("1600000000").ok());
+    assert!(hold.is_expired());
+    // Just fetched after it expired: not to be refetched on every read.
+    assert!(!hold.is_outdated());
+
+    let owner = Identity::find_by_platform_identity(&db, &Platform::Ethereum, FIXTURE_OWNER)
+        .await?
+        .expect("Record not found");
+    let names = |holds: Vec<HoldRecord>| -> Vec<String> {
+        holds.into_iter().map(|hold| hold.id.clone()).collect()
+    };
+    assert!(names(owner.nfts(&pool, None, None, true).await?).contains(&FIXTURE_NAME.to_string()));
+    assert!(!names(owner.nfts(&pool, None, None, false).await?).contains(&FIXTURE_NAME.to_string()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_name_of_namehash() -> Result<(), Error> {
+    let url = serve_domains();
+    let hash = namehash(FIXTURE_NAME);
+
+    // Found normalized, in whichever case the namehash is given.
+    assert_eq!(
+        name_of_namehash(&url, &hash).await?,
+        Some(FIXTURE_NAME.to_string())
+    );
+    assert_eq!(
+        name_of_namehash(&url, &hash.to_uppercase().replace("0X", "0x")).await?,
+        Some(FIXTURE_NAME.to_string())
+    );
+    // A name given back doesn't count if it isn't of the namehash asked.
+    assert_eq!(
+        name_of_namehash(&url, &namehash("vitalik.eth")).await?,
+        None
+    );
+
+    Ok(())
+}
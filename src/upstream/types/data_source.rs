@@ -11,6 +11,7 @@ use strum_macros::{Display, EnumIter, EnumString};
     EnumString,
     PartialEq,
     Eq,
+    Hash,
     EnumIter,
     Default,
     Copy,
@@ -74,9 +75,60 @@ pub enum DataSource {
     #[graphql(name = "dotbit")]
     Dotbit,
 
-    /// Unknown
+    /// Aggregation service, which collects connections from other sources.
+    /// Connections it provides carry their original `source`;
+    /// this one stands for the upstream itself.
+    #[strum(serialize = "aggregation")]
+    #[serde(rename = "aggregation")]
+    #[graphql(name = "aggregation")]
+    Aggregation,
+
+    /// ENS reverse lookup service.
+    #[strum(serialize = "ens_reverse")]
+    #[serde(rename = "ens_reverse")]
+    #[graphql(name = "ens_reverse")]
+    ENSReverse,
+
+    /// Signed proof file hosted by user on their own domain.
+    /// See `upstream/web_proof`.
+    #[strum(serialize = "web_proof")]
+    #[serde(rename = "web_proof")]
+    #[graphql(name = "web_proof")]
+    WebProof,
+
+    /// Ethereum Attestation Service.
+    /// https://docs.attest.sh
+    #[strum(serialize = "eas")]
+    #[serde(rename = "eas")]
+    #[graphql(name = "eas")]
+    Eas,
+
+    /// GitHub profile (bio, blog and profile README).
+    /// https://docs.github.com/en/rest/users/users
+    #[strum(serialize = "github")]
+    #[serde(rename = "github")]
+    #[graphql(name = "github")]
+    Github,
+
+    /// POAPs (attendance badges) held by wallets.
+    /// https://documentation.poap.tech
+    #[strum(serialize = "poap")]
+    #[serde(rename = "poap")]
+    #[graphql(name = "poap")]
+    Poap,
+
+    /// SPACE ID, name service of `.bnb` names on BNB Smart Chain.
+    /// https://docs.space.id
+    #[strum(serialize = "space_id")]
+    #[serde(rename = "space_id")]
+    #[graphql(name = "space_id")]
+    SpaceId,
+
+    /// Unknown.
+    /// Also what values added by newer versions deserialize into,
+    /// so records written by them can still be read during a rolling deployment.
     #[strum(serialize = "unknown")]
-    #[serde(rename = "unknown")]
+    #[serde(rename = "unknown", other)]
     #[graphql(name = "unknown")]
     #[default]
     Unknown,
@@ -8,16 +8,18 @@ use serde::{Deserialize, Serialize};
 pub use data_fetcher::DataFetcher;
 pub use data_source::DataSource;
 pub use platform::Platform;
-pub use target::{Target, TargetProcessedList};
+pub use target::{validate_identity, Target, TargetProcessedList};
 
 /// All asymmetric cryptography algorithm supported by RelationService.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Algorithm {
+    #[default]
     EllipticCurve,
 }
 
 /// All elliptic curve supported by RelationService.
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Curve {
+    #[default]
     Secp256K1,
 }
@@ -1,3 +1,4 @@
+use crate::config::C;
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumIter, EnumString};
 
@@ -67,6 +68,12 @@ pub enum Platform {
     #[graphql(name = "dotbit")]
     Dotbit,
 
+    /// SPACE ID (`.bnb`)
+    #[strum(serialize = "space_id")]
+    #[serde(rename = "space_id")]
+    #[graphql(name = "space_id")]
+    SpaceId,
+
     /// DNS
     #[strum(serialize = "dns")]
     #[serde(rename = "dns")]
@@ -79,10 +86,34 @@ pub enum Platform {
     #[graphql(name = "minds")]
     Minds,
 
-    /// Unknown
+    /// Unknown.
+    /// Also what values added by newer versions deserialize into,
+    /// so records written by them can still be read during a rolling deployment.
     #[strum(serialize = "unknown")]
-    #[serde(rename = "unknown")]
+    #[serde(rename = "unknown", other)]
     #[graphql(name = "unknown")]
     #[default]
     Unknown,
 }
+
+impl Platform {
+    /// Is an identity on this platform a wallet (i.e. an on-chain address)?
+    pub fn is_wallet(&self) -> bool {
+        matches!(self, Platform::Ethereum)
+    }
+
+    /// Are identities on this platform case-insensitive (e.g. `0xABC` and `0xabc` are the same wallet)?
+    /// See `C.upstream.case_insensitive_platforms`.
+    pub fn is_case_insensitive(&self) -> bool {
+        C.upstream.case_insensitive_platforms.contains(self)
+    }
+
+    /// `identity` in the form it is stored in: lowercased if this platform is case-insensitive.
+    pub fn fold_identity(&self, identity: &str) -> String {
+        if self.is_case_insensitive() {
+            identity.to_lowercase()
+        } else {
+            identity.to_string()
+        }
+    }
+}
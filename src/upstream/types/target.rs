@@ -1,8 +1,10 @@
 use http::StatusCode;
 
 use crate::{
+    config::C,
     error::Error,
     graph::vertex::contract::{Chain, ContractCategory},
+    util::redact_identity,
 };
 
 use super::platform::Platform;
@@ -25,6 +27,45 @@ impl Default for Target {
     }
 }
 impl Target {
+    /// `Target::Identity`, rejecting an empty (or whitespace-only) `identity`.
+    pub fn new_identity(platform: Platform, identity: impl Into<String>) -> Result<Self, Error> {
+        let target = Self::Identity(platform, identity.into());
+        target.validate()?;
+        Ok(target)
+    }
+
+    /// `Err(Error::ParamMissing)` if nothing is given to look up, e.g. `Target::default()`.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Self::Identity(platform, identity) => validate_identity(identity).map_err(|_| {
+                Error::ParamMissing(format!("Target: Empty identity on {}", platform))
+            }),
+            Self::NFT(chain, _, address, nft_id) => {
+                if address.trim().is_empty() || nft_id.trim().is_empty() {
+                    Err(Error::ParamMissing(format!(
+                        "Target: Empty contract address or NFT ID on {}",
+                        chain
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /// Canonical form to tell if two targets are the same one:
+    /// identity is folded as stored (see `Platform::fold_identity`), contract address is lowercased.
+    pub fn normalized(&self) -> Target {
+        match self {
+            Self::Identity(platform, identity) => {
+                Self::Identity(*platform, platform.fold_identity(identity))
+            }
+            Self::NFT(chain, category, address, nft_id) => {
+                Self::NFT(*chain, *category, address.to_lowercase(), nft_id.clone())
+            }
+        }
+    }
+
     /// Judge if this target is in supported platforms list given by upstream.
     pub fn in_platform_supported(&self, platforms: Vec<Platform>) -> bool {
         match self {
@@ -102,11 +143,48 @@ impl Target {
 }
 impl std::fmt::Display for Target {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Identity(platform, identity) => write!(f, "Identity/{}/{}", platform, identity),
-            Self::NFT(chain, category, address, nft_id) => {
-                write!(f, "NFT/{}/{}/{}/{}", chain, category, address, nft_id)
+        self.fmt_with(f, C.log.redact_identities)
+    }
+}
+
+impl Target {
+    /// Same as `to_string()`, but hides identities if `redact` is set. See `C.log`.
+    pub fn to_log_string(&self, redact: bool) -> String {
+        struct Log<'a>(&'a Target, bool);
+        impl std::fmt::Display for Log<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt_with(f, self.1)
             }
         }
+        Log(self, redact).to_string()
+    }
+
+    fn fmt_with(&self, f: &mut std::fmt::Formatter<'_>, redact: bool) -> std::fmt::Result {
+        match self {
+            Self::Identity(platform, identity) => write!(
+                f,
+                "Identity/{}/{}",
+                platform,
+                redact_identity(identity, redact)
+            ),
+            // NFT_ID may also be an identity (e.g. ENS name).
+            Self::NFT(chain, category, address, nft_id) => write!(
+                f,
+                "NFT/{}/{}/{}/{}",
+                chain,
+                category,
+                address,
+                redact_identity(nft_id, redact)
+            ),
+        }
+    }
+}
+
+/// `Err(Error::ParamMissing)` if `identity` is empty or whitespace-only.
+pub fn validate_identity(identity: &str) -> Result<(), Error> {
+    if identity.trim().is_empty() {
+        Err(Error::ParamMissing("identity".into()))
+    } else {
+        Ok(())
     }
 }
@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod tests;
+
+use crate::config::C;
+use crate::error::Error;
+use crate::graph::vertex::Identity;
+use crate::graph::{create_identity_to_identity_record, edge::Proof, new_db_connection};
+use crate::upstream::{
+    Algorithm, Curve, DataSource, Fetcher, Platform, Target, TargetProcessedList,
+};
+use crate::util::{
+    make_public_client, naive_now, parse_body, timestamp_to_naive, verify_signature,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Where the proof file is hosted when only a domain is given.
+pub const WELL_KNOWN_PATH: &str = "/.well-known/nextid.json";
+
+/// Self-hosted proof: a user puts a file signed by their NextID persona
+/// on their own domain to prove they control it.
+pub struct WebProof {}
+
+#[async_trait]
+impl Fetcher for WebProof {
+    async fn fetch(target: &Target) -> Result<TargetProcessedList, Error> {
+        if !Self::can_fetch(target) {
+            return Ok(vec![]);
+        }
+
+        match target {
+            Target::Identity(_, identity) => fetch_web_proof(identity).await,
+            Target::NFT(_, _, _, _) => Ok(vec![]),
+        }
+    }
+
+    fn can_fetch(target: &Target) -> bool {
+        target.in_platform_supported(vec![Platform::DNS])
+    }
+}
+
+/// Content of the proof file.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct WebProofFile {
+    /// Public key of the persona claiming this domain. Hexstring, `0x` prefixed.
+    pub public_key: String,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub curve: Curve,
+    /// Unix timestamp (seconds) when this proof is signed.
+    pub created_at: i64,
+    /// Base64-encoded signature of `signature_payload()`.
+    pub signature: String,
+}
+
+/// The message a persona should sign to claim `domain`.
+pub fn signature_payload(domain: &str, public_key: &str, created_at: i64) -> String {
+    format!(
+        "NextID web proof\ndomain: {}\npublic_key: {}\ncreated_at: {}",
+        domain, public_key, created_at
+    )
+}
+
+/// Only a domain (`example.com`) is accepted, never a URL: the proof file is always fetched from
+/// `https://{domain}/.well-known/nextid.json`, so that nobody can make us request arbitrary URLs
+/// (e.g. inside our own network), and the file can't be tampered with in transit.
+/// Returns the domain being proved and the URL to fetch.
+fn proof_location(identity: &str) -> Result<(String, http::Uri), Error> {
+    let domain = validate_domain(identity)?;
+    let uri: http::Uri = format!("https://{}{}", domain, WELL_KNOWN_PATH)
+        .parse()
+        .map_err(|err: http::uri::InvalidUri| {
+            Error::ParamError(format!("URI Format error: {}", err))
+        })?;
+    Ok((domain, uri))
+}
+
+/// `identity` lowercased, if it is a public domain name. IP literals, single-label names
+/// (e.g. `localhost`) and names reserved for private networks are rejected.
+fn validate_domain(identity: &str) -> Result<String, Error> {
+    let invalid = |reason: &str| Error::ParamError(format!("{:?} {}", identity, reason));
+    let domain = identity.trim().trim_end_matches('.').to_lowercase();
+    let is_label = |label: &str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    let labels: Vec<&str> = domain.split('.').collect();
+    if domain.len() > 253 || !labels.iter().all(|label| is_label(label)) {
+        return Err(invalid("is not a domain name"));
+    }
+    // Top-level domains are never numeric, so this rules out IPv4 literals in any form.
+    let tld = labels[labels.len() - 1];
+    if labels.len() < 2 || tld.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid("is not a public domain name"));
+    }
+    const PRIVATE_SUFFIXES: &[&str] = &[
+        "localhost",
+        "local",
+        "localdomain",
+        "internal",
+        "intranet",
+        "lan",
+        "home",
+        "corp",
+        "home.arpa",
+        "in-addr.arpa",
+        "ip6.arpa",
+    ];
+    if PRIVATE_SUFFIXES
+        .iter()
+        .any(|suffix| domain == *suffix || domain.ends_with(&format!(".{}", suffix)))
+    {
+        return Err(invalid("is a private host name"));
+    }
+    Ok(domain)
+}
+
+async fn fetch_web_proof(identity: &str) -> Result<TargetProcessedList, Error> {
+    let (domain, uri) = proof_location(identity)?;
+    fetch_proof_file(domain, uri).await
+}
+
+/// Fetch the proof file of `domain` from `uri`, verify it and save the proof.
+async fn fetch_proof_file(domain: String, uri: http::Uri) -> Result<TargetProcessedList, Error> {
+    // A public name may still point into a private network. This client refuses to connect to it.
+    let client = make_public_client(Duration::from_secs(C.upstream.web_proof.timeout_seconds));
+    let mut resp = client.get(uri.clone()).await?;
+    if !resp.status().is_success() {
+        return Err(Error::General(
+            format!("WebProof fetch error: {}", resp.status()),
+            resp.status(),
+        ));
+    }
+    let file: WebProofFile = parse_body(&mut resp).await?;
+    let public_key = file.public_key.to_lowercase();
+
+    let message = signature_payload(&domain, &file.public_key, file.created_at);
+    if let Err(err) = verify_signature(
+        file.algorithm,
+        file.curve,
+        &message,
+        &file.signature,
+        &file.public_key,
+    ) {
+        warn!("WebProof | Invalid proof file for {}: {}", domain, err);
+        return Err(err);
+    }
+
+    let db = new_db_connection().await?;
+    let created_at = timestamp_to_naive(file.created_at, 0);
+
+    let from: Identity = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::NextID,
+        identity: public_key.clone(),
+        created_at: None,
+        display_name: None,
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: None,
+        updated_at: naive_now(),
+    };
+
+    let to: Identity = Identity {
+        uuid: Some(Uuid::new_v4()),
+        platform: Platform::DNS,
+        identity: domain.clone(),
+        created_at: None,
+        display_name: Some(domain.clone()),
+        display_name_source: None,
+        added_at: naive_now(),
+        avatar_url: None,
+        profile_url: Some(format!("https://{}", domain)),
+        updated_at: naive_now(),
+    };
+
+    let proof: Proof = Proof {
+        uuid: Uuid::new_v4(),
+        source: DataSource::WebProof,
+        record_id: Some(uri.to_string()),
+        proof_url: Some(uri.to_string()),
+        created_at: Some(created_at),
+        updated_at: naive_now(),
+        fetcher: C.upstream.web_proof.fetcher.unwrap_or_default(),
+    };
+
+    create_identity_to_identity_record(&db, &from, &to, &proof).await?;
+
+    Ok(vec![Target::Identity(Platform::NextID, public_key)])
+}
@@ -0,0 +1,141 @@
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+use crate::{
+    error::Error,
+    graph::{
+        arangopool::new_connection_pool,
+        new_db_connection,
+        vertex::{Identity, NeighborOptions},
+    },
+    upstream::{
+        mock::{self, Fixture},
+        web_proof::{
+            fetch_proof_file, proof_location, signature_payload, WebProof, WebProofFile,
+            WELL_KNOWN_PATH,
+        },
+        Fetcher, Platform, Target,
+    },
+    util::personal_message_hash,
+};
+
+/// Start a local server which serves `body` on `WELL_KNOWN_PATH`.
+/// Returns URL of the proof file.
+fn mock_host(body: String) -> http::Uri {
+    format!(
+        "{}{}",
+        mock::serve(vec![Fixture::ok(WELL_KNOWN_PATH, body)]),
+        WELL_KNOWN_PATH
+    )
+    .parse()
+    .unwrap()
+}
+
+/// Generate a random persona and let it sign a proof for `domain`.
+fn signed_proof(domain: &str) -> WebProofFile {
+    let secp = Secp256k1::new();
+    let secret_key = SecretKey::from_slice(&rand::random::<[u8; 32]>()).unwrap();
+    let public_key = format!(
+        "0x{}",
+        hex::encode(PublicKey::from_secret_key(&secp, &secret_key).serialize())
+    );
+    let created_at = 1660000000;
+
+    let message = Message::from_slice(&personal_message_hash(&signature_payload(
+        domain,
+        &public_key,
+        created_at,
+    )))
+    .unwrap();
+    let signature = secp.sign_ecdsa(&message, &secret_key);
+
+    WebProofFile {
+        public_key,
+        algorithm: Default::default(),
+        curve: Default::default(),
+        created_at,
+        signature: base64::encode(signature.serialize_compact()),
+    }
+}
+
+#[tokio::test]
+async fn test_valid_proof() -> Result<(), Error> {
+    let file = signed_proof("example.com");
+    let url = mock_host(serde_json::to_string(&file)?);
+
+    let result = fetch_proof_file("example.com".into(), url).await?;
+    assert_eq!(
+        result,
+        vec![Target::Identity(Platform::NextID, file.public_key.clone())]
+    );
+
+    let db = new_db_connection().await?;
+    let persona = Identity::find_by_platform_identity(&db, &Platform::NextID, &file.public_key)
+        .await?
+        .expect("Record not found");
+    let pool = new_connection_pool().await?;
+    let neighbors = persona.neighbors(&pool, NeighborOptions::default()).await?;
+    assert!(neighbors
+        .iter()
+        .any(|n| n.identity.platform == Platform::DNS && n.identity.identity == "example.com"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tampered_proof() -> Result<(), Error> {
+    let mut file = signed_proof("example.com");
+    // Signed one second earlier than claimed.
+    file.created_at += 1;
+    let url = mock_host(serde_json::to_string(&file)?);
+
+    let result = fetch_proof_file("example.com".into(), url).await;
+    assert!(matches!(result, Err(Error::SignatureValidationError(_))));
+
+    let db = new_db_connection().await?;
+    assert!(
+        Identity::find_by_platform_identity(&db, &Platform::NextID, &file.public_key)
+            .await?
+            .is_none()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_proof_location() {
+    let (domain, uri) = proof_location("Example.COM").unwrap();
+    assert_eq!(domain, "example.com");
+    assert_eq!(
+        uri.to_string(),
+        format!("https://example.com{}", WELL_KNOWN_PATH)
+    );
+
+    for rejected in [
+        "http://example.com/proof.json",
+        "https://example.com/.well-known/nextid.json",
+        "127.0.0.1",
+        "2130706433",
+        "[::1]",
+        "::1",
+        "localhost",
+        "metadata.internal",
+        "printer.local",
+        "example.com:8080",
+        "user@example.com",
+    ] {
+        assert!(
+            matches!(proof_location(rejected), Err(Error::ParamError(_))),
+            "{} is rejected",
+            rejected
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_url_is_not_fetched() -> Result<(), Error> {
+    let url = mock_host(serde_json::to_string(&signed_proof("127.0.0.1"))?);
+    let result = WebProof::fetch(&Target::Identity(Platform::DNS, url.to_string())).await;
+    assert!(matches!(result, Err(Error::ParamError(_))));
+
+    Ok(())
+}
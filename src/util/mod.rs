@@ -1,14 +1,57 @@
 #[cfg(test)]
 mod tests;
 
-use std::{collections::HashSet, hash::Hash};
-
-use crate::error::Error;
+use crate::{
+    config::{ConfigProxy, ConfigTls, C},
+    error::Error,
+    upstream::{Algorithm, Curve},
+};
+use brotli::Decompressor;
 use chrono::NaiveDateTime;
-use http::Response;
-use hyper::{body::HttpBody as _, client::HttpConnector, Body, Client};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use headers::Authorization;
+use http::{
+    header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+    HeaderValue, Request, Response, StatusCode, Uri,
+};
+use hyper::{
+    body::HttpBody as _,
+    client::{
+        connect::dns::{GaiResolver, Name},
+        HttpConnector,
+    },
+    Body, Client,
+};
+use hyper_proxy::{Custom, Intercept, Proxy, ProxyConnector};
 use hyper_tls::HttpsConnector;
+use native_tls::{Certificate, TlsConnector};
+use rand::Rng;
+use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1};
 use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+use std::{
+    borrow::Cow,
+    future::Future,
+    io::Read,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tower_service::Service;
+use tracing::warn;
+
+lazy_static! {
+    /// TLS settings of `make_client`, built from `C.upstream.tls` once.
+    static ref TLS: TlsConnector =
+        tls_connector(&C.upstream.tls).expect("Invalid TLS config in upstream.tls");
+    /// Proxies of `make_client`, built from `C.upstream.proxy` once.
+    static ref PROXIES: Vec<Proxy> =
+        proxies(&C.upstream.proxy).expect("Invalid proxy config in upstream.proxy");
+}
+
+/// Upstream response bodies larger than this (in bytes, after decompression) are rejected.
+pub const MAX_BODY_SIZE: usize = 32 * 1024 * 1024;
 
 /// Returns current UNIX timestamp (unit: second).
 pub fn timestamp() -> i64 {
@@ -31,31 +74,541 @@ pub fn timestamp_to_naive(ts: i64, ms: u32) -> NaiveDateTime {
     NaiveDateTime::from_timestamp(ts, ms * 1000000)
 }
 
-pub fn make_client() -> Client<HttpsConnector<HttpConnector>> {
-    let https = HttpsConnector::new();
+type Connector = ProxyConnector<HttpsConnector<HttpConnector<UpstreamResolver>>>;
 
-    Client::builder().build::<_, hyper::Body>(https)
+/// HTTP(S) client for upstreams, which asks for compressed responses.
+/// Use `read_body` / `parse_body` to read the (decompressed) response.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client<Connector>,
+    /// Same as the one of `client`. Tells headers plain HTTP requests to proxies need.
+    connector: Connector,
+    /// How long to wait for a response, both headers and body. `None` to wait forever.
+    timeout: Option<Duration>,
 }
 
-pub async fn parse_body<T>(resp: &mut Response<Body>) -> Result<T, Error>
+/// When the body of a response must be read by, set by `HttpClient::request`.
+/// See `read_body_with_limit`.
+#[derive(Clone, Copy)]
+struct BodyDeadline(tokio::time::Instant);
+
+impl HttpClient {
+    pub async fn get(&self, uri: Uri) -> Result<Response<Body>, Error> {
+        let mut req = Request::new(Body::empty());
+        *req.uri_mut() = uri;
+        self.request(req).await
+    }
+
+    /// `Accept-Encoding` is added if `req` doesn't have one.
+    /// Fails with `Error::Timeout` if no response comes in time. `read_body` of the response
+    /// fails the same way if its body isn't received by then (counted from this request).
+    pub async fn request(&self, mut req: Request<Body>) -> Result<Response<Body>, Error> {
+        req.headers_mut()
+            .entry(ACCEPT_ENCODING)
+            .or_insert(HeaderValue::from_static("gzip, deflate, br"));
+        let uri = req.uri().clone();
+        // Those through a CONNECT tunnel (HTTPS) are authorized when the tunnel is set up.
+        if let Some(proxy_headers) = self.connector.http_headers(&uri) {
+            req.headers_mut().extend(proxy_headers.clone());
+        }
+        let responding = self.client.request(req);
+        match self.timeout {
+            Some(timeout) => {
+                let deadline = tokio::time::Instant::now() + timeout;
+                match tokio::time::timeout_at(deadline, responding).await {
+                    Ok(resp) => {
+                        let mut resp = resp?;
+                        resp.extensions_mut().insert(BodyDeadline(deadline));
+                        Ok(resp)
+                    }
+                    Err(_) => Err(Error::Timeout(format!(
+                        "No response from {} in {:?}",
+                        uri.host().unwrap_or_default(),
+                        timeout
+                    ))),
+                }
+            }
+            None => Ok(responding.await?),
+        }
+    }
+}
+
+/// Wait for `f` (e.g. a `gql_client` query, which can't time out by itself)
+/// at most `timeout_seconds`. `0` means forever.
+/// Fails with `Error::Timeout` (mentioning `upstream`) if it doesn't finish in time.
+pub async fn with_timeout<T>(
+    upstream: &str,
+    timeout_seconds: u64,
+    f: impl Future<Output = T>,
+) -> Result<T, Error> {
+    if timeout_seconds == 0 {
+        return Ok(f.await);
+    }
+    let timeout = Duration::from_secs(timeout_seconds);
+    tokio::time::timeout(timeout, f)
+        .await
+        .map_err(|_| Error::Timeout(format!("No response from {} in {:?}", upstream, timeout)))
+}
+
+/// Request (by calling `f`) up to `max_attempts` times, until it neither fails with
+/// `Error::HttpClientError` nor is answered with `5xx`. Waits between attempts doubles from
+/// `base_delay`, each shortened by a random jitter so clients don't retry in lockstep.
+/// The last response (or error) is returned if all of them fail.
+pub async fn retry_request<F, Fut>(
+    mut f: F,
+    max_attempts: u32,
+    base_delay: Duration,
+) -> Result<Response<Body>, Error>
 where
-    T: for<'de> Deserialize<'de>,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Response<Body>, Error>>,
 {
-    let mut body_bytes: Vec<u8> = vec![];
-    while let Some(chunk) = resp.body_mut().data().await {
-        let mut chunk_bytes = chunk.unwrap().to_vec();
-        body_bytes.append(&mut chunk_bytes);
+    let mut attempt = 1;
+    loop {
+        let result = f().await;
+        let retryable = match &result {
+            Ok(resp) => resp.status().is_server_error(),
+            Err(err) => matches!(err, Error::HttpClientError(_)),
+        };
+        if !retryable || attempt >= max_attempts {
+            return result;
+        }
+        let delay = base_delay.saturating_mul(1u32 << (attempt - 1).min(16));
+        let delay = delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0));
+        match &result {
+            Ok(resp) => warn!(
+                "Attempt {} | {}, retrying in {:?}",
+                attempt,
+                resp.status(),
+                delay
+            ),
+            Err(err) => warn!("Attempt {} | {}, retrying in {:?}", attempt, err, delay),
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+pub fn make_client() -> HttpClient {
+    make_client_with_tls(TLS.clone())
+}
+
+/// Same as `make_client`, but gives up on requests taking longer than `timeout`.
+/// A zero `timeout` means none, so `timeout_seconds = 0` in config works as expected.
+pub fn make_client_with_timeout(timeout: Duration) -> HttpClient {
+    HttpClient {
+        timeout: Some(timeout).filter(|t| !t.is_zero()),
+        ..make_client()
+    }
+}
+
+/// Same as `make_client`, but with given TLS settings instead of `C.upstream.tls`.
+pub fn make_client_with_tls(tls: TlsConnector) -> HttpClient {
+    make_client_with_proxies(tls, PROXIES.clone())
+}
+
+/// Same as `make_client_with_timeout`, but only connects to public addresses (see `is_private_ip`),
+/// for URLs anyone can make us request (e.g. `upstream::web_proof`).
+/// Never goes through proxies, which would resolve host names by themselves.
+/// IP literals in URLs are connected to as they are, so reject them before requesting.
+pub fn make_public_client(timeout: Duration) -> HttpClient {
+    HttpClient {
+        timeout: Some(timeout).filter(|t| !t.is_zero()),
+        ..build_client(TLS.clone(), vec![], UpstreamResolver { public_only: true })
+    }
+}
+
+/// Same as `make_client_with_tls`, but through given proxies (see `proxies`)
+/// instead of `C.upstream.proxy`.
+pub fn make_client_with_proxies(tls: TlsConnector, proxies: Vec<Proxy>) -> HttpClient {
+    build_client(tls, proxies, UpstreamResolver::default())
+}
+
+fn build_client(tls: TlsConnector, proxies: Vec<Proxy>, resolver: UpstreamResolver) -> HttpClient {
+    // Resolved right before connecting, so the addresses checked are the ones connected to.
+    let mut http = HttpConnector::new_with_resolver(resolver);
+    http.enforce_http(false);
+    let https = HttpsConnector::from((http, tls.clone().into()));
+    let mut connector = ProxyConnector::unsecured(https);
+    // HTTPS upstreams behind a proxy are talked to inside a tunnel, trusting the same CAs.
+    connector.set_tls(Some(tls));
+    connector.extend_proxies(proxies);
+
+    HttpClient {
+        client: Client::builder().build::<_, hyper::Body>(connector.clone()),
+        connector,
+        timeout: None,
     }
-    let body = std::str::from_utf8(&body_bytes).unwrap();
+}
 
-    Ok(serde_json::from_str(body)?)
+/// Resolves host names of upstreams for `HttpClient`.
+#[derive(Clone, Default)]
+pub struct UpstreamResolver {
+    /// Fail if a name resolves to any private address. See `make_public_client`.
+    public_only: bool,
 }
 
-pub(crate) fn hashset_append<T>(set: &mut HashSet<T>, items: Vec<T>)
+impl Service<Name> for UpstreamResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = std::io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let public_only = self.public_only;
+        let host = name.as_str().to_string();
+        let resolving = GaiResolver::new().call(name);
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = resolving.await?.collect();
+            if public_only && addresses.iter().any(|address| is_private_ip(&address.ip())) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::PermissionDenied,
+                    format!("{} resolves to a private address", host),
+                ));
+            }
+            Ok(addresses.into_iter())
+        })
+    }
+}
+
+/// Is `ip` one we shouldn't connect to on behalf of others, e.g. loopback or
+/// in a private network?
+pub fn is_private_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                // Shared address space (`100.64.0.0/10`).
+                || (ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_private_ip(&IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local (`fc00::/7`) and link local (`fe80::/10`).
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Proxies in `config`, in the order they're matched against hosts of requests.
+/// Requests to hosts matching none of them are sent directly.
+pub fn proxies(config: &ConfigProxy) -> Result<Vec<Proxy>, Error> {
+    let no_proxy = std::env::var("NO_PROXY")
+        .or_else(|_| std::env::var("no_proxy"))
+        .unwrap_or_default();
+    proxies_with_no_proxy(config, &no_proxy)
+}
+
+/// Same as `proxies`, but `no_proxy` is given instead of read from `NO_PROXY` in ENV.
+/// See `bypasses_proxy`.
+pub fn proxies_with_no_proxy(config: &ConfigProxy, no_proxy: &str) -> Result<Vec<Proxy>, Error> {
+    let no_proxy: Vec<String> = no_proxy
+        .split(',')
+        .map(|entry| entry.trim().to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    let mut proxies = vec![];
+    let overridden: Vec<String> = config
+        .hosts
+        .keys()
+        .map(|host| host.to_lowercase())
+        .collect();
+    for (host, url) in config.hosts.iter().filter(|(_, url)| !url.is_empty()) {
+        let host = host.to_lowercase();
+        let intercept = Custom::from(move |_: Option<&str>, to: Option<&str>, _: Option<u16>| {
+            to.map_or(false, |to| to.eq_ignore_ascii_case(&host))
+        });
+        proxies.push(proxy(url, Intercept::Custom(intercept))?);
+    }
+
+    let default = config
+        .url
+        .clone()
+        .or_else(|| std::env::var("HTTPS_PROXY").ok())
+        .or_else(|| std::env::var("https_proxy").ok())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = default {
+        let intercept = Custom::from(move |_: Option<&str>, to: Option<&str>, _: Option<u16>| {
+            to.map_or(true, |to| {
+                !overridden.iter().any(|host| to.eq_ignore_ascii_case(host))
+                    && !bypasses_proxy(&no_proxy, to)
+            })
+        });
+        proxies.push(proxy(&url, Intercept::Custom(intercept))?);
+    }
+    Ok(proxies)
+}
+
+/// Is `host` reached directly instead of through the default proxy? Loopback hosts always are.
+/// `no_proxy`: lowercased entries of `NO_PROXY`. An entry matches the host itself and
+/// its subdomains (`example.com` and `.example.com` are the same), `*` matches any.
+/// Ports and CIDR ranges aren't supported.
+fn bypasses_proxy(no_proxy: &[String], host: &str) -> bool {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase();
+    let is_loopback = match host.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback(),
+        Err(_) => host == "localhost" || host.ends_with(".localhost"),
+    };
+    is_loopback
+        || no_proxy.iter().any(|entry| {
+            let entry = entry.trim_start_matches('*').trim_start_matches('.');
+            entry.is_empty() || host == entry || host.ends_with(&format!(".{}", entry))
+        })
+}
+
+/// `url`: `http://[user:password@]host:port`.
+fn proxy(url: &str, intercept: Intercept) -> Result<Proxy, Error> {
+    let proxy_error = |err: String| {
+        Error::General(
+            format!("Proxy config error ({}): {}", redact_credentials(url), err),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+    };
+    let parsed = url::Url::parse(url).map_err(|err| proxy_error(err.to_string()))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| proxy_error("no host".into()))?;
+    let port = parsed
+        .port_or_known_default()
+        .ok_or_else(|| proxy_error("no port".into()))?;
+    let uri: Uri = format!("{}://{}:{}", parsed.scheme(), host, port)
+        .parse()
+        .map_err(|err: http::uri::InvalidUri| proxy_error(err.to_string()))?;
+
+    let mut proxy = Proxy::new(intercept, uri);
+    if !parsed.username().is_empty() {
+        proxy.set_authorization(Authorization::basic(
+            parsed.username(),
+            parsed.password().unwrap_or_default(),
+        ));
+    }
+    Ok(proxy)
+}
+
+/// `url` without credentials in it, for logging.
+fn redact_credentials(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(mut parsed) => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.to_string()
+        }
+        Err(_) => "invalid URL".into(),
+    }
+}
+
+/// Build TLS settings trusting system root CAs plus `config.ca_certs`.
+pub fn tls_connector(config: &ConfigTls) -> Result<TlsConnector, Error> {
+    let tls_error = |path: &str, err: String| {
+        Error::General(
+            format!("TLS config error ({}): {}", path, err),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        )
+    };
+    let mut builder = TlsConnector::builder();
+    for path in config.ca_certs.iter() {
+        let pem = std::fs::read(path).map_err(|err| tls_error(path, err.to_string()))?;
+        // A bundle may contain many certificates.
+        let certs =
+            Certificate::stack_from_pem(&pem).map_err(|err| tls_error(path, err.to_string()))?;
+        if certs.is_empty() {
+            return Err(tls_error(path, "no certificate found".into()));
+        }
+        for cert in certs.into_iter() {
+            builder.add_root_certificate(cert);
+        }
+    }
+    if config.danger_accept_invalid_certs {
+        warn!("!!! TLS certificate verification of upstreams is DISABLED. Never do this in production !!!");
+        builder.danger_accept_invalid_certs(true);
+    }
+    builder
+        .build()
+        .map_err(|err| tls_error("build", err.to_string()))
+}
+
+/// Read the whole body of `resp`, decompressed according to its `Content-Encoding`.
+/// Fails if it is larger than `MAX_BODY_SIZE`.
+pub async fn read_body(resp: &mut Response<Body>) -> Result<Vec<u8>, Error> {
+    read_body_with_limit(resp, MAX_BODY_SIZE).await
+}
+
+/// Same as `read_body`, with a custom size limit (in bytes).
+/// The limit applies to both compressed and decompressed size.
+/// Fails with `Error::Timeout` if `resp` comes from a client with timeout
+/// (see `make_client_with_timeout`) and its body doesn't arrive in time.
+pub async fn read_body_with_limit(
+    resp: &mut Response<Body>,
+    limit: usize,
+) -> Result<Vec<u8>, Error> {
+    let too_large = || {
+        Error::General(
+            format!("Response body too large (> {} bytes)", limit),
+            StatusCode::BAD_GATEWAY,
+        )
+    };
+
+    let deadline = resp.extensions().get::<BodyDeadline>().copied();
+    let receiving = async {
+        let mut body_bytes: Vec<u8> = vec![];
+        while let Some(chunk) = resp.body_mut().data().await {
+            body_bytes.extend_from_slice(&chunk?);
+            if body_bytes.len() > limit {
+                return Err(too_large());
+            }
+        }
+        Ok::<_, Error>(body_bytes)
+    };
+    let body_bytes = match deadline {
+        Some(BodyDeadline(deadline)) => tokio::time::timeout_at(deadline, receiving)
+            .await
+            .map_err(|_| Error::Timeout("Response body not received in time".into()))??,
+        None => receiving.await?,
+    };
+
+    let encoding = resp
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    let decoder: Box<dyn Read + '_> = match encoding.as_str() {
+        "" | "identity" => return Ok(body_bytes),
+        "gzip" | "x-gzip" => Box::new(GzDecoder::new(body_bytes.as_slice())),
+        "deflate" => Box::new(ZlibDecoder::new(body_bytes.as_slice())),
+        "br" => Box::new(Decompressor::new(body_bytes.as_slice(), 4096)),
+        other => {
+            return Err(Error::General(
+                format!("Unsupported Content-Encoding: {}", other),
+                StatusCode::BAD_GATEWAY,
+            ))
+        }
+    };
+
+    // Read one more byte than allowed to tell if it is too large.
+    let mut decoded: Vec<u8> = vec![];
+    decoder
+        .take((limit as u64).saturating_add(1))
+        .read_to_end(&mut decoded)
+        .map_err(|err| {
+            Error::General(
+                format!("Failed to decode {} body: {}", encoding, err),
+                StatusCode::BAD_GATEWAY,
+            )
+        })?;
+    if decoded.len() > limit {
+        return Err(too_large());
+    }
+    Ok(decoded)
+}
+
+pub async fn parse_body<T>(resp: &mut Response<Body>) -> Result<T, Error>
 where
-    T: Eq + Clone + Hash,
+    T: for<'de> Deserialize<'de>,
 {
-    for i in items {
-        set.insert(i);
+    let body_bytes = read_body(resp).await?;
+
+    Ok(serde_json::from_slice(&body_bytes)?)
+}
+
+/// Verify a signature of `message`.
+/// - `signature`: base64-encoded, `r || s` (64 bytes) with an optional trailing recovery ID.
+/// - `public_key`: hexstring (`0x` prefix is optional), compressed or uncompressed.
+/// For `secp256k1`, `message` is hashed in `personal_sign` (EIP-191) way before verification.
+pub fn verify_signature(
+    algorithm: Algorithm,
+    curve: Curve,
+    message: &str,
+    signature: &str,
+    public_key: &str,
+) -> Result<(), Error> {
+    match (algorithm, curve) {
+        (Algorithm::EllipticCurve, Curve::Secp256K1) => {
+            verify_secp256k1(message, signature, public_key)
+        }
+    }
+}
+
+fn verify_secp256k1(message: &str, signature: &str, public_key: &str) -> Result<(), Error> {
+    let public_key = hex::decode(public_key.trim_start_matches("0x"))
+        .map_err(|err| Error::SignatureValidationError(format!("Public key: {}", err)))?;
+    let public_key = PublicKey::from_slice(&public_key)
+        .map_err(|err| Error::SignatureValidationError(format!("Public key: {}", err)))?;
+
+    let signature = base64::decode(signature)
+        .map_err(|err| Error::SignatureValidationError(format!("Signature: {}", err)))?;
+    if signature.len() != 64 && signature.len() != 65 {
+        return Err(Error::SignatureValidationError(format!(
+            "Signature: invalid length {}",
+            signature.len()
+        )));
+    }
+    let mut signature = Signature::from_compact(&signature[..64])
+        .map_err(|err| Error::SignatureValidationError(format!("Signature: {}", err)))?;
+    signature.normalize_s();
+
+    let message = Message::from_slice(&personal_message_hash(message))
+        .map_err(|err| Error::SignatureValidationError(format!("Message: {}", err)))?;
+
+    Secp256k1::verification_only()
+        .verify_ecdsa(&message, &signature, &public_key)
+        .map_err(|err| Error::SignatureValidationError(err.to_string()))
+}
+
+/// Hash `message` in the way of `personal_sign` (EIP-191).
+pub fn personal_message_hash(message: &str) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+    hasher.update(message.as_bytes());
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hasher.finalize());
+    hash
+}
+
+/// ENS namehash (EIP-137) of a normalized `name`, as `0xHEX_STRING`.
+/// Token ID of an ENS name is its namehash.
+pub fn namehash(name: &str) -> String {
+    let mut node = [0u8; 32];
+    if !name.is_empty() {
+        for label in name.rsplit('.') {
+            let mut hasher = Keccak256::new();
+            hasher.update(node);
+            hasher.update(Keccak256::digest(label.as_bytes()));
+            node.copy_from_slice(&hasher.finalize());
+        }
+    }
+    format!("0x{}", hex::encode(node))
+}
+
+/// Does `id` look like a namehash (`0x` and 64 hex digits, in any case)?
+pub fn is_namehash(id: &str) -> bool {
+    id.len() == 66 && id.starts_with("0x") && id[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Hide an identity in log output if `redact` is set.
+/// Redacted one is a truncated hash, which can't be reversed
+/// but stays the same for the same identity.
+pub fn redact_identity(identity: &str, redact: bool) -> Cow<'_, str> {
+    if !redact {
+        return Cow::Borrowed(identity);
     }
+    let hash = Keccak256::digest(identity.as_bytes());
+    Cow::Owned(format!("redacted:{}", hex::encode(&hash[..4])))
 }